@@ -17,6 +17,7 @@ pub struct Config {
     pub sea_col: Color,
 
     pub roof_col: Color,
+    pub wall_col: Color,
 
     pub gui_bg_col: Color,
     pub gui_title_col: Color,
@@ -33,6 +34,7 @@ pub struct Config {
     pub lot_unassigned_col: Color,
     pub lot_residential_col: Color,
     pub lot_commercial_col: Color,
+    pub lot_industrial_col: Color,
 
     pub special_building_col: Color,
     pub special_building_invalid_col: Color,
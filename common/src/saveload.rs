@@ -34,22 +34,22 @@ pub trait Encoder {
         Self::decode(&*buf)
     }
 
-    fn filename(name: &'static str) -> String {
+    fn filename(name: &str) -> String {
         format!("world/{}.{}", name, Self::EXTENSION)
     }
 
-    fn load_reader(name: &'static str) -> Option<BufReader<File>> {
+    fn load_reader(name: &str) -> Option<BufReader<File>> {
         let file = open_file(&Self::filename(name))?;
         Some(BufReader::new(file))
     }
 
-    fn save(x: &impl Serialize, name: &'static str) -> Option<()> {
+    fn save(x: &impl Serialize, name: &str) -> Option<()> {
         Self::save_silent(x, name)?;
         log::info!("successfully saved {}", name);
         Some(())
     }
 
-    fn save_silent(x: &impl Serialize, name: &'static str) -> Option<()> {
+    fn save_silent(x: &impl Serialize, name: &str) -> Option<()> {
         let _ = std::fs::create_dir("world");
 
         let file = create_file(&Self::filename(name))?;
@@ -62,7 +62,7 @@ pub trait Encoder {
         Some(())
     }
 
-    fn load<T: DeserializeOwned>(name: &'static str) -> Option<T> {
+    fn load<T: DeserializeOwned>(name: &str) -> Option<T> {
         Self::decode_reader(Self::load_reader(name)?)
             .map_err(|err| log::error!("failed deserializing {}: {}", name, err))
             .map(|x| {
@@ -72,7 +72,7 @@ pub trait Encoder {
             .ok()
     }
 
-    fn load_or_default<T: DeserializeOwned + Default>(name: &'static str) -> T {
+    fn load_or_default<T: DeserializeOwned + Default>(name: &str) -> T {
         Self::load(name).unwrap_or_default()
     }
 }
@@ -15,8 +15,10 @@ macro_rules! gen_z {
 
 gen_z! {
     Z_TERRAIN
+    Z_WATER
     Z_GRID
     Z_LOT
+    Z_BRIDGE
     Z_BSPRITE
     Z_INTER_BG
     Z_LANE_BG
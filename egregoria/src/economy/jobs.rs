@@ -0,0 +1,509 @@
+use crate::economy::{building_upkeep, road_upkeep, Bankruptcy, Ledger, Market, Money, Treasury};
+use crate::map_dynamic::BuildingInfos;
+use crate::pedestrians::Location;
+use crate::souls::desire::{Desire, Work, WorkKind};
+use crate::souls::goods_company::{close_company, GoodsCompany};
+use crate::souls::household::HouseholdMember;
+use crate::souls::services::{PublicService, ServiceBudget};
+use crate::utils::rand_provider::RandProvider;
+use crate::utils::time::GameTime;
+use crate::vehicles::{spawn_parked_vehicle, Vehicle, VehicleKind};
+use crate::{Egregoria, ParCommandBuffer, SoulID};
+use geom::Vec2;
+use imgui_inspect_derive::*;
+use legion::world::SubWorld;
+use legion::{system, Entity, EntityStore, Query};
+use map_model::{BuildingID, Map};
+use ordered_float::OrderedFloat;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+/// Average speed used to estimate commute times, in m/s
+pub const COMMUTE_SPEED: f32 = 8.0;
+/// Nobody takes a job further than this from home
+pub const MAX_COMMUTE_TIME: f32 = GameTime::HOUR as f32 * 2.0;
+/// A worker quits for a job whose value is at least this much better than its current one
+pub const QUIT_THRESHOLD: f32 = 1.2;
+/// Companies only hire when they can pay their whole payroll for this many days
+pub const HIRING_RESERVE_DAYS: i64 = 3;
+/// Money given to a company when it is created, to pay its first wages
+pub const COMPANY_STARTING_CAPITAL: Money = Money::new(5000);
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Skill {
+    Unskilled,
+    Skilled,
+    Expert,
+}
+
+debug_inspect_impl!(Skill);
+
+impl Skill {
+    /// Wage paid each day for a job requiring this skill
+    pub fn wage(self) -> Money {
+        match self {
+            Skill::Unskilled => Money::new(30),
+            Skill::Skilled => Money::new(50),
+            Skill::Expert => Money::new(80),
+        }
+    }
+
+    pub fn random(rng: &mut RandProvider) -> Self {
+        let r = rng.random::<f32>();
+        if r < 0.5 {
+            Skill::Unskilled
+        } else if r < 0.85 {
+            Skill::Skilled
+        } else {
+            Skill::Expert
+        }
+    }
+}
+
+#[derive(Inspect, Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct JobSlot {
+    pub skill: Skill,
+    pub worker: Option<SoulID>,
+}
+
+/// Job slots of a company
+#[derive(Inspect, Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Workers {
+    pub slots: Vec<JobSlot>,
+}
+
+impl Workers {
+    /// 60% unskilled, 30% skilled and 10% expert jobs
+    pub fn new(n_workers: i32) -> Self {
+        let n = n_workers.max(0) as usize;
+        let n_expert = n / 10;
+        let n_skilled = (n * 3) / 10;
+        let slots = (0..n)
+            .map(|i| JobSlot {
+                skill: if i < n_expert {
+                    Skill::Expert
+                } else if i < n_expert + n_skilled {
+                    Skill::Skilled
+                } else {
+                    Skill::Unskilled
+                },
+                worker: None,
+            })
+            .collect();
+        Self { slots }
+    }
+
+    pub fn employees(&self) -> impl Iterator<Item = SoulID> + '_ {
+        self.slots.iter().filter_map(|s| s.worker)
+    }
+
+    pub fn n_employed(&self) -> usize {
+        self.employees().count()
+    }
+
+    /// Wages paid each day to the employees
+    pub fn payroll(&self) -> Money {
+        self.slots
+            .iter()
+            .filter(|s| s.worker.is_some())
+            .fold(Money::ZERO, |acc, s| acc + s.skill.wage())
+    }
+
+    fn free(&mut self, worker: SoulID) {
+        for slot in &mut self.slots {
+            if slot.worker == Some(worker) {
+                slot.worker = None;
+            }
+        }
+    }
+}
+
+#[derive(Inspect, Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct Job {
+    pub company: SoulID,
+    pub workplace: BuildingID,
+    pub skill: Skill,
+}
+
+/// Skill and current job of a human of working age
+#[derive(Inspect, Clone, Debug, Serialize, Deserialize)]
+pub struct Occupation {
+    pub skill: Skill,
+    pub job: Option<Job>,
+}
+
+impl Occupation {
+    pub fn new(skill: Skill) -> Self {
+        Self { skill, job: None }
+    }
+}
+
+/// How much a worker living at `home` values a job, taking the commute into account.
+/// None if the commute is too long.
+fn job_value(skill: Skill, workplace: Vec2, home: Vec2) -> Option<f32> {
+    let commute = home.distance(workplace) / COMMUTE_SPEED;
+    if commute > MAX_COMMUTE_TIME {
+        return None;
+    }
+    Some(skill.wage().cents() as f32 * (1.0 - 0.5 * commute / MAX_COMMUTE_TIME))
+}
+
+register_resource!(JobMarket, "job_market");
+/// Statistics about the labour market, updated when jobs are matched
+#[derive(Inspect, Clone, Default, Debug, Serialize, Deserialize)]
+pub struct JobMarket {
+    pub open_jobs: u32,
+    pub unemployed: u32,
+    /// Hour at which jobs were last matched
+    last_matching: i32,
+    /// Day at which wages were last paid
+    last_payday: i32,
+}
+
+register_system!(job_market_update);
+/// Every hour, matches job seekers with the open job slots, most valuable jobs first.
+/// Every day, companies pay their workers and lay off some if they cannot afford them.
+/// Public services hire as many workers as their budget allows.
+/// The city collects its taxes and pays for the services and the roads every day.
+#[system]
+#[write_component(Workers)]
+#[write_component(Ledger)]
+#[write_component(Occupation)]
+#[read_component(GoodsCompany)]
+#[read_component(PublicService)]
+#[read_component(HouseholdMember)]
+pub fn job_market_update(
+    #[resource] time: &GameTime,
+    #[resource] jobs: &mut JobMarket,
+    #[resource] market: &mut Market,
+    #[resource] binfos: &BuildingInfos,
+    #[resource] map: &Map,
+    #[resource] bankruptcy: &Bankruptcy,
+    #[resource] budget: &mut ServiceBudget,
+    #[resource] treasury: &mut Treasury,
+    #[resource] cbuf: &ParCommandBuffer,
+    companies: &mut Query<(Entity, &GoodsCompany, &mut Workers, &mut Ledger)>,
+    services: &mut Query<(Entity, &PublicService, &mut Workers)>,
+    humans: &mut Query<(Entity, &mut Occupation, &HouseholdMember)>,
+    sw: &mut SubWorld,
+) {
+    let hour = time.total_hours();
+    if hour == jobs.last_matching {
+        return;
+    }
+    jobs.last_matching = hour;
+
+    if time.daytime.day != jobs.last_payday {
+        jobs.last_payday = time.daytime.day;
+        payday(market, map, bankruptcy, treasury, cbuf, companies, sw);
+        pay_services(market, map, budget, treasury, cbuf, services, sw);
+        treasury.pay_upkeep(road_upkeep(map));
+        treasury.close_day();
+    }
+
+    // Forget about the souls that left
+    let alive: BTreeSet<SoulID> = humans.iter_mut(sw).map(|(&e, _, _)| SoulID(e)).collect();
+    let mut openings: Vec<(SoulID, BuildingID, Vec2, Skill)> = vec![];
+    let mut existing_companies = BTreeSet::new();
+    // Drivers stay so that their truck is not left stranded
+    let mut drivers = vec![];
+    for (&e, comp, workers, _) in companies.iter_mut(sw) {
+        existing_companies.insert(SoulID(e));
+        drivers.extend(comp.driver);
+
+        forget_dead(workers, &alive);
+
+        let door = unwrap_cont!(map.buildings().get(comp.building)).door_pos;
+        let reserve = market.money(SoulID(e));
+        let mut payroll = workers.payroll();
+        for slot in &workers.slots {
+            if slot.worker.is_some() {
+                continue;
+            }
+            payroll += slot.skill.wage();
+            if reserve < payroll * HIRING_RESERVE_DAYS {
+                break;
+            }
+            openings.push((SoulID(e), comp.building, door, slot.skill));
+        }
+    }
+    // The treasury must be able to pay the wages of the services for a day
+    let mut service_payroll = Money::ZERO;
+    for (&e, service, workers) in services.iter_mut(sw) {
+        existing_companies.insert(SoulID(e));
+        forget_dead(workers, &alive);
+        service_payroll += workers.payroll();
+
+        let door = unwrap_cont!(map.buildings().get(service.building)).door_pos;
+        let n_open = budget
+            .funded_slots(workers.slots.len())
+            .saturating_sub(workers.n_employed());
+        for slot in workers
+            .slots
+            .iter()
+            .filter(|s| s.worker.is_none())
+            .take(n_open)
+        {
+            service_payroll += slot.skill.wage();
+            if !treasury.can_afford(service_payroll) {
+                break;
+            }
+            openings.push((SoulID(e), service.building, door, slot.skill));
+        }
+    }
+
+    let mut hires = vec![];
+    let mut unemployed = 0;
+    for (&e, occ, member) in humans.iter_mut(sw) {
+        let soul = SoulID(e);
+        if let Some(job) = occ.job {
+            if !existing_companies.contains(&job.company) {
+                occ.job = None;
+                cbuf.remove_component::<Desire<Work>>(soul.0);
+            }
+        }
+
+        let home = unwrap_cont!(binfos
+            .home_of(member.household)
+            .and_then(|h| map.buildings().get(h)))
+        .door_pos;
+
+        let current = occ.job.and_then(|job| {
+            let door = map.buildings().get(job.workplace)?.door_pos;
+            job_value(job.skill, door, home)
+        });
+        if occ.job.is_none() {
+            unemployed += 1;
+        } else if drivers.contains(&soul) {
+            continue;
+        }
+
+        let best = openings
+            .iter()
+            .enumerate()
+            .filter(|(_, &(_, _, _, skill))| skill <= occ.skill)
+            .filter_map(|(i, &(_, _, door, skill))| Some((i, job_value(skill, door, home)?)))
+            .max_by_key(|&(_, v)| OrderedFloat(v));
+
+        let (i, value) = unwrap_cont!(best);
+        if current.map(|c| value < c * QUIT_THRESHOLD).unwrap_or(false) {
+            continue;
+        }
+
+        let (company, workplace, _, skill) = openings.swap_remove(i);
+        let job = Job {
+            company,
+            workplace,
+            skill,
+        };
+        hires.push((soul, occ.job.replace(job), job));
+        if current.is_none() {
+            unemployed -= 1;
+        }
+    }
+
+    for (worker, old_job, job) in hires {
+        if let Some(old) = old_job {
+            if let Ok(mut ent) = sw.entry_mut(old.company.0) {
+                if let Ok(w) = ent.get_component_mut::<Workers>() {
+                    w.free(worker);
+                }
+            }
+            log::info!("{:?} quit {:?} for {:?}", worker, old.company, job.company);
+        }
+
+        let mut ent = unwrap_orr!(sw.entry_mut(job.company.0), continue);
+        let workers = unwrap_orr!(ent.get_component_mut::<Workers>(), continue);
+        if let Some(slot) = workers
+            .slots
+            .iter_mut()
+            .find(|s| s.worker.is_none() && s.skill == job.skill)
+        {
+            slot.worker = Some(worker);
+        }
+        cbuf.add_component(
+            worker.0,
+            Desire::new(Work::new(job.workplace, WorkKind::Worker)),
+        );
+    }
+
+    jobs.open_jobs = openings.len() as u32;
+    jobs.unemployed = unemployed;
+}
+
+/// The worker leaves its job for good, like when it dies. The truck it was driving
+/// is replaced by a new one parked at the company, since nobody can bring it back.
+pub(crate) fn leave_job(goria: &mut Egregoria, worker: SoulID) {
+    let job = unwrap_ret!(goria.comp::<Occupation>(worker.0).and_then(|o| o.job));
+    if let Some(w) = goria.comp_mut::<Workers>(job.company.0) {
+        w.free(worker);
+    }
+
+    let truck = match goria.comp::<Desire<Work>>(worker.0).map(|w| w.v.kind) {
+        Some(WorkKind::Driver { truck, .. }) => truck,
+        _ => return,
+    };
+    if let Some(company) = goria.comp_mut::<GoodsCompany>(job.company.0) {
+        if company.driver == Some(worker) {
+            company.driver = None;
+        }
+    }
+
+    if goria.comp::<Location>(worker.0) != Some(&Location::Vehicle(truck)) {
+        if let Some(v) = goria.comp_mut::<Vehicle>(truck.0) {
+            if v.claimed_by == Some(worker) {
+                v.claimed_by = None;
+            }
+        }
+        return;
+    }
+
+    goria.read::<ParCommandBuffer>().kill(truck.0);
+    let door = unwrap_ret!(goria.map().buildings().get(job.workplace)).door_pos;
+    let new_truck = spawn_parked_vehicle(goria, VehicleKind::Truck, door);
+    let company = unwrap_ret!(goria.comp_mut::<GoodsCompany>(job.company.0));
+    match new_truck {
+        Some(new_truck) => {
+            for t in &mut company.trucks {
+                if *t == truck {
+                    *t = new_truck;
+                }
+            }
+        }
+        None => company.trucks.retain(|&t| t != truck),
+    }
+}
+
+fn forget_dead(workers: &mut Workers, alive: &BTreeSet<SoulID>) {
+    for slot in &mut workers.slots {
+        if slot.worker.map(|w| !alive.contains(&w)).unwrap_or(false) {
+            slot.worker = None;
+        }
+    }
+}
+
+/// Companies pay their workers, laying off the most expensive ones first if they cannot.
+/// The truck driver is laid off last so that deliveries can go on.
+/// The upkeep of the building and the taxes on the profit are paid too,
+/// and companies losing money for too long close.
+fn payday(
+    market: &mut Market,
+    map: &Map,
+    bankruptcy: &Bankruptcy,
+    treasury: &mut Treasury,
+    cbuf: &ParCommandBuffer,
+    companies: &mut Query<(Entity, &GoodsCompany, &mut Workers, &mut Ledger)>,
+    sw: &mut SubWorld,
+) {
+    let mut laid_off = vec![];
+    for (&e, comp, workers, ledger) in companies.iter_mut(sw) {
+        let soul = SoulID(e);
+
+        if let Some(b) = map.buildings().get(comp.building) {
+            let upkeep = building_upkeep(b);
+            market.add_money(soul, -upkeep);
+            ledger.today.upkeep += upkeep;
+        }
+
+        let money = market.money(soul);
+
+        while workers.payroll() > money {
+            let slot = unwrap_or!(
+                workers
+                    .slots
+                    .iter_mut()
+                    .filter(|s| s.worker.is_some())
+                    .max_by_key(|s| (s.worker != comp.driver, s.skill)),
+                break
+            );
+            laid_off.extend(slot.worker.take());
+        }
+
+        for slot in &workers.slots {
+            let worker = unwrap_cont!(slot.worker);
+            let wage = slot.skill.wage();
+            market.add_money(soul, -wage);
+            pay_wage(market, treasury, worker, wage);
+            ledger.today.wages += wage;
+        }
+
+        let profit = ledger.today.profit();
+        if profit > Money::ZERO {
+            let tax = profit * treasury.taxes.company;
+            market.add_money(soul, -tax);
+            treasury.collect(tax);
+            ledger.today.taxes += tax;
+        }
+
+        ledger.close_day();
+        if ledger.unprofitable_days >= bankruptcy.unprofitable_days {
+            cbuf.exec_ent(e, move |goria| close_company(goria, soul));
+        }
+    }
+
+    lay_off(laid_off, cbuf, sw);
+}
+
+/// Public services pay their workers and the upkeep of their building out of the treasury,
+/// laying off the workers they are not funded for anymore or that the city cannot afford
+fn pay_services(
+    market: &mut Market,
+    map: &Map,
+    budget: &mut ServiceBudget,
+    treasury: &mut Treasury,
+    cbuf: &ParCommandBuffer,
+    services: &mut Query<(Entity, &PublicService, &mut Workers)>,
+    sw: &mut SubWorld,
+) {
+    let mut spent = Money::ZERO;
+    let mut laid_off = vec![];
+    for (_, service, workers) in services.iter_mut(sw) {
+        if let Some(b) = map.buildings().get(service.building) {
+            spent += building_upkeep(b);
+        }
+
+        let funded = budget.funded_slots(workers.slots.len());
+        // Wages are not paid with money the city does not have
+        while workers.n_employed() > funded || !treasury.can_afford(spent + workers.payroll()) {
+            let slot = unwrap_or!(
+                workers
+                    .slots
+                    .iter_mut()
+                    .filter(|s| s.worker.is_some())
+                    .max_by_key(|s| s.skill),
+                break
+            );
+            laid_off.extend(slot.worker.take());
+        }
+
+        for slot in &workers.slots {
+            let worker = unwrap_cont!(slot.worker);
+            let wage = slot.skill.wage();
+            pay_wage(market, treasury, worker, wage);
+            spent += wage;
+        }
+    }
+    budget.spent = spent;
+    treasury.pay_services(spent);
+
+    lay_off(laid_off, cbuf, sw);
+}
+
+/// The worker receives the wage minus the income tax
+fn pay_wage(market: &mut Market, treasury: &mut Treasury, worker: SoulID, wage: Money) {
+    let tax = wage * treasury.taxes.income;
+    market.add_money(worker, wage - tax);
+    treasury.collect(tax);
+}
+
+fn lay_off(laid_off: Vec<SoulID>, cbuf: &ParCommandBuffer, sw: &mut SubWorld) {
+    for worker in laid_off {
+        log::info!("{:?} was laid off", worker);
+        if let Ok(mut ent) = sw.entry_mut(worker.0) {
+            if let Ok(occ) = ent.get_component_mut::<Occupation>() {
+                occ.job = None;
+            }
+        }
+        cbuf.remove_component::<Desire<Work>>(worker.0);
+    }
+}
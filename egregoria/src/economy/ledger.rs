@@ -0,0 +1,128 @@
+use crate::economy::Money;
+use imgui_inspect_derive::*;
+use map_model::Building;
+use serde::{Deserialize, Serialize};
+
+/// Building upkeep paid each day per m² of lot, in cents
+pub const UPKEEP_PER_AREA: f32 = 0.1;
+
+/// Maintenance cost of a building for one day, proportional to its lot area
+pub fn building_upkeep(building: &Building) -> Money {
+    let [a, b] = building.obb.axis();
+    Money((a.magnitude() * b.magnitude() * UPKEEP_PER_AREA) as i64)
+}
+
+/// Money earned and spent by a company during one day
+#[derive(Inspect, Copy, Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Accounts {
+    /// Goods sold on the market
+    pub revenue: Money,
+    /// Goods bought on the market to be used by the recipe
+    pub input_costs: Money,
+    pub wages: Money,
+    pub upkeep: Money,
+    /// Taxes on the profit paid to the city
+    #[serde(default)]
+    pub taxes: Money,
+}
+
+impl Accounts {
+    pub fn expenses(&self) -> Money {
+        self.input_costs + self.wages + self.upkeep + self.taxes
+    }
+
+    pub fn profit(&self) -> Money {
+        self.revenue - self.expenses()
+    }
+}
+
+/// Finances of a company, closed every day when wages are paid
+#[derive(Inspect, Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Ledger {
+    pub today: Accounts,
+    pub yesterday: Accounts,
+    /// Sum of every day since the company was created
+    pub total: Accounts,
+    /// Number of days in a row the company lost money on what it sold
+    pub unprofitable_days: u32,
+}
+
+impl Ledger {
+    /// Days without any sale at a price are not counted, most goods have no price yet
+    /// and the companies making them would always go bankrupt
+    pub fn close_day(&mut self) {
+        let today = std::mem::take(&mut self.today);
+
+        self.total.revenue += today.revenue;
+        self.total.input_costs += today.input_costs;
+        self.total.wages += today.wages;
+        self.total.upkeep += today.upkeep;
+        self.total.taxes += today.taxes;
+
+        if today.revenue > Money::ZERO {
+            if today.profit() < Money::ZERO {
+                self.unprofitable_days += 1;
+            } else {
+                self.unprofitable_days = 0;
+            }
+        }
+        self.yesterday = today;
+    }
+}
+
+register_resource!(Bankruptcy, "bankruptcy");
+#[derive(Inspect, Clone, Serialize, Deserialize, Debug)]
+pub struct Bankruptcy {
+    /// Companies close after losing money this many days in a row
+    pub unprofitable_days: u32,
+}
+
+impl Default for Bankruptcy {
+    fn default() -> Self {
+        Self {
+            unprofitable_days: 7,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Accounts, Ledger};
+    use crate::economy::Money;
+
+    #[test]
+    fn test_close_day() {
+        let mut ledger = Ledger::default();
+
+        ledger.today = Accounts {
+            revenue: Money::new(100),
+            wages: Money::new(30),
+            upkeep: Money::new(10),
+            ..Default::default()
+        };
+        ledger.close_day();
+        assert_eq!(ledger.yesterday.profit(), Money::new(60));
+        assert_eq!(ledger.today.expenses(), Money::ZERO);
+        assert_eq!(ledger.unprofitable_days, 0);
+
+        ledger.today.revenue = Money::new(10);
+        ledger.today.wages = Money::new(80);
+        ledger.close_day();
+        ledger.today.revenue = Money::new(10);
+        ledger.today.upkeep = Money::new(20);
+        ledger.close_day();
+        assert_eq!(ledger.unprofitable_days, 2);
+        assert_eq!(ledger.total.revenue, Money::new(120));
+        assert_eq!(ledger.total.profit(), Money::new(-20));
+
+        // nothing was sold at a price, the day is not counted
+        ledger.today.upkeep = Money::new(10);
+        ledger.close_day();
+        assert_eq!(ledger.unprofitable_days, 2);
+
+        // a profitable day resets the count of days before bankruptcy
+        ledger.today.revenue = Money::new(10);
+        ledger.close_day();
+        assert_eq!(ledger.unprofitable_days, 0);
+    }
+}
@@ -1,4 +1,4 @@
-use crate::economy::CommodityKind;
+use crate::economy::{CommodityKind, Money};
 use crate::SoulID;
 use geom::Vec2;
 use ordered_float::OrderedFloat;
@@ -32,6 +32,9 @@ register_resource!(Market, "market");
 #[derive(Serialize, Deserialize)]
 pub struct Market {
     markets: BTreeMap<CommodityKind, SingleMarket>,
+    money: BTreeMap<SoulID, Money>,
+    /// Souls that spend and earn from the money of another soul, like the members of a household
+    wallets: BTreeMap<SoulID, SoulID>,
 }
 
 impl Default for Market {
@@ -41,6 +44,8 @@ impl Default for Market {
                 .iter()
                 .map(|&v| (v, SingleMarket::default()))
                 .collect(),
+            money: Default::default(),
+            wallets: Default::default(),
         }
     }
 }
@@ -84,6 +89,16 @@ impl Market {
         self.m(kind).buy_orders.insert(soul, (near, qty));
     }
 
+    /// Removes the buy order placed by this agent, if any
+    pub fn cancel_buy(&mut self, soul: SoulID, kind: CommodityKind) {
+        self.m(kind).buy_orders.remove(&soul);
+    }
+
+    /// Removes the sell order placed by this agent, if any
+    pub fn cancel_sell(&mut self, soul: SoulID, kind: CommodityKind) {
+        self.m(kind).sell_orders.remove(&soul);
+    }
+
     pub fn buy_until(&mut self, soul: SoulID, near: Vec2, kind: CommodityKind, qty: i32) {
         let c = self.capital(soul, kind);
         if c >= qty {
@@ -97,6 +112,40 @@ impl Market {
         self.markets.get(&kind).unwrap().capital(soul).unwrap_or(0)
     }
 
+    /// Get the money that this agent can spend
+    pub fn money(&self, soul: SoulID) -> Money {
+        self.money
+            .get(&wallet_of(&self.wallets, soul))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Called when money enters or leaves the economy, for example an allowance or a maintenance bill
+    pub fn add_money(&mut self, soul: SoulID, delta: Money) -> Money {
+        let v = self
+            .money
+            .entry(wallet_of(&self.wallets, soul))
+            .or_default();
+        *v += delta;
+        *v
+    }
+
+    /// Makes the soul spend and earn from the money of `owner` instead of its own
+    pub fn share_money(&mut self, soul: SoulID, owner: SoulID) {
+        self.wallets.insert(soul, owner);
+    }
+
+    /// Forgets about the soul, its own money is lost
+    pub fn forget(&mut self, soul: SoulID) {
+        self.wallets.remove(&soul);
+        self.money.remove(&soul);
+        for m in self.markets.values_mut() {
+            m.capital.remove(&soul);
+            m.buy_orders.remove(&soul);
+            m.sell_orders.remove(&soul);
+        }
+    }
+
     /// Registers a soul to the market, not obligatory
     pub fn register(&mut self, soul: SoulID, kind: CommodityKind) {
         self.m(kind).capital.entry(soul).or_default();
@@ -114,12 +163,18 @@ impl Market {
 
     /// Returns a list of buy and sell orders matched together.
     /// A trade updates the buy and sell orders from the market, and the capital of the buyers and sellers.
-    /// A trade can only be completed if the seller has enough capital.
+    /// A trade can only be completed if the seller has enough capital and the buyer enough money.
     pub fn make_trades(&mut self) -> impl Iterator<Item = Trade> + '_ {
         let mut all_trades = vec![];
         let mut potential = vec![];
 
-        for (&kind, market) in &mut self.markets {
+        let Market {
+            markets,
+            money,
+            wallets,
+        } = self;
+
+        for (&kind, market) in markets {
             // Naive O(n²) alg
             for (&seller, &(sell_pos, qty_sell)) in &market.sell_orders {
                 let capital_sell = unwrap_or!(market.capital(seller), continue);
@@ -154,6 +209,9 @@ impl Market {
             }
             potential.sort_unstable_by_key(|(x, _, _)| OrderedFloat(*x));
             let mut already_sold = BTreeSet::default();
+            let price = kind.price();
+            let money = &mut *money;
+            let wallets = &*wallets;
             let SingleMarket {
                 buy_orders,
                 sell_orders,
@@ -164,6 +222,12 @@ impl Market {
                 potential
                     .drain(..)
                     .filter(move |(_, trade, complete)| {
+                        let cost = price * trade.qty as i64;
+                        let buyer_wallet = wallet_of(wallets, trade.buyer);
+                        if money.get(&buyer_wallet).copied().unwrap_or_default() < cost {
+                            return false;
+                        }
+
                         let ok =
                             already_sold.insert(trade.buyer) && already_sold.insert(trade.seller);
                         if !ok {
@@ -179,6 +243,11 @@ impl Market {
                         *capital.entry(trade.buyer).or_default() += trade.qty;
                         *capital.entry(trade.seller).or_default() -= trade.qty;
 
+                        if cost != Money::ZERO {
+                            *money.entry(buyer_wallet).or_default() -= cost;
+                            *money.entry(wallet_of(wallets, trade.seller)).or_default() += cost;
+                        }
+
                         true
                     })
                     .map(|(_, x, _)| x),
@@ -193,10 +262,14 @@ impl Market {
     }
 }
 
+fn wallet_of(wallets: &BTreeMap<SoulID, SoulID>, soul: SoulID) -> SoulID {
+    wallets.get(&soul).copied().unwrap_or(soul)
+}
+
 #[cfg(test)]
 mod tests {
     use super::Market;
-    use crate::economy::CommodityKind;
+    use crate::economy::{CommodityKind, Money};
     use crate::SoulID;
     use geom::{vec2, Vec2};
     use legion::Entity;
@@ -228,4 +301,28 @@ mod tests {
         assert_eq!(t0.buyer, buyer);
         assert_eq!(t0.qty, 2);
     }
+
+    #[test]
+    fn test_buyer_pays() {
+        let seller = SoulID(mk_ent(1));
+        let poor = SoulID(mk_ent(2));
+        let rich = SoulID(mk_ent(3));
+
+        let mut m = Market::default();
+
+        m.produce(seller, CommodityKind::Car, 1);
+        m.add_money(rich, CommodityKind::Car.price());
+
+        m.sell(seller, Vec2::ZERO, CommodityKind::Car, 1);
+        m.buy(poor, Vec2::ZERO, CommodityKind::Car, 1);
+        m.buy(rich, vec2(10.0, 10.0), CommodityKind::Car, 1);
+
+        let trades = m.make_trades().collect::<Vec<_>>();
+
+        assert_eq!(trades.len(), 1);
+        assert_eq!(trades[0].buyer, rich);
+        assert_eq!(m.money(rich), Money::ZERO);
+        assert_eq!(m.money(seller), CommodityKind::Car.price());
+        assert_eq!(m.capital(rich, CommodityKind::Car), 1);
+    }
 }
@@ -1,13 +1,20 @@
-use crate::SoulID;
 use common::FastMap;
 use legion::world::SubWorld;
 use legion::{system, EntityStore};
 use serde::{Deserialize, Serialize};
 use std::fmt::Display;
 
+mod jobs;
+mod ledger;
 mod market;
+mod money;
+mod treasury;
 
+pub use jobs::*;
+pub use ledger::*;
 pub use market::*;
+pub use money::*;
+pub use treasury::*;
 
 #[derive(Default, Serialize, Deserialize)]
 pub struct Sold(pub Vec<Trade>);
@@ -15,11 +22,6 @@ pub struct Sold(pub Vec<Trade>);
 #[derive(Default, Serialize, Deserialize)]
 pub struct Bought(pub FastMap<CommodityKind, Vec<Trade>>);
 
-#[derive(Debug, Default, Serialize, Deserialize)]
-pub struct Workers(pub Vec<SoulID>);
-
-debug_inspect_impl!(Workers);
-
 macro_rules! commodity {
     {$($member:tt => $display:literal),*,} => {
         #[derive(Copy, Clone, Debug, PartialOrd, Ord, Eq, PartialEq, Hash, Serialize, Deserialize)]
@@ -45,7 +47,6 @@ macro_rules! commodity {
 debug_inspect_impl!(CommodityKind);
 
 commodity! {
-    JobOpening => "Job opening",
     Cereal => "Cereal",
     Flour => "Flour",
     Bread => "Bread",
@@ -67,36 +68,53 @@ commodity! {
     Oil => "Oil",
     Polyester => "Polyester",
     Petrol => "Petrol",
+    Car => "Car",
+    Meal => "Meal",
+    Medicine => "Medicine",
+}
+
+impl CommodityKind {
+    /// Price of one unit, paid by the buyer to the seller when a trade is made
+    pub fn price(self) -> Money {
+        match self {
+            CommodityKind::Car => Money::new(8000),
+            CommodityKind::Petrol => Money::new(2),
+            CommodityKind::HighTechProduct => Money::new(500),
+            CommodityKind::Furniture => Money::new(300),
+            CommodityKind::Cloth => Money::new(40),
+            CommodityKind::Medicine => Money::new(30),
+            CommodityKind::Meal => Money::new(15),
+            _ => Money::ZERO,
+        }
+    }
 }
 
 register_system!(market_update);
 #[system]
 #[write_component(Sold)]
 #[write_component(Bought)]
-#[write_component(Workers)]
+#[write_component(Ledger)]
 pub fn market_update(#[resource] m: &mut Market, subworld: &mut SubWorld) {
     for trade in m.make_trades() {
         log::info!("A trade was made! {:?}", trade);
+        let cost = trade.kind.price() * trade.qty as i64;
 
         let mut ent = unwrap_orr!(subworld.entry_mut(trade.seller.0), continue);
 
-        match trade.kind {
-            CommodityKind::JobOpening => ent
-                .get_component_mut::<Workers>()
-                .expect("employer has no component Workers")
-                .0
-                .push(trade.buyer),
-            _ => {
-                if let Ok(v) = ent.get_component_mut::<Sold>() {
-                    v.0.push(trade)
-                }
-            }
+        if let Ok(v) = ent.get_component_mut::<Sold>() {
+            v.0.push(trade)
+        }
+        if let Ok(ledger) = ent.get_component_mut::<Ledger>() {
+            ledger.today.revenue += cost;
         }
 
-        if let Ok(v) =
-            unwrap_orr!(subworld.entry_mut(trade.buyer.0), continue).get_component_mut::<Bought>()
-        {
+        let mut ent = unwrap_orr!(subworld.entry_mut(trade.buyer.0), continue);
+
+        if let Ok(v) = ent.get_component_mut::<Bought>() {
             v.0.entry(trade.kind).or_default().push(trade);
         }
+        if let Ok(ledger) = ent.get_component_mut::<Ledger>() {
+            ledger.today.input_costs += cost;
+        }
     }
 }
@@ -0,0 +1,88 @@
+use serde::{Deserialize, Serialize};
+use std::fmt::{Debug, Display, Formatter};
+use std::ops::{Add, AddAssign, Mul, Neg, Sub, SubAssign};
+
+/// An amount of money, stored in cents to avoid rounding errors
+#[derive(Copy, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct Money(pub i64);
+
+debug_inspect_impl!(Money);
+
+impl Money {
+    pub const ZERO: Money = Money(0);
+
+    pub const fn new(base: i64) -> Self {
+        Money(base * 100)
+    }
+
+    pub fn cents(self) -> i64 {
+        self.0
+    }
+}
+
+impl Display for Money {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let sign = if self.0 < 0 { "-" } else { "" };
+        let abs = self.0.abs();
+        write!(f, "{}{}.{:02}$", sign, abs / 100, abs % 100)
+    }
+}
+
+impl Debug for Money {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(self, f)
+    }
+}
+
+impl Add for Money {
+    type Output = Money;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        Money(self.0 + rhs.0)
+    }
+}
+
+impl Sub for Money {
+    type Output = Money;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        Money(self.0 - rhs.0)
+    }
+}
+
+impl AddAssign for Money {
+    fn add_assign(&mut self, rhs: Self) {
+        self.0 += rhs.0
+    }
+}
+
+impl SubAssign for Money {
+    fn sub_assign(&mut self, rhs: Self) {
+        self.0 -= rhs.0
+    }
+}
+
+impl Mul<i64> for Money {
+    type Output = Money;
+
+    fn mul(self, rhs: i64) -> Self::Output {
+        Money(self.0 * rhs)
+    }
+}
+
+/// Rounded down to the cent
+impl Mul<f32> for Money {
+    type Output = Money;
+
+    fn mul(self, rhs: f32) -> Self::Output {
+        Money((self.0 as f64 * rhs as f64) as i64)
+    }
+}
+
+impl Neg for Money {
+    type Output = Money;
+
+    fn neg(self) -> Self::Output {
+        Money(-self.0)
+    }
+}
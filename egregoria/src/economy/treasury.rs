@@ -0,0 +1,227 @@
+use crate::economy::Money;
+use geom::OBB;
+use imgui_inspect_derive::*;
+use map_model::{LanePattern, LightPolicy, Map};
+use serde::{Deserialize, Serialize};
+
+/// Money the city starts with
+pub const STARTING_TREASURY: Money = Money::new(1_000_000);
+/// Construction cost of a road per m² of asphalt
+pub const ROAD_COST_PER_AREA: Money = Money::new(5);
+/// How many times more a bridge costs than a road of the same size
+pub const BRIDGE_COST_FACTOR: f32 = 4.0;
+/// Construction cost of a building per m² of lot
+pub const BUILDING_COST_PER_AREA: Money = Money::new(5);
+pub const STOP_SIGNS_COST: Money = Money::new(500);
+pub const TRAFFIC_LIGHTS_COST: Money = Money::new(5000);
+/// Cost of moving a m³ of earth when terraforming
+pub const TERRAFORM_COST_PER_VOLUME: Money = Money(10);
+/// Road upkeep paid each day per m² of asphalt, in cents
+pub const ROAD_UPKEEP_PER_AREA: f32 = 0.1;
+/// Number of refused commands remembered for the UI
+const MAX_REFUSALS: usize = 10;
+
+/// Construction cost of a road of the given length
+pub fn road_cost(length: f32, pattern: &LanePattern) -> Money {
+    ROAD_COST_PER_AREA * (length * pattern.width())
+}
+
+/// Construction cost of a bridge of the given length
+pub fn bridge_cost(length: f32, pattern: &LanePattern) -> Money {
+    ROAD_COST_PER_AREA * (BRIDGE_COST_FACTOR * length * pattern.width())
+}
+
+/// Construction cost of a building covering the given lot
+pub fn building_cost(lot: &OBB) -> Money {
+    let [a, b] = lot.axis();
+    BUILDING_COST_PER_AREA * (a.magnitude() * b.magnitude())
+}
+
+/// Cost of moving the given volume of earth, in m³
+pub fn terraform_cost(volume: f32) -> Money {
+    TERRAFORM_COST_PER_VOLUME * volume
+}
+
+/// Cost of changing the light policy of an intersection. Removing signs and lights is free.
+pub fn light_policy_cost(old: LightPolicy, new: LightPolicy) -> Money {
+    let level = |lp| match lp {
+        LightPolicy::NoLights => 0,
+        LightPolicy::StopSigns => 1,
+        LightPolicy::Lights | LightPolicy::Auto => 2,
+    };
+    if level(new) <= level(old) {
+        return Money::ZERO;
+    }
+    match new {
+        LightPolicy::StopSigns => STOP_SIGNS_COST,
+        _ => TRAFFIC_LIGHTS_COST,
+    }
+}
+
+/// Maintenance cost of all the roads of the map for one day
+pub fn road_upkeep(map: &Map) -> Money {
+    let area: f32 = map.roads().values().map(|r| r.length() * r.width).sum();
+    Money((area * ROAD_UPKEEP_PER_AREA) as i64)
+}
+
+#[derive(Inspect, Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct Taxes {
+    /// Fraction of the wages of the residents paid to the city
+    pub income: f32,
+    /// Fraction of the daily profit of the companies paid to the city
+    pub company: f32,
+}
+
+impl Default for Taxes {
+    fn default() -> Self {
+        Self {
+            income: 0.15,
+            company: 0.2,
+        }
+    }
+}
+
+/// Money earned and spent by the city during one day
+#[derive(Inspect, Copy, Clone, Debug, Default, Serialize, Deserialize)]
+pub struct CityAccounts {
+    pub taxes: Money,
+    pub construction: Money,
+    /// Upkeep of the roads
+    pub upkeep: Money,
+    /// Wages and upkeep of the public services
+    pub services: Money,
+}
+
+impl CityAccounts {
+    pub fn balance(&self) -> Money {
+        self.taxes - self.construction - self.upkeep - self.services
+    }
+}
+
+/// A command that was not applied and why, to be shown to the player
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Refusal {
+    pub tick: u32,
+    pub reason: String,
+}
+
+register_resource!(Treasury, "treasury");
+/// Money of the city. It pays for construction, the upkeep of the roads and the public
+/// services, and is filled by taxes on the residents and the companies.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct Treasury {
+    pub money: Money,
+    pub taxes: Taxes,
+    /// Nothing costs anything and no command is refused
+    pub sandbox: bool,
+    pub today: CityAccounts,
+    pub yesterday: CityAccounts,
+    /// Last commands refused because the city could not afford them, most recent last
+    pub refusals: Vec<Refusal>,
+}
+
+impl Default for Treasury {
+    fn default() -> Self {
+        Self {
+            money: STARTING_TREASURY,
+            taxes: Taxes::default(),
+            sandbox: false,
+            today: CityAccounts::default(),
+            yesterday: CityAccounts::default(),
+            refusals: vec![],
+        }
+    }
+}
+
+impl Treasury {
+    pub fn can_afford(&self, cost: Money) -> bool {
+        self.sandbox || cost <= self.money
+    }
+
+    /// Pays for a construction. Returns false if the city cannot afford it.
+    pub fn pay_construction(&mut self, cost: Money) -> bool {
+        if self.sandbox {
+            return true;
+        }
+        if !self.can_afford(cost) {
+            return false;
+        }
+        self.money -= cost;
+        self.today.construction += cost;
+        true
+    }
+
+    pub fn pay_upkeep(&mut self, cost: Money) {
+        self.spend(cost);
+        self.today.upkeep += cost;
+    }
+
+    pub fn pay_services(&mut self, cost: Money) {
+        self.spend(cost);
+        self.today.services += cost;
+    }
+
+    pub fn collect(&mut self, taxes: Money) {
+        self.money += taxes;
+        self.today.taxes += taxes;
+    }
+
+    /// Running costs are paid even if the city goes into debt
+    fn spend(&mut self, cost: Money) {
+        if !self.sandbox {
+            self.money -= cost;
+        }
+    }
+
+    pub fn close_day(&mut self) {
+        self.yesterday = std::mem::take(&mut self.today);
+    }
+
+    pub fn refuse(&mut self, tick: u32, reason: String) {
+        log::info!("{}", reason);
+        self.refusals.push(Refusal { tick, reason });
+        if self.refusals.len() > MAX_REFUSALS {
+            self.refusals.remove(0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Treasury;
+    use crate::economy::{Money, STARTING_TREASURY};
+
+    #[test]
+    fn test_pay_construction() {
+        let mut treasury = Treasury::default();
+
+        assert!(treasury.pay_construction(Money::new(1000)));
+        assert_eq!(treasury.money, STARTING_TREASURY - Money::new(1000));
+        assert_eq!(treasury.today.construction, Money::new(1000));
+
+        assert!(!treasury.pay_construction(treasury.money + Money::new(1)));
+        assert_eq!(treasury.today.construction, Money::new(1000));
+
+        treasury.sandbox = true;
+        assert!(treasury.pay_construction(treasury.money + Money::new(1)));
+        assert_eq!(treasury.money, STARTING_TREASURY - Money::new(1000));
+    }
+
+    #[test]
+    fn test_running_costs_go_into_debt() {
+        let mut treasury = Treasury::default();
+
+        treasury.pay_upkeep(STARTING_TREASURY);
+        treasury.pay_services(Money::new(100));
+        treasury.collect(Money::new(40));
+        assert_eq!(treasury.money, Money::new(-60));
+        assert_eq!(
+            treasury.today.balance(),
+            Money::new(40) - STARTING_TREASURY - Money::new(100)
+        );
+
+        treasury.close_day();
+        assert_eq!(treasury.yesterday.upkeep, STARTING_TREASURY);
+        assert_eq!(treasury.today.balance(), Money::ZERO);
+    }
+}
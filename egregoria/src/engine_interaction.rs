@@ -1,7 +1,9 @@
 use crate::{ent_from_id, ent_id, Egregoria};
+use map_model::procgen::{CityGenParams, OsmData, OsmReport};
 use map_model::{
-    BuildingGen, BuildingID, BuildingKind, IntersectionID, LanePattern, LightPolicy, LotID, Map,
-    MapProject, RoadID, TurnPolicy,
+    BuildingGen, BuildingID, BuildingKind, HeightEdit, IntersectionID, LanePattern, LightPolicy,
+    LotID, LotKind, Map, MapDescription, MapProject, ProjectFilter, ProjectKind, RoadID,
+    RoadSegmentKind, TerraformKind, TurnPolicy, MAX_ROAD_SLOPE,
 };
 use serde::{Deserialize, Serialize};
 
@@ -34,21 +36,68 @@ pub enum WorldCommand {
     MapRemoveRoad(RoadID),
     MapRemoveBuilding(BuildingID),
     MapBuildHouse(LotID),
+    MapSetLotKind(LotID, LotKind),
     MapMakeConnection(MapProject, MapProject, Option<Vec2>, LanePattern),
+    MapMakeBridge(MapProject, MapProject, Option<Vec2>, LanePattern),
     MapUpdateIntersectionPolicy(IntersectionID, TurnPolicy, LightPolicy),
     MapBuildSpecialBuilding(RoadID, OBB, BuildingKind, BuildingGen),
+    /// Roads, intersection policies and buildings of a blueprint placed on the map
+    MapPasteBlueprint(MapDescription),
     MapLoadParis,
+    MapLoadOsm(OsmData),
+    MapLoadJson(MapDescription),
     MapLoadTestField(Vec2, u32, f32),
+    MapGenerateCity(Vec2, CityGenParams),
+    MapTerraform(Vec2, f32, TerraformKind, f32),
+    MapRepair,
     ResetSave,
     SetGameTime(GameTime),
+    SetParkingSearch(bool),
+    SetBirthsAndDeaths(bool),
+    SetBankruptcyDelay(u32),
+    SetServiceFunding(f32),
+    SetTaxes(Taxes),
+    SetSandbox(bool),
     MapGenerateTrees(AABB),
     UpdateTransform(u64, Transform),
+    // Inverses of the map commands, used to undo them. They refer to the map objects by
+    // position since their IDs change when they are rebuilt.
+    /// Source, destination, segment, pattern and whether the road is a bridge
+    MapRestoreRoad(Vec2, Vec2, RoadSegmentKind, LanePattern, bool),
+    MapRemoveRoadAt(Vec2, Vec2),
+    MapRestoreBuilding(OBB, Vec2, BuildingKind, BuildingGen, u32),
+    MapRemoveBuildingAt(Vec2),
+    MapSetPolicyAt(Vec2, TurnPolicy, LightPolicy),
+    MapSetLotKindAt(Vec2, LotKind),
+    MapSetHeights(Vec<HeightEdit>),
+    /// Commands undoing the last action of a player, their inverse is a `Redo`
+    Undo(Vec<WorldCommand>),
+    /// Commands redoing the last undone action of a player, their inverse is an `Undo`
+    Redo(Vec<WorldCommand>),
 }
 
-use crate::map_dynamic::BuildingInfos;
+register_resource_noserialize!(LastUndo);
+/// Commands undoing each of the commands applied during the last tick, in the same order.
+/// Empty for the commands that cannot be undone.
+#[derive(Default)]
+pub struct LastUndo(pub Vec<Vec<WorldCommand>>);
+
+register_resource_noserialize!(LastOsmReport);
+/// What the last OpenStreetMap import built and skipped, to be shown to the player
+#[derive(Default)]
+pub struct LastOsmReport(pub Option<OsmReport>);
+
+use crate::economy::{
+    bridge_cost, building_cost, light_policy_cost, road_cost, terraform_cost, Bankruptcy, Money,
+    Taxes, Treasury,
+};
+use crate::map_dynamic::{BuildingInfos, ParkingManagement};
+use crate::souls::migration::Migration;
+use crate::souls::services::ServiceBudget;
 use crate::utils::time::GameTime;
 use geom::{Transform, Vec2, AABB, OBB};
 use legion::Entity;
+use std::collections::BTreeSet;
 use WorldCommand::*;
 
 impl WorldCommands {
@@ -72,10 +121,26 @@ impl WorldCommands {
         self.commands.push(MapLoadParis)
     }
 
+    pub fn map_load_osm(&mut self, data: OsmData) {
+        self.commands.push(MapLoadOsm(data))
+    }
+
+    pub fn map_load_json(&mut self, desc: MapDescription) {
+        self.commands.push(MapLoadJson(desc))
+    }
+
     pub fn map_load_testfield(&mut self, pos: Vec2, size: u32, spacing: f32) {
         self.commands.push(MapLoadTestField(pos, size, spacing))
     }
 
+    pub fn map_generate_city(&mut self, pos: Vec2, params: CityGenParams) {
+        self.commands.push(MapGenerateCity(pos, params))
+    }
+
+    pub fn map_repair(&mut self) {
+        self.commands.push(MapRepair)
+    }
+
     pub fn update_transform(&mut self, e: Entity, trans: Transform) {
         self.commands.push(UpdateTransform(ent_id(e), trans))
     }
@@ -88,6 +153,34 @@ impl WorldCommands {
         self.commands.push(SetGameTime(gt))
     }
 
+    pub fn set_parking_search(&mut self, search_on_arrival: bool) {
+        self.commands.push(SetParkingSearch(search_on_arrival))
+    }
+
+    pub fn set_births_and_deaths(&mut self, enabled: bool) {
+        self.commands.push(SetBirthsAndDeaths(enabled))
+    }
+
+    pub fn set_bankruptcy_delay(&mut self, unprofitable_days: u32) {
+        self.commands.push(SetBankruptcyDelay(unprofitable_days))
+    }
+
+    pub fn set_service_funding(&mut self, funding: f32) {
+        self.commands.push(SetServiceFunding(funding))
+    }
+
+    pub fn set_taxes(&mut self, taxes: Taxes) {
+        self.commands.push(SetTaxes(taxes))
+    }
+
+    pub fn set_sandbox(&mut self, sandbox: bool) {
+        self.commands.push(SetSandbox(sandbox))
+    }
+
+    pub fn map_set_lot_kind(&mut self, lot: LotID, kind: LotKind) {
+        self.commands.push(MapSetLotKind(lot, kind))
+    }
+
     pub fn map_build_special_building(
         &mut self,
         id: RoadID,
@@ -126,6 +219,27 @@ impl WorldCommands {
             .push(MapMakeConnection(from, to, interpoint, pat))
     }
 
+    pub fn map_make_bridge(
+        &mut self,
+        from: MapProject,
+        to: MapProject,
+        interpoint: Option<Vec2>,
+        pat: LanePattern,
+    ) {
+        self.commands.push(MapMakeBridge(from, to, interpoint, pat))
+    }
+
+    /// Builds the roads of the layout, joined to the roads and intersections already there,
+    /// then its buildings. The roads crossing water are built as bridges.
+    pub fn map_paste_blueprint(&mut self, layout: MapDescription) {
+        self.commands.push(MapPasteBlueprint(layout))
+    }
+
+    pub fn map_terraform(&mut self, center: Vec2, radius: f32, kind: TerraformKind, amount: f32) {
+        self.commands
+            .push(MapTerraform(center, radius, kind, amount))
+    }
+
     pub fn map_update_intersection_policy(
         &mut self,
         id: IntersectionID,
@@ -134,44 +248,522 @@ impl WorldCommands {
     ) {
         self.commands.push(MapUpdateIntersectionPolicy(id, tp, lp))
     }
+
+    pub fn undo(&mut self, commands: Vec<WorldCommand>) {
+        self.commands.push(Undo(commands))
+    }
+
+    pub fn redo(&mut self, commands: Vec<WorldCommand>) {
+        self.commands.push(Redo(commands))
+    }
+}
+
+/// Where to connect a restored road: the intersection at `pos` if it still exists,
+/// a road passing there or the ground
+fn project_at(map: &Map, pos: Vec2) -> MapProject {
+    if let Some(id) = map.intersection_at(pos) {
+        return MapProject {
+            pos,
+            kind: ProjectKind::Inter(id),
+        };
+    }
+    let road = map
+        .spatial_map()
+        .query_around(pos, 0.1, ProjectFilter::ROAD)
+        .find_map(|k| match k {
+            ProjectKind::Road(id) => map.roads().get(id),
+            _ => None,
+        });
+    match road {
+        Some(r) => MapProject {
+            pos: r.points.project(pos),
+            kind: ProjectKind::Road(r.id),
+        },
+        None => MapProject::ground(pos),
+    }
+}
+
+/// Projections made before the commands of the same tick were applied may be outdated:
+/// the intersections built since then are reused and the roads split since then are found again
+fn reproject(map: &Map, proj: MapProject) -> MapProject {
+    match proj.kind {
+        ProjectKind::Ground => project_at(map, proj.pos),
+        ProjectKind::Road(_) if !proj.kind.check_valid(map) => project_at(map, proj.pos),
+        _ => proj,
+    }
+}
+
+/// Commands rebuilding the road and the policies of its intersections
+fn restore_road(map: &Map, id: RoadID) -> Vec<WorldCommand> {
+    let road = unwrap_or!(map.roads().get(id), return vec![]);
+    let src = unwrap_or!(map.intersections().get(road.src), return vec![]);
+    let dst = unwrap_or!(map.intersections().get(road.dst), return vec![]);
+    vec![
+        MapRestoreRoad(
+            src.pos,
+            dst.pos,
+            road.segment,
+            road.pattern(map.lanes()),
+            road.bridge,
+        ),
+        MapSetPolicyAt(src.pos, src.turn_policy, src.light_policy),
+        MapSetPolicyAt(dst.pos, dst.turn_policy, dst.light_policy),
+    ]
+}
+
+fn restore_building(map: &Map, id: BuildingID) -> Vec<WorldCommand> {
+    let b = unwrap_or!(map.buildings().get(id), return vec![]);
+    vec![MapRestoreBuilding(
+        b.obb, b.door_pos, b.kind, b.gen, b.level,
+    )]
+}
+
+fn remove_building_at(map: &Map, id: Option<BuildingID>) -> Vec<WorldCommand> {
+    let b = unwrap_or!(id.and_then(|id| map.buildings().get(id)), return vec![]);
+    vec![MapRemoveBuildingAt(b.obb.center())]
+}
+
+fn remove_road_at(map: &Map, id: Option<RoadID>) -> Vec<WorldCommand> {
+    let road = unwrap_or!(id.and_then(|id| map.roads().get(id)), return vec![]);
+    let src = unwrap_or!(map.intersections().get(road.src), return vec![]);
+    let dst = unwrap_or!(map.intersections().get(road.dst), return vec![]);
+    vec![MapRemoveRoadAt(src.pos, dst.pos)]
+}
+
+/// The command restoring the terrain, or the refusal of the edit
+fn set_heights(goria: &Egregoria, edited: Result<Vec<HeightEdit>, String>) -> Vec<WorldCommand> {
+    match edited {
+        Ok(restore) if restore.is_empty() => vec![],
+        Ok(restore) => vec![MapSetHeights(restore)],
+        Err(reason) => {
+            let tick = goria.get_tick();
+            goria.write::<Treasury>().refuse(tick, reason);
+            vec![]
+        }
+    }
+}
+
+fn road_at(map: &Map, src: Vec2, dst: Vec2) -> Option<RoadID> {
+    map.intersection_at(src)
+        .zip(map.intersection_at(dst))
+        .and_then(|(src, dst)| map.find_road(src, dst))
+}
+
+/// Builds the roads of the layout, the policies of the intersections it creates and its
+/// buildings, each paid for and checked like the command building it.
+/// Returns the commands undoing the paste.
+fn paste_blueprint(
+    goria: &mut Egregoria,
+    layout: &MapDescription,
+    paid: bool,
+) -> Vec<WorldCommand> {
+    let before: BTreeSet<IntersectionID> = goria.map().intersections().keys().collect();
+    // intersections of the map the intersections of the layout were built on
+    let mut inters: Vec<Option<IntersectionID>> = vec![None; layout.intersections.len()];
+    let mut roads = Vec::with_capacity(layout.roads.len());
+    let mut undos = vec![];
+
+    for road in &layout.roads {
+        let map = goria.map();
+        let project = |i: usize| {
+            let pos = layout.intersections.get(i)?.pos;
+            if let Some(inter) = inters.get(i).copied().flatten() {
+                let inter = map.intersections().get(inter)?;
+                return Some(MapProject {
+                    pos: inter.pos,
+                    kind: ProjectKind::Inter(inter.id),
+                });
+            }
+            let mut proj = map.project(pos, 0.0);
+            match proj.kind {
+                ProjectKind::Lot(_) => proj.kind = ProjectKind::Ground,
+                ProjectKind::Building(_) | ProjectKind::Water(_) => return None,
+                _ => {}
+            }
+            Some(proj)
+        };
+        let (from, to) = match project(road.src).zip(project(road.dst)) {
+            Some(x) => x,
+            None => {
+                roads.push(None);
+                continue;
+            }
+        };
+        let interpoint = match road.segment {
+            RoadSegmentKind::Straight => None,
+            RoadSegmentKind::Curved((d, _)) => Some(from.pos + d * std::f32::consts::SQRT_2),
+        };
+        let points = road.segment.points(from.pos, to.pos);
+        let command = if map.crosses_water(&points, road.pattern.width()) {
+            MapMakeBridge(from, to, interpoint, road.pattern.clone())
+        } else {
+            MapMakeConnection(from, to, interpoint, road.pattern.clone())
+        };
+        drop(map);
+
+        let undo = command.apply_priced(goria, paid);
+        let map = goria.map();
+        let built = match undo.first() {
+            Some(&MapRemoveRoadAt(src, dst)) => road_at(&map, src, dst),
+            _ => None,
+        };
+        if let Some(r) = built.and_then(|id| map.roads().get(id)) {
+            for (i, inter) in &[(road.src, r.src), (road.dst, r.dst)] {
+                if let Some(x) = inters.get_mut(*i) {
+                    *x = Some(*inter);
+                }
+            }
+        }
+        roads.push(built);
+        undos.push(undo);
+    }
+
+    for (inter, id) in layout.intersections.iter().zip(inters) {
+        let id = unwrap_cont!(id);
+        if before.contains(&id) {
+            continue;
+        }
+        let pos = unwrap_cont!(goria.map().intersections().get(id)).pos;
+        let command = MapSetPolicyAt(pos, inter.turn_policy, inter.light_policy);
+        undos.push(command.apply_priced(goria, paid));
+    }
+
+    for b in &layout.buildings {
+        let road = unwrap_cont!(roads.get(b.road).copied().flatten());
+        let command = MapBuildSpecialBuilding(road, b.obb, b.kind, b.gen);
+        undos.push(command.apply_priced(goria, paid));
+    }
+
+    // the last command applied must be the first one undone
+    undos.into_iter().rev().flatten().collect()
+}
+
+fn connection_length(from: MapProject, to: MapProject, interpoint: Option<Vec2>) -> f32 {
+    match interpoint {
+        Some(p) => from.pos.distance(p) + p.distance(to.pos),
+        None => from.pos.distance(to.pos),
+    }
+}
+
+fn register_building(goria: &Egregoria, id: Option<BuildingID>) {
+    let map = goria.map();
+    let b = unwrap_ret!(id.and_then(|id| map.buildings().get(id)));
+    if let BuildingKind::House = b.kind {
+        goria.write::<BuildingInfos>().insert_house(b);
+    } else {
+        goria.write::<BuildingInfos>().insert(b.id);
+    }
 }
 
 impl WorldCommand {
-    pub(crate) fn apply(&self, goria: &mut Egregoria) {
+    /// What the command builds and how much it costs, None if it is free
+    fn cost(&self, goria: &Egregoria) -> Option<(&'static str, Money)> {
+        let map = goria.map();
+        Some(match *self {
+            MapBuildHouse(id) => ("house", building_cost(&map.lots().get(id)?.shape)),
+            MapMakeConnection(from, to, interpoint, ref pat) => (
+                "road",
+                road_cost(connection_length(from, to, interpoint), pat),
+            ),
+            MapMakeBridge(from, to, interpoint, ref pat) => (
+                "bridge",
+                bridge_cost(connection_length(from, to, interpoint), pat),
+            ),
+            MapRestoreRoad(src, dst, _, ref pat, false) => {
+                ("road", road_cost(src.distance(dst), pat))
+            }
+            MapRestoreRoad(src, dst, _, ref pat, true) => {
+                ("bridge", bridge_cost(src.distance(dst), pat))
+            }
+            MapUpdateIntersectionPolicy(id, _, lp) => {
+                let old = map.intersections().get(id)?.light_policy;
+                ("intersection upgrade", light_policy_cost(old, lp))
+            }
+            MapSetPolicyAt(pos, _, lp) => {
+                let old = map
+                    .intersections()
+                    .get(map.intersection_at(pos)?)?
+                    .light_policy;
+                ("intersection upgrade", light_policy_cost(old, lp))
+            }
+            MapBuildSpecialBuilding(_, obb, _, _) | MapRestoreBuilding(obb, _, _, _, _) => {
+                ("building", building_cost(&obb))
+            }
+            MapTerraform(center, radius, kind, amount) => {
+                let edits = map.heightmap.brush(center, radius, kind, amount);
+                ("terraforming", terraform_cost(map.heightmap.volume(&edits)))
+            }
+            MapSetHeights(ref edits) => {
+                ("terraforming", terraform_cost(map.heightmap.volume(edits)))
+            }
+            _ => return None,
+        })
+    }
+
+    /// Why the road built by the command cannot be built, None if it can or if it builds no road.
+    /// Bridges are flat and may cross water.
+    fn road_refusal(&self, goria: &Egregoria) -> Option<String> {
+        let (from, to, segment, pat) = match *self {
+            MapMakeConnection(from, to, interpoint, ref pat) => {
+                let segment = match interpoint {
+                    Some(x) => RoadSegmentKind::from_elbow(from.pos, to.pos, x),
+                    None => RoadSegmentKind::Straight,
+                };
+                (from.pos, to.pos, segment, pat)
+            }
+            MapRestoreRoad(src, dst, segment, ref pat, false) => (src, dst, segment, pat),
+            _ => return None,
+        };
+        let points = segment.points(from, to);
+        let map = goria.map();
+        if map.crosses_water(&points, pat.width()) {
+            return Some("The road crosses water, build a bridge instead".to_string());
+        }
+        let slope = map.heightmap.max_slope(&points);
+        if slope > MAX_ROAD_SLOPE {
+            return Some(format!(
+                "The road is too steep ({:.0}% slope, the maximum is {:.0}%)",
+                slope * 100.0,
+                MAX_ROAD_SLOPE * 100.0
+            ));
+        }
+        None
+    }
+
+    /// Applies the command, unless the city cannot afford it or the road cannot be built there.
+    /// The construction is paid once it is built.
+    /// Returns the commands undoing it, empty if it cannot be undone.
+    pub(crate) fn apply(&self, goria: &mut Egregoria) -> Vec<WorldCommand> {
+        self.apply_priced(goria, true)
+    }
+
+    /// The commands of an undo or a redo are not `paid` for, they rebuild what was already paid
+    fn apply_priced(&self, goria: &mut Egregoria, paid: bool) -> Vec<WorldCommand> {
+        if let Some(reason) = self.road_refusal(goria) {
+            let tick = goria.get_tick();
+            goria.write::<Treasury>().refuse(tick, reason);
+            return vec![];
+        }
+
+        let cost = if paid { self.cost(goria) } else { None };
+        if let Some((what, cost)) = cost {
+            let tick = goria.get_tick();
+            let mut treasury = goria.write::<Treasury>();
+            if !treasury.can_afford(cost) {
+                let reason = format!(
+                    "Cannot afford the {} ({}), the city only has {}",
+                    what, cost, treasury.money
+                );
+                treasury.refuse(tick, reason);
+                return vec![];
+            }
+        }
+
+        let mut undo = vec![];
         match *self {
-            MapRemoveIntersection(id) => goria.map_mut().remove_intersection(id),
-            MapRemoveRoad(id) => drop(goria.map_mut().remove_road(id)),
-            MapRemoveBuilding(id) => drop(goria.map_mut().remove_building(id)),
+            MapRemoveIntersection(id) => {
+                let mut map = goria.map_mut();
+                if let Some(inter) = map.intersections().get(id) {
+                    for &road in &inter.roads {
+                        undo.extend(restore_road(&map, road));
+                    }
+                }
+                map.remove_intersection(id)
+            }
+            MapRemoveRoad(id) => {
+                let mut map = goria.map_mut();
+                undo = restore_road(&map, id);
+                map.remove_road(id);
+            }
+            MapRemoveRoadAt(src, dst) => {
+                let mut map = goria.map_mut();
+                if let Some(id) = road_at(&map, src, dst) {
+                    undo = restore_road(&map, id);
+                    map.remove_road(id);
+                }
+            }
+            MapRemoveBuilding(id) => {
+                let mut map = goria.map_mut();
+                undo = restore_building(&map, id);
+                map.remove_building(id);
+            }
+            MapRemoveBuildingAt(pos) => {
+                let mut map = goria.map_mut();
+                if let Some(id) = map.building_at(pos) {
+                    undo = restore_building(&map, id);
+                    map.remove_building(id);
+                }
+            }
             MapBuildHouse(id) => {
-                if let Some(build) = goria.map_mut().build_house(id) {
-                    let mut infos = goria.write::<BuildingInfos>();
-                    infos.insert(build);
+                let build = goria.map_mut().build_house(id);
+                undo = remove_building_at(&goria.map(), build);
+                register_building(goria, build);
+            }
+            MapSetLotKind(id, kind) => {
+                let mut map = goria.map_mut();
+                if let Some(lot) = map.lots().get(id) {
+                    undo = vec![MapSetLotKindAt(lot.shape.center(), lot.kind)];
+                }
+                map.set_lot_kind(id, kind)
+            }
+            MapSetLotKindAt(pos, kind) => {
+                let mut map = goria.map_mut();
+                let lot = map.lot_at(pos).and_then(|id| map.lots().get(id));
+                if let Some(lot) = lot {
+                    let id = lot.id;
+                    undo = vec![MapSetLotKindAt(lot.shape.center(), lot.kind)];
+                    map.set_lot_kind(id, kind)
                 }
             }
             MapMakeConnection(from, to, interpoint, ref pat) => {
-                goria
-                    .write::<Map>()
-                    .make_connection(from, to, interpoint, pat);
+                let mut map = goria.map_mut();
+                let (from, to) = (reproject(&map, from), reproject(&map, to));
+                let road = map.make_connection(from, to, interpoint, pat);
+                undo = remove_road_at(&map, road.map(|(_, r)| r));
+            }
+            MapMakeBridge(from, to, interpoint, ref pat) => {
+                let mut map = goria.map_mut();
+                let (from, to) = (reproject(&map, from), reproject(&map, to));
+                let road = map.make_bridge(from, to, interpoint, pat);
+                undo = remove_road_at(&map, road.map(|(_, r)| r));
+            }
+            MapRestoreRoad(src, dst, segment, ref pat, bridge) => {
+                let mut map = goria.map_mut();
+                let interpoint = match segment {
+                    RoadSegmentKind::Straight => None,
+                    RoadSegmentKind::Curved((d, _)) => Some(src + d * std::f32::consts::SQRT_2),
+                };
+                let from = project_at(&map, src);
+                let to = project_at(&map, dst);
+                let road = if bridge {
+                    map.make_bridge(from, to, interpoint, pat)
+                } else {
+                    map.make_connection(from, to, interpoint, pat)
+                };
+                undo = remove_road_at(&map, road.map(|(_, r)| r));
             }
             MapUpdateIntersectionPolicy(id, tp, lp) => {
-                goria.map_mut().update_intersection(id, move |i| {
+                let mut map = goria.map_mut();
+                if let Some(inter) = map.intersections().get(id) {
+                    undo = vec![MapSetPolicyAt(
+                        inter.pos,
+                        inter.turn_policy,
+                        inter.light_policy,
+                    )];
+                }
+                map.update_intersection(id, move |i| {
                     i.light_policy = lp;
                     i.turn_policy = tp;
                 })
             }
+            MapSetPolicyAt(pos, tp, lp) => {
+                let mut map = goria.map_mut();
+                let inter = map
+                    .intersection_at(pos)
+                    .and_then(|id| map.intersections().get(id));
+                if let Some(inter) = inter {
+                    let id = inter.id;
+                    undo = vec![MapSetPolicyAt(
+                        inter.pos,
+                        inter.turn_policy,
+                        inter.light_policy,
+                    )];
+                    map.update_intersection(id, move |i| {
+                        i.light_policy = lp;
+                        i.turn_policy = tp;
+                    })
+                }
+            }
             MapBuildSpecialBuilding(id, obb, kind, gen) => {
-                if let Some(id) = goria
+                let build = goria
                     .write::<Map>()
-                    .build_special_building(id, &obb, kind, gen)
-                {
-                    goria.write::<BuildingInfos>().insert(id);
+                    .build_special_building(id, &obb, kind, gen);
+                undo = remove_building_at(&goria.map(), build);
+                register_building(goria, build);
+            }
+            MapPasteBlueprint(ref layout) => undo = paste_blueprint(goria, layout, paid),
+            MapRestoreBuilding(obb, door_pos, kind, gen, level) => {
+                let mut map = goria.map_mut();
+                let road = map.door_road(door_pos).map(|r| r.id);
+                let build = road.and_then(|r| map.build_special_building(r, &obb, kind, gen));
+                if let Some(id) = build {
+                    map.restore_house_level(id, level);
+                }
+                undo = remove_building_at(&map, build);
+                drop(map);
+                register_building(goria, build);
+            }
+            Undo(ref commands) | Redo(ref commands) => {
+                let inverses: Vec<_> = commands
+                    .iter()
+                    .map(|c| c.apply_priced(goria, false))
+                    .collect();
+                // the last command applied must be the first one undone
+                let inverse: Vec<_> = inverses.into_iter().rev().flatten().collect();
+                // nothing could be undone, the player keeps the entry to try again
+                if !inverse.is_empty() {
+                    undo = vec![match *self {
+                        Undo(_) => Redo(inverse),
+                        _ => Undo(inverse),
+                    }];
                 }
             }
             SetGameTime(gt) => *goria.write::<GameTime>() = gt,
+            SetParkingSearch(v) => goria.write::<ParkingManagement>().search_on_arrival = v,
+            SetBirthsAndDeaths(v) => goria.write::<Migration>().births_and_deaths = v,
+            SetBankruptcyDelay(v) => goria.write::<Bankruptcy>().unprofitable_days = v,
+            SetServiceFunding(v) => goria.write::<ServiceBudget>().funding = v.clamp(0.0, 1.0),
+            SetTaxes(taxes) => {
+                goria.write::<Treasury>().taxes = Taxes {
+                    income: taxes.income.clamp(0.0, 1.0),
+                    company: taxes.company.clamp(0.0, 1.0),
+                }
+            }
+            SetSandbox(v) => goria.write::<Treasury>().sandbox = v,
             MapLoadParis => map_model::procgen::load_parismap(&mut *goria.map_mut()),
+            MapLoadOsm(ref data) => {
+                let report = map_model::procgen::import_osm(&mut *goria.map_mut(), data);
+                goria.write::<LastOsmReport>().0 = Some(report);
+            }
+            MapLoadJson(ref desc) => {
+                let mut map = goria.map_mut();
+                let before: BTreeSet<BuildingID> = map.buildings().keys().collect();
+                if let Err(e) = map.load_description(desc) {
+                    log::error!("could not load the map description: {}", e);
+                    return vec![];
+                }
+                let mut infos = goria.write::<BuildingInfos>();
+                for b in map.buildings().values() {
+                    if before.contains(&b.id) {
+                        continue;
+                    }
+                    if let BuildingKind::House = b.kind {
+                        infos.insert_house(b);
+                    } else {
+                        infos.insert(b.id);
+                    }
+                }
+            }
             MapLoadTestField(pos, size, spacing) => {
                 map_model::procgen::load_testfield(&mut *goria.map_mut(), pos, size, spacing)
             }
+            MapGenerateCity(pos, ref params) => {
+                map_model::procgen::generate_city(&mut *goria.map_mut(), pos, params)
+            }
+            MapRepair => {
+                goria.map_mut().repair();
+            }
+            MapTerraform(center, radius, kind, amount) => {
+                let mut map = goria.map_mut();
+                let edits = map.heightmap.brush(center, radius, kind, amount);
+                let edited = map.set_heights(&edits);
+                drop(map);
+                undo = set_heights(goria, edited);
+            }
+            MapSetHeights(ref edits) => {
+                let edited = goria.map_mut().set_heights(edits);
+                undo = set_heights(goria, edited);
+            }
             ResetSave => {
                 *goria = Egregoria::empty();
             }
@@ -184,6 +776,15 @@ impl WorldCommand {
                 }
             }
         }
+
+        // Only what was actually built is paid for, the commands building something
+        // can be undone when they succeed
+        if let Some((_, cost)) = cost {
+            if !undo.is_empty() {
+                goria.write::<Treasury>().pay_construction(cost);
+            }
+        }
+        undo
     }
 }
 
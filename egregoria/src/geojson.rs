@@ -0,0 +1,79 @@
+use crate::map_dynamic::Itinerary;
+use crate::pedestrians::{Location, Pedestrian};
+use crate::physics::Kinematics;
+use crate::vehicles::Vehicle;
+use crate::Egregoria;
+use geom::Transform;
+use legion::{Entity, IntoQuery};
+use map_model::geojson::{feature, feature_collection, id, line_string, map_features, point};
+use map_model::{LaneID, TraverseKind};
+use serde_json::{json, Value};
+use slotmap::SecondaryMap;
+
+/// GeoJSON of the map. With `agents`, the vehicles and the pedestrians walking outside
+/// are added as points, along with the number of vehicles and their mean speed on each lane.
+pub fn to_geojson(goria: &Egregoria, agents: bool) -> Value {
+    let map = goria.map();
+    let mut features = map_features(&map);
+
+    if agents {
+        // (number of vehicles, sum of their speeds)
+        let mut traffic: SecondaryMap<LaneID, (u32, f32)> = SecondaryMap::new();
+
+        for (e, trans, kin, vehicle, itin) in
+            <(Entity, &Transform, &Kinematics, &Vehicle, &Itinerary)>::query().iter(goria.world())
+        {
+            let speed = kin.velocity.magnitude();
+            features.push(feature(
+                "vehicle",
+                point(trans.position()),
+                json!({
+                    "entity": format!("{:?}", e),
+                    "kind": format!("{:?}", vehicle.kind),
+                    "state": format!("{:?}", vehicle.state),
+                    "speed": speed,
+                }),
+            ));
+
+            if let Some(TraverseKind::Lane(lane)) = itin.get_travers().map(|t| t.kind) {
+                if let Some(entry) = traffic.entry(lane) {
+                    let (n, total) = entry.or_insert((0, 0.0));
+                    *n += 1;
+                    *total += speed;
+                }
+            }
+        }
+
+        for (e, trans, kin, ped, loc) in
+            <(Entity, &Transform, &Kinematics, &Pedestrian, &Location)>::query().iter(goria.world())
+        {
+            if !matches!(loc, Location::Outside) {
+                continue;
+            }
+            features.push(feature(
+                "pedestrian",
+                point(trans.position()),
+                json!({
+                    "entity": format!("{:?}", e),
+                    "speed": kin.velocity.magnitude(),
+                    "walking_speed": ped.walking_speed,
+                }),
+            ));
+        }
+
+        for (lane_id, lane) in map.lanes() {
+            let (n, total) = traffic.get(lane_id).copied().unwrap_or((0, 0.0));
+            features.push(feature(
+                "lane_traffic",
+                line_string(lane.points.iter()),
+                json!({
+                    "lane": id(lane_id),
+                    "vehicles": n,
+                    "mean_speed": if n > 0 { total / n as f32 } else { 0.0 },
+                }),
+            ));
+        }
+    }
+
+    feature_collection(features)
+}
@@ -5,17 +5,23 @@
 #![deny(clippy::indexing_slicing)]
 #![deny(clippy::unwrap_used)]
 
-use crate::economy::{Bought, Sold, Workers};
-use crate::engine_interaction::{Selectable, WorldCommands};
-use crate::map_dynamic::{Itinerary, Router};
+use crate::economy::{Bought, Ledger, Occupation, Sold, Workers};
+use crate::engine_interaction::{LastUndo, Selectable, WorldCommands};
+use crate::map_dynamic::{free_removed_lot_spots, land_value_update, Itinerary, Router};
 use crate::pedestrians::Pedestrian;
 use crate::physics::CollisionWorld;
 use crate::physics::{Collider, Kinematics};
 use crate::rendering::assets::AssetRender;
 use crate::souls::add_souls_to_empty_buildings;
-use crate::souls::desire::{BuyFood, Home, Work};
+use crate::souls::desire::DesireRegistration;
 use crate::souls::goods_company::GoodsCompany;
+use crate::souls::household::{Household, HouseholdMember};
 use crate::souls::human::HumanDecision;
+use crate::souls::migration::migration;
+use crate::souls::needs::Needs;
+use crate::souls::schedule::Agenda;
+use crate::souls::services::{services_update, PublicService, ServiceVehicle};
+use crate::souls::zoning::zoning;
 use crate::vehicles::Vehicle;
 use atomic_refcell::{AtomicRef, AtomicRefMut};
 use common::saveload::Encoder;
@@ -133,6 +139,7 @@ extern crate log as extern_log;
 
 pub mod economy;
 pub mod engine_interaction;
+pub mod geojson;
 pub mod map_dynamic;
 pub mod pedestrians;
 pub mod physics;
@@ -243,12 +250,16 @@ impl Egregoria {
             *time = GameTime::new(WORLD_TICK_DT, time.timestamp + WORLD_TICK_DT as f64);
         }
 
-        for command in &commands.commands {
-            command.apply(self);
-        }
+        let undo = commands.commands.iter().map(|c| c.apply(self)).collect();
+        *self.write::<LastUndo>() = LastUndo(undo);
+        free_removed_lot_spots(self);
 
         game_schedule.execute(self);
+        zoning(self);
+        land_value_update(self);
+        services_update(self);
         add_souls_to_empty_buildings(self);
+        migration(self);
         t.elapsed()
     }
 
@@ -277,7 +288,7 @@ impl Egregoria {
         hashes
     }
 
-    pub fn load_from_disk(save_name: &'static str) -> Option<Self> {
+    pub fn load_from_disk(save_name: &str) -> Option<Self> {
         let ser: SerPreparedEgregoria = common::saveload::CompressedBincode::load(save_name)?;
         if ser.version != goria_version::VERSION {
             log::error!(
@@ -290,7 +301,7 @@ impl Egregoria {
         Self::try_from(ser).ok()
     }
 
-    pub fn save_to_disk(&self, save_name: &'static str) {
+    pub fn save_to_disk(&self, save_name: &str) {
         let ser = match SerPreparedEgregoria::try_from(self) {
             Ok(x) => x,
             Err(e) => {
@@ -457,26 +468,35 @@ pub struct NoSerialize;
 fn registry() -> Registry<u64> {
     let mut registry = Registry::default();
     register!(registry;
+        Agenda,
         AssetRender,
         Bought,
-        BuyFood,
         Collider,
         GoodsCompany,
-        Home,
+        Household,
+        HouseholdMember,
         HumanDecision,
         Itinerary,
         Kinematics,
+        Ledger,
         Location,
+        Needs,
+        Occupation,
         Pedestrian,
+        PublicService,
         Router,
         Selectable,
+        ServiceVehicle,
         Sold,
         Transform,
         Vehicle,
-        Work,
         Workers,
     );
 
+    for desire in inventory::iter::<DesireRegistration> {
+        (desire.register)(&mut registry);
+    }
+
     registry
 }
 
@@ -0,0 +1,133 @@
+use crate::map_dynamic::{cell_of, Cell};
+use crate::utils::time::GameTime;
+use crate::vehicles::{Vehicle, VehicleKind, VehicleState};
+use geom::{Transform, Vec2};
+use legion::world::SubWorld;
+use legion::{system, Query};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Time between two diffusion steps of the fields, in seconds
+const DIFFUSION_PERIOD: f32 = 10.0;
+/// Fraction of a cell that spreads to its neighbours every second
+const DIFFUSION_RATE: f32 = 0.004;
+
+/// Time for pollution to mostly disappear, in seconds
+const POLLUTION_LIFETIME: f32 = GameTime::DAY as f32;
+/// Pollution above which a cell is unbearable
+const POLLUTION_NORM: f32 = 4.0;
+/// Pollution emitted by a driving vehicle every second
+const VEHICLE_POLLUTION: f32 = 0.005;
+
+/// Time for noise to fade away, in seconds
+const NOISE_LIFETIME: f32 = 60.0;
+/// Noise above which a cell is unbearable, about 2 vehicles always driving in it
+const NOISE_NORM: f32 = 2.0 * NOISE_LIFETIME;
+
+/// Cells with less than this are removed from the fields
+const EPSILON: f32 = 0.01;
+
+/// Quantity spread on the cells of the map that fades away and diffuses over time
+#[derive(Clone, Default, Serialize, Deserialize, Debug)]
+pub struct Field {
+    cells: BTreeMap<Cell, f32>,
+}
+
+impl Field {
+    pub fn add(&mut self, pos: Vec2, amount: f32) {
+        *self.cells.entry(cell_of(pos)).or_default() += amount;
+    }
+
+    pub fn get(&self, cell: Cell) -> f32 {
+        self.cells.get(&cell).copied().unwrap_or(0.0)
+    }
+
+    pub fn cells(&self) -> impl Iterator<Item = (Cell, f32)> + '_ {
+        self.cells.iter().map(|(&c, &v)| (c, v))
+    }
+
+    fn diffuse(&mut self, dt: f32, lifetime: f32) {
+        let keep = (-dt / lifetime).exp();
+        let spread = (DIFFUSION_RATE * dt).min(0.5);
+
+        let mut next: BTreeMap<Cell, f32> = BTreeMap::new();
+        for (&(x, y), &v) in &self.cells {
+            let v = v * keep;
+            *next.entry((x, y)).or_default() += v * (1.0 - spread);
+            for &n in &[(x + 1, y), (x - 1, y), (x, y + 1), (x, y - 1)] {
+                *next.entry(n).or_default() += v * spread * 0.25;
+            }
+        }
+        next.retain(|_, v| *v >= EPSILON);
+        self.cells = next;
+    }
+}
+
+register_resource!(Environment, "environment");
+/// Pollution emitted by the factories and the vehicles, and noise made by the traffic
+#[derive(Clone, Default, Serialize, Deserialize, Debug)]
+pub struct Environment {
+    pub pollution: Field,
+    pub noise: Field,
+    /// Time since the fields last diffused
+    since_diffusion: f32,
+}
+
+impl Environment {
+    /// Pollution at `pos` in [0; 1]
+    pub fn pollution(&self, pos: Vec2) -> f32 {
+        self.pollution_at(cell_of(pos))
+    }
+
+    /// Noise at `pos` in [0; 1]
+    pub fn noise(&self, pos: Vec2) -> f32 {
+        self.noise_at(cell_of(pos))
+    }
+
+    pub fn pollution_at(&self, cell: Cell) -> f32 {
+        (self.pollution.get(cell) / POLLUTION_NORM).min(1.0)
+    }
+
+    pub fn noise_at(&self, cell: Cell) -> f32 {
+        (self.noise.get(cell) / NOISE_NORM).min(1.0)
+    }
+
+    /// How unpleasant it is to live at `pos`, in [0; 1]
+    pub fn nuisance(&self, pos: Vec2) -> f32 {
+        (self.pollution(pos) + 0.5 * self.noise(pos)).min(1.0)
+    }
+}
+
+register_system!(environment_update);
+#[system]
+pub fn environment_update(
+    #[resource] time: &GameTime,
+    #[resource] env: &mut Environment,
+    qry: &mut Query<(&Vehicle, &Transform)>,
+    sw: &SubWorld,
+) {
+    let delta = time.delta;
+    let Environment {
+        ref mut pollution,
+        ref mut noise,
+        ..
+    } = *env;
+    qry.for_each(sw, |(v, trans)| {
+        if !matches!(v.state, VehicleState::Driving | VehicleState::Panicking(_)) {
+            return;
+        }
+        let loudness = match v.kind {
+            VehicleKind::Car => 1.0,
+            VehicleKind::Truck | VehicleKind::Bus => 3.0,
+        };
+        noise.add(trans.position(), loudness * delta);
+        pollution.add(trans.position(), loudness * VEHICLE_POLLUTION * delta);
+    });
+
+    env.since_diffusion += delta;
+    if env.since_diffusion >= DIFFUSION_PERIOD {
+        let dt = std::mem::take(&mut env.since_diffusion);
+        env.pollution.diffuse(dt, POLLUTION_LIFETIME);
+        env.noise.diffuse(dt, NOISE_LIFETIME);
+    }
+}
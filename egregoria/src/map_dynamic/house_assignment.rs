@@ -1,13 +1,48 @@
-use crate::SoulID;
-use map_model::BuildingID;
+use crate::map_dynamic::Router;
+use crate::pedestrians::Location;
+use crate::souls::desire::{Desire, Home};
+use crate::souls::household::{leave_city, Household};
+use crate::{ParCommandBuffer, SoulID};
+use geom::{Transform, Vec2};
+use legion::world::SubWorld;
+use legion::{system, Entity, EntityStore};
+use map_model::{Building, BuildingID, BuildingKind, Map};
+use ordered_float::OrderedFloat;
 use serde::{Deserialize, Serialize};
 use slotmap::SecondaryMap;
 use std::collections::BTreeMap;
 
+/// Lot area needed per resident, in m²
+pub const AREA_PER_RESIDENT: f32 = 100.0;
+pub const MAX_HOUSE_CAPACITY: u32 = 16;
+
+/// Number of people that can live in the house, derived from the size of its lot.
+/// Each level added by densification houses as many people as the original house.
+pub fn house_capacity(house: &Building) -> u32 {
+    let [a, b] = house.obb.axis();
+    let base =
+        ((a.magnitude() * b.magnitude() / AREA_PER_RESIDENT) as u32).clamp(1, MAX_HOUSE_CAPACITY);
+    base * (1 + house.level)
+}
+
 #[derive(Clone, Default, Serialize, Deserialize, Debug)]
 pub struct BuildingInfo {
     pub owner: Option<SoulID>,
     pub inside: Vec<SoulID>,
+    /// Households living in the building along with their number of members
+    pub residents: Vec<(SoulID, u32)>,
+    /// Number of people that can live in the building, 0 if it is not a house
+    pub capacity: u32,
+}
+
+impl BuildingInfo {
+    pub fn n_residents(&self) -> u32 {
+        self.residents.iter().map(|&(_, n)| n).sum()
+    }
+
+    pub fn free_capacity(&self) -> u32 {
+        self.capacity.saturating_sub(self.n_residents())
+    }
 }
 
 register_resource!(BuildingInfos, "binfos");
@@ -15,6 +50,7 @@ register_resource!(BuildingInfos, "binfos");
 pub struct BuildingInfos {
     assignment: SecondaryMap<BuildingID, BuildingInfo>,
     owners: BTreeMap<SoulID, BuildingID>,
+    homes: BTreeMap<SoulID, BuildingID>,
 }
 
 impl BuildingInfos {
@@ -22,6 +58,27 @@ impl BuildingInfos {
         self.assignment.insert(building, BuildingInfo::default());
     }
 
+    pub fn insert_house(&mut self, house: &Building) {
+        self.assignment.insert(
+            house.id,
+            BuildingInfo {
+                capacity: house_capacity(house),
+                ..Default::default()
+            },
+        );
+    }
+
+    pub fn remove(&mut self, building: BuildingID) -> Option<BuildingInfo> {
+        let info = self.assignment.remove(building)?;
+        if let Some(owner) = info.owner {
+            self.owners.remove(&owner);
+        }
+        for (household, _) in &info.residents {
+            self.homes.remove(household);
+        }
+        Some(info)
+    }
+
     pub fn get(&self, building: BuildingID) -> Option<&BuildingInfo> {
         self.assignment.get(building)
     }
@@ -41,6 +98,82 @@ impl BuildingInfos {
         self.owners.insert(soul, building);
     }
 
+    /// The soul does not own its building anymore, which can be taken by someone else
+    pub fn remove_owner(&mut self, soul: SoulID) {
+        let building = unwrap_ret!(self.owners.remove(&soul));
+        if let Some(x) = self.get_mut(building) {
+            x.owner = None
+        }
+    }
+
+    pub fn home_of(&self, household: SoulID) -> Option<BuildingID> {
+        self.homes.get(&household).copied()
+    }
+
+    /// Moves the household in the house, leaving its previous home.
+    /// Returns false if there is not enough room left, in which case nothing changes.
+    pub fn move_in(&mut self, house: BuildingID, household: SoulID, size: u32) -> bool {
+        let free = unwrap_ret!(self.get(house), false).free_capacity();
+        if free < size {
+            return false;
+        }
+        self.move_out(household);
+
+        let info = unwrap_ret!(self.get_mut(house), false);
+        info.residents.push((household, size));
+        self.homes.insert(household, house);
+        true
+    }
+
+    /// Returns the house the household left, if it had one
+    pub fn move_out(&mut self, household: SoulID) -> Option<BuildingID> {
+        let house = self.homes.remove(&household)?;
+        if let Some(info) = self.get_mut(house) {
+            info.residents.retain(|&(h, _)| h != household);
+        }
+        Some(house)
+    }
+
+    /// Counts one more member in the household, for example after a birth.
+    /// Returns false if there is no room left in its house.
+    pub fn add_resident(&mut self, household: SoulID) -> bool {
+        let house = unwrap_ret!(self.home_of(household), false);
+        let info = unwrap_ret!(self.get_mut(house), false);
+        if info.free_capacity() == 0 {
+            return false;
+        }
+        for (h, n) in &mut info.residents {
+            if *h == household {
+                *n += 1;
+            }
+        }
+        true
+    }
+
+    pub fn remove_resident(&mut self, household: SoulID) {
+        let house = unwrap_ret!(self.home_of(household));
+        let info = unwrap_ret!(self.get_mut(house));
+        for (h, n) in &mut info.residents {
+            if *h == household {
+                *n = n.saturating_sub(1);
+            }
+        }
+    }
+
+    /// Closest house to `near` where `size` more people can live
+    pub fn find_house(&self, size: u32, near: Vec2, map: &Map) -> Option<BuildingID> {
+        map.buildings()
+            .values()
+            .filter(|b| matches!(b.kind, BuildingKind::House))
+            .filter(|b| {
+                self.get(b.id)
+                    .map(|info| info.free_capacity() >= size)
+                    .unwrap_or(false)
+            })
+            .min_by_key(|b| OrderedFloat(b.door_pos.distance2(near)))
+            .map(|b| b.id)
+    }
+
     pub fn get_in(&mut self, building: BuildingID, e: SoulID) {
         let b = unwrap_ret!(self.get_mut(building));
         if cfg!(debug_assertions) && b.inside.contains(&e) {
@@ -67,3 +200,61 @@ impl BuildingInfos {
         }
     }
 }
+
+register_system!(house_assignment);
+/// Households whose house was destroyed move to the closest house with enough room,
+/// or leave the city if there is none.
+#[system(for_each)]
+#[read_component(Router)]
+#[read_component(Transform)]
+#[read_component(Location)]
+pub fn house_assignment(
+    #[resource] map: &Map,
+    #[resource] binfos: &mut BuildingInfos,
+    #[resource] cbuf: &ParCommandBuffer,
+    me: &Entity,
+    household: &mut Household,
+    subworld: &SubWorld,
+) {
+    if map.buildings().contains_key(household.house) {
+        return;
+    }
+
+    let soul = SoulID(*me);
+    let old_house = household.house;
+    binfos.move_out(soul);
+    if binfos
+        .get(old_house)
+        .map(|info| info.residents.is_empty())
+        .unwrap_or(false)
+    {
+        binfos.remove(old_house);
+    }
+
+    let near = household
+        .members
+        .iter()
+        .find_map(|m| {
+            subworld
+                .entry_ref(m.0)
+                .ok()?
+                .get_component::<Transform>()
+                .ok()
+                .map(|t| t.position())
+        })
+        .unwrap_or_default();
+
+    let size = household.members.len() as u32;
+    if let Some(new_house) = binfos.find_house(size, near, map) {
+        binfos.move_in(new_house, soul, size);
+        household.house = new_house;
+        for &member in &household.members {
+            cbuf.add_component(member.0, Desire::new(Home::new(new_house)));
+        }
+        log::info!("{:?} moved from {:?} to {:?}", soul, old_house, new_house);
+        return;
+    }
+
+    log::info!("{:?} found no house and left the city", soul);
+    leave_city(soul, household, subworld, binfos, cbuf);
+}
@@ -0,0 +1,161 @@
+use crate::map_dynamic::{house_capacity, BuildingInfos, Environment};
+use crate::souls::goods_company::{CompanyKind, GoodsCompanyRegistry};
+use crate::utils::rand_provider::RandProvider;
+use crate::utils::time::GameTime;
+use crate::Egregoria;
+use geom::Vec2;
+use map_model::{BuildingID, BuildingKind, Map};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Size of the cells of the land value field, in meters
+pub const CELL_SIZE: f32 = 50.0;
+
+/// Distance under which jobs are considered accessible
+const JOB_RADIUS: f32 = 1500.0;
+/// Number of nearby jobs for a location to be perfectly accessible
+const JOB_ACCESS_NORM: f32 = 40.0;
+/// Distance under which parks and stores are nearby amenities
+const AMENITY_RADIUS: f32 = 400.0;
+const AMENITY_NORM: f32 = 3.0;
+
+/// Land value above which houses densify
+pub const DENSIFY_VALUE: f32 = 0.5;
+/// Probability per hour for a full house on valuable land to densify
+pub const DENSIFY_RATE: f32 = 0.05;
+pub const MAX_HOUSE_LEVEL: u32 = 3;
+
+pub type Cell = (i32, i32);
+
+pub fn cell_of(pos: Vec2) -> Cell {
+    (
+        (pos.x / CELL_SIZE).floor() as i32,
+        (pos.y / CELL_SIZE).floor() as i32,
+    )
+}
+
+pub fn cell_center((x, y): Cell) -> Vec2 {
+    Vec2::new((x as f32 + 0.5) * CELL_SIZE, (y as f32 + 0.5) * CELL_SIZE)
+}
+
+register_resource!(LandValue, "land_value");
+/// How much people want to live at each place of the map, recomputed every hour
+/// from the accessibility to jobs, the nearby amenities, the traffic noise and the pollution
+#[derive(Clone, Default, Serialize, Deserialize, Debug)]
+pub struct LandValue {
+    /// Value in [0; 1] of the cells with a building
+    values: BTreeMap<Cell, f32>,
+    /// Hour at which the values were last updated
+    last_update: i32,
+}
+
+impl LandValue {
+    /// Value of the land at `pos` in [0; 1], 0 where there are no buildings
+    pub fn value(&self, pos: Vec2) -> f32 {
+        self.values.get(&cell_of(pos)).copied().unwrap_or(0.0)
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = (Cell, f32)> + '_ {
+        self.values.iter().map(|(&c, &v)| (c, v))
+    }
+}
+
+/// Every hour, recomputes the land value and densifies some of the houses
+/// on valuable land that are full
+pub(crate) fn land_value_update(goria: &mut Egregoria) {
+    let hour = goria.read::<GameTime>().total_hours();
+
+    let mut lv = goria.write::<LandValue>();
+    if lv.last_update == hour {
+        return;
+    }
+    lv.last_update = hour;
+
+    let map = goria.map();
+    let values = compute_values(
+        &map,
+        &goria.read::<GoodsCompanyRegistry>(),
+        &goria.read::<Environment>(),
+    );
+    lv.values = values;
+    drop(lv);
+
+    let binfos = goria.read::<BuildingInfos>();
+    let lv = goria.read::<LandValue>();
+    let mut rng = goria.write::<RandProvider>();
+    let to_densify: Vec<BuildingID> = map
+        .buildings()
+        .values()
+        .filter(|b| matches!(b.kind, BuildingKind::House) && b.level < MAX_HOUSE_LEVEL)
+        .filter(|b| {
+            binfos
+                .get(b.id)
+                .map(|info| info.free_capacity() == 0)
+                .unwrap_or(false)
+        })
+        .filter(|b| lv.value(b.door_pos) >= DENSIFY_VALUE)
+        .filter(|_| rng.random::<f32>() < DENSIFY_RATE)
+        .map(|b| b.id)
+        .collect();
+    drop((map, binfos, lv, rng));
+
+    for id in to_densify {
+        let level = unwrap_cont!(goria.map_mut().densify_house(id));
+        let map = goria.map();
+        let house = unwrap_cont!(map.buildings().get(id));
+        if let Some(info) = goria.write::<BuildingInfos>().get_mut(id) {
+            info.capacity = house_capacity(house);
+        }
+        log::info!("{:?} densified to level {}", id, level);
+    }
+}
+
+fn compute_values(
+    map: &Map,
+    registry: &GoodsCompanyRegistry,
+    env: &Environment,
+) -> BTreeMap<Cell, f32> {
+    let mut jobs = vec![];
+    let mut amenities = vec![];
+    for b in map.buildings().values() {
+        match b.kind {
+            BuildingKind::Park | BuildingKind::Service(_) => amenities.push(b.door_pos),
+            BuildingKind::Company(_) => {
+                let des = unwrap_cont!(registry.descriptions.get(&b.kind));
+                jobs.push((b.door_pos, des.n_workers as f32));
+                if let CompanyKind::Store = des.kind {
+                    amenities.push(b.door_pos);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let falloff = |d: f32, radius: f32| (1.0 - d / radius).max(0.0);
+
+    let mut values = BTreeMap::new();
+    for b in map.buildings().values() {
+        let cell = cell_of(b.door_pos);
+        if values.contains_key(&cell) {
+            continue;
+        }
+        let center = cell_center(cell);
+
+        let access = jobs
+            .iter()
+            .map(|&(p, n)| n * falloff(p.distance(center), JOB_RADIUS))
+            .sum::<f32>()
+            / JOB_ACCESS_NORM;
+        let amenity = amenities
+            .iter()
+            .map(|&p| falloff(p.distance(center), AMENITY_RADIUS))
+            .sum::<f32>()
+            / AMENITY_NORM;
+
+        let value = 0.5 * access.min(1.0) + 0.5 * amenity.min(1.0)
+            - 0.3 * env.noise_at(cell)
+            - 0.5 * env.pollution_at(cell);
+        values.insert(cell, value.clamp(0.0, 1.0));
+    }
+    values
+}
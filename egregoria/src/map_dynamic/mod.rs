@@ -1,9 +1,13 @@
+mod environment;
 mod house_assignment;
 mod itinerary;
+mod land_value;
 mod parking;
 mod router;
 
+pub use environment::*;
 pub use house_assignment::*;
 pub use itinerary::*;
+pub use land_value::*;
 pub use parking::*;
 pub use router::*;
@@ -1,6 +1,12 @@
+use crate::vehicles::{Vehicle, VehicleState};
+use crate::{Egregoria, ParCommandBuffer};
 use common::PtrCmp;
-use geom::Vec2;
-use map_model::{LaneKind, Map, ParkingSpot, ParkingSpotID, ParkingSpots};
+use geom::{Transform, Vec2};
+use legion::{Entity, IntoQuery};
+use map_model::{
+    LaneKind, Map, ParkingSpot, ParkingSpotID, ParkingSpots, ProjectFilter, ProjectKind,
+};
+use ordered_float::OrderedFloat;
 use serde::{Deserialize, Serialize};
 use std::collections::{BTreeSet, HashSet};
 
@@ -12,8 +18,19 @@ register_resource!(ParkingManagement, "pmanagement");
 #[derive(Default, Serialize, Deserialize)]
 pub struct ParkingManagement {
     reserved_spots: BTreeSet<ParkingSpotID>,
+    /// When enabled, drivers only look for a spot once they are close to their destination
+    pub search_on_arrival: bool,
 }
 
+/// How far from the destination a driver looks for parking when it first arrives
+pub const PARKING_SEARCH_RADIUS: f32 = 40.0;
+/// Radius increase for each lane cruised without finding a spot
+pub const PARKING_SEARCH_RADIUS_STEP: f32 = 20.0;
+/// Number of lanes cruised before giving up and parking further away
+pub const PARKING_SEARCH_MAX_TRIES: u8 = 6;
+/// Parking lots further away than this are never considered
+const MAX_LOT_DIST: f32 = 300.0;
+
 impl ParkingManagement {
     pub fn free(&mut self, spot: SpotReservation) {
         if !self.reserved_spots.remove(&spot.0) {
@@ -27,7 +44,7 @@ impl ParkingManagement {
     }
 
     pub fn is_spot_free(&self, spot: ParkingSpotID) -> bool {
-        self.reserved_spots.contains(&spot)
+        !self.reserved_spots.contains(&spot)
     }
 
     pub fn reserve_near(&mut self, near: Vec2, map: &Map) -> Option<SpotReservation> {
@@ -73,10 +90,167 @@ impl ParkingManagement {
             }
             std::mem::swap(&mut potential, &mut next);
         }
+
+        self.reserve_in_lot(near, MAX_LOT_DIST, map)
+    }
+
+    /// Only reserves spots that are less than `radius` away from `near`, either by the road or
+    /// in a parking lot
+    pub fn reserve_around(
+        &mut self,
+        near: Vec2,
+        radius: f32,
+        map: &Map,
+    ) -> Option<SpotReservation> {
+        let r2 = radius * radius;
+        let roads = map.roads();
+        let lanes = map.lanes();
+
+        let mut candidates: Vec<(f32, ParkingSpotID)> = map
+            .spatial_map()
+            .query_around(near, radius, ProjectFilter::ROAD)
+            .filter_map(|kind| match kind {
+                ProjectKind::Road(id) => roads.get(id),
+                _ => None,
+            })
+            .flat_map(|road| road.lanes_iter())
+            .filter(|(_, kind)| matches!(kind, LaneKind::Parking))
+            .flat_map(|(id, _)| {
+                let lane = lanes.get(id)?;
+                Some(map.parking.closest_spots(lane.id, near)?.take(3))
+            })
+            .flatten()
+            .filter(|&spot| !self.reserved_spots.contains(&spot))
+            .filter_map(|spot| {
+                let d = map.parking.get(spot)?.trans.position().distance2(near);
+                if d > r2 {
+                    return None;
+                }
+                Some((d, spot))
+            })
+            .collect();
+
+        candidates.sort_unstable_by_key(|&(d, _)| OrderedFloat(d));
+
+        for (_, spot) in candidates {
+            if self.reserved_spots.insert(spot) {
+                return Some(SpotReservation(spot));
+            }
+        }
+
+        self.reserve_in_lot(near, radius, map)
+    }
+
+    /// Reserves a spot in the closest parking lot that still has room
+    pub fn reserve_in_lot(
+        &mut self,
+        near: Vec2,
+        max_dist: f32,
+        map: &Map,
+    ) -> Option<SpotReservation> {
+        let buildings = map.buildings();
+        let mut lots: Vec<(f32, &[ParkingSpotID])> = map
+            .parking
+            .lots()
+            .filter_map(|(id, spots)| {
+                let d = buildings.get(id)?.door_pos.distance(near);
+                if d > max_dist {
+                    return None;
+                }
+                Some((d, spots))
+            })
+            .collect();
+
+        lots.sort_unstable_by_key(|&(d, _)| OrderedFloat(d));
+
+        for (_, spots) in lots {
+            for &spot in spots {
+                if self.reserved_spots.insert(spot) {
+                    return Some(SpotReservation(spot));
+                }
+            }
+        }
         None
     }
 }
 
+/// Moves the cars parked in the removed parking lots to other spots and frees the reservations
+/// of the removed spots, then deletes them.
+/// Cars that cannot find another spot are removed along with their spot.
+/// Their reservation is freed when the car is dropped.
+pub fn free_removed_lot_spots(goria: &mut Egregoria) {
+    let removed: BTreeSet<ParkingSpotID> = goria
+        .map()
+        .parking
+        .removed_lot_spots()
+        .iter()
+        .copied()
+        .collect();
+    if removed.is_empty() {
+        return;
+    }
+
+    let cars: Vec<(Entity, Vec2, ParkingSpotID)> = <(Entity, &Vehicle, &Transform)>::query()
+        .iter(goria.world())
+        .filter_map(|(&e, v, trans)| match v.state {
+            VehicleState::Parked(ref r) | VehicleState::RoadToPark(_, _, ref r)
+                if removed.contains(&r.0) =>
+            {
+                Some((e, trans.position(), r.0))
+            }
+            _ => None,
+        })
+        .collect();
+
+    // spots of the cars that stay there are freed when the cars are removed
+    let mut kept = BTreeSet::new();
+    let mut stranded = vec![];
+    for (e, pos, old_spot) in cars {
+        let map = goria.map();
+        let mut pm = goria.write::<ParkingManagement>();
+        let spot = unwrap_or!(pm.reserve_near(pos, &map), {
+            kept.insert(old_spot);
+            stranded.push(e);
+            continue;
+        });
+        let target = unwrap_or!(spot.get(&map.parking), {
+            pm.free(spot);
+            kept.insert(old_spot);
+            stranded.push(e);
+            continue;
+        })
+        .trans;
+        drop(pm);
+        drop(map);
+
+        let v = unwrap_cont!(goria.comp_mut::<Vehicle>(e));
+        let old = match v.state {
+            VehicleState::Parked(ref mut r) => std::mem::replace(r, spot),
+            VehicleState::RoadToPark(ref mut s, _, ref mut r) => {
+                s.to = target.position();
+                s.to_derivative = target.direction() * 2.0;
+                std::mem::replace(r, spot)
+            }
+            _ => spot,
+        };
+        goria.write::<ParkingManagement>().free(old);
+    }
+
+    let cbuf = goria.read::<ParCommandBuffer>();
+    for e in stranded {
+        cbuf.kill(e);
+    }
+    drop(cbuf);
+
+    let mut pm = goria.write::<ParkingManagement>();
+    for id in removed.difference(&kept) {
+        pm.reserved_spots.remove(id);
+    }
+    drop(pm);
+
+    goria.map_mut().parking.clean_removed_lot_spots();
+}
+
 impl SpotReservation {
     pub fn exists(&self, spots: &ParkingSpots) -> bool {
         spots.contains(self.0)
@@ -1,15 +1,19 @@
-use crate::map_dynamic::{Itinerary, ParkingManagement, SpotReservation};
+use crate::map_dynamic::{
+    Itinerary, ParkingManagement, SpotReservation, PARKING_SEARCH_MAX_TRIES, PARKING_SEARCH_RADIUS,
+    PARKING_SEARCH_RADIUS_STEP,
+};
 use crate::pedestrians::{put_pedestrian_in_coworld, Location};
 use crate::physics::{Collider, CollisionWorld, Kinematics};
 use crate::utils::par_command_buffer::ComponentDrop;
 use crate::vehicles::{unpark, Vehicle, VehicleID, VehicleState};
-use crate::{Egregoria, ParCommandBuffer};
+use crate::{Egregoria, ParCommandBuffer, SoulID};
 use geom::{Spline, Transform, Vec2};
 use imgui_inspect_derive::*;
 use legion::storage::Component;
 use legion::world::SubWorld;
 use legion::{system, Entity, EntityStore, IntoQuery, Resources};
-use map_model::{BuildingID, Map, PathKind};
+use map_model::{BuildingID, LaneKind, Map, PathKind};
+use ordered_float::OrderedFloat;
 use serde::{Deserialize, Serialize};
 
 #[derive(Inspect, Serialize, Deserialize)]
@@ -35,6 +39,8 @@ pub enum RoutingStep {
     WalkTo(Vec2),
     DriveTo(VehicleID, Vec2),
     Park(VehicleID, Option<SpotReservation>),
+    /// Cruise around the position looking for a free spot, the u8 is the number of lanes already cruised
+    SearchPark(VehicleID, Vec2, u8),
     Unpark(VehicleID),
     GetInVehicle(VehicleID),
     GetOutVehicle(VehicleID),
@@ -46,25 +52,28 @@ debug_inspect_impl!(RoutingStep);
 
 register_system!(routing_changed);
 register_system!(routing_update);
+register_system!(parking_search);
 
 #[system(for_each)]
 #[read_component(Transform)]
-#[read_component(Vehicle)]
+#[write_component(Vehicle)]
 #[read_component(Itinerary)]
 pub fn routing_changed(
     #[resource] map: &Map,
     #[resource] parking: &mut ParkingManagement,
+    me: &Entity,
     router: &mut Router,
     loc: &Location,
-    subworld: &SubWorld,
+    subworld: &mut SubWorld,
 ) {
+    let soul = SoulID(*me);
     if router.cur_dest != router.target_dest {
         let dest = unwrap_ret!(router.target_dest);
 
         router.clear_steps(parking);
         match dest {
             Destination::Outside(pos) => {
-                router.steps = unwrap_ret!(router.steps_to(pos, parking, map, loc, subworld));
+                router.steps = unwrap_ret!(router.steps_to(pos, parking, map, soul, loc, subworld));
             }
             Destination::Building(build) => {
                 if let Location::Building(cur_build) = loc {
@@ -75,7 +84,8 @@ pub fn routing_changed(
                 }
 
                 let door_pos = unwrap_ret!(map.buildings().get(build)).door_pos;
-                router.steps = unwrap_ret!(router.steps_to(door_pos, parking, map, loc, subworld));
+                router.steps =
+                    unwrap_ret!(router.steps_to(door_pos, parking, map, soul, loc, subworld));
                 router.steps.push(RoutingStep::GetInBuilding(build));
             }
         }
@@ -128,6 +138,8 @@ pub fn routing_update(
             RoutingStep::Park(vehicle, _) => comp::<Vehicle>(subworld, vehicle.0)
                 .map(|x| matches!(x.state, VehicleState::Parked(_)))
                 .unwrap_or(true),
+            // Resolved by the parking_search system
+            RoutingStep::SearchPark(..) => false,
             RoutingStep::Unpark(_) => true,
             RoutingStep::GetInVehicle(_) => true,
             RoutingStep::GetOutVehicle(_) => true,
@@ -142,6 +154,7 @@ pub fn routing_update(
             RoutingStep::WalkTo(_) => true,
             RoutingStep::DriveTo(_, _) => true,
             RoutingStep::Park(_, _) => true,
+            RoutingStep::SearchPark(..) => true,
             RoutingStep::Unpark(_) => true,
             RoutingStep::GetInVehicle(vehicle) => comp::<Transform>(subworld, vehicle.0)
                 .map(|x| x.position().is_close(pos, 3.0))
@@ -184,6 +197,7 @@ pub fn routing_update(
                     cbuf.exec_ent(vehicle.0, park(vehicle, x));
                 }
             }
+            RoutingStep::SearchPark(..) => {}
             RoutingStep::Unpark(vehicle) => {
                 cbuf.exec_ent(vehicle.0, move |goria| unpark(goria, vehicle));
             }
@@ -200,6 +214,13 @@ pub fn routing_update(
                     .map(|vtrans| vtrans.position() + vtrans.direction().perpendicular() * 2.0)
                     .unwrap_or(pos);
                 walk_outside(*body, pos, cbuf, loc);
+                let soul = SoulID(*body);
+                cbuf.exec_ent(vehicle.0, move |goria| {
+                    let v = unwrap_ret!(goria.comp_mut::<Vehicle>(vehicle.0));
+                    if v.claimed_by == Some(soul) {
+                        v.claimed_by = None;
+                    }
+                });
             }
             RoutingStep::GetInBuilding(build) => {
                 if !map.buildings().contains_key(build) {
@@ -221,6 +242,77 @@ pub fn routing_update(
     }
 }
 
+/// Resolves the SearchPark steps: either a spot was found close enough and the driver goes
+/// park there, or it cruises to the next lane and searches a bit further.
+#[system(for_each)]
+#[read_component(Transform)]
+pub fn parking_search(
+    #[resource] map: &Map,
+    #[resource] parking: &mut ParkingManagement,
+    router: &mut Router,
+    subworld: &SubWorld,
+) {
+    let (vehicle, near, tries) = match router.cur_step {
+        Some(RoutingStep::SearchPark(vehicle, near, tries)) => (vehicle, near, tries),
+        _ => return,
+    };
+
+    let radius = PARKING_SEARCH_RADIUS + tries as f32 * PARKING_SEARCH_RADIUS_STEP;
+
+    let spot = if tries >= PARKING_SEARCH_MAX_TRIES {
+        // Give up and park further away
+        parking.reserve_near(near, map)
+    } else {
+        parking.reserve_around(near, radius, map)
+    };
+
+    if let Some(spot) = spot {
+        if let Some(park_pos) = spot.park_pos(map) {
+            router.cur_step = None;
+            router.steps.push(RoutingStep::Park(vehicle, Some(spot)));
+            router.steps.push(RoutingStep::DriveTo(vehicle, park_pos));
+            return;
+        }
+        parking.free(spot);
+    }
+
+    if tries >= PARKING_SEARCH_MAX_TRIES {
+        router.reset_dest();
+        return;
+    }
+
+    let pos = unwrap_ret!(comp::<Transform>(subworld, vehicle.0)).position();
+    let cruise_to = unwrap_or!(cruise_target(map, pos, near, tries), {
+        router.reset_dest();
+        return;
+    });
+
+    router.cur_step = None;
+    router
+        .steps
+        .push(RoutingStep::SearchPark(vehicle, near, tries + 1));
+    router.steps.push(RoutingStep::DriveTo(vehicle, cruise_to));
+}
+
+/// Picks the end of one of the lanes following the current one, preferring the ones that
+/// stay close to the destination
+fn cruise_target(map: &Map, pos: Vec2, near: Vec2, tries: u8) -> Option<Vec2> {
+    let lanes = map.lanes();
+    let lane = lanes.get(map.nearest_lane(pos, LaneKind::Driving)?)?;
+    let inter = map.intersections().get(lane.dst)?;
+
+    inter
+        .turns_from(lane.id)
+        .filter_map(|(turn, _)| lanes.get(turn.dst))
+        .map(|next| {
+            let end = next.points.last();
+            let r = common::rand::rand3(end.x, end.y, tries as f32);
+            (end, end.distance(near) * (1.0 + r))
+        })
+        .min_by_key(|&(_, score)| OrderedFloat(score))
+        .map(|(end, _)| end)
+}
+
 impl ComponentDrop for Router {
     fn drop(&mut self, res: &mut Resources, _: Entity) {
         self.clear_steps(&mut *res.get_mut::<ParkingManagement>().unwrap())
@@ -231,6 +323,22 @@ fn comp<'a, T: Component>(sw: &'a SubWorld, e: Entity) -> Option<&'a T> {
     <(&T,)>::query().get(sw, e).map(|x| x.0).ok()
 }
 
+/// Whether the soul can take the vehicle: it must be parked and not planned by someone else
+fn can_claim(sw: &SubWorld, vehicle: VehicleID, soul: SoulID) -> bool {
+    comp::<Vehicle>(sw, vehicle.0)
+        .map(|v| {
+            matches!(v.state, VehicleState::Parked(_))
+                && v.claimed_by.map(|c| c == soul).unwrap_or(true)
+        })
+        .unwrap_or(false)
+}
+
+fn claim(sw: &mut SubWorld, vehicle: VehicleID, soul: SoulID) {
+    if let Ok((v,)) = <(&mut Vehicle,)>::query().get_mut(sw, vehicle.0) {
+        v.claimed_by = Some(soul);
+    }
+}
+
 fn walk_inside(body: Entity, cbuf: &ParCommandBuffer, kin: &mut Kinematics) {
     cbuf.remove_component_drop::<Collider>(body);
     kin.velocity = Vec2::ZERO;
@@ -288,6 +396,24 @@ impl Router {
         self.vehicle = v;
     }
 
+    /// Sets the personal car, it is used right away unless another vehicle (like a truck) is in use
+    pub fn set_personal_car(&mut self, car: VehicleID) {
+        if self.vehicle.is_none() || self.vehicle == self.personal_car {
+            self.vehicle = Some(car);
+        }
+        self.personal_car = Some(car);
+    }
+
+    /// Stops using and owning the vehicle, for example when it is sold
+    pub fn forget_vehicle(&mut self, v: VehicleID) {
+        if self.personal_car == Some(v) {
+            self.personal_car = None;
+        }
+        if self.vehicle == Some(v) {
+            self.vehicle = None;
+        }
+    }
+
     fn clear_steps(&mut self, parking: &mut ParkingManagement) {
         for s in self.steps.drain(..).chain(self.cur_step.take()) {
             if let RoutingStep::Park(_, Some(spot)) = s {
@@ -316,15 +442,54 @@ impl Router {
         obj: Vec2,
         parking: &mut ParkingManagement,
         map: &Map,
+        soul: SoulID,
         loc: &Location,
-        subworld: &SubWorld,
+        subworld: &mut SubWorld,
     ) -> Option<Vec<RoutingStep>> {
         let mut steps = vec![];
         if let Location::Building(cur_build) = loc {
             steps.push(RoutingStep::GetOutBuilding(*cur_build));
         }
 
-        if let Some(car) = self.vehicle {
+        let in_vehicle = matches!(loc, Location::Vehicle(_));
+        if let Some(car) = self
+            .vehicle
+            .filter(|&car| in_vehicle || can_claim(subworld, car, soul))
+        {
+            // The destination changed while the car was parking, wait for it to be parked to leave again
+            if in_vehicle
+                && comp::<Vehicle>(subworld, car.0)
+                    .map(|v| {
+                        matches!(
+                            v.state,
+                            VehicleState::Parked(_) | VehicleState::RoadToPark(..)
+                        )
+                    })
+                    .unwrap_or(false)
+            {
+                steps.push(RoutingStep::Park(car, None));
+                steps.push(RoutingStep::Unpark(car));
+            }
+
+            if parking.search_on_arrival {
+                if !matches!(loc, Location::Vehicle(_)) {
+                    let trans = unwrap_or!(comp::<Transform>(subworld, car.0), {
+                        self.vehicle = None;
+                        return None;
+                    });
+                    steps.push(RoutingStep::WalkTo(trans.position()));
+                    steps.push(RoutingStep::GetInVehicle(car));
+                    steps.push(RoutingStep::Unpark(car));
+                }
+
+                steps.push(RoutingStep::DriveTo(car, obj));
+                steps.push(RoutingStep::SearchPark(car, obj, 0));
+                steps.push(RoutingStep::GetOutVehicle(car));
+                steps.push(RoutingStep::WalkTo(obj));
+                claim(subworld, car, soul);
+                return Some(steps);
+            }
+
             let spot_resa = parking.reserve_near(obj, map)?;
             let parking_pos = match spot_resa.park_pos(map) {
                 Some(x) => x,
@@ -349,6 +514,7 @@ impl Router {
             steps.push(RoutingStep::DriveTo(car, parking_pos));
             steps.push(RoutingStep::Park(car, Some(spot_resa)));
             steps.push(RoutingStep::GetOutVehicle(car));
+            claim(subworld, car, soul);
         }
 
         steps.push(RoutingStep::WalkTo(obj));
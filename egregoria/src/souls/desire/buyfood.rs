@@ -1,14 +1,15 @@
-use crate::economy::{Bought, CommodityKind, Market};
-use crate::map_dynamic::{BuildingInfos, Destination};
+use crate::economy::{CommodityKind, Market};
+use crate::map_dynamic::Destination;
 use crate::pedestrians::Location;
+use crate::souls::desire::{DesireBehavior, DesireCtx};
 use crate::souls::human::HumanDecisionKind;
 use crate::utils::time::{GameInstant, GameTime};
-use crate::{ParCommandBuffer, SoulID};
-use geom::Transform;
 use imgui_inspect_derive::*;
 use map_model::BuildingID;
 use serde::{Deserialize, Serialize};
 
+register_desire!(BuyFood);
+
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub enum BuyFoodState {
     Empty,
@@ -31,10 +32,13 @@ impl BuyFood {
             state: BuyFoodState::Empty,
         }
     }
+}
 
-    pub fn score(&self, time: &GameTime, loc: &Location, bought: &Bought) -> f32 {
+impl DesireBehavior for BuyFood {
+    fn score(&self, ctx: &DesireCtx<'_>) -> f32 {
         if matches!(self.state, BuyFoodState::WaitingForTrade)
-            && bought
+            && ctx
+                .bought
                 .0
                 .get(&CommodityKind::Bread)
                 .map(Vec::is_empty)
@@ -43,45 +47,44 @@ impl BuyFood {
             return 0.0;
         }
         if let BuyFoodState::BoughtAt(id) = self.state {
-            if loc == &Location::Building(id) {
+            if ctx.loc == &Location::Building(id) {
                 return 1.0;
             }
         }
-        self.last_ate.elapsed(time) as f32 / GameTime::DAY as f32 - 1.0
+        self.last_ate.elapsed(ctx.time) as f32 / GameTime::DAY as f32 - 1.0
     }
 
-    pub fn apply(
-        &mut self,
-        cbuf: &ParCommandBuffer,
-        binfos: &BuildingInfos,
-        time: &GameTime,
-        soul: SoulID,
-        trans: &Transform,
-        loc: &Location,
-        bought: &mut Bought,
-    ) -> HumanDecisionKind {
+    fn apply(&mut self, ctx: &mut DesireCtx<'_>) -> HumanDecisionKind {
         use HumanDecisionKind::*;
+        let soul = ctx.soul;
         match self.state {
             BuyFoodState::Empty => {
-                let pos = trans.position();
-                cbuf.exec_on(soul.0, move |market: &mut Market| {
+                let pos = ctx.trans.position();
+                ctx.cbuf.exec_on(soul.0, move |market: &mut Market| {
                     market.buy(soul, pos, CommodityKind::Bread, 1)
                 });
                 self.state = BuyFoodState::WaitingForTrade;
                 Yield
             }
             BuyFoodState::WaitingForTrade => {
-                for trade in bought.0.entry(CommodityKind::Bread).or_default().drain(..) {
-                    if let Some(b) = binfos.building_owned_by(trade.seller) {
+                for trade in ctx
+                    .bought
+                    .0
+                    .entry(CommodityKind::Bread)
+                    .or_default()
+                    .drain(..)
+                {
+                    if let Some(b) = ctx.binfos.building_owned_by(trade.seller) {
                         self.state = BuyFoodState::BoughtAt(b);
                     }
                 }
                 Yield
             }
             BuyFoodState::BoughtAt(b) => {
-                if loc == &Location::Building(b) {
+                if ctx.loc == &Location::Building(b) {
                     self.state = BuyFoodState::Empty;
-                    self.last_ate = time.instant();
+                    self.last_ate = ctx.time.instant();
+                    ctx.needs.eat(1.0);
                     log::info!("{:?} ate at {:?}", soul, b);
                     Yield
                 } else {
@@ -0,0 +1,98 @@
+use crate::economy::CommodityKind;
+use crate::souls::desire::{DesireBehavior, DesireCtx, Errand, ErrandStep};
+use crate::souls::human::HumanDecisionKind;
+use crate::souls::schedule::Activity;
+use crate::utils::time::{GameInstant, GameTime};
+use imgui_inspect_derive::*;
+use ordered_float::OrderedFloat;
+use serde::{Deserialize, Serialize};
+
+register_desire!(BuyGoods);
+
+/// Goods bought in the stores from time to time, with the time between two purchases
+pub const GOODS_NEEDS: [(CommodityKind, i32); 3] = [
+    (CommodityKind::Cloth, GameTime::DAY * 10),
+    (CommodityKind::Furniture, GameTime::DAY * 60),
+    (CommodityKind::HighTechProduct, GameTime::DAY * 90),
+];
+
+#[derive(Inspect, Clone, Serialize, Deserialize, Debug)]
+pub struct BuyGoods {
+    last_bought: Vec<(CommodityKind, GameInstant)>,
+    errand: Errand,
+}
+
+impl BuyGoods {
+    /// `start` is shifted for each good so that everyone does not go shopping at the same time
+    pub fn new(mut start: impl FnMut(CommodityKind) -> GameInstant) -> Self {
+        BuyGoods {
+            last_bought: GOODS_NEEDS
+                .iter()
+                .map(|&(kind, _)| (kind, start(kind)))
+                .collect(),
+            errand: Errand::new(CommodityKind::Cloth),
+        }
+    }
+
+    /// The good whose purchase is the most overdue, if any
+    fn most_needed(&self, time: &GameTime) -> Option<CommodityKind> {
+        self.last_bought
+            .iter()
+            .zip(GOODS_NEEDS.iter())
+            .map(|(&(kind, last), &(_, period))| (kind, last.elapsed(time) / period as f64))
+            .filter(|&(_, overdue)| overdue > 1.0)
+            .max_by_key(|&(_, overdue)| OrderedFloat(overdue))
+            .map(|(kind, _)| kind)
+    }
+
+    fn bought(&mut self, kind: CommodityKind, time: &GameTime) {
+        for (k, last) in &mut self.last_bought {
+            if *k == kind {
+                *last = time.instant();
+            }
+        }
+    }
+}
+
+impl DesireBehavior for BuyGoods {
+    fn score(&self, ctx: &DesireCtx<'_>) -> f32 {
+        if !self.errand.is_idle() {
+            return if self.errand.needs_attention(ctx) {
+                0.35
+            } else {
+                -1.0
+            };
+        }
+
+        if !matches!(ctx.activity, None | Some(Activity::Leisure)) {
+            return -1.0;
+        }
+
+        match self.most_needed(ctx.time) {
+            Some(_) => 0.3,
+            None => -1.0,
+        }
+    }
+
+    fn apply(&mut self, ctx: &mut DesireCtx<'_>) -> HumanDecisionKind {
+        if self.errand.is_idle() {
+            let kind = unwrap_or!(self.most_needed(ctx.time), return HumanDecisionKind::Yield);
+            self.errand = Errand::new(kind);
+            if self.errand.can_afford(ctx) {
+                self.errand.start(ctx);
+            } else {
+                // Try again later
+                self.bought(kind, ctx.time);
+            }
+            return HumanDecisionKind::Yield;
+        }
+
+        match self.errand.apply(ctx) {
+            ErrandStep::Ongoing(decision) => decision,
+            ErrandStep::Done(_) | ErrandStep::Failed => {
+                self.bought(self.errand.kind, ctx.time);
+                HumanDecisionKind::Yield
+            }
+        }
+    }
+}
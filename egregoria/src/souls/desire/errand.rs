@@ -0,0 +1,125 @@
+use crate::economy::{CommodityKind, Market};
+use crate::map_dynamic::Destination;
+use crate::pedestrians::Location;
+use crate::souls::desire::DesireCtx;
+use crate::souls::human::HumanDecisionKind;
+use crate::utils::time::{GameInstant, GameTime};
+use imgui_inspect_derive::*;
+use map_model::BuildingID;
+use serde::{Deserialize, Serialize};
+
+/// Time after which an order that found no seller is cancelled
+pub const ERRAND_TIMEOUT: i32 = GameTime::DAY;
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub enum ErrandState {
+    Idle,
+    WaitingForTrade(GameInstant),
+    PickUpAt(BuildingID),
+}
+
+debug_inspect_impl!(ErrandState);
+
+pub enum ErrandStep {
+    Ongoing(HumanDecisionKind),
+    /// The goods were picked up at the seller's building (if it has one) and consumed
+    Done(Option<BuildingID>),
+    /// Nobody sold the goods in time, the order was cancelled
+    Failed,
+}
+
+/// Buys one unit of a commodity on the market, goes to pick it up at the seller's and consumes it.
+/// Used by desires that need goods from a store.
+#[derive(Inspect, Clone, Serialize, Deserialize, Debug)]
+pub struct Errand {
+    pub kind: CommodityKind,
+    state: ErrandState,
+}
+
+impl Errand {
+    pub fn new(kind: CommodityKind) -> Self {
+        Self {
+            kind,
+            state: ErrandState::Idle,
+        }
+    }
+
+    pub fn is_idle(&self) -> bool {
+        matches!(self.state, ErrandState::Idle)
+    }
+
+    pub fn can_afford(&self, ctx: &DesireCtx<'_>) -> bool {
+        ctx.market.money(ctx.soul) >= self.kind.price()
+    }
+
+    /// Whether the errand has something to do: the goods were bought, the order timed out
+    /// or the goods are waiting to be picked up
+    pub fn needs_attention(&self, ctx: &DesireCtx<'_>) -> bool {
+        match self.state {
+            ErrandState::Idle => false,
+            ErrandState::WaitingForTrade(since) => {
+                ctx.bought
+                    .0
+                    .get(&self.kind)
+                    .map(|v| !v.is_empty())
+                    .unwrap_or(false)
+                    || since.elapsed(ctx.time) > ERRAND_TIMEOUT as f64
+            }
+            ErrandState::PickUpAt(_) => true,
+        }
+    }
+
+    pub fn start(&mut self, ctx: &mut DesireCtx<'_>) {
+        let soul = ctx.soul;
+        let pos = ctx.trans.position();
+        let kind = self.kind;
+        ctx.cbuf.exec_on(soul.0, move |market: &mut Market| {
+            market.buy(soul, pos, kind, 1)
+        });
+        self.state = ErrandState::WaitingForTrade(ctx.time.instant());
+    }
+
+    pub fn apply(&mut self, ctx: &mut DesireCtx<'_>) -> ErrandStep {
+        let soul = ctx.soul;
+        let kind = self.kind;
+        match self.state {
+            ErrandState::Idle => ErrandStep::Failed,
+            ErrandState::WaitingForTrade(since) => {
+                if let Some(trade) = ctx.bought.0.entry(kind).or_default().pop() {
+                    match ctx.binfos.building_owned_by(trade.seller) {
+                        Some(b) => self.state = ErrandState::PickUpAt(b),
+                        None => return self.consume(ctx, None),
+                    }
+                    return ErrandStep::Ongoing(HumanDecisionKind::Yield);
+                }
+
+                if since.elapsed(ctx.time) > ERRAND_TIMEOUT as f64 {
+                    ctx.cbuf.exec_on(soul.0, move |market: &mut Market| {
+                        market.cancel_buy(soul, kind)
+                    });
+                    self.state = ErrandState::Idle;
+                    return ErrandStep::Failed;
+                }
+
+                ErrandStep::Ongoing(HumanDecisionKind::Yield)
+            }
+            ErrandState::PickUpAt(b) => {
+                if ctx.loc != &Location::Building(b) {
+                    return ErrandStep::Ongoing(HumanDecisionKind::GoTo(Destination::Building(b)));
+                }
+                self.consume(ctx, Some(b))
+            }
+        }
+    }
+
+    fn consume(&mut self, ctx: &mut DesireCtx<'_>, at: Option<BuildingID>) -> ErrandStep {
+        let soul = ctx.soul;
+        let kind = self.kind;
+        ctx.cbuf.exec_on(soul.0, move |market: &mut Market| {
+            market.produce(soul, kind, -1);
+        });
+        log::info!("{:?} got {} at {:?}", soul, kind, at);
+        self.state = ErrandState::Idle;
+        ErrandStep::Done(at)
+    }
+}
@@ -0,0 +1,94 @@
+use crate::economy::CommodityKind;
+use crate::souls::desire::{DesireBehavior, DesireCtx, Errand, ErrandStep};
+use crate::souls::human::HumanDecisionKind;
+use crate::utils::time::{GameInstant, GameTime};
+use imgui_inspect_derive::*;
+use serde::{Deserialize, Serialize};
+
+register_desire!(Healthcare);
+
+/// Average time between two sicknesses
+pub const MEAN_HEALTHY_TIME: f64 = GameTime::DAY as f64 * 30.0;
+/// Sick souls that cannot afford medicine get better on their own after some time
+pub const NATURAL_RECOVERY_TIME: f64 = GameTime::DAY as f64 * 7.0;
+/// How much rest getting better restores
+const RECOVERY_REST: f32 = 0.5;
+
+#[derive(Inspect, Clone, Serialize, Deserialize, Debug)]
+pub struct Healthcare {
+    /// The soul gets sick once this much time has passed since the last recovery
+    healthy_for: f64,
+    last_recovery: GameInstant,
+    medicine: Errand,
+}
+
+impl Healthcare {
+    /// `r` in [0; 1[ picks how long the soul stays healthy
+    pub fn new(start: GameInstant, r: f32) -> Self {
+        Healthcare {
+            healthy_for: r as f64 * 2.0 * MEAN_HEALTHY_TIME,
+            last_recovery: start,
+            medicine: Errand::new(CommodityKind::Medicine),
+        }
+    }
+
+    pub fn is_sick(&self, time: &GameTime) -> bool {
+        self.last_recovery.elapsed(time) > self.healthy_for
+    }
+
+    fn sick_for(&self, time: &GameTime) -> f64 {
+        self.last_recovery.elapsed(time) - self.healthy_for
+    }
+
+    fn recover(&mut self, ctx: &mut DesireCtx<'_>) {
+        let pos = ctx.trans.position();
+        let r = common::rand::rand3(pos.x, pos.y, ctx.time.timestamp as f32);
+        log::info!("{:?} recovered", ctx.soul);
+
+        self.healthy_for = r as f64 * 2.0 * MEAN_HEALTHY_TIME;
+        self.last_recovery = ctx.time.instant();
+        ctx.needs.relax(RECOVERY_REST);
+    }
+}
+
+impl DesireBehavior for Healthcare {
+    fn score(&self, ctx: &DesireCtx<'_>) -> f32 {
+        if !self.medicine.is_idle() {
+            return if self.medicine.needs_attention(ctx) {
+                0.55
+            } else {
+                -1.0
+            };
+        }
+
+        if !self.is_sick(ctx.time) {
+            return -1.0;
+        }
+
+        if self.medicine.can_afford(ctx) || self.sick_for(ctx.time) > NATURAL_RECOVERY_TIME {
+            0.55
+        } else {
+            -1.0
+        }
+    }
+
+    fn apply(&mut self, ctx: &mut DesireCtx<'_>) -> HumanDecisionKind {
+        if self.medicine.is_idle() {
+            if self.medicine.can_afford(ctx) {
+                self.medicine.start(ctx);
+            } else if self.sick_for(ctx.time) > NATURAL_RECOVERY_TIME {
+                self.recover(ctx);
+            }
+            return HumanDecisionKind::Yield;
+        }
+
+        match self.medicine.apply(ctx) {
+            ErrandStep::Ongoing(decision) => decision,
+            ErrandStep::Done(_) => {
+                self.recover(ctx);
+                HumanDecisionKind::Yield
+            }
+            ErrandStep::Failed => HumanDecisionKind::Yield,
+        }
+    }
+}
@@ -1,9 +1,13 @@
 use crate::map_dynamic::Destination;
+use crate::souls::desire::{DesireBehavior, DesireCtx};
 use crate::souls::human::HumanDecisionKind;
+use crate::souls::schedule::Activity;
 use imgui_inspect_derive::*;
 use map_model::BuildingID;
 use serde::{Deserialize, Serialize};
 
+register_desire!(Home);
+
 #[derive(Inspect, Clone, Serialize, Deserialize, Debug)]
 pub struct Home {
     house: BuildingID,
@@ -13,12 +17,17 @@ impl Home {
     pub fn new(house: BuildingID) -> Self {
         Home { house }
     }
+}
 
-    pub fn apply(&mut self) -> HumanDecisionKind {
-        HumanDecisionKind::GoTo(Destination::Building(self.house))
+impl DesireBehavior for Home {
+    fn score(&self, ctx: &DesireCtx<'_>) -> f32 {
+        match ctx.activity {
+            Some(Activity::Rest) => 0.45,
+            _ => 0.2,
+        }
     }
 
-    pub fn score(&self) -> f32 {
-        0.2
+    fn apply(&mut self, _: &mut DesireCtx<'_>) -> HumanDecisionKind {
+        HumanDecisionKind::GoTo(Destination::Building(self.house))
     }
 }
@@ -0,0 +1,140 @@
+use crate::economy::CommodityKind;
+use crate::map_dynamic::Destination;
+use crate::pedestrians::Location;
+use crate::souls::desire::{DesireBehavior, DesireCtx, Errand, ErrandStep};
+use crate::souls::human::HumanDecisionKind;
+use crate::souls::schedule::Activity;
+use crate::utils::time::{GameInstant, GameTime};
+use geom::Vec2;
+use imgui_inspect_derive::*;
+use map_model::{BuildingID, BuildingKind, Map};
+use ordered_float::OrderedFloat;
+use serde::{Deserialize, Serialize};
+
+register_desire!(Leisure);
+
+/// Time spent at the park or at the restaurant
+pub const OUTING_DURATION: i32 = GameTime::HOUR * 2;
+/// Minimum time between two outings
+pub const OUTING_COOLDOWN: i32 = GameTime::HOUR * 6;
+const PARK_PROBABILITY: f32 = 0.5;
+/// How much hunger a meal at the restaurant restores
+const MEAL_NOURISHMENT: f32 = 0.5;
+/// How much rest a completed outing restores
+const OUTING_REST: f32 = 0.3;
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub enum LeisureState {
+    Idle,
+    EatingOut,
+    /// Goes to the building then stays there for a while
+    Visiting(BuildingID, Option<GameInstant>),
+}
+
+debug_inspect_impl!(LeisureState);
+
+/// Goes to the park or eats out at a restaurant during leisure time
+#[derive(Inspect, Clone, Serialize, Deserialize, Debug)]
+pub struct Leisure {
+    last_outing: GameInstant,
+    state: LeisureState,
+    meal: Errand,
+}
+
+impl Leisure {
+    pub fn new(start: GameInstant) -> Self {
+        Leisure {
+            last_outing: start,
+            state: LeisureState::Idle,
+            meal: Errand::new(CommodityKind::Meal),
+        }
+    }
+
+    fn closest_park(map: &Map, pos: Vec2) -> Option<BuildingID> {
+        map.buildings()
+            .values()
+            .filter(|b| matches!(b.kind, BuildingKind::Park))
+            .min_by_key(|b| OrderedFloat(b.door_pos.distance2(pos)))
+            .map(|b| b.id)
+    }
+
+    fn end_outing(&mut self, time: &GameTime) -> HumanDecisionKind {
+        self.state = LeisureState::Idle;
+        self.last_outing = time.instant();
+        HumanDecisionKind::Yield
+    }
+}
+
+impl DesireBehavior for Leisure {
+    fn score(&self, ctx: &DesireCtx<'_>) -> f32 {
+        match self.state {
+            LeisureState::Idle => {
+                if ctx.activity == Some(Activity::Leisure)
+                    && self.last_outing.elapsed(ctx.time) > OUTING_COOLDOWN as f64
+                {
+                    0.4
+                } else {
+                    -1.0
+                }
+            }
+            LeisureState::EatingOut => {
+                if self.meal.needs_attention(ctx) {
+                    0.6
+                } else {
+                    -1.0
+                }
+            }
+            LeisureState::Visiting(..) => 0.6,
+        }
+    }
+
+    fn apply(&mut self, ctx: &mut DesireCtx<'_>) -> HumanDecisionKind {
+        match self.state {
+            LeisureState::Idle => {
+                let pos = ctx.trans.position();
+                let park = Self::closest_park(ctx.map, pos);
+                let wants_park =
+                    common::rand::rand3(pos.x, pos.y, ctx.time.timestamp as f32) < PARK_PROBABILITY;
+
+                match park {
+                    Some(park) if wants_park || !self.meal.can_afford(ctx) => {
+                        self.state = LeisureState::Visiting(park, None);
+                        HumanDecisionKind::GoTo(Destination::Building(park))
+                    }
+                    _ if self.meal.can_afford(ctx) => {
+                        self.meal.start(ctx);
+                        self.state = LeisureState::EatingOut;
+                        HumanDecisionKind::Yield
+                    }
+                    _ => self.end_outing(ctx.time),
+                }
+            }
+            LeisureState::EatingOut => match self.meal.apply(ctx) {
+                ErrandStep::Ongoing(decision) => decision,
+                ErrandStep::Done(Some(restaurant)) => {
+                    ctx.needs.eat(MEAL_NOURISHMENT);
+                    self.state = LeisureState::Visiting(restaurant, Some(ctx.time.instant()));
+                    HumanDecisionKind::Yield
+                }
+                ErrandStep::Done(None) | ErrandStep::Failed => self.end_outing(ctx.time),
+            },
+            LeisureState::Visiting(b, None) => {
+                if !ctx.map.buildings().contains_key(b) {
+                    return self.end_outing(ctx.time);
+                }
+                if ctx.loc != &Location::Building(b) {
+                    return HumanDecisionKind::GoTo(Destination::Building(b));
+                }
+                self.state = LeisureState::Visiting(b, Some(ctx.time.instant()));
+                HumanDecisionKind::Yield
+            }
+            LeisureState::Visiting(_, Some(arrived)) => {
+                if arrived.elapsed(ctx.time) > OUTING_DURATION as f64 {
+                    ctx.needs.relax(OUTING_REST);
+                    return self.end_outing(ctx.time);
+                }
+                HumanDecisionKind::Yield
+            }
+        }
+    }
+}
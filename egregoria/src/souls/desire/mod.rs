@@ -1,7 +1,164 @@
+use crate::economy::{Bought, Market, Sold};
+use crate::map_dynamic::{BuildingInfos, Router};
+use crate::pedestrians::Location;
+use crate::souls::human::{HumanDecision, HumanDecisionKind};
+use crate::souls::needs::Needs;
+use crate::souls::schedule::Activity;
+use crate::utils::time::GameTime;
+use crate::{ParCommandBuffer, SoulID};
+use geom::Transform;
+use imgui_inspect::{InspectArgsDefault, InspectRenderDefault};
+use legion::Registry;
+use map_model::Map;
+use serde::{Deserialize, Serialize};
+
+/// Declares a desire: generates the system that lets humans having a `Desire<$t>` component vote
+/// for it and act on it when elected, and registers the component for serialization.
+/// `$t` must implement `DesireBehavior`.
+macro_rules! register_desire {
+    ($t: ident) => {
+        paste::paste! {
+            register_system!([<desire_ $t:snake>]);
+            #[legion::system(par_for_each)]
+            pub fn [<desire_ $t:snake>](
+                #[resource] cbuf: &$crate::ParCommandBuffer,
+                #[resource] time: &$crate::utils::time::GameTime,
+                #[resource] binfos: &$crate::map_dynamic::BuildingInfos,
+                #[resource] market: &$crate::economy::Market,
+                #[resource] map: &map_model::Map,
+                me: &legion::Entity,
+                trans: &geom::Transform,
+                loc: &$crate::pedestrians::Location,
+                router: &mut $crate::map_dynamic::Router,
+                bought: &mut $crate::economy::Bought,
+                sold: &mut $crate::economy::Sold,
+                decision: &mut $crate::souls::human::HumanDecision,
+                agenda: Option<&$crate::souls::schedule::Agenda>,
+                needs: &mut $crate::souls::needs::Needs,
+                desire: &mut $crate::souls::desire::Desire<$t>,
+            ) {
+                let mut ctx = $crate::souls::desire::DesireCtx {
+                    cbuf,
+                    time,
+                    binfos,
+                    market,
+                    map,
+                    soul: $crate::SoulID(*me),
+                    trans,
+                    loc,
+                    activity: agenda.and_then(|a| a.activity(time)),
+                    router,
+                    bought,
+                    sold,
+                    needs,
+                };
+                desire.update($crate::my_hash(stringify!($t)), &mut ctx, decision);
+            }
+        }
+
+        inventory::submit! {
+            $crate::souls::desire::DesireRegistration {
+                register: |registry| {
+                    registry.register::<$crate::souls::desire::Desire<$t>>(
+                        $crate::my_hash(stringify!(Desire<$t>)),
+                    )
+                },
+            }
+        }
+    };
+}
+
 mod buyfood;
+mod buygoods;
+mod errand;
+mod healthcare;
 mod home;
+mod leisure;
+mod owncar;
+mod school;
 mod work;
 
 pub use buyfood::*;
+pub use buygoods::*;
+pub use errand::*;
+pub use healthcare::*;
 pub use home::*;
+pub use leisure::*;
+pub use owncar::*;
+pub use school::*;
 pub use work::*;
+
+/// Everything a desire can look at (and act on) about the soul it belongs to
+pub struct DesireCtx<'a> {
+    pub cbuf: &'a ParCommandBuffer,
+    pub time: &'a GameTime,
+    pub binfos: &'a BuildingInfos,
+    pub market: &'a Market,
+    pub map: &'a Map,
+    pub soul: SoulID,
+    pub trans: &'a Transform,
+    pub loc: &'a Location,
+    pub activity: Option<Activity>,
+    pub router: &'a mut Router,
+    pub bought: &'a mut Bought,
+    pub sold: &'a mut Sold,
+    pub needs: &'a mut Needs,
+}
+
+pub trait DesireBehavior: Send + Sync + 'static {
+    /// How much the soul wants to act on this desire right now. The desire with the highest
+    /// score gets to decide what the soul does next.
+    fn score(&self, ctx: &DesireCtx<'_>) -> f32;
+
+    fn apply(&mut self, ctx: &mut DesireCtx<'_>) -> HumanDecisionKind;
+}
+
+/// Component wrapping a desire, declared with `register_desire!`
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct Desire<T> {
+    pub v: T,
+    /// Last score given to the desire, for debugging purposes
+    pub score: f32,
+}
+
+impl<T: DesireBehavior> Desire<T> {
+    pub fn new(v: T) -> Self {
+        Self { v, score: 0.0 }
+    }
+
+    pub fn update(&mut self, id: u64, ctx: &mut DesireCtx<'_>, decision: &mut HumanDecision) {
+        if decision.take_elected(id) {
+            decision.kind = self.v.apply(ctx);
+            return;
+        }
+
+        if decision.is_voting() {
+            self.score = self.v.score(ctx);
+            decision.vote(id, self.score);
+        }
+    }
+}
+
+impl<T: InspectRenderDefault<T>> InspectRenderDefault<Desire<T>> for Desire<T> {
+    fn render(data: &[&Desire<T>], label: &'static str, ui: &imgui::Ui, args: &InspectArgsDefault) {
+        let d = unwrap_ret!(data.get(0));
+        <T as InspectRenderDefault<T>>::render(&[&d.v], label, ui, args);
+        ui.text(imgui::im_str!("score: {:.2}", d.score));
+    }
+
+    fn render_mut(
+        data: &mut [&mut Desire<T>],
+        label: &'static str,
+        ui: &imgui::Ui,
+        args: &InspectArgsDefault,
+    ) -> bool {
+        let d = unwrap_or!(data.get_mut(0), return false);
+        ui.text(imgui::im_str!("score: {:.2}", d.score));
+        <T as InspectRenderDefault<T>>::render_mut(&mut [&mut d.v], label, ui, args)
+    }
+}
+
+pub(crate) struct DesireRegistration {
+    pub register: fn(&mut Registry<u64>),
+}
+inventory::collect!(DesireRegistration);
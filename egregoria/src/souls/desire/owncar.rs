@@ -0,0 +1,276 @@
+use crate::economy::{CommodityKind, Market, Money};
+use crate::map_dynamic::{Destination, Router};
+use crate::pedestrians::Location;
+use crate::souls::desire::{DesireBehavior, DesireCtx};
+use crate::souls::household::{share_car, unshare_car};
+use crate::souls::human::HumanDecisionKind;
+use crate::utils::time::{GameInstant, GameTime};
+use crate::vehicles::{spawn_parked_vehicle, VehicleID, VehicleKind};
+use crate::{ParCommandBuffer, SoulID};
+use geom::Vec2;
+use imgui_inspect_derive::*;
+use map_model::BuildingID;
+use serde::{Deserialize, Serialize};
+
+/// Money kept aside when buying a car, so that there is some left for petrol and maintenance
+pub const CAR_SAVINGS_MARGIN: Money = Money::new(1000);
+pub const CAR_MAINTENANCE_COST: Money = Money::new(150);
+pub const CAR_MAINTENANCE_PERIOD: i32 = GameTime::DAY * 7;
+pub const PETROL_PER_REFUEL: i32 = 40;
+pub const REFUEL_PERIOD: i32 = GameTime::DAY * 2;
+/// Time after which a car that found no buyer is scrapped
+pub const CAR_SELL_TIMEOUT: i32 = GameTime::DAY * 2;
+
+register_desire!(OwnCar);
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub enum OwnCarState {
+    /// Walks until a car is affordable
+    NoCar,
+    WaitingForCar,
+    Owned,
+    WaitingForPetrol,
+    RefuelAt(BuildingID),
+    Selling(VehicleID, GameInstant),
+}
+
+debug_inspect_impl!(OwnCarState);
+
+#[derive(Inspect, Clone, Serialize, Deserialize, Debug)]
+pub struct OwnCar {
+    state: OwnCarState,
+    last_refuel: GameInstant,
+    last_maintenance: GameInstant,
+}
+
+impl OwnCar {
+    pub fn new(start: GameInstant) -> Self {
+        OwnCar {
+            state: OwnCarState::NoCar,
+            last_refuel: start,
+            last_maintenance: start,
+        }
+    }
+
+    /// For souls that already have a car, like immigrants arriving in the city
+    pub fn owned(start: GameInstant) -> Self {
+        OwnCar {
+            state: OwnCarState::Owned,
+            last_refuel: start,
+            last_maintenance: start,
+        }
+    }
+
+    fn refuel_cost() -> Money {
+        CommodityKind::Petrol.price() * PETROL_PER_REFUEL as i64
+    }
+
+    /// Stops using the car and puts it on the market. The car cannot be sold while someone is in it.
+    #[allow(clippy::too_many_arguments)]
+    fn sell(
+        &mut self,
+        cbuf: &ParCommandBuffer,
+        time: &GameTime,
+        soul: SoulID,
+        pos: Vec2,
+        loc: &Location,
+        router: &mut Router,
+        car: VehicleID,
+    ) -> HumanDecisionKind {
+        if matches!(loc, Location::Vehicle(_)) {
+            return HumanDecisionKind::Yield;
+        }
+
+        log::info!("{:?} cannot afford {:?} anymore, selling it", soul, car);
+
+        router.forget_vehicle(car);
+        cbuf.exec_ent(soul.0, move |goria| unshare_car(goria, soul, car));
+        cbuf.exec_on(soul.0, move |market: &mut Market| {
+            market.sell(soul, pos, CommodityKind::Car, 1)
+        });
+        self.state = OwnCarState::Selling(car, time.instant());
+        HumanDecisionKind::Yield
+    }
+}
+
+impl DesireBehavior for OwnCar {
+    fn score(&self, ctx: &DesireCtx<'_>) -> f32 {
+        let (time, soul, market) = (ctx.time, ctx.soul, ctx.market);
+        let (router, bought, sold) = (&*ctx.router, &*ctx.bought, &*ctx.sold);
+        let has_bought =
+            |kind: CommodityKind| bought.0.get(&kind).map(|v| !v.is_empty()).unwrap_or(false);
+
+        match self.state {
+            OwnCarState::NoCar => {
+                if market.money(soul) >= CommodityKind::Car.price() + CAR_SAVINGS_MARGIN {
+                    0.3
+                } else {
+                    -1.0
+                }
+            }
+            OwnCarState::WaitingForCar => {
+                if has_bought(CommodityKind::Car) || market.money(soul) < CommodityKind::Car.price()
+                {
+                    1.0
+                } else {
+                    -1.0
+                }
+            }
+            OwnCarState::Owned => {
+                if router.personal_car.is_none()
+                    || self.last_maintenance.elapsed(time) > CAR_MAINTENANCE_PERIOD as f64
+                    || self.last_refuel.elapsed(time) > REFUEL_PERIOD as f64
+                {
+                    0.4
+                } else {
+                    -1.0
+                }
+            }
+            OwnCarState::WaitingForPetrol => {
+                if has_bought(CommodityKind::Petrol) || market.money(soul) < Self::refuel_cost() {
+                    1.0
+                } else {
+                    -1.0
+                }
+            }
+            OwnCarState::RefuelAt(_) => 0.4,
+            OwnCarState::Selling(_, since) => {
+                if sold.0.iter().any(|t| t.kind == CommodityKind::Car)
+                    || since.elapsed(time) > CAR_SELL_TIMEOUT as f64
+                {
+                    1.0
+                } else {
+                    -1.0
+                }
+            }
+        }
+    }
+
+    fn apply(&mut self, ctx: &mut DesireCtx<'_>) -> HumanDecisionKind {
+        use HumanDecisionKind::*;
+        let (cbuf, binfos, time, soul, loc, market) = (
+            ctx.cbuf, ctx.binfos, ctx.time, ctx.soul, ctx.loc, ctx.market,
+        );
+        let (router, bought, sold) = (&mut *ctx.router, &mut *ctx.bought, &mut *ctx.sold);
+        let pos = ctx.trans.position();
+        match self.state {
+            OwnCarState::NoCar => {
+                cbuf.exec_on(soul.0, move |market: &mut Market| {
+                    market.buy(soul, pos, CommodityKind::Car, 1)
+                });
+                self.state = OwnCarState::WaitingForCar;
+            }
+            OwnCarState::WaitingForCar => {
+                for trade in bought.0.entry(CommodityKind::Car).or_default().drain(..) {
+                    deliver_car(cbuf, soul, trade.sell_pos);
+                    self.last_refuel = time.instant();
+                    self.last_maintenance = time.instant();
+                    self.state = OwnCarState::Owned;
+                }
+
+                if matches!(self.state, OwnCarState::WaitingForCar)
+                    && market.money(soul) < CommodityKind::Car.price()
+                {
+                    cbuf.exec_on(soul.0, move |market: &mut Market| {
+                        market.cancel_buy(soul, CommodityKind::Car)
+                    });
+                    self.state = OwnCarState::NoCar;
+                }
+            }
+            OwnCarState::Owned => {
+                let car = unwrap_or!(router.personal_car, {
+                    // The car could not be delivered, maybe there was no parking spot
+                    deliver_car(cbuf, soul, pos);
+                    return Yield;
+                });
+
+                if self.last_maintenance.elapsed(time) > CAR_MAINTENANCE_PERIOD as f64 {
+                    if market.money(soul) < CAR_MAINTENANCE_COST {
+                        return self.sell(cbuf, time, soul, pos, loc, router, car);
+                    }
+                    cbuf.exec_on(soul.0, move |market: &mut Market| {
+                        market.add_money(soul, -CAR_MAINTENANCE_COST);
+                    });
+                    self.last_maintenance = time.instant();
+                    return Yield;
+                }
+
+                if self.last_refuel.elapsed(time) > REFUEL_PERIOD as f64 {
+                    if market.money(soul) < Self::refuel_cost() {
+                        return self.sell(cbuf, time, soul, pos, loc, router, car);
+                    }
+                    cbuf.exec_on(soul.0, move |market: &mut Market| {
+                        market.buy(soul, pos, CommodityKind::Petrol, PETROL_PER_REFUEL)
+                    });
+                    self.state = OwnCarState::WaitingForPetrol;
+                }
+            }
+            OwnCarState::WaitingForPetrol => {
+                for trade in bought.0.entry(CommodityKind::Petrol).or_default().drain(..) {
+                    cbuf.exec_on(soul.0, move |market: &mut Market| {
+                        market.produce(soul, CommodityKind::Petrol, -trade.qty);
+                    });
+                    self.state = match binfos.building_owned_by(trade.seller) {
+                        Some(b) => OwnCarState::RefuelAt(b),
+                        None => {
+                            self.last_refuel = time.instant();
+                            OwnCarState::Owned
+                        }
+                    };
+                }
+
+                if matches!(self.state, OwnCarState::WaitingForPetrol)
+                    && market.money(soul) < Self::refuel_cost()
+                {
+                    cbuf.exec_on(soul.0, move |market: &mut Market| {
+                        market.cancel_buy(soul, CommodityKind::Petrol)
+                    });
+                    let car = unwrap_or!(router.personal_car, {
+                        self.state = OwnCarState::Owned;
+                        return Yield;
+                    });
+                    return self.sell(cbuf, time, soul, pos, loc, router, car);
+                }
+            }
+            OwnCarState::RefuelAt(b) => {
+                if loc != &Location::Building(b) {
+                    return GoTo(Destination::Building(b));
+                }
+                log::info!("{:?} refueled at {:?}", soul, b);
+                self.last_refuel = time.instant();
+                self.state = OwnCarState::Owned;
+            }
+            OwnCarState::Selling(car, since) => {
+                let was_sold = sold.0.iter().any(|trade| trade.kind == CommodityKind::Car);
+                sold.0.retain(|trade| trade.kind != CommodityKind::Car);
+
+                if !was_sold && since.elapsed(time) <= CAR_SELL_TIMEOUT as f64 {
+                    return Yield;
+                }
+
+                if !was_sold {
+                    log::info!("{:?} found no buyer for {:?}, scrapping it", soul, car);
+                    cbuf.exec_on(soul.0, move |market: &mut Market| {
+                        market.cancel_sell(soul, CommodityKind::Car);
+                        market.produce(soul, CommodityKind::Car, -1);
+                    });
+                }
+
+                cbuf.kill(car.0);
+                self.state = OwnCarState::NoCar;
+            }
+        }
+        Yield
+    }
+}
+
+/// Creates the bought car near where it was sold and hands the keys to the buyer's household
+fn deliver_car(cbuf: &ParCommandBuffer, soul: SoulID, near: Vec2) {
+    cbuf.exec_ent(soul.0, move |goria| {
+        let car = unwrap_or!(spawn_parked_vehicle(goria, VehicleKind::Car, near), {
+            log::warn!("no parking spot to deliver the car of {:?}", soul);
+            return;
+        });
+        share_car(goria, soul, car);
+    });
+}
@@ -0,0 +1,33 @@
+use crate::map_dynamic::Destination;
+use crate::souls::desire::{DesireBehavior, DesireCtx};
+use crate::souls::human::HumanDecisionKind;
+use crate::souls::schedule::Activity;
+use imgui_inspect_derive::*;
+use map_model::BuildingID;
+use serde::{Deserialize, Serialize};
+
+register_desire!(School);
+
+/// Children go to their school during the school hours of their agenda
+#[derive(Inspect, Clone, Default, Serialize, Deserialize, Debug)]
+pub struct School {
+    /// The nearest staffed school covering the house, updated by the services every hour
+    pub school: Option<BuildingID>,
+}
+
+impl DesireBehavior for School {
+    fn score(&self, ctx: &DesireCtx<'_>) -> f32 {
+        if self.school.is_some() && ctx.activity == Some(Activity::School) {
+            0.5
+        } else {
+            -1.0
+        }
+    }
+
+    fn apply(&mut self, _: &mut DesireCtx<'_>) -> HumanDecisionKind {
+        match self.school {
+            Some(school) => HumanDecisionKind::GoTo(Destination::Building(school)),
+            None => HumanDecisionKind::Yield,
+        }
+    }
+}
@@ -1,12 +1,15 @@
-use crate::map_dynamic::{Destination, Router};
+use crate::map_dynamic::Destination;
 use crate::pedestrians::Location;
+use crate::souls::desire::{DesireBehavior, DesireCtx};
 use crate::souls::human::HumanDecisionKind;
-use crate::utils::time::{GameTime, RecTimeInterval, SECONDS_PER_HOUR};
+use crate::souls::schedule::Activity;
 use crate::vehicles::VehicleID;
 use imgui_inspect_derive::*;
 use map_model::BuildingID;
 use serde::{Deserialize, Serialize};
 
+register_desire!(Work);
+
 #[derive(Debug, Copy, Clone, Serialize, Deserialize)]
 pub enum WorkKind {
     Driver {
@@ -20,25 +23,30 @@ debug_inspect_impl!(WorkKind);
 #[derive(Inspect, Debug, Copy, Clone, Serialize, Deserialize)]
 pub struct Work {
     workplace: BuildingID,
-    work_inter: RecTimeInterval,
     pub kind: WorkKind,
     on_mission: bool,
 }
 
 impl Work {
-    pub fn new(workplace: BuildingID, kind: WorkKind, offset: f32) -> Self {
+    pub fn new(workplace: BuildingID, kind: WorkKind) -> Self {
         Work {
             workplace,
-            work_inter: RecTimeInterval::new(
-                (8, (offset * SECONDS_PER_HOUR as f32) as i32),
-                (18, (offset * SECONDS_PER_HOUR as f32) as i32),
-            ),
             kind,
             on_mission: false,
         }
     }
+}
+
+impl DesireBehavior for Work {
+    fn score(&self, ctx: &DesireCtx<'_>) -> f32 {
+        if self.on_mission || ctx.activity == Some(Activity::Work) {
+            0.5
+        } else {
+            0.0
+        }
+    }
 
-    pub fn apply(&mut self, loc: &Location, router: &Router) -> HumanDecisionKind {
+    fn apply(&mut self, ctx: &mut DesireCtx<'_>) -> HumanDecisionKind {
         use HumanDecisionKind::*;
         match self.kind {
             WorkKind::Worker => GoTo(Destination::Building(self.workplace)),
@@ -46,14 +54,14 @@ impl Work {
                 deliver_order,
                 truck,
             } => {
-                if &Location::Building(self.workplace) != loc {
+                if &Location::Building(self.workplace) != ctx.loc {
                     MultiStack(vec![
                         GoTo(Destination::Building(self.workplace)),
-                        SetVehicle(router.personal_car),
+                        SetVehicle(ctx.router.personal_car),
                     ])
                 } else if let Some(b) = deliver_order {
                     MultiStack(vec![
-                        SetVehicle(router.personal_car),
+                        SetVehicle(ctx.router.personal_car),
                         GoTo(Destination::Building(self.workplace)),
                         GoTo(Destination::Building(b)),
                         SetVehicle(Some(truck)),
@@ -64,12 +72,4 @@ impl Work {
             }
         }
     }
-
-    pub fn score(&self, time: &GameTime) -> f32 {
-        if self.on_mission || self.work_inter.dist_until(time.daytime) == 0 {
-            0.5
-        } else {
-            0.0
-        }
-    }
 }
@@ -1,11 +1,14 @@
-use super::desire::Work;
-use crate::economy::{CommodityKind, Market, Sold, Workers};
+use super::desire::{Desire, Work};
+use crate::economy::{
+    CommodityKind, Ledger, Market, Occupation, Sold, Workers, COMPANY_STARTING_CAPITAL,
+};
 use crate::engine_interaction::Selectable;
-use crate::map_dynamic::BuildingInfos;
+use crate::map_dynamic::{BuildingInfos, Environment};
+use crate::pedestrians::Location;
 use crate::souls::desire::WorkKind;
 use crate::utils::time::GameTime;
 use crate::vehicles::VehicleID;
-use crate::{my_hash, Egregoria, ParCommandBuffer, SoulID};
+use crate::{Egregoria, ParCommandBuffer, SoulID};
 use geom::{Transform, Vec2};
 use imgui_inspect_derive::*;
 use legion::world::SubWorld;
@@ -35,6 +38,8 @@ pub struct GoodsCompanyDescription {
     pub kind: CompanyKind,
     pub recipe: Recipe,
     pub n_workers: i32,
+    /// Pollution emitted each time the recipe is executed
+    pub pollution: f32,
     pub size: f32,
     pub asset_location: &'static str,
 }
@@ -48,6 +53,117 @@ impl Default for GoodsCompanyRegistry {
     fn default() -> Self {
         Self {
             descriptions: vec![
+                GoodsCompanyDescription {
+                    name: "Pharmacy",
+                    bkind: BuildingKind::Company(30),
+                    bgen: BuildingGen::CenteredDoor {
+                        vertical_factor: 1.0,
+                    },
+                    kind: CompanyKind::Store,
+                    recipe: Recipe {
+                        consumption: vec![(CommodityKind::Medicine, 1)], // TODO: actually implement stores
+                        production: vec![(CommodityKind::Medicine, 1)],
+                        complexity: 50,
+                        storage_multiplier: 10,
+                    },
+                    n_workers: 3,
+                    pollution: 0.0,
+                    size: 15.0,
+                    asset_location: "assets/pharmacy.png",
+                },
+                GoodsCompanyDescription {
+                    name: "Pharmaceutical lab",
+                    bkind: BuildingKind::Company(29),
+                    bgen: BuildingGen::CenteredDoor {
+                        vertical_factor: 1.0,
+                    },
+                    kind: CompanyKind::Factory { n_trucks: 1 },
+                    recipe: Recipe {
+                        consumption: vec![(CommodityKind::Oil, 1), (CommodityKind::Flower, 1)],
+                        production: vec![(CommodityKind::Medicine, 5)],
+                        complexity: 200,
+                        storage_multiplier: 5,
+                    },
+                    n_workers: 10,
+                    pollution: 1.0,
+                    size: 60.0,
+                    asset_location: "assets/pharmaceutical_lab.png",
+                },
+                GoodsCompanyDescription {
+                    name: "Restaurant",
+                    bkind: BuildingKind::Company(28),
+                    bgen: BuildingGen::CenteredDoor {
+                        vertical_factor: 1.0,
+                    },
+                    kind: CompanyKind::Store,
+                    recipe: Recipe {
+                        consumption: vec![(CommodityKind::Meat, 1), (CommodityKind::Vegetable, 1)],
+                        production: vec![(CommodityKind::Meal, 4)],
+                        complexity: 50,
+                        storage_multiplier: 5,
+                    },
+                    n_workers: 5,
+                    pollution: 0.0,
+                    size: 20.0,
+                    asset_location: "assets/restaurant.png",
+                },
+                GoodsCompanyDescription {
+                    name: "Car dealership",
+                    bkind: BuildingKind::Company(27),
+                    bgen: BuildingGen::CenteredDoor {
+                        vertical_factor: 0.6,
+                    },
+                    kind: CompanyKind::Store,
+                    recipe: Recipe {
+                        consumption: vec![
+                            (CommodityKind::Metal, 2),
+                            (CommodityKind::HighTechProduct, 1),
+                        ],
+                        production: vec![(CommodityKind::Car, 1)],
+                        complexity: 300,
+                        storage_multiplier: 3,
+                    },
+                    n_workers: 5,
+                    pollution: 0.0,
+                    size: 40.0,
+                    asset_location: "assets/car_dealership.png",
+                },
+                GoodsCompanyDescription {
+                    name: "Gas station",
+                    bkind: BuildingKind::Company(26),
+                    bgen: BuildingGen::CenteredDoor {
+                        vertical_factor: 0.6,
+                    },
+                    kind: CompanyKind::Store,
+                    recipe: Recipe {
+                        consumption: vec![(CommodityKind::Petrol, 10)], // TODO: actually implement stores
+                        production: vec![(CommodityKind::Petrol, 10)],
+                        complexity: 50,
+                        storage_multiplier: 10,
+                    },
+                    n_workers: 2,
+                    pollution: 0.0,
+                    size: 20.0,
+                    asset_location: "assets/gas_station.png",
+                },
+                GoodsCompanyDescription {
+                    name: "Petrol refinery",
+                    bkind: BuildingKind::Company(25),
+                    bgen: BuildingGen::CenteredDoor {
+                        vertical_factor: 1.0,
+                    },
+                    kind: CompanyKind::Factory { n_trucks: 1 },
+                    recipe: Recipe {
+                        consumption: vec![(CommodityKind::Oil, 1)],
+                        production: vec![(CommodityKind::Petrol, 10)],
+                        complexity: 100,
+                        storage_multiplier: 5,
+                    },
+                    n_workers: 5,
+                    pollution: 5.0,
+                    size: 80.0,
+                    asset_location: "assets/petrol_refinery.png",
+                },
                 GoodsCompanyDescription {
                     name: "Useless warehouse",
                     bkind: BuildingKind::Company(24),
@@ -62,6 +178,7 @@ impl Default for GoodsCompanyRegistry {
                         storage_multiplier: 0,
                     },
                     n_workers: 100,
+                    pollution: 0.0,
                     size: 100.0,
                     asset_location: "assets/warehouse.png",
                 },
@@ -87,6 +204,7 @@ impl Default for GoodsCompanyRegistry {
                         storage_multiplier: 5,
                     },
                     n_workers: 10,
+                    pollution: 0.0,
                     size: 80.0,
                     asset_location: "assets/supermarket.png",
                 },
@@ -104,6 +222,7 @@ impl Default for GoodsCompanyRegistry {
                         storage_multiplier: 5,
                     },
                     n_workers: 10,
+                    pollution: 0.0,
                     size: 10.0,
                     asset_location: "assets/clothes_store.png",
                 },
@@ -121,6 +240,7 @@ impl Default for GoodsCompanyRegistry {
                         storage_multiplier: 5,
                     },
                     n_workers: 10,
+                    pollution: 1.0,
                     size: 80.0,
                     asset_location: "assets/cloth_factory.png",
                 },
@@ -138,6 +258,7 @@ impl Default for GoodsCompanyRegistry {
                         storage_multiplier: 5,
                     },
                     n_workers: 5,
+                    pollution: 4.0,
                     size: 80.0,
                     asset_location: "assets/polyester_refinery.png",
                 },
@@ -155,6 +276,7 @@ impl Default for GoodsCompanyRegistry {
                         storage_multiplier: 5,
                     },
                     n_workers: 5,
+                    pollution: 2.0,
                     size: 20.0,
                     asset_location: "assets/oil_pump.png",
                 },
@@ -172,6 +294,7 @@ impl Default for GoodsCompanyRegistry {
                         storage_multiplier: 5,
                     },
                     n_workers: 10,
+                    pollution: 1.5,
                     size: 80.0,
                     asset_location: "assets/textile_processing_facility.png",
                 },
@@ -189,6 +312,7 @@ impl Default for GoodsCompanyRegistry {
                         storage_multiplier: 5,
                     },
                     n_workers: 10,
+                    pollution: 0.2,
                     size: 80.0,
                     asset_location: "assets/wool_farm.png",
                 },
@@ -206,6 +330,7 @@ impl Default for GoodsCompanyRegistry {
                         storage_multiplier: 5,
                     },
                     n_workers: 10,
+                    pollution: 0.0,
                     size: 10.0,
                     asset_location: "assets/florist.png",
                 },
@@ -223,6 +348,7 @@ impl Default for GoodsCompanyRegistry {
                         storage_multiplier: 5,
                     },
                     n_workers: 5,
+                    pollution: 0.2,
                     size: 80.0,
                     asset_location: "assets/horticulturalist.png",
                 },
@@ -240,6 +366,7 @@ impl Default for GoodsCompanyRegistry {
                         storage_multiplier: 5,
                     },
                     n_workers: 10,
+                    pollution: 0.0,
                     size: 80.0,
                     asset_location: "assets/hightech_store.png",
                 },
@@ -257,6 +384,7 @@ impl Default for GoodsCompanyRegistry {
                         storage_multiplier: 5,
                     },
                     n_workers: 10,
+                    pollution: 1.0,
                     size: 80.0,
                     asset_location: "assets/hightech_facility.png",
                 },
@@ -274,6 +402,7 @@ impl Default for GoodsCompanyRegistry {
                         storage_multiplier: 5,
                     },
                     n_workers: 10,
+                    pollution: 3.0,
                     size: 80.0,
                     asset_location: "assets/rare_metal_mine.png",
                 },
@@ -291,6 +420,7 @@ impl Default for GoodsCompanyRegistry {
                         storage_multiplier: 5,
                     },
                     n_workers: 10,
+                    pollution: 0.0,
                     size: 80.0,
                     asset_location: "assets/furniture_store.png",
                 },
@@ -308,6 +438,7 @@ impl Default for GoodsCompanyRegistry {
                         storage_multiplier: 5,
                     },
                     n_workers: 10,
+                    pollution: 5.0,
                     size: 80.0,
                     asset_location: "assets/foundry.png",
                 },
@@ -325,6 +456,7 @@ impl Default for GoodsCompanyRegistry {
                         storage_multiplier: 5,
                     },
                     n_workers: 10,
+                    pollution: 3.0,
                     size: 80.0,
                     asset_location: "assets/iron_mine.png",
                 },
@@ -342,6 +474,7 @@ impl Default for GoodsCompanyRegistry {
                         storage_multiplier: 5,
                     },
                     n_workers: 10,
+                    pollution: 1.0,
                     size: 80.0,
                     asset_location: "assets/woodmill.png",
                 },
@@ -357,6 +490,7 @@ impl Default for GoodsCompanyRegistry {
                         storage_multiplier: 5,
                     },
                     n_workers: 10,
+                    pollution: 0.0,
                     size: 200.0,
                     asset_location: "assets/lumber_yard.png",
                 },
@@ -374,6 +508,7 @@ impl Default for GoodsCompanyRegistry {
                         storage_multiplier: 5,
                     },
                     n_workers: 10,
+                    pollution: 1.0,
                     size: 80.0,
                     asset_location: "assets/meat_facility.png",
                 },
@@ -391,6 +526,7 @@ impl Default for GoodsCompanyRegistry {
                         storage_multiplier: 5,
                     },
                     n_workers: 5,
+                    pollution: 1.0,
                     size: 50.0,
                     asset_location: "assets/slaughterhouse.png",
                 },
@@ -406,6 +542,7 @@ impl Default for GoodsCompanyRegistry {
                         storage_multiplier: 5,
                     },
                     n_workers: 5,
+                    pollution: 0.5,
                     size: 80.0,
                     asset_location: "assets/animal_farm.png",
                 },
@@ -421,6 +558,7 @@ impl Default for GoodsCompanyRegistry {
                         storage_multiplier: 5,
                     },
                     n_workers: 10,
+                    pollution: 0.2,
                     size: 70.0,
                     asset_location: "assets/vegetable_farm.png",
                 },
@@ -438,6 +576,7 @@ impl Default for GoodsCompanyRegistry {
                         storage_multiplier: 5,
                     },
                     n_workers: 3,
+                    pollution: 0.0,
                     size: 10.0,
                     asset_location: "assets/bakery.png",
                 },
@@ -455,6 +594,7 @@ impl Default for GoodsCompanyRegistry {
                         storage_multiplier: 5,
                     },
                     n_workers: 10,
+                    pollution: 0.5,
                     size: 80.0,
                     asset_location: "assets/flour_factory.png",
                 },
@@ -470,6 +610,7 @@ impl Default for GoodsCompanyRegistry {
                         storage_multiplier: 5,
                     },
                     n_workers: 10,
+                    pollution: 0.2,
                     size: 120.0,
                     asset_location: "assets/cereal_farm.png",
                 },
@@ -531,6 +672,8 @@ pub struct GoodsCompany {
     pub recipe: Recipe,
     pub building: BuildingID,
     pub max_workers: i32,
+    #[serde(default)]
+    pub pollution: f32,
     /// In [0; 1] range, to show how much has been made until new product
     pub progress: f32,
     pub driver: Option<SoulID>,
@@ -544,14 +687,15 @@ pub fn company_soul(goria: &mut Egregoria, company: GoodsCompany) -> Option<Soul
     let obb = b.obb;
     drop(map);
 
+    let workers = Workers::new(company.max_workers);
+
     let e = goria.world.push(());
 
     let soul = SoulID(e);
 
     {
         let m = &mut *goria.write::<Market>();
-        m.produce(soul, CommodityKind::JobOpening, company.max_workers);
-        m.sell_all(soul, door_pos, CommodityKind::JobOpening);
+        m.add_money(soul, COMPANY_STARTING_CAPITAL);
 
         company.recipe.init(soul, door_pos, m);
     }
@@ -564,7 +708,8 @@ pub fn company_soul(goria: &mut Egregoria, company: GoodsCompany) -> Option<Soul
         e,
         (
             company,
-            Workers::default(),
+            workers,
+            Ledger::default(),
             Sold::default(),
             Transform::new(obb.center()),
             Selectable::new(obb.axis()[0].magnitude() * 0.5),
@@ -574,9 +719,51 @@ pub fn company_soul(goria: &mut Egregoria, company: GoodsCompany) -> Option<Soul
     Some(soul)
 }
 
+/// The company goes bankrupt: its workers lose their job, its trucks are sold and
+/// its building is demolished, so that no new company is started there with fresh money.
+/// Closing is delayed while the truck is on the road.
+pub fn close_company(goria: &mut Egregoria, soul: SoulID) {
+    let comp = unwrap_ret!(goria.comp::<GoodsCompany>(soul.0));
+    let trucks = comp.trucks.clone();
+    let building = comp.building;
+    let driving = comp
+        .driver
+        .and_then(|d| goria.comp::<Location>(d.0))
+        .map(|loc| matches!(loc, Location::Vehicle(v) if trucks.contains(v)))
+        .unwrap_or(false);
+    if driving {
+        return;
+    }
+
+    log::info!("{:?} went bankrupt", soul);
+
+    let employees: Vec<SoulID> = goria
+        .comp::<Workers>(soul.0)
+        .map(|w| w.employees().collect())
+        .unwrap_or_default();
+    for worker in employees {
+        if let Some(occ) = goria.comp_mut::<Occupation>(worker.0) {
+            occ.job = None;
+        }
+        goria
+            .read::<ParCommandBuffer>()
+            .remove_component::<Desire<Work>>(worker.0);
+    }
+
+    goria.write::<BuildingInfos>().remove(building);
+    goria.map_mut().remove_building(building);
+    goria.write::<Market>().forget(soul);
+
+    let cbuf = goria.read::<ParCommandBuffer>();
+    for truck in trucks {
+        cbuf.kill(truck.0);
+    }
+    cbuf.kill(soul.0);
+}
+
 register_system!(company);
 #[system(par_for_each)]
-#[read_component(Work)]
+#[read_component(Desire<Work>)]
 pub fn company(
     #[resource] time: &GameTime,
     #[resource] cbuf: &ParCommandBuffer,
@@ -589,7 +776,7 @@ pub fn company(
     workers: &Workers,
     sw: &SubWorld,
 ) {
-    let n_workers = workers.0.len();
+    let n_workers = workers.n_employed();
     let soul = SoulID(*me);
 
     if company.recipe.should_produce(soul, market) {
@@ -610,22 +797,28 @@ pub fn company(
         cbuf.exec_on(soul.0, move |market| {
             recipe.act(soul, bpos, market);
         });
+        let pollution = company.pollution;
+        if pollution > 0.0 {
+            cbuf.exec_on(soul.0, move |env: &mut Environment| {
+                env.pollution.add(bpos, pollution)
+            });
+        }
         return;
     }
 
     if_chain::if_chain! {
         if let Some(driver) = company.driver;
         if let Ok(ent) = sw.entry_ref(driver.0);
-        if let Ok(w) = ent.get_component::<Work>();
-        if matches!(w.kind, WorkKind::Driver { deliver_order: None, .. });
+        if let Ok(w) = ent.get_component::<Desire<Work>>();
+        if matches!(w.v.kind, WorkKind::Driver { deliver_order: None, .. });
         if let Some(trade) = sold.0.drain(..1.min(sold.0.len())).next();
         if let Some(owner_build) = binfos.building_owned_by(trade.buyer);
         then {
             log::info!("asked driver to deliver");
 
             cbuf.exec_ent(soul.0, move |goria| {
-                if let Some(w) = goria.comp_mut::<Work>(driver.0) {
-                    if let WorkKind::Driver { ref mut deliver_order, .. } = w.kind {
+                if let Some(w) = goria.comp_mut::<Desire<Work>>(driver.0) {
+                    if let WorkKind::Driver { ref mut deliver_order, .. } = w.v.kind {
                         *deliver_order = Some(owner_build)
                     }
                 }
@@ -633,28 +826,23 @@ pub fn company(
         }
     }
 
-    for &worker in workers.0.iter() {
-        if let Ok(ent) = sw.entry_ref(worker.0) {
-            if ent.get_component::<Work>().is_err() {
-                let mut kind = WorkKind::Worker;
-
-                if let Some(truck) = company.trucks.get(0) {
-                    if matches!(company.kind, CompanyKind::Factory { .. })
-                        && company.driver.is_none()
-                    {
-                        kind = WorkKind::Driver {
-                            deliver_order: None,
-                            truck: *truck,
-                        };
-
-                        company.driver = Some(worker);
-                    }
-                }
-
-                let offset = common::rand::randu(my_hash(worker) as u32);
+    // Workers are hired by the job market, one of them drives the truck
+    if company
+        .driver
+        .map(|d| !workers.employees().any(|w| w == d))
+        .unwrap_or(false)
+    {
+        company.driver = None;
+    }
 
-                cbuf.add_component(worker.0, Work::new(company.building, kind, offset))
-            }
-        }
+    if company.driver.is_none() && matches!(company.kind, CompanyKind::Factory { .. }) {
+        let truck = *unwrap_ret!(company.trucks.first());
+        let worker = unwrap_ret!(workers.employees().next());
+        let kind = WorkKind::Driver {
+            deliver_order: None,
+            truck,
+        };
+        company.driver = Some(worker);
+        cbuf.add_component(worker.0, Desire::new(Work::new(company.building, kind)))
     }
 }
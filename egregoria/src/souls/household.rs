@@ -0,0 +1,230 @@
+use crate::economy::{leave_job, Market, Money};
+use crate::map_dynamic::{BuildingInfos, Router};
+use crate::pedestrians::Location;
+use crate::souls::desire::{Desire, OwnCar};
+use crate::souls::human::spawn_human;
+use crate::souls::needs::Needs;
+use crate::utils::rand_provider::RandProvider;
+use crate::utils::time::GameTime;
+use crate::vehicles::{Vehicle, VehicleID};
+use crate::{Egregoria, ParCommandBuffer, SoulID};
+use imgui_inspect_derive::*;
+use legion::world::SubWorld;
+use legion::{system, Entity, EntityStore};
+use map_model::BuildingID;
+use serde::{Deserialize, Serialize};
+
+pub const MAX_HOUSEHOLD_SIZE: u32 = 5;
+const RETIREE_HOUSEHOLD_PROBABILITY: f32 = 0.2;
+
+/// Savings of a newly spawned household per adult, chosen uniformly between the two bounds
+const STARTING_MONEY: (Money, Money) = (Money::new(2000), Money::new(15000));
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Role {
+    /// Looks for a job and goes shopping
+    Worker,
+    /// Stays home
+    Child,
+    /// Goes shopping but does not work
+    Retiree,
+}
+
+debug_inspect_impl!(Role);
+
+/// A family living under the same roof, sharing its money and its car.
+/// The household is a soul of its own so that it can hold money on the market.
+#[derive(Inspect, Clone, Serialize, Deserialize)]
+pub struct Household {
+    pub house: BuildingID,
+    pub members: Vec<SoulID>,
+    /// The member buying and maintaining the household car, which is shared by all the adults
+    pub driver: Option<SoulID>,
+}
+
+#[derive(Inspect, Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct HouseholdMember {
+    pub household: SoulID,
+    pub role: Role,
+}
+
+fn random_roles(size: u32, rng: &mut RandProvider) -> Vec<Role> {
+    if rng.random::<f32>() < RETIREE_HOUSEHOLD_PROBABILITY {
+        return vec![Role::Retiree; size.min(2) as usize];
+    }
+    (0..size)
+        .map(|i| if i < 2 { Role::Worker } else { Role::Child })
+        .collect()
+}
+
+pub fn spawn_household(goria: &mut Egregoria, house: BuildingID, size: u32) -> Option<SoulID> {
+    let roles = random_roles(size, &mut *goria.write::<RandProvider>());
+    spawn_household_with(goria, house, &roles)
+}
+
+pub fn spawn_household_with(
+    goria: &mut Egregoria,
+    house: BuildingID,
+    roles: &[Role],
+) -> Option<SoulID> {
+    if roles.is_empty() {
+        return None;
+    }
+
+    let household = SoulID(goria.world.push(()));
+
+    if !goria
+        .write::<BuildingInfos>()
+        .move_in(house, household, roles.len() as u32)
+    {
+        goria.world.remove(household.0);
+        return None;
+    }
+
+    let n_adults = roles.iter().filter(|r| !matches!(r, Role::Child)).count() as i64;
+    let (min_money, max_money) = STARTING_MONEY;
+    let r = goria.write::<RandProvider>().random::<f32>();
+    let money = min_money + Money(((max_money - min_money).cents() as f32 * r) as i64);
+    goria
+        .write::<Market>()
+        .add_money(household, money * n_adults);
+
+    let mut members = vec![];
+    let mut driver = None;
+    for &role in roles {
+        let human = unwrap_cont!(spawn_human(goria, house, household, role));
+        if driver.is_none() && !matches!(role, Role::Child) {
+            driver = Some(human);
+        }
+        members.push(human);
+    }
+
+    if members.is_empty() {
+        goria.write::<BuildingInfos>().move_out(household);
+        goria.write::<Market>().forget(household);
+        goria.world.remove(household.0);
+        return None;
+    }
+
+    if let Some(driver) = driver {
+        let time = goria.read::<GameTime>().instant();
+        goria.add_comp(driver.0, Desire::new(OwnCar::new(time)));
+    }
+
+    goria.world.push_with_id(
+        household.0,
+        (Household {
+            house,
+            members,
+            driver,
+        },),
+    );
+
+    Some(household)
+}
+
+/// The adults of the household of the soul, or the soul itself if it has no household
+fn adults(goria: &Egregoria, soul: SoulID) -> Vec<SoulID> {
+    let household = unwrap_or!(goria.comp::<HouseholdMember>(soul.0), return vec![soul]).household;
+    let h = unwrap_or!(goria.comp::<Household>(household.0), return vec![soul]);
+    h.members
+        .iter()
+        .copied()
+        .filter(|m| {
+            goria
+                .comp::<HouseholdMember>(m.0)
+                .map(|m| !matches!(m.role, Role::Child))
+                .unwrap_or(false)
+        })
+        .collect()
+}
+
+/// Hands the car to all the adults of the household of the soul.
+/// Whoever finds it parked first can drive it.
+pub(crate) fn share_car(goria: &mut Egregoria, soul: SoulID, car: VehicleID) {
+    for adult in adults(goria, soul) {
+        if let Some(router) = goria.comp_mut::<Router>(adult.0) {
+            router.set_personal_car(car);
+        }
+    }
+}
+
+/// Takes the car back from all the members of the household of the soul, when it is sold
+pub(crate) fn unshare_car(goria: &mut Egregoria, soul: SoulID, car: VehicleID) {
+    for adult in adults(goria, soul) {
+        if let Some(router) = goria.comp_mut::<Router>(adult.0) {
+            router.forget_vehicle(car);
+        }
+    }
+    if let Some(v) = goria.comp_mut::<Vehicle>(car.0) {
+        v.claimed_by = None;
+    }
+}
+
+/// Removes the household and its members from the city, along with their cars.
+/// The members leave their job first.
+pub(crate) fn leave_city(
+    soul: SoulID,
+    household: &Household,
+    subworld: &SubWorld,
+    binfos: &mut BuildingInfos,
+    cbuf: &ParCommandBuffer,
+) {
+    binfos.move_out(soul);
+    for &member in &household.members {
+        if let Ok(ent) = subworld.entry_ref(member.0) {
+            if let Some(car) = ent
+                .get_component::<Router>()
+                .ok()
+                .and_then(|r| r.personal_car)
+            {
+                cbuf.kill(car.0);
+            }
+            if let Ok(&Location::Building(b)) = ent.get_component::<Location>() {
+                binfos.get_out(b, member);
+            }
+        }
+        cbuf.exec_on(member.0, move |market: &mut Market| market.forget(member));
+        // killed after leaving its job, so that the truck it was maybe driving is replaced
+        cbuf.exec_ent(member.0, move |goria| {
+            leave_job(goria, member);
+            goria.read::<ParCommandBuffer>().kill(member.0);
+        });
+    }
+    cbuf.exec_on(soul.0, move |market: &mut Market| market.forget(soul));
+    cbuf.kill(soul.0);
+}
+
+register_system!(household_emigration);
+/// Households where most members are unhappy leave the city
+#[system(for_each)]
+#[read_component(Needs)]
+#[read_component(Router)]
+#[read_component(Location)]
+pub fn household_emigration(
+    #[resource] binfos: &mut BuildingInfos,
+    #[resource] cbuf: &ParCommandBuffer,
+    me: &Entity,
+    household: &Household,
+    subworld: &SubWorld,
+) {
+    let n_unhappy = household
+        .members
+        .iter()
+        .filter(|m| {
+            subworld
+                .entry_ref(m.0)
+                .ok()
+                .and_then(|ent| ent.get_component::<Needs>().ok().map(Needs::is_unhappy))
+                .unwrap_or(false)
+        })
+        .count();
+
+    if n_unhappy * 2 <= household.members.len() {
+        return;
+    }
+
+    let soul = SoulID(*me);
+    log::info!("{:?} is unhappy and left the city", soul);
+    leave_city(soul, household, subworld, binfos, cbuf);
+}
@@ -1,22 +1,53 @@
-use crate::economy::CommodityKind::JobOpening;
-use crate::economy::{Bought, Market};
-use crate::map_dynamic::{BuildingInfos, Destination, Router};
-use crate::pedestrians::{spawn_pedestrian, Location};
-use crate::souls::desire::{BuyFood, Home, Work};
+use crate::economy::{Bought, Market, Occupation, Skill, Sold};
+use crate::map_dynamic::{Destination, Router};
+use crate::pedestrians::spawn_pedestrian;
+use crate::souls::desire::{BuyFood, BuyGoods, Desire, Healthcare, Home, Leisure, School};
+use crate::souls::household::{HouseholdMember, Role};
+use crate::souls::needs::Needs;
+use crate::souls::schedule::{Agenda, ScheduleRegistry};
+use crate::utils::rand_provider::RandProvider;
+use crate::utils::time::GameInstant;
 use crate::utils::time::GameTime;
-use crate::vehicles::{spawn_parked_vehicle, VehicleID, VehicleKind};
-use crate::{Egregoria, ParCommandBuffer, SoulID};
+use crate::vehicles::VehicleID;
+use crate::{Egregoria, SoulID};
 use geom::Transform;
 use imgui_inspect_derive::*;
 use legion::system;
-use legion::Entity;
 use map_model::BuildingID;
+use ordered_float::OrderedFloat;
 use serde::{Deserialize, Serialize};
 
+/// Each desire of a human votes with its score, the highest score gets elected and decides
+/// what the human does next. See `register_desire!`.
 #[derive(Inspect, Serialize, Deserialize, Default)]
 pub struct HumanDecision {
-    kind: HumanDecisionKind,
+    pub kind: HumanDecisionKind,
     wait: u8,
+    voting: bool,
+    votes: Vec<(u64, f32)>,
+    elected: Option<u64>,
+}
+
+impl HumanDecision {
+    pub fn is_voting(&self) -> bool {
+        self.voting
+    }
+
+    pub fn vote(&mut self, desire: u64, score: f32) {
+        match self.votes.iter_mut().find(|(id, _)| *id == desire) {
+            Some(v) => v.1 = score,
+            None => self.votes.push((desire, score)),
+        }
+    }
+
+    /// Returns true once after the desire was elected
+    pub fn take_elected(&mut self, desire: u64) -> bool {
+        if self.elected == Some(desire) {
+            self.elected = None;
+            return true;
+        }
+        false
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -58,104 +89,83 @@ impl HumanDecisionKind {
     }
 }
 
-#[derive(Debug)]
-enum NextDesire<'a> {
-    None,
-    Home(&'a mut Home),
-    Work(&'a mut Work),
-    Food(&'a mut BuyFood),
-}
-
 register_system!(update_decision);
 #[system(par_for_each)]
-pub fn update_decision(
-    #[resource] cbuf: &ParCommandBuffer,
-    #[resource] time: &GameTime,
-    #[resource] binfos: &BuildingInfos,
-    me: &Entity,
-    trans: &Transform,
-    loc: &Location,
-    router: &mut Router,
-    bought: &mut Bought,
-    decision: &mut HumanDecision,
-    food: Option<&mut BuyFood>,
-    home: Option<&mut Home>,
-    work: Option<&mut Work>,
-) {
+pub fn update_decision(trans: &Transform, router: &mut Router, decision: &mut HumanDecision) {
     if decision.wait != 0 {
         decision.wait -= 1;
         return;
     }
     let pos = trans.position();
-    decision.wait = (30.0 + common::rand::rand2(pos.x, pos.y) * 50.0) as u8;
-    if !decision.kind.update(router) {
+    let wait = (30.0 + common::rand::rand2(pos.x, pos.y) * 50.0) as u8;
+
+    if decision.voting {
+        decision.voting = false;
+        decision.elected = decision
+            .votes
+            .drain(..)
+            .max_by_key(|&(_, score)| OrderedFloat(score))
+            .map(|(id, _)| id);
+        decision.wait = wait;
         return;
     }
 
-    let soul = SoulID(*me);
-    let mut decision_id = NextDesire::None;
-    let mut max_score = f32::NEG_INFINITY;
-
-    if let Some(home) = home {
-        let score = home.score();
-
-        if score > max_score {
-            max_score = score;
-            decision_id = NextDesire::Home(home);
-        }
-    }
-
-    if let Some(work) = work {
-        let score = work.score(time);
-
-        if score > max_score {
-            max_score = score;
-            decision_id = NextDesire::Work(work);
-        }
-    }
-
-    if let Some(food) = food {
-        let score = food.score(time, loc, bought);
-
-        #[allow(unused_assignments)]
-        if score > max_score {
-            max_score = score;
-            decision_id = NextDesire::Food(food);
-        }
+    if !decision.kind.update(router) {
+        decision.wait = wait;
+        return;
     }
 
-    match decision_id {
-        NextDesire::Home(home) => decision.kind = home.apply(),
-        NextDesire::Work(work) => decision.kind = work.apply(loc, router),
-        NextDesire::Food(food) => {
-            decision.kind = food.apply(cbuf, binfos, time, soul, trans, loc, bought)
-        }
-        NextDesire::None => {}
-    }
+    // Desires vote during the next tick, whatever the order the systems run in
+    decision.voting = true;
+    decision.elected = None;
 }
 
-pub fn spawn_human(goria: &mut Egregoria, house: BuildingID) -> Option<SoulID> {
-    let map = goria.map();
-    let housepos = map.buildings().get(house)?.door_pos;
-    drop(map);
-
+pub fn spawn_human(
+    goria: &mut Egregoria,
+    house: BuildingID,
+    household: SoulID,
+    role: Role,
+) -> Option<SoulID> {
     let human = SoulID(spawn_pedestrian(goria, house)?);
-    let car = spawn_parked_vehicle(goria, VehicleKind::Car, housepos);
 
-    let mut m = goria.write::<Market>();
-    m.buy(human, housepos, JobOpening, 1);
-    drop(m);
-
-    goria.write::<BuildingInfos>().set_owner(house, human);
+    goria.write::<Market>().share_money(human, household);
 
     let time = goria.read::<GameTime>().instant();
 
+    let registry = goria.read::<ScheduleRegistry>();
+    let mut rng = goria.write::<RandProvider>();
+    let agenda = registry
+        .pick(role, &mut rng)
+        .map(|profile| Agenda::from_profile(profile, &mut rng))
+        .unwrap_or_default();
+    let healthcare = Healthcare::new(time, rng.random());
+    let skill = Skill::random(&mut rng);
+    let goods = BuyGoods::new(|_| GameInstant {
+        timestamp: time.timestamp - rng.random::<f64>() * GameTime::DAY as f64 * 30.0,
+    });
+    drop(rng);
+    drop(registry);
+
     let mut e = goria.world.entry(human.0)?;
 
     e.add_component(HumanDecision::default());
-    e.add_component(Home::new(house));
-    e.add_component(BuyFood::new(time));
+    e.add_component(Needs::default());
+    e.add_component(agenda);
+    e.add_component(Desire::new(Home::new(house)));
+    e.add_component(Desire::new(Leisure::new(time)));
+    e.add_component(Desire::new(healthcare));
+    if matches!(role, Role::Worker) {
+        e.add_component(Occupation::new(skill));
+    }
+    if matches!(role, Role::Child) {
+        e.add_component(Desire::new(School::default()));
+    } else {
+        e.add_component(Desire::new(BuyFood::new(time)));
+        e.add_component(Desire::new(goods));
+    }
     e.add_component(Bought::default());
-    e.add_component(Router::new(car));
+    e.add_component(Sold::default());
+    e.add_component(Router::new(None));
+    e.add_component(HouseholdMember { household, role });
     Some(human)
 }
@@ -0,0 +1,301 @@
+use crate::economy::{leave_job, JobMarket, Market};
+use crate::map_dynamic::{BuildingInfos, Itinerary, Router};
+use crate::pedestrians::Location;
+use crate::souls::desire::{Desire, OwnCar};
+use crate::souls::household::{
+    share_car, spawn_household, unshare_car, Household, HouseholdMember, Role, MAX_HOUSEHOLD_SIZE,
+};
+use crate::souls::human::spawn_human;
+use crate::souls::needs::Satisfaction;
+use crate::utils::rand_provider::RandProvider;
+use crate::utils::time::GameTime;
+use crate::vehicles::{make_vehicle_entity, Vehicle, VehicleID, VehicleKind};
+use crate::{Egregoria, ParCommandBuffer, SoulID};
+use geom::Transform;
+use imgui_inspect_derive::*;
+use legion::{Entity, IntoQuery};
+use map_model::{BuildingID, BuildingKind, LaneKind, Map};
+use ordered_float::OrderedFloat;
+use serde::{Deserialize, Serialize};
+
+/// Fraction of the open jobs that immigrants come to fill each in-game hour
+pub const IMMIGRATION_RATE: f32 = 0.2;
+pub const MAX_IMMIGRANTS_PER_TICK: usize = 10;
+/// Number of intersections furthest from the center where immigrants can enter the city
+const N_ENTRY_POINTS: usize = 4;
+
+/// Probability per day for a household with workers to have a child
+pub const BIRTH_RATE: f32 = 0.01;
+/// Probability per day for a human to die
+pub const DEATH_RATE: f32 = 0.001;
+pub const RETIREE_DEATH_RATE: f32 = 0.01;
+
+register_resource!(Migration, "migration");
+#[derive(Inspect, Clone, Default, Serialize, Deserialize, Debug)]
+pub struct Migration {
+    /// Whether humans are born and die in the city, on top of moving in and out of it
+    pub births_and_deaths: bool,
+}
+
+pub(crate) fn migration(goria: &mut Egregoria) {
+    immigration(goria);
+    if goria.read::<Migration>().births_and_deaths {
+        births(goria);
+        deaths(goria);
+    }
+}
+
+/// Immigrants come when there are more open jobs than people looking for one and
+/// there is room for them, as long as the city is pleasant enough
+fn immigration(goria: &mut Egregoria) {
+    let jobs = goria.read::<JobMarket>();
+    let open_jobs = jobs.open_jobs as i32 - jobs.unemployed as i32;
+    drop(jobs);
+
+    if open_jobs <= 0 {
+        return;
+    }
+
+    let free_houses = free_houses(&goria.map(), &goria.read::<BuildingInfos>());
+    if free_houses.is_empty() {
+        return;
+    }
+
+    let delta = goria.read::<GameTime>().delta;
+    let attractiveness = goria.read::<Satisfaction>().attractiveness();
+    let expected =
+        open_jobs as f32 * IMMIGRATION_RATE * attractiveness * delta / GameTime::HOUR as f32;
+
+    let mut rng = goria.write::<RandProvider>();
+    let n = (expected.floor() + (rng.random::<f32>() < expected.fract()) as i32 as f32) as usize;
+    let picks: Vec<_> = (0..n.min(MAX_IMMIGRANTS_PER_TICK))
+        .filter_map(|_| {
+            let &(house, free) =
+                free_houses.get((rng.random::<f32>() * free_houses.len() as f32) as usize)?;
+            let size = 1 + (rng.random::<f32>() * free.min(MAX_HOUSEHOLD_SIZE) as f32) as u32;
+            Some((house, size.min(free)))
+        })
+        .collect();
+    drop(rng);
+
+    for (house, size) in picks {
+        let household = unwrap_cont!(spawn_household(goria, house, size));
+        arrive_by_car(goria, household, house);
+        log::info!("{:?} immigrated to {:?}", household, house);
+    }
+}
+
+fn free_houses(map: &Map, binfos: &BuildingInfos) -> Vec<(BuildingID, u32)> {
+    map.buildings()
+        .values()
+        .filter(|b| matches!(b.kind, BuildingKind::House))
+        .filter_map(|b| {
+            let free = binfos.get(b.id)?.free_capacity();
+            if free == 0 {
+                return None;
+            }
+            Some((b.id, free))
+        })
+        .collect()
+}
+
+/// Where immigrants enter the city: on one of the dead ends furthest from the center,
+/// or on the intersections furthest from the center if there are none
+fn entry_point(map: &Map, rng: &mut RandProvider) -> Option<Transform> {
+    let inters = map.intersections();
+    if inters.is_empty() {
+        return None;
+    }
+    let center = inters.values().map(|i| i.pos).sum::<geom::Vec2>() / inters.len() as f32;
+
+    let mut candidates: Vec<_> = inters.values().filter(|i| i.roads.len() == 1).collect();
+    if candidates.is_empty() {
+        candidates = inters.values().filter(|i| !i.roads.is_empty()).collect();
+    }
+    candidates.sort_by_key(|i| OrderedFloat(-i.pos.distance2(center)));
+    candidates.truncate(N_ENTRY_POINTS);
+
+    let entry = candidates.get((rng.random::<f32>() * candidates.len() as f32) as usize)?;
+    let road = map.roads().get(*entry.roads.first()?)?;
+    let &(lane, _) = road
+        .outgoing_lanes_from(entry.id)
+        .iter()
+        .find(|(_, kind)| matches!(kind, LaneKind::Driving))?;
+    let lane = map.lanes().get(lane)?;
+
+    let mut trans = Transform::new(lane.points.first());
+    trans.set_direction(lane.points.first_dir()?);
+    Some(trans)
+}
+
+/// The household driver arrives with the household car from the edge of the map,
+/// the rest of the family is already at home
+fn arrive_by_car(goria: &mut Egregoria, household: SoulID, house: BuildingID) {
+    let driver = unwrap_ret!(goria.comp::<Household>(household.0).and_then(|h| h.driver));
+    let trans = unwrap_ret!(entry_point(
+        &goria.map(),
+        &mut *goria.write::<RandProvider>()
+    ));
+
+    let car = VehicleID(make_vehicle_entity(
+        goria,
+        trans,
+        Vehicle::new_driving(VehicleKind::Car),
+        Itinerary::none(),
+        true,
+    ));
+
+    goria.write::<BuildingInfos>().get_out(house, driver);
+    *unwrap_ret!(goria.comp_mut::<Location>(driver.0)) = Location::Vehicle(car);
+    *unwrap_ret!(goria.comp_mut::<Transform>(driver.0)) = trans;
+    share_car(goria, driver, car);
+
+    let time = goria.read::<GameTime>().instant();
+    goria.add_comp(driver.0, Desire::new(OwnCar::owned(time)));
+}
+
+fn births(goria: &mut Egregoria) {
+    let delta = goria.read::<GameTime>().delta;
+    let households: Vec<(SoulID, BuildingID)> = <(Entity, &Household)>::query()
+        .iter(&goria.world)
+        .filter(|(_, h)| (h.members.len() as u32) < MAX_HOUSEHOLD_SIZE)
+        .map(|(&e, h)| (SoulID(e), h.house))
+        .collect();
+
+    let expected = households.len() as f32 * BIRTH_RATE * delta / GameTime::DAY as f32;
+    let mut rng = goria.write::<RandProvider>();
+    if rng.random::<f32>() >= expected {
+        return;
+    }
+    let (household, house) = unwrap_ret!(households
+        .get((rng.random::<f32>() * households.len() as f32) as usize)
+        .copied());
+    drop(rng);
+
+    let has_workers = goria
+        .comp::<Household>(household.0)
+        .map(|h| {
+            h.members.iter().any(|m| {
+                goria
+                    .comp::<HouseholdMember>(m.0)
+                    .map(|m| matches!(m.role, Role::Worker))
+                    .unwrap_or(false)
+            })
+        })
+        .unwrap_or(false);
+    if !has_workers || !goria.write::<BuildingInfos>().add_resident(household) {
+        return;
+    }
+
+    let child = unwrap_ret!(spawn_human(goria, house, household, Role::Child));
+    unwrap_ret!(goria.comp_mut::<Household>(household.0))
+        .members
+        .push(child);
+    log::info!("{:?} was born in {:?}", child, household);
+}
+
+fn deaths(goria: &mut Egregoria) {
+    let delta = goria.read::<GameTime>().delta;
+    let humans: Vec<(SoulID, HouseholdMember, f32)> = <(Entity, &HouseholdMember)>::query()
+        .iter(&goria.world)
+        .map(|(&e, &m)| {
+            let rate = match m.role {
+                Role::Retiree => RETIREE_DEATH_RATE,
+                _ => DEATH_RATE,
+            };
+            (SoulID(e), m, rate)
+        })
+        .collect();
+
+    let total: f32 = humans.iter().map(|&(_, _, rate)| rate).sum();
+    let expected = total * delta / GameTime::DAY as f32;
+    let mut rng = goria.write::<RandProvider>();
+    if rng.random::<f32>() >= expected {
+        return;
+    }
+
+    // Pick the deceased proportionally to its death rate
+    let mut r = rng.random::<f32>() * total;
+    drop(rng);
+    let &(human, member, _) = unwrap_ret!(humans
+        .iter()
+        .find(|&&(_, _, rate)| {
+            r -= rate;
+            r <= 0.0
+        })
+        .or_else(|| humans.last()));
+
+    die(goria, human, member.household);
+}
+
+/// Removes the human from its household. The car goes to another adult of the household,
+/// and a household without members leaves the city.
+pub(crate) fn die(goria: &mut Egregoria, human: SoulID, household: SoulID) {
+    log::info!("{:?} died", human);
+
+    let mut car = goria.comp::<Router>(human.0).and_then(|r| r.personal_car);
+    match goria.comp::<Location>(human.0) {
+        Some(&Location::Building(b)) => goria.write::<BuildingInfos>().get_out(b, human),
+        Some(&Location::Vehicle(v)) if Some(v) == car => {
+            // Nobody can bring the car back
+            goria.read::<ParCommandBuffer>().kill(v.0);
+            unshare_car(goria, human, v);
+            car = None;
+        }
+        _ => {}
+    }
+    // The car was maybe waiting for the deceased
+    if let Some(v) = car.and_then(|car| goria.comp_mut::<Vehicle>(car.0)) {
+        if v.claimed_by == Some(human) {
+            v.claimed_by = None;
+        }
+    }
+    leave_job(goria, human);
+    goria.write::<Market>().forget(human);
+    goria.write::<BuildingInfos>().remove_resident(household);
+
+    let h = unwrap_ret!(goria.comp_mut::<Household>(household.0));
+    h.members.retain(|&m| m != human);
+    let was_driver = h.driver == Some(human);
+    let members = h.members.clone();
+
+    let cbuf = goria.read::<ParCommandBuffer>();
+    cbuf.kill(human.0);
+    drop(cbuf);
+
+    if members.is_empty() {
+        goria.write::<BuildingInfos>().move_out(household);
+        goria.write::<Market>().forget(household);
+        if let Some(car) = car {
+            goria.read::<ParCommandBuffer>().kill(car.0);
+        }
+        goria.read::<ParCommandBuffer>().kill(household.0);
+        return;
+    }
+
+    if !was_driver {
+        return;
+    }
+
+    let heir = members.iter().copied().find(|m| {
+        goria
+            .comp::<HouseholdMember>(m.0)
+            .map(|m| !matches!(m.role, Role::Child))
+            .unwrap_or(false)
+    });
+    unwrap_ret!(goria.comp_mut::<Household>(household.0)).driver = heir;
+
+    let heir = unwrap_or!(heir, {
+        if let Some(car) = car {
+            goria.read::<ParCommandBuffer>().kill(car.0);
+        }
+        return;
+    });
+
+    let time = goria.read::<GameTime>().instant();
+    // The heir already shares the car
+    match car {
+        Some(_) => goria.add_comp(heir.0, Desire::new(OwnCar::owned(time))),
+        None => goria.add_comp(heir.0, Desire::new(OwnCar::new(time))),
+    }
+}
@@ -1,6 +1,6 @@
 use crate::map_dynamic::BuildingInfos;
 use crate::souls::goods_company::{company_soul, CompanyKind, GoodsCompany, GoodsCompanyRegistry};
-use crate::souls::human::spawn_human;
+use crate::souls::services::service_soul;
 use crate::vehicles::{spawn_parked_vehicle, VehicleKind};
 use crate::Egregoria;
 use common::FastMap;
@@ -11,7 +11,13 @@ use map_model::{BuildingID, BuildingKind};
 pub mod desire;
 
 pub mod goods_company;
+pub mod household;
 pub mod human;
+pub mod migration;
+pub mod needs;
+pub mod schedule;
+pub mod services;
+pub mod zoning;
 
 pub(crate) fn add_souls_to_empty_buildings(goria: &mut Egregoria) {
     let map = goria.map();
@@ -19,7 +25,12 @@ pub(crate) fn add_souls_to_empty_buildings(goria: &mut Egregoria) {
     let mut empty_buildings: FastMap<BuildingKind, Vec<(BuildingID, Vec2)>> = FastMap::default();
 
     for (id, building) in map.buildings() {
-        if unwrap_cont!(infos.get(id)).owner.is_some() {
+        let info = unwrap_cont!(infos.get(id));
+        // Houses are filled by immigrants, see `migration`
+        if matches!(building.kind, BuildingKind::House) {
+            continue;
+        }
+        if info.owner.is_some() {
             continue;
         }
 
@@ -33,20 +44,17 @@ pub(crate) fn add_souls_to_empty_buildings(goria: &mut Egregoria) {
 
     let mut n_souls_added = 0;
 
-    for &(build_id, _) in empty_buildings
-        .get(&BuildingKind::House)
-        .unwrap_or(&vec![])
-        .iter()
-        .take(100)
-    {
-        spawn_human(goria, build_id);
-        n_souls_added += 1;
-    }
-
     for (bkind, &(build_id, pos)) in empty_buildings
         .iter()
         .flat_map(|(bkind, v)| v.iter().map(move |x| (bkind, x)))
     {
+        if let BuildingKind::Service(kind) = *bkind {
+            if service_soul(goria, kind, build_id).is_some() {
+                n_souls_added += 1;
+            }
+            continue;
+        }
+
         let registry = goria.read::<GoodsCompanyRegistry>();
         let des = &unwrap_or!(registry.descriptions.get(bkind), continue);
 
@@ -69,6 +77,7 @@ pub(crate) fn add_souls_to_empty_buildings(goria: &mut Egregoria) {
             building: build_id,
             recipe: des.recipe.clone(),
             max_workers: des.n_workers,
+            pollution: des.pollution,
             progress: 0.0,
             driver: None,
             trucks: {
@@ -0,0 +1,274 @@
+use crate::economy::{Market, Money};
+use crate::map_dynamic::{BuildingInfos, Environment};
+use crate::pedestrians::Location;
+use crate::souls::household::{HouseholdMember, Role};
+use crate::souls::services::ServiceCoverage;
+use crate::utils::time::GameTime;
+use imgui_inspect_derive::*;
+use legion::world::SubWorld;
+use legion::{system, Query};
+use map_model::Map;
+use serde::{Deserialize, Serialize};
+
+/// Time for a soul that does not eat to starve
+pub const HUNGER_DECAY: f32 = GameTime::DAY as f32 * 2.0;
+/// Time for a soul that never goes home to be exhausted
+pub const REST_DECAY: f32 = GameTime::DAY as f32;
+/// Time spent at home to be fully rested
+pub const REST_RECOVERY: f32 = GameTime::HOUR as f32 * 8.0;
+/// Money of the household per member under which it feels poor
+pub const COMFORTABLE_MONEY: Money = Money::new(5000);
+/// Daily time spent travelling after which a soul cannot stand its commute anymore
+pub const COMMUTE_TOLERANCE: f32 = GameTime::HOUR as f32 * 3.0;
+
+/// Souls whose satisfaction is below this are unhappy
+pub const UNHAPPY_THRESHOLD: f32 = 0.3;
+
+/// Well-being of a human. Each need goes from 0 (unbearable) to 1 (fully satisfied),
+/// decays over time and is restored when the desires are fulfilled.
+#[derive(Inspect, Clone, Serialize, Deserialize, Debug)]
+pub struct Needs {
+    pub hunger: f32,
+    pub rest: f32,
+    pub money: f32,
+    pub commute: f32,
+    /// How clean and quiet the surroundings of the home are
+    pub environment: f32,
+    /// How well the home is covered by schools, hospitals, police and firefighters
+    pub services: f32,
+    /// Time spent travelling during the last day or so
+    travel_time: f32,
+    /// Weighted average of the needs, see `Needs::satisfaction`
+    pub satisfaction: f32,
+}
+
+impl Default for Needs {
+    fn default() -> Self {
+        Self {
+            hunger: 1.0,
+            rest: 1.0,
+            money: 1.0,
+            commute: 1.0,
+            environment: 1.0,
+            services: 1.0,
+            travel_time: 0.0,
+            satisfaction: 1.0,
+        }
+    }
+}
+
+impl Needs {
+    pub fn eat(&mut self, amount: f32) {
+        self.hunger = (self.hunger + amount).min(1.0);
+    }
+
+    pub fn relax(&mut self, amount: f32) {
+        self.rest = (self.rest + amount).min(1.0);
+    }
+
+    pub fn is_unhappy(&self) -> bool {
+        self.satisfaction < UNHAPPY_THRESHOLD
+    }
+
+    fn satisfaction(&self) -> f32 {
+        0.3 * self.hunger
+            + 0.2 * self.rest
+            + 0.2 * self.money
+            + 0.1 * self.commute
+            + 0.1 * self.environment
+            + 0.1 * self.services
+    }
+
+    fn update(
+        &mut self,
+        delta: f32,
+        at_home: bool,
+        travelling: bool,
+        money_per_member: Money,
+        nuisance: f32,
+        coverage: f32,
+    ) {
+        self.hunger = (self.hunger - delta / HUNGER_DECAY).max(0.0);
+
+        if at_home {
+            self.rest = (self.rest + delta / REST_RECOVERY).min(1.0);
+        } else {
+            self.rest = (self.rest - delta / REST_DECAY).max(0.0);
+        }
+
+        let wealth =
+            (money_per_member.cents() as f32 / COMFORTABLE_MONEY.cents() as f32).clamp(0.0, 1.0);
+        self.money += (wealth - self.money) * (delta / GameTime::DAY as f32).min(1.0);
+        self.environment +=
+            (1.0 - nuisance - self.environment) * (delta / GameTime::DAY as f32).min(1.0);
+        self.services += (coverage - self.services) * (delta / GameTime::DAY as f32).min(1.0);
+
+        self.travel_time -= self.travel_time * (delta / GameTime::DAY as f32).min(1.0);
+        if travelling {
+            self.travel_time += delta;
+        }
+        self.commute = 1.0 - (self.travel_time / COMMUTE_TOLERANCE).clamp(0.0, 1.0);
+
+        self.satisfaction = self.satisfaction();
+    }
+}
+
+register_system!(needs_update);
+#[system(par_for_each)]
+pub fn needs_update(
+    #[resource] time: &GameTime,
+    #[resource] binfos: &BuildingInfos,
+    #[resource] market: &Market,
+    #[resource] map: &Map,
+    #[resource] env: &Environment,
+    #[resource] coverage: &ServiceCoverage,
+    loc: &Location,
+    member: &HouseholdMember,
+    needs: &mut Needs,
+) {
+    let at_home =
+        matches!(*loc, Location::Building(b) if binfos.home_of(member.household) == Some(b));
+    let travelling = !matches!(*loc, Location::Building(_));
+
+    let home = binfos.home_of(member.household);
+    let n_members = home
+        .and_then(|h| binfos.get(h))
+        .and_then(|info| {
+            info.residents
+                .iter()
+                .find(|&&(h, _)| h == member.household)
+                .map(|&(_, n)| n)
+        })
+        .unwrap_or(1)
+        .max(1);
+    let money_per_member = Money(market.money(member.household).cents() / n_members as i64);
+
+    let nuisance = home
+        .and_then(|h| map.buildings().get(h))
+        .map(|b| env.nuisance(b.door_pos))
+        .unwrap_or(0.0);
+
+    let coverage = home.map(|h| coverage.overall(h)).unwrap_or(0.0);
+
+    needs.update(
+        time.delta,
+        at_home,
+        travelling,
+        money_per_member,
+        nuisance,
+        coverage,
+    );
+
+    // Children are fed by their family
+    if at_home && matches!(member.role, Role::Child) {
+        needs.eat(1.0);
+    }
+}
+
+register_resource_noserialize!(Satisfaction);
+/// Satisfaction of the population as a whole, recomputed every tick
+#[derive(Clone, Debug)]
+pub struct Satisfaction {
+    /// Average satisfaction of the humans, 1 if there is nobody
+    pub average: f32,
+    pub n_humans: u32,
+    pub n_unhappy: u32,
+}
+
+impl Default for Satisfaction {
+    fn default() -> Self {
+        Self {
+            average: 1.0,
+            n_humans: 0,
+            n_unhappy: 0,
+        }
+    }
+}
+
+impl Satisfaction {
+    /// How much people want to move in the city, in [0; 1].
+    /// Nobody wants to live in a city where everyone is unhappy.
+    pub fn attractiveness(&self) -> f32 {
+        ((self.average - UNHAPPY_THRESHOLD) / (1.0 - UNHAPPY_THRESHOLD)).clamp(0.0, 1.0)
+    }
+}
+
+register_system!(satisfaction_update);
+#[system]
+pub fn satisfaction_update(
+    #[resource] satisfaction: &mut Satisfaction,
+    qry: &mut Query<&Needs>,
+    sw: &SubWorld,
+) {
+    let mut total = 0.0;
+    let mut n_humans = 0;
+    let mut n_unhappy = 0;
+    qry.for_each(sw, |needs| {
+        total += needs.satisfaction;
+        n_humans += 1;
+        if needs.is_unhappy() {
+            n_unhappy += 1;
+        }
+    });
+    satisfaction.n_humans = n_humans;
+    satisfaction.n_unhappy = n_unhappy;
+    satisfaction.average = if n_humans == 0 {
+        1.0
+    } else {
+        total / n_humans as f32
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Needs, Satisfaction, COMMUTE_TOLERANCE};
+    use crate::economy::Money;
+    use crate::souls::needs::COMFORTABLE_MONEY;
+    use crate::utils::time::GameTime;
+
+    const HOUR: f32 = GameTime::HOUR as f32;
+
+    #[test]
+    fn test_needs_decay() {
+        let mut needs = Needs::default();
+
+        // a day out without eating
+        for _ in 0..24 {
+            needs.update(HOUR, false, false, COMFORTABLE_MONEY, 0.0, 1.0);
+        }
+        assert!((needs.hunger - 0.5).abs() < 1e-3);
+        assert_eq!(needs.rest, 0.0);
+        assert_eq!(needs.money, 1.0);
+        assert!(!needs.is_unhappy());
+
+        needs.eat(1.0);
+        assert_eq!(needs.hunger, 1.0);
+        for _ in 0..8 {
+            needs.update(HOUR, true, false, COMFORTABLE_MONEY, 0.0, 1.0);
+        }
+        assert!((needs.rest - 1.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_poor_and_commuting_souls_are_unhappy() {
+        let mut needs = Needs::default();
+
+        for _ in 0..48 {
+            needs.update(HOUR, false, true, Money::ZERO, 1.0, 0.0);
+        }
+        assert!(needs.hunger < 1e-3);
+        assert!(needs.money < 0.2);
+        assert!(needs.travel_time > COMMUTE_TOLERANCE);
+        assert_eq!(needs.commute, 0.0);
+        assert!(needs.is_unhappy());
+    }
+
+    #[test]
+    fn test_attractiveness() {
+        let mut satisfaction = Satisfaction::default();
+        assert_eq!(satisfaction.attractiveness(), 1.0);
+
+        satisfaction.average = 0.1;
+        assert_eq!(satisfaction.attractiveness(), 0.0);
+    }
+}
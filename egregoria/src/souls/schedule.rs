@@ -0,0 +1,185 @@
+use crate::souls::household::Role;
+use crate::utils::rand_provider::RandProvider;
+use crate::utils::time::{DayTime, GameTime, RecTimeInterval, Weekday, SECONDS_PER_HOUR};
+use common::saveload::{Encoder, JSON};
+use imgui_inspect_derive::*;
+use serde::{Deserialize, Serialize};
+
+const SCHEDULES_PATH: &str = "assets/schedules.json";
+/// Built-in copy of the schedules, used when the file can't be read like when running the tests
+const DEFAULT_SCHEDULES: &[u8] = include_bytes!("../../../assets/schedules.json");
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Activity {
+    Work,
+    School,
+    Leisure,
+    Rest,
+}
+
+debug_inspect_impl!(Activity);
+
+/// An activity of a schedule profile, as written in assets/schedules.json
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ProfileEntry {
+    pub activity: Activity,
+    pub days: Vec<Weekday>,
+    /// Hours in [0; 24[, the activity goes through midnight if end < start
+    pub start: f32,
+    pub end: f32,
+    /// Start and end are delayed by a random amount of hours up to jitter, picked once per soul
+    #[serde(default)]
+    pub jitter: f32,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ScheduleProfile {
+    pub name: String,
+    pub role: Role,
+    /// Relative chance of being picked among the profiles of the same role
+    pub weight: f32,
+    pub entries: Vec<ProfileEntry>,
+}
+
+register_resource_noserialize!(ScheduleRegistry);
+pub struct ScheduleRegistry {
+    pub profiles: Vec<ScheduleProfile>,
+}
+
+impl Default for ScheduleRegistry {
+    fn default() -> Self {
+        let profiles = std::fs::read(SCHEDULES_PATH)
+            .and_then(|x| JSON::decode(&x))
+            .or_else(|e| {
+                log::error!("couldn't read {}: {}", SCHEDULES_PATH, e);
+                JSON::decode(DEFAULT_SCHEDULES)
+            })
+            .unwrap_or_else(|e| {
+                log::error!("couldn't read the built-in schedules: {}", e);
+                vec![]
+            });
+
+        Self { profiles }
+    }
+}
+
+impl ScheduleRegistry {
+    pub fn pick(&self, role: Role, rng: &mut RandProvider) -> Option<&ScheduleProfile> {
+        let candidates = || self.profiles.iter().filter(move |p| p.role == role);
+        let total: f32 = candidates().map(|p| p.weight).sum();
+
+        let mut r = rng.random::<f32>() * total;
+        for p in candidates() {
+            r -= p.weight;
+            if r <= 0.0 {
+                return Some(p);
+            }
+        }
+        candidates().last()
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AgendaEntry {
+    pub activity: Activity,
+    /// Bitset of the weekdays on which the activity starts
+    days: u8,
+    interval: RecTimeInterval,
+}
+
+debug_inspect_impl!(AgendaEntry);
+
+impl AgendaEntry {
+    pub fn is_active(&self, t: DayTime) -> bool {
+        if self.interval.dist_until(t) != 0 {
+            return false;
+        }
+
+        // After midnight, we are still in the activity that started the day before
+        let mut day = t.weekday();
+        if self.interval.overlaps_midnight()
+            && (t.hour, t.second) < (self.interval.end_hour, self.interval.end_second)
+        {
+            day = day.prev();
+        }
+
+        self.days & (1 << day as u8) != 0
+    }
+}
+
+/// The weekly schedule of a soul
+#[derive(Inspect, Clone, Default, Serialize, Deserialize)]
+pub struct Agenda {
+    pub profile: String,
+    pub entries: Vec<AgendaEntry>,
+}
+
+impl Agenda {
+    pub fn from_profile(profile: &ScheduleProfile, rng: &mut RandProvider) -> Self {
+        let entries = profile
+            .entries
+            .iter()
+            .map(|e| {
+                let delay = rng.random::<f32>() * e.jitter;
+                AgendaEntry {
+                    activity: e.activity,
+                    days: e.days.iter().fold(0, |acc, &d| acc | (1 << d as u8)),
+                    interval: RecTimeInterval::new(
+                        hour_second(e.start + delay),
+                        hour_second(e.end + delay),
+                    ),
+                }
+            })
+            .collect();
+
+        Self {
+            profile: profile.name.clone(),
+            entries,
+        }
+    }
+
+    /// The activity planned right now, if any. The first matching entry wins.
+    pub fn activity(&self, time: &GameTime) -> Option<Activity> {
+        self.entries
+            .iter()
+            .find(|e| e.is_active(time.daytime))
+            .map(|e| e.activity)
+    }
+}
+
+fn hour_second(hours: f32) -> (i32, i32) {
+    let hours = hours.rem_euclid(24.0);
+    (
+        hours as i32,
+        (hours.fract() * SECONDS_PER_HOUR as f32) as i32,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Activity, AgendaEntry};
+    use crate::utils::time::{DayTime, RecTimeInterval, Weekday};
+
+    #[test]
+    fn night_shift_belongs_to_the_day_it_started() {
+        // Friday night shift, from 22h to 6h
+        let e = AgendaEntry {
+            activity: Activity::Work,
+            days: 1 << Weekday::Friday as u8,
+            interval: RecTimeInterval::new((22, 0), (6, 0)),
+        };
+
+        let friday = 4;
+        let at = |day, hour| DayTime {
+            day,
+            hour,
+            second: 0,
+        };
+
+        assert!(!e.is_active(at(friday, 21)));
+        assert!(e.is_active(at(friday, 23)));
+        assert!(e.is_active(at(friday + 1, 3)));
+        assert!(!e.is_active(at(friday + 1, 23)));
+        assert!(!e.is_active(at(friday, 3)));
+    }
+}
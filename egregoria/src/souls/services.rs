@@ -0,0 +1,420 @@
+use crate::economy::{Money, Occupation, Workers};
+use crate::engine_interaction::Selectable;
+use crate::map_dynamic::{BuildingInfos, Itinerary};
+use crate::rendering::assets::AssetRender;
+use crate::souls::desire::{Desire, School, Work};
+use crate::souls::household::{Household, HouseholdMember};
+use crate::utils::rand_provider::RandProvider;
+use crate::utils::time::{GameInstant, GameTime};
+use crate::vehicles::{make_vehicle_entity, Vehicle, VehicleID, VehicleKind};
+use crate::{Egregoria, ParCommandBuffer, SoulID};
+use geom::{Color, Transform, Vec2};
+use imgui_inspect_derive::*;
+use legion::{system, Entity, IntoQuery};
+use map_model::{BuildingID, BuildingKind, LaneKind, Map, PathKind, ServiceKind};
+use ordered_float::OrderedFloat;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Chance per day for an inhabited house to call each of the emergency services
+pub const EMERGENCY_RATE: f32 = 0.02;
+/// Time spent by a service vehicle on the scene of an emergency, in seconds
+pub const ON_SCENE_TIME: f64 = 300.0;
+/// Distance to its destination under which a service vehicle stuck in traffic is considered arrived
+const ARRIVED_DIST: f32 = 30.0;
+
+pub struct ServiceDescription {
+    pub name: &'static str,
+    pub n_workers: i32,
+    pub size: f32,
+    /// Distance under which the service covers the buildings when fully staffed
+    pub radius: f32,
+    /// Vehicle sent to emergencies and its colour, if the service answers emergencies
+    pub vehicle: Option<(VehicleKind, Color)>,
+    pub asset_location: &'static str,
+}
+
+pub fn service_description(kind: ServiceKind) -> ServiceDescription {
+    match kind {
+        ServiceKind::School => ServiceDescription {
+            name: "School",
+            n_workers: 10,
+            size: 50.0,
+            radius: 800.0,
+            vehicle: None,
+            asset_location: "assets/warehouse.png",
+        },
+        ServiceKind::Hospital => ServiceDescription {
+            name: "Hospital",
+            n_workers: 20,
+            size: 60.0,
+            radius: 1500.0,
+            vehicle: Some((VehicleKind::Car, Color::WHITE)),
+            asset_location: "assets/pharmaceutical_lab.png",
+        },
+        ServiceKind::Police => ServiceDescription {
+            name: "Police station",
+            n_workers: 8,
+            size: 35.0,
+            radius: 1000.0,
+            vehicle: Some((VehicleKind::Car, Color::new(0.1, 0.2, 0.6, 1.0))),
+            asset_location: "assets/warehouse.png",
+        },
+        ServiceKind::FireStation => ServiceDescription {
+            name: "Fire station",
+            n_workers: 8,
+            size: 40.0,
+            radius: 1000.0,
+            vehicle: Some((VehicleKind::Truck, Color::new(0.8, 0.1, 0.1, 1.0))),
+            asset_location: "assets/warehouse.png",
+        },
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, Inspect)]
+pub struct PublicService {
+    pub kind: ServiceKind,
+    pub building: BuildingID,
+    /// Vehicle currently answering an emergency
+    pub dispatched: Option<VehicleID>,
+}
+
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub enum CallState {
+    Going,
+    OnScene(GameInstant),
+    Returning,
+}
+
+debug_inspect_impl!(CallState);
+
+/// Ambulance, police car or fire truck answering an emergency
+#[derive(Clone, Serialize, Deserialize, Inspect)]
+pub struct ServiceVehicle {
+    pub service: SoulID,
+    pub station: Vec2,
+    pub call: BuildingID,
+    pub state: CallState,
+}
+
+register_resource!(ServiceBudget, "service_budget");
+/// Money spent on the public services, which pay their workers and the upkeep of their buildings
+#[derive(Inspect, Clone, Serialize, Deserialize, Debug)]
+pub struct ServiceBudget {
+    /// Fraction of the job slots of the services that are funded, in [0; 1]
+    pub funding: f32,
+    /// Money spent on the services at the last payday
+    pub spent: Money,
+}
+
+impl Default for ServiceBudget {
+    fn default() -> Self {
+        Self {
+            funding: 1.0,
+            spent: Money::ZERO,
+        }
+    }
+}
+
+impl ServiceBudget {
+    /// Number of the job slots that can be filled
+    pub fn funded_slots(&self, n_slots: usize) -> usize {
+        (self.funding.clamp(0.0, 1.0) * n_slots as f32).round() as usize
+    }
+}
+
+register_resource!(ServiceCoverage, "service_coverage");
+/// How well each house is covered by each kind of service, recomputed every hour
+#[derive(Clone, Default, Serialize, Deserialize, Debug)]
+pub struct ServiceCoverage {
+    coverage: BTreeMap<(BuildingID, ServiceKind), f32>,
+    /// Hour at which the coverage was last updated
+    last_update: i32,
+}
+
+impl ServiceCoverage {
+    /// Coverage of the building by the kind of service in [0; 1]
+    pub fn coverage(&self, building: BuildingID, kind: ServiceKind) -> f32 {
+        self.coverage.get(&(building, kind)).copied().unwrap_or(0.0)
+    }
+
+    /// Average coverage of the building by all the kinds of services in [0; 1]
+    pub fn overall(&self, building: BuildingID) -> f32 {
+        let kinds = ServiceKind::values();
+        kinds
+            .iter()
+            .map(|&kind| self.coverage(building, kind))
+            .sum::<f32>()
+            / kinds.len() as f32
+    }
+}
+
+pub fn service_soul(
+    goria: &mut Egregoria,
+    kind: ServiceKind,
+    building: BuildingID,
+) -> Option<SoulID> {
+    let obb = goria.map().buildings().get(building)?.obb;
+    let des = service_description(kind);
+
+    let e = goria.world.push((
+        PublicService {
+            kind,
+            building,
+            dispatched: None,
+        },
+        Workers::new(des.n_workers),
+        Transform::new(obb.center()),
+        Selectable::new(obb.axis()[0].magnitude() * 0.5),
+    ));
+    let soul = SoulID(e);
+    goria.write::<BuildingInfos>().set_owner(building, soul);
+    Some(soul)
+}
+
+/// The building of the service was removed: its workers lose their job
+fn close_service(goria: &mut Egregoria, soul: SoulID) {
+    let employees: Vec<SoulID> = goria
+        .comp::<Workers>(soul.0)
+        .map(|w| w.employees().collect())
+        .unwrap_or_default();
+    for worker in employees {
+        if let Some(occ) = goria.comp_mut::<Occupation>(worker.0) {
+            occ.job = None;
+        }
+        goria
+            .read::<ParCommandBuffer>()
+            .remove_component::<Desire<Work>>(worker.0);
+    }
+    goria.write::<BuildingInfos>().remove_owner(soul);
+    goria.read::<ParCommandBuffer>().kill(soul.0);
+    log::info!("{:?} closed", soul);
+}
+
+/// Every hour, closes the services whose building is gone, recomputes the coverage
+/// and sends vehicles to the emergencies
+pub(crate) fn services_update(goria: &mut Egregoria) {
+    let hour = goria.read::<GameTime>().total_hours();
+
+    let mut cov = goria.write::<ServiceCoverage>();
+    if cov.last_update == hour {
+        return;
+    }
+    cov.last_update = hour;
+    drop(cov);
+
+    let map = goria.map();
+    let mut services = vec![];
+    let mut schools = vec![];
+    let mut closed = vec![];
+    for (&e, service, workers) in <(Entity, &PublicService, &Workers)>::query().iter(&goria.world) {
+        let b = unwrap_or!(map.buildings().get(service.building), {
+            closed.push(SoulID(e));
+            continue;
+        });
+        let staffing = if workers.slots.is_empty() {
+            0.0
+        } else {
+            workers.n_employed() as f32 / workers.slots.len() as f32
+        };
+        services.push((SoulID(e), service.kind, b.door_pos, staffing));
+        if service.kind == ServiceKind::School && workers.n_employed() > 0 {
+            schools.push((b.id, b.door_pos));
+        }
+    }
+
+    let coverage = compute_coverage(&map, &services);
+    drop(map);
+    goria.write::<ServiceCoverage>().coverage = coverage;
+
+    for soul in closed {
+        close_service(goria, soul);
+    }
+
+    enrol_pupils(goria, &schools);
+
+    for (house, kind) in emergencies(goria) {
+        dispatch(goria, house, kind);
+    }
+}
+
+/// Children go to the nearest staffed school covering their house
+fn enrol_pupils(goria: &mut Egregoria, schools: &[(BuildingID, Vec2)]) {
+    let radius = service_description(ServiceKind::School).radius;
+    let map = goria.map();
+    let houses: BTreeMap<SoulID, Vec2> = <(Entity, &Household)>::query()
+        .iter(&goria.world)
+        .filter_map(|(&e, h)| Some((SoulID(e), map.buildings().get(h.house)?.door_pos)))
+        .collect();
+    drop(map);
+
+    for (member, school) in
+        <(&HouseholdMember, &mut Desire<School>)>::query().iter_mut(&mut goria.world)
+    {
+        school.v.school = houses.get(&member.household).and_then(|&house| {
+            schools
+                .iter()
+                .filter(|(_, pos)| pos.distance(house) < radius)
+                .min_by_key(|(_, pos)| OrderedFloat(pos.distance2(house)))
+                .map(|&(id, _)| id)
+        });
+    }
+}
+
+fn compute_coverage(
+    map: &Map,
+    services: &[(SoulID, ServiceKind, Vec2, f32)],
+) -> BTreeMap<(BuildingID, ServiceKind), f32> {
+    let mut coverage = BTreeMap::new();
+    for b in map.buildings().values() {
+        if !matches!(b.kind, BuildingKind::House) {
+            continue;
+        }
+        for &(_, kind, pos, staffing) in services {
+            let radius = service_description(kind).radius;
+            // Full coverage up to half the radius, then fading out
+            let c = staffing * (2.0 - 2.0 * pos.distance(b.door_pos) / radius).clamp(0.0, 1.0);
+            let v = coverage.entry((b.id, kind)).or_insert(0.0f32);
+            *v = v.max(c);
+        }
+    }
+    coverage
+}
+
+/// Emergencies that happened in the inhabited houses during the last hour
+fn emergencies(goria: &Egregoria) -> Vec<(BuildingID, ServiceKind)> {
+    let map = goria.map();
+    let binfos = goria.read::<BuildingInfos>();
+    let mut rng = goria.write::<RandProvider>();
+    let chance = EMERGENCY_RATE / 24.0;
+
+    let mut calls = vec![];
+    for b in map.buildings().values() {
+        if !matches!(b.kind, BuildingKind::House) {
+            continue;
+        }
+        if binfos
+            .get(b.id)
+            .map(|info| info.residents.is_empty())
+            .unwrap_or(true)
+        {
+            continue;
+        }
+        for &kind in ServiceKind::values() {
+            if service_description(kind).vehicle.is_some() && rng.random::<f32>() < chance {
+                calls.push((b.id, kind));
+            }
+        }
+    }
+    calls
+}
+
+/// Position on the nearest driving lane to `pos`, and the direction of the lane
+fn curb(map: &Map, pos: Vec2) -> Option<(Vec2, Vec2)> {
+    let lane = map.lanes().get(map.nearest_lane(pos, LaneKind::Driving)?)?;
+    let (proj, _, dir) = lane.points.project_segment_dir(pos);
+    Some((proj, dir))
+}
+
+/// The closest staffed service of the kind covering the house sends its vehicle
+fn dispatch(goria: &mut Egregoria, house: BuildingID, kind: ServiceKind) {
+    let des = service_description(kind);
+    let (vkind, tint) = unwrap_ret!(des.vehicle);
+
+    let map = goria.map();
+    let target = unwrap_ret!(map.buildings().get(house)).door_pos;
+    let service = <(Entity, &PublicService, &Workers)>::query()
+        .iter(&goria.world)
+        .filter(|(_, s, w)| s.kind == kind && s.dispatched.is_none() && w.n_employed() > 0)
+        .filter_map(|(&e, s, _)| Some((SoulID(e), map.buildings().get(s.building)?.door_pos)))
+        .filter(|&(_, pos)| pos.distance(target) < des.radius)
+        .min_by_key(|&(_, pos)| OrderedFloat(pos.distance2(target)));
+    let (service, station) = unwrap_or!(service, {
+        log::info!("nobody answered the {:?} emergency at {:?}", kind, house);
+        return;
+    });
+
+    let (start, dir) = unwrap_ret!(curb(&map, station));
+    let (end, _) = unwrap_ret!(curb(&map, target));
+    let it = unwrap_ret!(Itinerary::route(start, end, &map, PathKind::Vehicle));
+    drop(map);
+
+    let mut trans = Transform::new(start);
+    trans.set_direction(dir);
+    let mut vehicle = Vehicle::new_driving(vkind);
+    vehicle.siren = true;
+    let e = make_vehicle_entity(goria, trans, vehicle, it, true);
+    goria.add_comp(
+        e,
+        ServiceVehicle {
+            service,
+            station: start,
+            call: house,
+            state: CallState::Going,
+        },
+    );
+    if let Some(render) = goria.comp_mut::<AssetRender>(e) {
+        render.tint = tint;
+    }
+    if let Some(s) = goria.comp_mut::<PublicService>(service.0) {
+        s.dispatched = Some(VehicleID(e));
+    }
+    log::info!("{:?} sent {:?} to {:?}", service, e, house);
+}
+
+register_system!(service_vehicle_update);
+/// Service vehicles drive to the emergency, stay on the scene for a while then come back
+#[system(par_for_each)]
+pub fn service_vehicle_update(
+    #[resource] time: &GameTime,
+    #[resource] map: &Map,
+    #[resource] cbuf: &ParCommandBuffer,
+    me: &Entity,
+    sv: &mut ServiceVehicle,
+    vehicle: &mut Vehicle,
+    it: &mut Itinerary,
+    trans: &Transform,
+) {
+    match sv.state {
+        CallState::Going => {
+            if arrived(it, trans, time) {
+                vehicle.siren = false;
+                sv.state = CallState::OnScene(time.instant());
+            }
+        }
+        CallState::OnScene(since) => {
+            if since.elapsed(time) < ON_SCENE_TIME {
+                return;
+            }
+            *it = unwrap_or!(
+                Itinerary::route(trans.position(), sv.station, map, PathKind::Vehicle),
+                return end_call(cbuf, *me, sv.service)
+            );
+            sv.state = CallState::Returning;
+        }
+        CallState::Returning => {
+            if arrived(it, trans, time) {
+                end_call(cbuf, *me, sv.service);
+            }
+        }
+    }
+}
+
+fn arrived(it: &Itinerary, trans: &Transform, time: &GameTime) -> bool {
+    it.has_ended(time.timestamp)
+        || it
+            .end_pos()
+            .map(|p| p.is_close(trans.position(), ARRIVED_DIST))
+            .unwrap_or(false)
+}
+
+fn end_call(cbuf: &ParCommandBuffer, vehicle: Entity, service: SoulID) {
+    cbuf.kill(vehicle);
+    cbuf.exec_ent(vehicle, move |goria| {
+        if let Some(s) = goria.comp_mut::<PublicService>(service.0) {
+            if s.dispatched == Some(VehicleID(vehicle)) {
+                s.dispatched = None;
+            }
+        }
+    });
+}
@@ -0,0 +1,180 @@
+use crate::economy::{CommodityKind, Market};
+use crate::map_dynamic::BuildingInfos;
+use crate::souls::goods_company::{CompanyKind, GoodsCompanyRegistry, Recipe};
+use crate::utils::time::GameTime;
+use crate::Egregoria;
+use map_model::{BuildingKind, LotKind, Map};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Unmet demand of a commodity above which another company is built to produce it.
+/// Any demand is enough for commodities that nobody produces yet.
+pub const MIN_DEMAND: i32 = 3;
+/// Hours to wait before building another company producing the same commodity,
+/// to give time to the last one to start producing
+pub const BUILD_COOLDOWN: i32 = 24;
+
+/// Goods the residents need. As long as there is room for more residents, a company
+/// producing each of them is built even if nobody asks for them yet, so that the city
+/// offers jobs to attract its first immigrants.
+const STAPLES: &[CommodityKind] = &[
+    CommodityKind::Bread,
+    CommodityKind::Meal,
+    CommodityKind::Cloth,
+    CommodityKind::Medicine,
+];
+
+register_resource!(ZoneDemand, "zone_demand");
+#[derive(Clone, Default, Serialize, Deserialize, Debug)]
+pub struct ZoneDemand {
+    /// Quantity of each commodity asked for on the market but that nobody sells,
+    /// as of the last update
+    pub demand: BTreeMap<CommodityKind, i32>,
+    /// Hour at which a company producing each commodity was last built
+    last_built: BTreeMap<CommodityKind, i32>,
+    /// Hour at which the demand was last updated
+    last_update: i32,
+}
+
+/// Stores go on commercial lots, factories on industrial lots
+pub fn zone_of(kind: CompanyKind) -> LotKind {
+    match kind {
+        CompanyKind::Store => LotKind::Commercial,
+        CompanyKind::Factory { .. } => LotKind::Industrial,
+    }
+}
+
+/// Every hour, builds a company on a zoned lot for the commodity with the highest unmet demand.
+/// The company itself is created by `add_souls_to_empty_buildings`.
+pub(crate) fn zoning(goria: &mut Egregoria) {
+    let hour = goria.read::<GameTime>().total_hours();
+
+    let mut zd = goria.write::<ZoneDemand>();
+    if zd.last_update == hour {
+        return;
+    }
+    zd.last_update = hour;
+
+    zd.demand = unmet_demand(&goria.read::<Market>());
+    let produced = produced_commodities(&goria.map(), &goria.read::<GoodsCompanyRegistry>());
+    if has_free_houses(&goria.map(), &goria.read::<BuildingInfos>()) {
+        for &staple in STAPLES {
+            if !produced.contains(&staple) {
+                *zd.demand.entry(staple).or_default() += 1;
+            }
+        }
+    }
+
+    let mut wanted: Vec<(CommodityKind, i32)> = zd
+        .demand
+        .iter()
+        .filter(|&(kind, &d)| d >= MIN_DEMAND || !produced.contains(kind))
+        .filter(|&(kind, _)| {
+            zd.last_built
+                .get(kind)
+                .map(|&h| hour - h >= BUILD_COOLDOWN)
+                .unwrap_or(true)
+        })
+        .map(|(&kind, &d)| (kind, d))
+        .collect();
+    drop(zd);
+    wanted.sort_by_key(|&(_, d)| -d);
+
+    for (commodity, _) in wanted {
+        if build_producer(goria, commodity, produced.contains(&commodity)) {
+            goria
+                .write::<ZoneDemand>()
+                .last_built
+                .insert(commodity, hour);
+            return;
+        }
+    }
+}
+
+fn unmet_demand(market: &Market) -> BTreeMap<CommodityKind, i32> {
+    market
+        .inner()
+        .iter()
+        .map(|(&kind, m)| {
+            let asked: i32 = m.buy_orders().values().map(|&(_, qty)| qty).sum();
+            let offered: i32 = m.sell_orders().values().map(|&(_, qty)| qty).sum();
+            (kind, asked - offered)
+        })
+        .filter(|&(_, d)| d > 0)
+        .collect()
+}
+
+fn has_free_houses(map: &Map, binfos: &BuildingInfos) -> bool {
+    map.buildings()
+        .values()
+        .filter(|b| matches!(b.kind, BuildingKind::House))
+        .any(|b| {
+            binfos
+                .get(b.id)
+                .map(|info| info.free_capacity() > 0)
+                .unwrap_or(false)
+        })
+}
+
+/// Commodities produced by the companies of the city, or that will be once
+/// their building is occupied
+fn produced_commodities(map: &Map, registry: &GoodsCompanyRegistry) -> BTreeSet<CommodityKind> {
+    map.buildings()
+        .values()
+        .filter_map(|b| registry.descriptions.get(&b.kind))
+        .flat_map(|des| made_by(&des.recipe))
+        .collect()
+}
+
+/// Commodities actually made by the recipe. Stores that only resell what they buy
+/// don't make anything.
+fn made_by(recipe: &Recipe) -> impl Iterator<Item = CommodityKind> + '_ {
+    recipe
+        .production
+        .iter()
+        .map(|&(kind, _)| kind)
+        .filter(move |&kind| !recipe.consumption.iter().any(|&(k, _)| k == kind))
+}
+
+/// Commodities sold by the recipe, whether it makes them or resells them
+fn sold_by(recipe: &Recipe) -> impl Iterator<Item = CommodityKind> + '_ {
+    recipe.production.iter().map(|&(kind, _)| kind)
+}
+
+/// Builds the building of a company selling the commodity on the first zoned lot with
+/// enough room. Returns false if there was none.
+/// Until the commodity is made in the city a company making it is preferred, then stores
+/// reselling it are built on commercial lots closer to the residents.
+fn build_producer(goria: &mut Egregoria, commodity: CommodityKind, made: bool) -> bool {
+    let registry = goria.read::<GoodsCompanyRegistry>();
+    let mut candidates: Vec<_> = registry
+        .descriptions
+        .values()
+        .filter(|des| sold_by(&des.recipe).any(|kind| kind == commodity))
+        .map(|des| {
+            let makes = made_by(&des.recipe).any(|kind| kind == commodity);
+            (
+                makes,
+                des.bkind,
+                des.bgen,
+                des.size,
+                zone_of(des.kind),
+                des.name,
+            )
+        })
+        .collect();
+    drop(registry);
+    candidates.sort_by_key(|&(makes, ..)| makes == made);
+
+    for (_, bkind, bgen, size, zone, name) in candidates {
+        let mut map = goria.map_mut();
+        let (road, obb) = unwrap_cont!(map.find_zoned_spot(zone, size));
+        let id = unwrap_cont!(map.build_special_building(road, &obb, bkind, bgen));
+        drop(map);
+
+        goria.write::<BuildingInfos>().insert(id);
+        log::info!("built a {} to sell {:?}", name, commodity);
+        return true;
+    }
+    false
+}
@@ -0,0 +1,63 @@
+use super::*;
+use crate::engine_interaction::WorldCommand;
+use geom::{vec2, Vec2, AABB, OBB};
+use map_model::{Blueprint, BuildingGen, BuildingKind, LightPolicy};
+
+#[test]
+fn paste_blueprint() {
+    let mut ctx = TestCtx::init();
+    ctx.g.write::<crate::economy::Treasury>().sandbox = true;
+
+    ctx.build_roads(&[
+        vec2(0.0, 0.0),
+        vec2(200.0, 0.0),
+        vec2(200.0, 200.0),
+        vec2(0.0, 0.0),
+    ]);
+    let inter = ctx.g.map().intersection_at(vec2(200.0, 0.0)).unwrap();
+    ctx.g
+        .map_mut()
+        .update_intersection(inter, |i| i.light_policy = LightPolicy::StopSigns);
+    let road = ctx.g.map().roads().keys().next().unwrap();
+    let obb = OBB::new(vec2(100.0, -30.0), vec2(1.0, 0.0), 20.0, 20.0);
+    ctx.g
+        .map_mut()
+        .build_special_building(road, &obb, BuildingKind::Park, BuildingGen::Park)
+        .unwrap();
+
+    let bp = Blueprint::capture(
+        &ctx.g.map(),
+        AABB::new(vec2(-50.0, -50.0), vec2(210.0, 210.0)),
+    );
+    let layout = bp.placed(vec2(1000.0, 0.0), vec2(0.0, 1.0));
+    assert_eq!(layout.buildings.len(), 1);
+
+    // an intersection already there keeps its policy
+    let stop = layout
+        .intersections
+        .iter()
+        .find(|i| i.light_policy == LightPolicy::StopSigns)
+        .unwrap()
+        .pos;
+    let center = layout
+        .intersections
+        .iter()
+        .fold(Vec2::ZERO, |acc, i| acc + i.pos)
+        / layout.intersections.len() as f32;
+    ctx.build_roads(&[stop, stop + (stop - center).normalize() * 300.0]);
+
+    ctx.apply(vec![WorldCommand::MapPasteBlueprint(layout)]);
+
+    // the pasted roads share their intersections and the building is built at once
+    let map = ctx.g.map();
+    assert_eq!(map.roads().len(), 7);
+    assert_eq!(map.intersections().len(), 7);
+    assert_eq!(map.buildings().len(), 2);
+    assert_eq!(
+        map.intersections()
+            .values()
+            .filter(|i| i.light_policy == LightPolicy::StopSigns)
+            .count(),
+        1
+    );
+}
@@ -0,0 +1,204 @@
+use super::*;
+use crate::economy::{
+    building_upkeep, Bankruptcy, Job, JobMarket, Ledger, Market, Money, Occupation, Treasury,
+    Workers,
+};
+use crate::souls::desire::{Desire, Work, WorkKind};
+use crate::souls::household::{spawn_household_with, Household, Role};
+use crate::vehicles::Vehicle;
+use legion::IntoQuery;
+use map_model::{BuildingKind, MapProject};
+
+fn hire(ctx: &mut TestCtx, company: SoulID, workplace: BuildingID, human: SoulID, slot: usize) {
+    let workers = ctx.g.comp_mut::<Workers>(company.0).unwrap();
+    workers.slots[slot].worker = Some(human);
+    let skill = workers.slots[slot].skill;
+    ctx.g.comp_mut::<Occupation>(human.0).unwrap().job = Some(Job {
+        company,
+        workplace,
+        skill,
+    });
+}
+
+fn set_money(ctx: &mut TestCtx, soul: SoulID, money: Money) {
+    let mut market = ctx.g.write::<Market>();
+    let delta = money - market.money(soul);
+    market.add_money(soul, delta);
+}
+
+#[test]
+fn payday_lays_off_the_driver_last() {
+    let mut ctx = TestCtx::init();
+
+    ctx.build_roads(&[vec2(0.0, 0.0), vec2(200.0, 0.0)]);
+    let house = ctx.build_house_near(vec2(0.0, 0.0));
+    let household = spawn_household_with(&mut ctx.g, house, &[Role::Worker, Role::Worker]).unwrap();
+    let members = ctx
+        .g
+        .comp::<Household>(household.0)
+        .unwrap()
+        .members
+        .clone();
+    let (driver, worker) = (members[0], members[1]);
+
+    let (company, building) = ctx.build_factory(vec2(150.0, 40.0));
+    let truck = ctx.g.comp::<GoodsCompany>(company.0).unwrap().trucks[0];
+    let last = ctx.g.comp::<Workers>(company.0).unwrap().slots.len() - 1;
+    hire(&mut ctx, company, building, worker, 0);
+    hire(&mut ctx, company, building, driver, last);
+    ctx.g.comp_mut::<GoodsCompany>(company.0).unwrap().driver = Some(driver);
+    let work = Work::new(
+        building,
+        WorkKind::Driver {
+            deliver_order: None,
+            truck,
+        },
+    );
+    ctx.g.add_comp(driver.0, Desire::new(work));
+
+    // only the driver can be paid
+    let upkeep = building_upkeep(&ctx.g.map().buildings()[building]);
+    let wage = ctx.g.comp::<Workers>(company.0).unwrap().slots[last]
+        .skill
+        .wage();
+    set_money(&mut ctx, company, upkeep + wage);
+    ctx.tick();
+
+    let employees: Vec<_> = ctx
+        .g
+        .comp::<Workers>(company.0)
+        .unwrap()
+        .employees()
+        .collect();
+    assert_eq!(employees, vec![driver]);
+    assert!(ctx.g.comp::<Occupation>(worker.0).unwrap().job.is_none());
+    assert_eq!(ctx.g.read::<Market>().money(company), Money::ZERO);
+
+    // the next day, not even the driver can be paid
+    ctx.skip(GameTime::DAY);
+    ctx.tick();
+
+    assert_eq!(ctx.g.comp::<Workers>(company.0).unwrap().n_employed(), 0);
+    assert!(ctx.g.comp::<Occupation>(driver.0).unwrap().job.is_none());
+}
+
+#[test]
+fn unemployed_find_a_job_nearby() {
+    let mut ctx = TestCtx::init();
+
+    ctx.build_roads(&[vec2(0.0, 0.0), vec2(200.0, 0.0)]);
+    let house = ctx.build_house_near(vec2(0.0, 0.0));
+    let household = spawn_household_with(&mut ctx.g, house, &[Role::Worker]).unwrap();
+    let human = ctx.g.comp::<Household>(household.0).unwrap().members[0];
+
+    let (company, building) = ctx.build_factory(vec2(150.0, 40.0));
+    ctx.tick();
+
+    let job = ctx.g.comp::<Occupation>(human.0).unwrap().job.unwrap();
+    assert_eq!(job.company, company);
+    assert_eq!(job.workplace, building);
+    let employees: Vec<_> = ctx
+        .g
+        .comp::<Workers>(company.0)
+        .unwrap()
+        .employees()
+        .collect();
+    assert_eq!(employees, vec![human]);
+    assert!(ctx.g.comp::<Desire<Work>>(human.0).is_some());
+    assert_eq!(ctx.g.read::<JobMarket>().unemployed, 0);
+}
+
+#[test]
+fn bankrupt_company_building_stays_empty() {
+    let mut ctx = TestCtx::init();
+
+    ctx.build_roads(&[vec2(0.0, 0.0), vec2(200.0, 0.0)]);
+    let (company, building) = ctx.build_factory(vec2(150.0, 40.0));
+    let truck = ctx.g.comp::<GoodsCompany>(company.0).unwrap().trucks[0];
+
+    // a sale too small to pay the upkeep makes the day unprofitable
+    ctx.g.write::<Bankruptcy>().unprofitable_days = 1;
+    ctx.g.comp_mut::<Ledger>(company.0).unwrap().today.revenue = Money(1);
+    set_money(&mut ctx, company, Money::ZERO);
+    for _ in 0..3 {
+        ctx.tick();
+    }
+
+    assert!(ctx.g.comp::<GoodsCompany>(company.0).is_none());
+    assert!(ctx.g.comp::<Vehicle>(truck.0).is_none());
+    assert!(ctx.g.map().buildings().get(building).is_none());
+    assert!(ctx.g.read::<BuildingInfos>().get(building).is_none());
+    assert_eq!(<&GoodsCompany>::query().iter(&ctx.g.world).count(), 0);
+}
+
+#[test]
+fn supply_chain_outlives_bankruptcy_delay() {
+    let mut ctx = TestCtx::init();
+
+    ctx.build_roads(&[vec2(0.0, 0.0), vec2(300.0, 0.0)]);
+    let house = ctx.build_house_near(vec2(0.0, 0.0));
+    spawn_household_with(
+        &mut ctx.g,
+        house,
+        &[Role::Worker, Role::Worker, Role::Worker],
+    )
+    .unwrap();
+
+    // cereal, flour and bread have no price, nothing the chain sells earns money
+    let companies: Vec<(SoulID, BuildingID)> = [
+        (vec2(80.0, 40.0), "Cereal Farm"),
+        (vec2(160.0, 40.0), "Cereal Factory"),
+        (vec2(240.0, 40.0), "Bakery"),
+    ]
+    .iter()
+    .map(|&(p, name)| ctx.build_company(p, name))
+    .collect();
+
+    let delay = ctx.g.read::<Bankruptcy>().unprofitable_days as i32;
+    for _ in 0..delay + 2 {
+        ctx.skip(GameTime::DAY);
+        for _ in 0..20 {
+            ctx.tick();
+        }
+    }
+
+    for (company, building) in companies {
+        assert!(ctx.g.comp::<GoodsCompany>(company.0).is_some());
+        assert!(ctx.g.map().buildings().get(building).is_some());
+    }
+    assert_eq!(ctx.g.read::<JobMarket>().unemployed, 0);
+}
+
+#[test]
+fn construction_is_paid_once_built() {
+    let mut ctx = TestCtx::init();
+    let pattern = LanePatternBuilder::default().build();
+    ctx.tick();
+
+    let construction = |g: &Egregoria| g.read::<Treasury>().today.construction;
+    let money = ctx.g.read::<Treasury>().money;
+
+    ctx.apply(vec![WorldCommand::MapMakeConnection(
+        MapProject::ground(vec2(0.0, 0.0)),
+        MapProject::ground(vec2(200.0, 0.0)),
+        None,
+        pattern,
+    )]);
+    let cost = construction(&ctx.g);
+    assert!(cost > Money::ZERO);
+    assert_eq!(ctx.g.read::<Treasury>().money, money - cost);
+
+    // the building overlaps the factory, nothing is built and nothing is paid
+    ctx.build_factory(vec2(150.0, 40.0));
+    let road = ctx.g.map().roads().keys().next().unwrap();
+    let obb = OBB::new(vec2(160.0, 40.0), vec2(1.0, 0.0), 30.0, 30.0);
+    let undo = ctx.apply(vec![WorldCommand::MapBuildSpecialBuilding(
+        road,
+        obb,
+        BuildingKind::Park,
+        BuildingGen::Park,
+    )]);
+    assert!(undo.is_empty());
+    assert_eq!(construction(&ctx.g), cost);
+    assert_eq!(ctx.g.read::<Treasury>().money, money - cost);
+}
@@ -0,0 +1,33 @@
+use super::*;
+use crate::map_dynamic::ParkingManagement;
+use crate::vehicles::{spawn_parked_vehicle, VehicleKind};
+use crate::ParCommandBuffer;
+use geom::vec2;
+
+#[test]
+fn killed_car_frees_its_spot() {
+    let mut ctx = TestCtx::init();
+
+    ctx.build_roads(&[vec2(0.0, 0.0), vec2(100.0, 0.0)]);
+
+    let car = spawn_parked_vehicle(&mut ctx.g, VehicleKind::Car, vec2(50.0, 0.0)).unwrap();
+    let pos = ctx.g.pos(car.0).unwrap();
+
+    ctx.g.write::<ParCommandBuffer>().kill(car.0);
+    ctx.tick();
+    assert!(ctx.g.pos(car.0).is_none());
+
+    // the spot of the car was freed when it was removed, so it is the closest one again
+    let map = ctx.g.map();
+    let spot = ctx
+        .g
+        .write::<ParkingManagement>()
+        .reserve_near(vec2(50.0, 0.0), &map)
+        .unwrap();
+    assert!(spot
+        .get(&map.parking)
+        .unwrap()
+        .trans
+        .position()
+        .is_close(pos, 0.01));
+}
@@ -0,0 +1,83 @@
+use super::*;
+use crate::economy::{Job, Occupation, Skill, Workers};
+use crate::pedestrians::Location;
+use crate::souls::desire::{Desire, Work, WorkKind};
+use crate::souls::goods_company::GoodsCompany;
+use crate::souls::household::{spawn_household_with, Household, Role};
+use crate::souls::migration::die;
+use crate::souls::needs::Needs;
+use crate::vehicles::{unpark, Vehicle, VehicleID, VehicleState};
+use geom::vec2;
+
+/// A household of one driver, driving the truck of its company
+fn driver_on_the_road(ctx: &mut TestCtx) -> (SoulID, SoulID, SoulID, VehicleID) {
+    ctx.build_roads(&[vec2(0.0, 0.0), vec2(200.0, 0.0)]);
+    let house = ctx.build_house_near(vec2(0.0, 0.0));
+    let household = spawn_household_with(&mut ctx.g, house, &[Role::Worker]).unwrap();
+    let human = ctx.g.comp::<Household>(household.0).unwrap().members[0];
+
+    let (company, building) = ctx.build_factory(vec2(150.0, 40.0));
+    let truck = ctx.g.comp::<GoodsCompany>(company.0).unwrap().trucks[0];
+    ctx.g.comp_mut::<GoodsCompany>(company.0).unwrap().driver = Some(human);
+    ctx.g.comp_mut::<Workers>(company.0).unwrap().slots[0].worker = Some(human);
+    ctx.g.comp_mut::<Occupation>(human.0).unwrap().job = Some(Job {
+        company,
+        workplace: building,
+        skill: Skill::Unskilled,
+    });
+    let work = Work::new(
+        building,
+        WorkKind::Driver {
+            deliver_order: None,
+            truck,
+        },
+    );
+    ctx.g.add_comp(human.0, Desire::new(work));
+
+    unpark(&mut ctx.g, truck);
+    ctx.g.comp_mut::<Vehicle>(truck.0).unwrap().claimed_by = Some(human);
+    *ctx.g.comp_mut::<Location>(human.0).unwrap() = Location::Vehicle(truck);
+    (household, human, company, truck)
+}
+
+/// The truck left on the road was replaced by a new one parked at the company
+fn assert_truck_replaced(ctx: &TestCtx, company: SoulID, truck: VehicleID) {
+    assert!(ctx.g.comp::<Vehicle>(truck.0).is_none());
+    assert_eq!(ctx.g.comp::<Workers>(company.0).unwrap().n_employed(), 0);
+    let comp = ctx.g.comp::<GoodsCompany>(company.0).unwrap();
+    assert_eq!(comp.driver, None);
+    let new_truck = comp.trucks[0];
+    assert_ne!(new_truck, truck);
+    let v = ctx.g.comp::<Vehicle>(new_truck.0).unwrap();
+    assert!(matches!(v.state, VehicleState::Parked(_)));
+    assert_eq!(v.claimed_by, None);
+}
+
+#[test]
+fn dead_driver_leaves_job_and_truck() {
+    let mut ctx = TestCtx::init();
+    let (household, human, company, truck) = driver_on_the_road(&mut ctx);
+
+    die(&mut ctx.g, human, household);
+    ctx.tick();
+
+    assert_truck_replaced(&ctx, company, truck);
+}
+
+#[test]
+fn emigrating_driver_leaves_job_and_truck() {
+    let mut ctx = TestCtx::init();
+    let (household, human, company, truck) = driver_on_the_road(&mut ctx);
+
+    let needs = ctx.g.comp_mut::<Needs>(human.0).unwrap();
+    needs.hunger = 0.0;
+    needs.rest = 0.0;
+    needs.money = 0.0;
+    needs.satisfaction = 0.0;
+    ctx.tick();
+    ctx.tick();
+
+    assert!(ctx.g.comp::<Household>(household.0).is_none());
+    assert!(ctx.g.comp::<Occupation>(human.0).is_none());
+    assert_truck_replaced(&ctx, company, truck);
+}
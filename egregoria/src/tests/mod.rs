@@ -1,14 +1,24 @@
 #![cfg(test)]
 
-use crate::engine_interaction::WorldCommands;
+use crate::engine_interaction::{LastUndo, WorldCommand, WorldCommands};
 use crate::map_dynamic::BuildingInfos;
+use crate::souls::goods_company::{company_soul, CompanyKind, GoodsCompany, GoodsCompanyRegistry};
 use crate::utils::scheduler::SeqSchedule;
-use crate::Egregoria;
+use crate::utils::time::GameTime;
+use crate::vehicles::{spawn_parked_vehicle, VehicleKind};
+use crate::{Egregoria, SoulID};
 use common::logger::MyLog;
-use geom::Vec2;
-use map_model::{BuildingID, LanePatternBuilder};
+use geom::{vec2, Vec2, OBB};
+use map_model::{BuildingGen, BuildingID, LanePatternBuilder};
 
+mod blueprint;
+mod economy;
+mod kill;
+mod migration;
+mod services;
+mod undo;
 mod vehicles;
+mod zoning;
 
 struct TestCtx {
     pub g: Egregoria,
@@ -45,11 +55,82 @@ impl TestCtx {
             .id;
 
         let b = self.g.map_mut().build_house(lot).unwrap();
-        self.g.write::<BuildingInfos>().insert(b);
+        self.g
+            .write::<BuildingInfos>()
+            .insert_house(self.g.map().buildings().get(b).unwrap());
         b
     }
 
+    /// A factory with a parked truck and nobody working there yet, along the first road
+    fn build_factory(&mut self, p: Vec2) -> (SoulID, BuildingID) {
+        let name = self
+            .g
+            .read::<GoodsCompanyRegistry>()
+            .descriptions
+            .values()
+            .find(|d| matches!(d.kind, CompanyKind::Factory { .. }))
+            .unwrap()
+            .name;
+        self.build_company(p, name)
+    }
+
+    /// A company from the registry along the first road, factories get a parked truck
+    fn build_company(&mut self, p: Vec2, name: &str) -> (SoulID, BuildingID) {
+        let registry = self.g.read::<GoodsCompanyRegistry>();
+        let des = registry
+            .descriptions
+            .values()
+            .find(|d| d.name == name)
+            .unwrap();
+        let (bkind, kind, recipe, n_workers) =
+            (des.bkind, des.kind, des.recipe.clone(), des.n_workers);
+        drop(registry);
+
+        let road = self.g.map().roads().keys().next().unwrap();
+        let obb = OBB::new(p, vec2(1.0, 0.0), 30.0, 30.0);
+        let building = self
+            .g
+            .map_mut()
+            .build_special_building(road, &obb, bkind, BuildingGen::House)
+            .unwrap();
+        self.g.write::<BuildingInfos>().insert(building);
+        let door = self.g.map().buildings()[building].door_pos;
+        let trucks = match kind {
+            CompanyKind::Factory { .. } => {
+                vec![spawn_parked_vehicle(&mut self.g, VehicleKind::Truck, door).unwrap()]
+            }
+            _ => vec![],
+        };
+        let company = company_soul(
+            &mut self.g,
+            GoodsCompany {
+                kind,
+                recipe,
+                building,
+                max_workers: n_workers,
+                pollution: 0.0,
+                progress: 0.0,
+                driver: None,
+                trucks,
+            },
+        )
+        .unwrap();
+        (company, building)
+    }
+
+    /// Skips time forward, the systems running every hour or every day catch up on the next tick
+    fn skip(&mut self, seconds: i32) {
+        let mut time = self.g.write::<GameTime>();
+        *time = GameTime::new(0.0, time.timestamp + seconds as f64);
+    }
+
     fn tick(&mut self) {
         self.g.tick(&mut self.sched, &WorldCommands::default());
     }
+
+    /// Applies the commands and returns what undoes them
+    fn apply(&mut self, commands: Vec<WorldCommand>) -> Vec<WorldCommand> {
+        self.g.tick(&mut self.sched, &commands.into());
+        self.g.read::<LastUndo>().0.concat()
+    }
 }
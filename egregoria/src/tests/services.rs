@@ -0,0 +1,103 @@
+use super::*;
+use crate::economy::{building_upkeep, Money, Occupation, Treasury, Workers, STARTING_TREASURY};
+use crate::souls::desire::{Desire, School};
+use crate::souls::household::{spawn_household_with, Household, Role};
+use crate::souls::services::{services_update, ServiceCoverage};
+use crate::utils::time::GameTime;
+use geom::{vec2, OBB};
+use map_model::{BuildingGen, BuildingKind, ServiceKind};
+
+#[test]
+fn children_go_to_staffed_school() {
+    let mut ctx = TestCtx::init();
+
+    ctx.build_roads(&[vec2(0.0, 0.0), vec2(200.0, 0.0)]);
+    let house = ctx.build_house_near(vec2(0.0, 0.0));
+    let household = spawn_household_with(&mut ctx.g, house, &[Role::Worker, Role::Child]).unwrap();
+    let members = ctx
+        .g
+        .comp::<Household>(household.0)
+        .unwrap()
+        .members
+        .clone();
+
+    let road = ctx.g.map().roads().keys().next().unwrap();
+    let obb = OBB::new(vec2(150.0, 40.0), vec2(1.0, 0.0), 30.0, 30.0);
+    let school = ctx
+        .g
+        .map_mut()
+        .build_special_building(
+            road,
+            &obb,
+            BuildingKind::Service(ServiceKind::School),
+            BuildingGen::House,
+        )
+        .unwrap();
+    ctx.g.write::<BuildingInfos>().insert(school);
+    ctx.tick();
+
+    let enrolled = |g: &mut Egregoria| {
+        *g.write::<ServiceCoverage>() = ServiceCoverage::default();
+        services_update(g);
+        g.comp::<Desire<School>>(members[1].0).unwrap().v.school
+    };
+
+    // nobody works at the school yet
+    assert_eq!(enrolled(&mut ctx.g), None);
+
+    let service = ctx
+        .g
+        .read::<BuildingInfos>()
+        .get(school)
+        .unwrap()
+        .owner
+        .unwrap();
+    ctx.g.comp_mut::<Workers>(service.0).unwrap().slots[0].worker = Some(members[0]);
+    assert_eq!(enrolled(&mut ctx.g), Some(school));
+    assert!(ctx.g.comp::<Desire<School>>(members[0].0).is_none());
+}
+
+#[test]
+fn services_are_staffed_only_if_the_city_can_pay() {
+    let mut ctx = TestCtx::init();
+
+    ctx.build_roads(&[vec2(0.0, 0.0), vec2(200.0, 0.0)]);
+    let house = ctx.build_house_near(vec2(0.0, 0.0));
+    let household = spawn_household_with(&mut ctx.g, house, &[Role::Worker]).unwrap();
+    let human = ctx.g.comp::<Household>(household.0).unwrap().members[0];
+
+    let road = ctx.g.map().roads().keys().next().unwrap();
+    let obb = OBB::new(vec2(150.0, 40.0), vec2(1.0, 0.0), 30.0, 30.0);
+    let school = ctx
+        .g
+        .map_mut()
+        .build_special_building(
+            road,
+            &obb,
+            BuildingKind::Service(ServiceKind::School),
+            BuildingGen::House,
+        )
+        .unwrap();
+    ctx.g.write::<BuildingInfos>().insert(school);
+    ctx.tick();
+
+    let job = |g: &Egregoria| g.comp::<Occupation>(human.0).unwrap().job;
+
+    ctx.g.write::<Treasury>().money = Money::ZERO;
+    ctx.skip(GameTime::HOUR);
+    ctx.tick();
+    assert!(job(&ctx.g).is_none());
+
+    ctx.g.write::<Treasury>().money = STARTING_TREASURY;
+    ctx.skip(GameTime::HOUR);
+    ctx.tick();
+    assert_eq!(job(&ctx.g).unwrap().workplace, school);
+
+    // the wages are not paid with money the city does not have
+    ctx.g.write::<Treasury>().money = Money::ZERO;
+    ctx.skip(GameTime::DAY);
+    ctx.tick();
+    assert!(job(&ctx.g).is_none());
+    let upkeep = building_upkeep(&ctx.g.map().buildings()[school]);
+    assert_eq!(ctx.g.read::<Treasury>().yesterday.services, upkeep);
+}
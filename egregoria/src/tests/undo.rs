@@ -0,0 +1,199 @@
+use super::*;
+use crate::economy::Treasury;
+use crate::engine_interaction::WorldCommand;
+use geom::vec2;
+use map_model::{LightPolicy, LotKind, MapProject, TerraformKind, WaterKind};
+
+#[test]
+fn undo_redo_road() {
+    let mut ctx = TestCtx::init();
+    ctx.g.write::<crate::economy::Treasury>().sandbox = true;
+
+    let ground = |x, y| MapProject::ground(vec2(x, y));
+    let pattern = LanePatternBuilder::default().build();
+
+    let undo_build = ctx.apply(vec![WorldCommand::MapMakeConnection(
+        ground(0.0, 0.0),
+        ground(200.0, 0.0),
+        Some(vec2(100.0, 50.0)),
+        pattern.clone(),
+    )]);
+    ctx.apply(vec![WorldCommand::MapMakeConnection(
+        ground(200.0, 0.0),
+        ground(200.0, 200.0),
+        None,
+        pattern,
+    )]);
+    assert_eq!(ctx.g.map().roads().len(), 2);
+
+    let inter = ctx.g.map().intersection_at(vec2(200.0, 0.0)).unwrap();
+    let undo_policy = ctx.apply(vec![WorldCommand::MapUpdateIntersectionPolicy(
+        inter,
+        Default::default(),
+        LightPolicy::StopSigns,
+    )]);
+
+    let curved = ctx.g.map().roads().values().next().unwrap().id;
+    let length = ctx.g.map().roads().get(curved).unwrap().length();
+    let undo_remove = ctx.apply(vec![WorldCommand::MapRemoveRoad(curved)]);
+    assert_eq!(ctx.g.map().roads().len(), 1);
+
+    let redo_remove = ctx.apply(vec![WorldCommand::Undo(undo_remove)]);
+    assert_eq!(ctx.g.map().roads().len(), 2);
+    let restored = ctx
+        .g
+        .map()
+        .roads()
+        .values()
+        .find(|r| (r.length() - length).abs() < 0.1)
+        .is_some();
+    assert!(restored);
+
+    // the policy is undone on the intersection rebuilt by the undo
+    ctx.apply(vec![WorldCommand::Undo(undo_policy)]);
+    let inter = ctx.g.map().intersection_at(vec2(200.0, 0.0)).unwrap();
+    assert_eq!(
+        ctx.g.map().intersections().get(inter).unwrap().light_policy,
+        LightPolicy::default()
+    );
+
+    ctx.apply(redo_remove);
+    assert_eq!(ctx.g.map().roads().len(), 1);
+
+    // the road removed by the redo cannot be removed again
+    ctx.apply(vec![WorldCommand::Undo(undo_build)]);
+    assert_eq!(ctx.g.map().roads().len(), 1);
+}
+
+#[test]
+fn lot_kind_is_redone_on_rebuilt_lot() {
+    let mut ctx = TestCtx::init();
+    ctx.g.write::<Treasury>().sandbox = true;
+
+    ctx.apply(vec![WorldCommand::MapMakeConnection(
+        MapProject::ground(vec2(0.0, 0.0)),
+        MapProject::ground(vec2(200.0, 0.0)),
+        None,
+        LanePatternBuilder::default().build(),
+    )]);
+    let (lot, center) = {
+        let map = ctx.g.map();
+        let lot = map.lots().values().next().unwrap();
+        (lot.id, lot.shape.center())
+    };
+    let kind_at = |g: &Egregoria| {
+        let map = g.map();
+        map.lots().get(map.lot_at(center).unwrap()).unwrap().kind
+    };
+    let before = kind_at(&ctx.g);
+
+    let undo_kind = ctx.apply(vec![WorldCommand::MapSetLotKind(lot, LotKind::Industrial)]);
+    let redo_kind = ctx.apply(vec![WorldCommand::Undo(undo_kind)]);
+    assert_eq!(kind_at(&ctx.g), before);
+
+    // rebuilding the road makes new lots
+    let road = ctx.g.map().roads().keys().next().unwrap();
+    let undo_remove = ctx.apply(vec![WorldCommand::MapRemoveRoad(road)]);
+    ctx.apply(vec![WorldCommand::Undo(undo_remove)]);
+    assert_ne!(ctx.g.map().lot_at(center), Some(lot));
+
+    ctx.apply(redo_kind);
+    assert_eq!(kind_at(&ctx.g), LotKind::Industrial);
+}
+
+#[test]
+fn undo_is_free_and_keeps_bridges() {
+    let mut ctx = TestCtx::init();
+    ctx.tick();
+
+    let ground = |x, y| MapProject::ground(vec2(x, y));
+    let pattern = LanePatternBuilder::default().build();
+    let money = |g: &Egregoria| g.read::<Treasury>().money;
+
+    ctx.g.map_mut().add_water(
+        WaterKind::River { width: 20.0 },
+        vec![vec2(100.0, -100.0), vec2(100.0, 100.0)],
+    );
+    ctx.apply(vec![WorldCommand::MapMakeBridge(
+        ground(0.0, 0.0),
+        ground(200.0, 0.0),
+        None,
+        pattern.clone(),
+    )]);
+    ctx.apply(vec![WorldCommand::MapMakeConnection(
+        ground(0.0, 200.0),
+        ground(200.0, 200.0),
+        None,
+        pattern,
+    )]);
+    assert_eq!(ctx.g.map().roads().len(), 2);
+
+    let roads: Vec<_> = ctx.g.map().roads().keys().collect();
+    let undo_remove = ctx.apply(roads.into_iter().map(WorldCommand::MapRemoveRoad).collect());
+    assert_eq!(ctx.g.map().roads().len(), 0);
+
+    let before = money(&ctx.g);
+    ctx.apply(vec![WorldCommand::Undo(undo_remove.clone())]);
+    assert_eq!(money(&ctx.g), before);
+    assert_eq!(ctx.g.map().roads().len(), 2);
+    assert_eq!(ctx.g.map().roads().values().filter(|r| r.bridge).count(), 1);
+
+    // the river now crosses the road, it cannot be rebuilt as it was
+    let roads: Vec<_> = ctx.g.map().roads().keys().collect();
+    let undo_remove = ctx.apply(roads.into_iter().map(WorldCommand::MapRemoveRoad).collect());
+    ctx.g.map_mut().add_water(
+        WaterKind::River { width: 20.0 },
+        vec![vec2(150.0, 100.0), vec2(150.0, 300.0)],
+    );
+    let redo = ctx.apply(vec![WorldCommand::Undo(undo_remove)]);
+    assert_eq!(ctx.g.map().roads().len(), 1);
+    assert!(ctx.g.map().roads().values().all(|r| r.bridge));
+    assert_eq!(redo.len(), 1);
+}
+
+#[test]
+fn terraform_is_paid_undone_and_spares_roads() {
+    let mut ctx = TestCtx::init();
+    ctx.build_roads(&[vec2(0.0, 0.0), vec2(200.0, 0.0)]);
+    ctx.tick();
+
+    let money = |g: &Egregoria| g.read::<Treasury>().money;
+    let height = |g: &Egregoria, p| g.map().heightmap.height(p);
+
+    // the road would end up under water
+    let before = money(&ctx.g);
+    let undo = ctx.apply(vec![WorldCommand::MapTerraform(
+        vec2(100.0, 0.0),
+        100.0,
+        TerraformKind::Lower,
+        1.0,
+    )]);
+    assert!(undo.is_empty());
+    assert_eq!(money(&ctx.g), before);
+    let refusal = ctx
+        .g
+        .read::<Treasury>()
+        .refusals
+        .last()
+        .unwrap()
+        .reason
+        .clone();
+    assert!(refusal.contains("road"), "{}", refusal);
+
+    let far = vec2(3000.0, 3000.0);
+    let h = height(&ctx.g, far);
+    let undo = ctx.apply(vec![WorldCommand::MapTerraform(
+        far,
+        100.0,
+        TerraformKind::Raise,
+        0.001,
+    )]);
+    assert_eq!(undo.len(), 1);
+    assert!(money(&ctx.g) < before);
+    assert!(height(&ctx.g, far) > h);
+
+    let paid = money(&ctx.g);
+    ctx.apply(vec![WorldCommand::Undo(undo)]);
+    assert!((height(&ctx.g, far) - h).abs() < 1e-6);
+    assert_eq!(money(&ctx.g), paid);
+}
@@ -1,14 +1,17 @@
 use crate::map_dynamic::{Destination, Itinerary, ParkingManagement, Router};
 use crate::utils::time::GameTime;
-use crate::vehicles::{spawn_parked_vehicle, unpark, VehicleKind};
-use geom::vec2;
-use map_model::{Map, PathKind};
+use crate::vehicles::{
+    make_vehicle_entity, spawn_parked_vehicle, unpark, Vehicle, VehicleKind, VehicleState,
+};
+use geom::{vec2, OBB};
+use legion::Entity;
+use map_model::{BuildingGen, BuildingKind, Map, ParkingSpotID, PathKind};
 
 use super::*;
 use crate::pedestrians::Location;
 use crate::souls::desire::{BuyFood, Desire, Home};
-use crate::souls::human::spawn_human;
-use crate::ParCommandBuffer;
+use crate::souls::household::{share_car, spawn_household_with, unshare_car, Household, Role};
+use crate::{ParCommandBuffer, SoulID};
 
 #[test]
 fn test_car_simple() {
@@ -27,7 +30,7 @@ fn test_car_simple() {
         .write::<ParkingManagement>()
         .reserve_near(vec2(100.0, 50.0), &*g.map())
         .unwrap();
-    let end_pos = g.map().parking_to_drive_pos(spot_id).unwrap();
+    let end_pos = spot_id.park_pos(&*g.map()).unwrap();
 
     let itin = Itinerary::route(pos, end_pos, &*g.read::<Map>(), PathKind::Vehicle).unwrap();
     *g.comp_mut::<Itinerary>(car.0).unwrap() = itin;
@@ -54,7 +57,13 @@ fn test_router_and_back() {
     ctx.build_roads(&[vec2(0.0, 0.0), vec2(100.0, 0.0), vec2(100.0, 50.0)]);
 
     let b1 = ctx.build_house_near(vec2(0.0, 0.0));
-    let human = spawn_human(&mut ctx.g, b1);
+    let household = spawn_household_with(&mut ctx.g, b1, &[Role::Worker]).unwrap();
+    let human = ctx.g.comp::<Household>(household.0).unwrap().members[0];
+    let car = spawn_parked_vehicle(&mut ctx.g, VehicleKind::Car, vec2(0.0, 0.0)).unwrap();
+    ctx.g
+        .comp_mut::<Router>(human.0)
+        .unwrap()
+        .set_personal_car(car);
 
     ctx.g
         .write::<ParCommandBuffer>()
@@ -107,7 +116,13 @@ fn test_router_and_back_change_middle() {
     ctx.build_roads(&[vec2(0.0, 0.0), vec2(100.0, 0.0), vec2(100.0, 50.0)]);
 
     let b1 = ctx.build_house_near(vec2(0.0, 0.0));
-    let human = spawn_human(&mut ctx.g, b1);
+    let household = spawn_household_with(&mut ctx.g, b1, &[Role::Worker]).unwrap();
+    let human = ctx.g.comp::<Household>(household.0).unwrap().members[0];
+    let car = spawn_parked_vehicle(&mut ctx.g, VehicleKind::Car, vec2(0.0, 0.0)).unwrap();
+    ctx.g
+        .comp_mut::<Router>(human.0)
+        .unwrap()
+        .set_personal_car(car);
 
     ctx.g
         .write::<ParCommandBuffer>()
@@ -155,3 +170,142 @@ fn test_router_and_back_change_middle() {
         }
     }
 }
+
+/// A car parked in a parking lot along a road, with or without parking lanes, that is removed
+fn car_in_removed_lot(lane_parking: bool) -> (TestCtx, Entity, ParkingSpotID) {
+    let mut ctx = TestCtx::init();
+    ctx.g.write::<crate::economy::Treasury>().sandbox = true;
+
+    let road = {
+        let mut m = ctx.g.map_mut();
+        let a = m.project(vec2(0.0, 0.0), 0.0);
+        let b = m.project(vec2(200.0, 0.0), 0.0);
+        let pattern = LanePatternBuilder::new().parking(lane_parking).build();
+        m.make_connection(a, b, None, &pattern).unwrap().1
+    };
+    let obb = OBB::new(vec2(100.0, 40.0), vec2(1.0, 0.0), 50.0, 50.0);
+    let lot = ctx
+        .g
+        .map_mut()
+        .build_special_building(
+            road,
+            &obb,
+            BuildingKind::ParkingLot,
+            BuildingGen::ParkingLot,
+        )
+        .unwrap();
+
+    let spot = {
+        let map = ctx.g.map();
+        let mut pm = ctx.g.write::<ParkingManagement>();
+        pm.reserve_in_lot(vec2(100.0, 40.0), 100.0, &map).unwrap()
+    };
+    let trans = spot.get(&ctx.g.map().parking).unwrap().trans;
+    let spot_id = ctx
+        .g
+        .map()
+        .parking
+        .all_spots()
+        .find(|(_, s)| s.trans.position() == trans.position())
+        .unwrap()
+        .0;
+    let car = make_vehicle_entity(
+        &mut ctx.g,
+        trans,
+        Vehicle::new(VehicleKind::Car, spot),
+        Itinerary::none(),
+        false,
+    );
+
+    let mut commands = WorldCommands::default();
+    commands.map_remove_building(lot);
+    ctx.g.tick(&mut ctx.sched, &commands);
+    ctx.tick();
+
+    assert!(ctx.g.map().parking.removed_lot_spots().is_empty());
+    (ctx, car, spot_id)
+}
+
+#[test]
+fn car_moved_out_of_removed_lot() {
+    let (ctx, car, _) = car_in_removed_lot(true);
+    let map = ctx.g.map();
+    let v = ctx.g.comp::<Vehicle>(car).expect("the car was removed");
+    match v.state {
+        VehicleState::Parked(ref spot) => assert!(spot.exists(&map.parking)),
+        _ => panic!("the car is not parked anymore"),
+    }
+
+    // there is no other spot, the car is removed with its spot
+    let (ctx, car, spot) = car_in_removed_lot(false);
+    assert!(ctx.g.comp::<Vehicle>(car).is_none());
+    assert!(ctx.g.read::<ParkingManagement>().is_spot_free(spot));
+}
+
+#[test]
+fn household_shares_car() {
+    let mut ctx = TestCtx::init();
+
+    ctx.build_roads(&[vec2(0.0, 0.0), vec2(100.0, 0.0), vec2(100.0, 50.0)]);
+
+    let b1 = ctx.build_house_near(vec2(0.0, 0.0));
+    let household =
+        spawn_household_with(&mut ctx.g, b1, &[Role::Worker, Role::Worker, Role::Child]).unwrap();
+    let members = ctx
+        .g
+        .comp::<Household>(household.0)
+        .unwrap()
+        .members
+        .clone();
+    let car = spawn_parked_vehicle(&mut ctx.g, VehicleKind::Car, vec2(0.0, 0.0)).unwrap();
+    share_car(&mut ctx.g, members[0], car);
+
+    let personal_car = |g: &Egregoria, m: SoulID| g.comp::<Router>(m.0).unwrap().personal_car;
+    assert_eq!(personal_car(&ctx.g, members[0]), Some(car));
+    assert_eq!(personal_car(&ctx.g, members[1]), Some(car));
+    assert_eq!(personal_car(&ctx.g, members[2]), None);
+
+    for &m in &members[..2] {
+        ctx.g
+            .write::<ParCommandBuffer>()
+            .remove_component::<Desire<Home>>(m.0);
+        ctx.g
+            .write::<ParCommandBuffer>()
+            .remove_component::<Desire<BuyFood>>(m.0);
+    }
+    ctx.tick();
+
+    let b2 = ctx.build_house_near(vec2(100.0, 5.0));
+    for &m in &members[..2] {
+        ctx.g
+            .comp_mut::<Router>(m.0)
+            .unwrap()
+            .go_to(Destination::Building(b2));
+    }
+
+    // only one of them takes the car, the other one walks
+    let mut drivers = vec![];
+    for i in 0..1000 {
+        ctx.tick();
+        for &m in &members[..2] {
+            if ctx.g.comp::<Location>(m.0) == Some(&Location::Vehicle(car)) && !drivers.contains(&m)
+            {
+                drivers.push(m);
+            }
+        }
+        if members[..2]
+            .iter()
+            .all(|m| ctx.g.comp::<Location>(m.0) == Some(&Location::Building(b2)))
+        {
+            break;
+        }
+        if i == 999 {
+            panic!("not arrived")
+        }
+    }
+    assert_eq!(drivers.len(), 1);
+
+    unshare_car(&mut ctx.g, members[0], car);
+    assert_eq!(personal_car(&ctx.g, members[0]), None);
+    assert_eq!(personal_car(&ctx.g, members[1]), None);
+}
@@ -0,0 +1,43 @@
+use super::*;
+use crate::economy::{CommodityKind, Market};
+use crate::souls::zoning::zoning;
+use map_model::{BuildingKind, LotKind};
+
+fn building_of(ctx: &TestCtx, name: &str) -> BuildingKind {
+    ctx.g
+        .read::<GoodsCompanyRegistry>()
+        .descriptions
+        .values()
+        .find(|d| d.name == name)
+        .unwrap()
+        .bkind
+}
+
+#[test]
+fn stores_resell_what_is_made() {
+    let mut ctx = TestCtx::init();
+
+    ctx.build_roads(&[vec2(0.0, 0.0), vec2(400.0, 0.0)]);
+    let lots: Vec<_> = ctx.g.map().lots().keys().collect();
+    for lot in lots {
+        ctx.g.map_mut().set_lot_kind(lot, LotKind::Commercial);
+    }
+
+    // medicine is already made in the city, but nobody sells it close to the residents
+    let lab = building_of(&ctx, "Pharmaceutical lab");
+    let road = ctx.g.map().roads().keys().next().unwrap();
+    let obb = OBB::new(vec2(200.0, -60.0), vec2(1.0, 0.0), 60.0, 60.0);
+    ctx.g
+        .map_mut()
+        .build_special_building(road, &obb, lab, BuildingGen::House)
+        .unwrap();
+
+    let buyer = SoulID(ctx.g.world.push(()));
+    ctx.g
+        .write::<Market>()
+        .buy(buyer, vec2(0.0, 0.0), CommodityKind::Medicine, 10);
+    zoning(&mut ctx.g);
+
+    let pharmacy = building_of(&ctx, "Pharmacy");
+    assert!(ctx.g.map().buildings().values().any(|b| b.kind == pharmacy));
+}
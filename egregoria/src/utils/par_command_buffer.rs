@@ -116,11 +116,11 @@ impl ParCommandBuffer {
         deleted.sort_unstable_by_key(|&x| ent_id(x));
 
         for entity in deleted {
-            if goria.world.remove(entity) {
-                Self::parse_del::<Collider>(goria, entity);
-                Self::parse_del::<Vehicle>(goria, entity);
-                Self::parse_del::<Router>(goria, entity);
-            }
+            // the components must be dropped before the entity is removed from the world
+            Self::parse_del::<Collider>(goria, entity);
+            Self::parse_del::<Vehicle>(goria, entity);
+            Self::parse_del::<Router>(goria, entity);
+            goria.world.remove(entity);
         }
 
         let added = std::mem::take(
@@ -4,6 +4,7 @@ use serde::{Deserialize, Serialize};
 pub const SECONDS_PER_HOUR: i32 = 100;
 pub const HOURS_PER_DAY: i32 = 24;
 pub const SECONDS_PER_DAY: i32 = SECONDS_PER_HOUR * HOURS_PER_DAY;
+pub const DAYS_PER_WEEK: i32 = 7;
 
 /// An in-game instant used to measure time differences
 #[derive(Inspect, Debug, Copy, Clone, Serialize, Deserialize)]
@@ -42,6 +43,50 @@ pub struct DayTime {
     pub second: i32,
 }
 
+/// Day of the week, the game starts on a monday
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Weekday {
+    Monday,
+    Tuesday,
+    Wednesday,
+    Thursday,
+    Friday,
+    Saturday,
+    Sunday,
+}
+
+debug_inspect_impl!(Weekday);
+
+impl Weekday {
+    pub fn from_day(day: i32) -> Self {
+        match day.rem_euclid(DAYS_PER_WEEK) {
+            0 => Weekday::Monday,
+            1 => Weekday::Tuesday,
+            2 => Weekday::Wednesday,
+            3 => Weekday::Thursday,
+            4 => Weekday::Friday,
+            5 => Weekday::Saturday,
+            _ => Weekday::Sunday,
+        }
+    }
+
+    pub fn short_name(self) -> &'static str {
+        match self {
+            Weekday::Monday => "Mon",
+            Weekday::Tuesday => "Tue",
+            Weekday::Wednesday => "Wed",
+            Weekday::Thursday => "Thu",
+            Weekday::Friday => "Fri",
+            Weekday::Saturday => "Sat",
+            Weekday::Sunday => "Sun",
+        }
+    }
+
+    pub fn prev(self) -> Self {
+        Self::from_day(self as i32 - 1)
+    }
+}
+
 /// An interval of in-game time
 #[derive(Copy, Clone, Serialize, Deserialize)]
 pub struct TimeInterval {
@@ -90,6 +135,10 @@ impl RecTimeInterval {
         }
     }
 
+    pub fn overlaps_midnight(&self) -> bool {
+        self.overlap
+    }
+
     pub fn dist_until(&self, t: DayTime) -> i32 {
         let mut start_dt = DayTime {
             day: t.day,
@@ -142,6 +191,10 @@ impl DayTime {
     pub fn gamesec(&self) -> i32 {
         self.day * SECONDS_PER_DAY + self.daysec()
     }
+
+    pub fn weekday(&self) -> Weekday {
+        Weekday::from_day(self.day)
+    }
 }
 
 impl GameTime {
@@ -177,6 +230,15 @@ impl GameTime {
     pub fn daysec(&self) -> f64 {
         self.timestamp % Self::DAY as f64
     }
+
+    /// Hours elapsed since the start of the game
+    pub fn total_hours(&self) -> i32 {
+        self.daytime.day * 24 + self.daytime.hour
+    }
+
+    pub fn weekday(&self) -> Weekday {
+        self.daytime.weekday()
+    }
 }
 
 impl GameInstant {
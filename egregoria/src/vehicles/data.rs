@@ -5,7 +5,7 @@ use crate::rendering::assets::{AssetID, AssetRender};
 use crate::utils::par_command_buffer::ComponentDrop;
 use crate::utils::rand_provider::RandProvider;
 use crate::utils::time::GameInstant;
-use crate::Egregoria;
+use crate::{Egregoria, SoulID};
 use geom::Color;
 use geom::{Spline, Transform, Vec2};
 use imgui_inspect::InspectDragf;
@@ -50,6 +50,16 @@ pub struct Vehicle {
     pub state: VehicleState,
     pub kind: VehicleKind,
 
+    /// Emergency vehicles answering a call go through red lights and stop signs, slowing down
+    /// to yield to the crossing traffic
+    #[serde(default)]
+    pub siren: bool,
+
+    /// The soul that planned a trip with the vehicle, a parked car shared by a household
+    /// can only be taken by one of its members at a time
+    #[serde(default)]
+    pub claimed_by: Option<SoulID>,
+
     /// Used to detect gridlock
     pub flag: u64,
 }
@@ -235,6 +245,20 @@ impl Vehicle {
             wait_time: 0.0,
             state: VehicleState::Parked(spot),
             kind,
+            siren: false,
+            claimed_by: None,
+            flag: 0,
+        }
+    }
+
+    pub fn new_driving(kind: VehicleKind) -> Vehicle {
+        Self {
+            ang_velocity: 0.0,
+            wait_time: 0.0,
+            state: VehicleState::Driving,
+            kind,
+            siren: false,
+            claimed_by: None,
             flag: 0,
         }
     }
@@ -9,6 +9,11 @@ use legion::system;
 use legion::Entity;
 use map_model::{Map, TrafficBehavior, Traversable, TraverseKind};
 
+/// Distance to a red light or a stop sign at which emergency vehicles slow down
+const SIREN_SLOWDOWN_DIST: f32 = 15.0;
+/// Speed at which emergency vehicles go through red lights and stop signs
+const SIREN_CROSSING_SPEED: f32 = 4.0;
+
 register_system!(vehicle_decision);
 #[system(par_for_each)]
 pub fn vehicle_decision(
@@ -224,6 +229,14 @@ pub fn calc_decision<'a>(
             let light = l.control_point();
 
             match l.control.get_behavior(time.seconds) {
+                // Emergency vehicles go through, slowly enough to yield to the crossing traffic
+                TrafficBehavior::RED | TrafficBehavior::ORANGE | TrafficBehavior::STOP
+                    if vehicle.siren =>
+                {
+                    if light.is_close(position, SIREN_SLOWDOWN_DIST + stop_dist) {
+                        speed = speed.min(SIREN_CROSSING_SPEED);
+                    }
+                }
                 TrafficBehavior::RED | TrafficBehavior::ORANGE => {
                     if light.is_close(
                         position,
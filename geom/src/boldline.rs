@@ -40,8 +40,25 @@ impl Intersect<Circle> for BoldLine {
 
 defer_inter!(Polygon => BoldLine);
 impl Intersect<Polygon> for BoldLine {
-    fn intersects(&self, _: &Polygon) -> bool {
-        println!("collision between polygon and boldline not implemented");
+    fn intersects(&self, p: &Polygon) -> bool {
+        if p.contains(self.line.first()) {
+            return true;
+        }
+        let r = self.radius;
+        let pbb = p.bbox().expand(r);
+        for seg1 in self.line.segments().filter(|x| x.intersects(&pbb)) {
+            for seg2 in p.segments() {
+                if seg1.intersects(&seg2) {
+                    return true;
+                }
+                if seg1.project(seg2.src).is_close(seg2.src, r)
+                    || seg2.project(seg1.src).is_close(seg1.src, r)
+                    || seg2.project(seg1.dst).is_close(seg1.dst, r)
+                {
+                    return true;
+                }
+            }
+        }
         false
     }
 }
@@ -2,6 +2,8 @@ use common::logger::MyLog;
 use common::unwrap_or;
 use egregoria::engine_interaction::WorldCommands;
 use egregoria::{Egregoria, SerPreparedEgregoria};
+use geom::Vec2;
+use map_model::procgen::CityGenParams;
 use networking::{Frame, Server, ServerConfiguration, ServerPollResult};
 use std::convert::TryFrom;
 use std::iter::FromIterator;
@@ -27,6 +29,30 @@ struct Opt {
     /// i.e. 20ms = 50FPS
     #[structopt(long, default_value = "20")]
     timestep: u64,
+
+    /// Name of the save file to load and autosave to
+    #[structopt(long, default_value = "world")]
+    save: String,
+
+    /// Write the map of the save as GeoJSON to this file and exit instead of running the server
+    #[structopt(long)]
+    export_geojson: Option<String>,
+
+    /// Also export the agents and the per-lane traffic with --export-geojson
+    #[structopt(long)]
+    agents: bool,
+
+    /// Check the map of the save for problems and exit instead of running the server
+    #[structopt(long)]
+    validate: bool,
+
+    /// Also repair the problems found by --validate and overwrite the save
+    #[structopt(long)]
+    repair: bool,
+
+    /// Start a new game on a city generated from this seed instead of loading the save
+    #[structopt(long)]
+    new_city: Option<u64>,
 }
 
 fn main() {
@@ -35,13 +61,61 @@ fn main() {
 
     log::info!("starting server with version: {}", goria_version::VERSION);
 
-    let mut w = unwrap_or!(Egregoria::load_from_disk("world"), {
-        log::info!("savegame not found defaulting to empty");
-        Egregoria::empty()
-    });
+    let save = opt.save.as_str();
+
+    if let Some(path) = &opt.export_geojson {
+        let w = unwrap_or!(Egregoria::load_from_disk(save), {
+            log::error!("could not load savegame {}", save);
+            return;
+        });
+        let geojson = egregoria::geojson::to_geojson(&w, opt.agents);
+        match std::fs::write(path, geojson.to_string()) {
+            Ok(()) => log::info!("exported {} to {}", save, path),
+            Err(e) => log::error!("could not write {}: {}", path, e),
+        }
+        return;
+    }
 
     let mut sched = Egregoria::schedule();
 
+    if opt.validate {
+        let mut w = unwrap_or!(Egregoria::load_from_disk(save), {
+            log::error!("could not load savegame {}", save);
+            return;
+        });
+        let problems = w.map().validate();
+        log::info!("found {} problems in {}", problems.len(), save);
+        for problem in &problems {
+            log::info!("{}", problem);
+        }
+        if opt.repair && problems.iter().any(|p| p.repairable()) {
+            let mut commands = WorldCommands::default();
+            commands.map_repair();
+            w.tick(&mut sched, &commands);
+            log::info!("{} problems left after repair", w.map().validate().len());
+            w.save_to_disk(save);
+        }
+        return;
+    }
+
+    let mut w = match opt.new_city {
+        Some(seed) => {
+            let mut w = Egregoria::empty();
+            let mut commands = WorldCommands::default();
+            let params = CityGenParams {
+                seed,
+                ..Default::default()
+            };
+            commands.map_generate_city(Vec2::ZERO, params);
+            w.tick(&mut sched, &commands);
+            w
+        }
+        None => unwrap_or!(Egregoria::load_from_disk(save), {
+            log::info!("savegame not found defaulting to empty");
+            Egregoria::empty()
+        }),
+    };
+
     let mut server: Server<SerPreparedEgregoria, WorldCommands> =
         match Server::start(ServerConfiguration {
             start_frame: Frame(w.get_tick()),
@@ -79,7 +153,7 @@ fn main() {
         }
 
         if last_saved.elapsed().as_secs() > opt.autosave {
-            w.save_to_disk("world");
+            w.save_to_disk(save);
             last_saved = Instant::now();
         }
 
@@ -0,0 +1,192 @@
+//! Connectivity of the lanes, to find the parts of the network where agents cannot route
+
+use crate::{BuildingID, LaneID, LaneKind, Map, PathKind};
+use common::{FastMap, FastSet};
+use pathfinding::directed::bfs::bfs_reach;
+use pathfinding::directed::strongly_connected_components::strongly_connected_components;
+
+#[derive(Debug, Clone)]
+pub struct Connectivity {
+    pub kind: PathKind,
+    /// Strongly connected components of the lanes, the main one is the largest and comes first
+    pub components: Vec<Vec<LaneID>>,
+    /// Lanes that can be entered from the main component but from which it cannot be reached,
+    /// like one-way roads leading into a dead end
+    pub dead_ends: Vec<LaneID>,
+    /// Lanes that cannot be reached from the main component
+    pub unreachable: Vec<LaneID>,
+    component: FastMap<LaneID, usize>,
+}
+
+impl Connectivity {
+    /// Index of the component of the lane in `components`
+    pub fn component(&self, lane: LaneID) -> Option<usize> {
+        self.component.get(&lane).copied()
+    }
+
+    pub fn in_main(&self, lane: LaneID) -> bool {
+        self.component(lane) == Some(0)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct NetworkAnalysis {
+    pub vehicle: Connectivity,
+    pub pedestrian: Connectivity,
+    /// Buildings whose door cannot be reached from the main components, by car or on foot
+    pub unreachable_buildings: Vec<BuildingID>,
+}
+
+impl Map {
+    pub fn analyze_network(&self) -> NetworkAnalysis {
+        let vehicle = self.connectivity(PathKind::Vehicle);
+        let pedestrian = self.connectivity(PathKind::Pedestrian);
+
+        let unreachable_buildings = self
+            .buildings
+            .values()
+            .filter(|b| {
+                let road = unwrap_ret!(self.door_road(b.door_pos), true);
+                let reachable = |c: &Connectivity, kind| {
+                    let mut lanes = road.lanes_iter().filter(|&(_, k)| k == kind).peekable();
+                    // a road without lanes of this kind doesn't need them to be reachable
+                    lanes.peek().is_none() || lanes.any(|(id, _)| c.in_main(id))
+                };
+                !reachable(&vehicle, LaneKind::Driving)
+                    || !reachable(&pedestrian, LaneKind::Walking)
+            })
+            .map(|b| b.id)
+            .collect();
+
+        NetworkAnalysis {
+            vehicle,
+            pedestrian,
+            unreachable_buildings,
+        }
+    }
+
+    pub fn connectivity(&self, kind: PathKind) -> Connectivity {
+        let lane_kind = match kind {
+            PathKind::Vehicle => LaneKind::Driving,
+            PathKind::Pedestrian => LaneKind::Walking,
+        };
+
+        let mut successors: FastMap<LaneID, Vec<LaneID>> = FastMap::default();
+        let mut predecessors: FastMap<LaneID, Vec<LaneID>> = FastMap::default();
+        for lane in self.lanes.values().filter(|l| l.kind == lane_kind) {
+            // cars go to the end of the lane, pedestrians walk both ways
+            let ends = match kind {
+                PathKind::Vehicle => vec![lane.dst],
+                PathKind::Pedestrian => vec![lane.src, lane.dst],
+            };
+            let next: Vec<LaneID> = ends
+                .into_iter()
+                .filter_map(|i| self.intersections.get(i))
+                .flat_map(|inter| inter.turns_from(lane.id))
+                .map(|(turn, _)| {
+                    if turn.src == lane.id {
+                        turn.dst
+                    } else {
+                        turn.src
+                    }
+                })
+                .filter(|l| matches!(self.lanes.get(*l), Some(l) if l.kind == lane_kind))
+                .collect();
+            for &n in &next {
+                predecessors.entry(n).or_default().push(lane.id);
+            }
+            predecessors.entry(lane.id).or_default();
+            successors.insert(lane.id, next);
+        }
+
+        let mut nodes: Vec<LaneID> = successors.keys().copied().collect();
+        nodes.sort();
+        let neighbours = |map: &FastMap<LaneID, Vec<LaneID>>, id: &LaneID| {
+            map.get(id).cloned().unwrap_or_default()
+        };
+
+        let mut components =
+            strongly_connected_components(&nodes, |id| neighbours(&successors, id));
+        for comp in &mut components {
+            comp.sort();
+        }
+        components.sort_by(|a, b| b.len().cmp(&a.len()).then_with(|| a.cmp(b)));
+
+        let component = components
+            .iter()
+            .enumerate()
+            .flat_map(|(i, comp)| comp.iter().map(move |&id| (id, i)))
+            .collect();
+
+        let (dead_ends, unreachable) = match components.first().and_then(|c| c.first()) {
+            Some(&start) => {
+                let from_main: FastSet<LaneID> =
+                    bfs_reach(start, |id| neighbours(&successors, id)).collect();
+                let to_main: FastSet<LaneID> =
+                    bfs_reach(start, |id| neighbours(&predecessors, id)).collect();
+                (
+                    nodes
+                        .iter()
+                        .copied()
+                        .filter(|id| from_main.contains(id) && !to_main.contains(id))
+                        .collect(),
+                    nodes
+                        .iter()
+                        .copied()
+                        .filter(|id| !from_main.contains(id))
+                        .collect(),
+                )
+            }
+            None => (vec![], vec![]),
+        };
+
+        Connectivity {
+            kind,
+            components,
+            dead_ends,
+            unreachable,
+            component,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{LanePattern, LanePatternBuilder, Map, PathKind, RoadID};
+    use geom::{vec2, Vec2};
+
+    fn connect(m: &mut Map, a: Vec2, b: Vec2, pat: &LanePattern) -> RoadID {
+        let (from, to) = (m.project(a, 1.0), m.project(b, 1.0));
+        m.make_connection(from, to, None, pat).unwrap().1
+    }
+
+    #[test]
+    fn one_way_dead_end() {
+        let mut m = Map::empty();
+        let two_way = LanePatternBuilder::new().build();
+        let one_way = LanePatternBuilder::new().one_way(true).build();
+
+        let (a, b, c) = (Vec2::ZERO, vec2(200.0, 0.0), vec2(100.0, 200.0));
+        connect(&mut m, a, b, &two_way);
+        connect(&mut m, b, c, &two_way);
+        connect(&mut m, c, a, &two_way);
+        let dead_end = connect(&mut m, b, vec2(400.0, -100.0), &one_way);
+
+        let analysis = m.analyze_network();
+        let lanes: Vec<_> = m.roads()[dead_end].lanes_iter().map(|(id, _)| id).collect();
+        assert!(!analysis.vehicle.dead_ends.is_empty());
+        assert!(analysis
+            .vehicle
+            .dead_ends
+            .iter()
+            .all(|id| lanes.contains(id)));
+        // without u-turns, each way around the triangle is a component
+        assert!(analysis
+            .vehicle
+            .unreachable
+            .iter()
+            .all(|id| !lanes.contains(id)));
+        assert_eq!(analysis.pedestrian.kind, PathKind::Pedestrian);
+        assert!(analysis.pedestrian.dead_ends.is_empty());
+    }
+}
@@ -0,0 +1,177 @@
+//! Blueprints are parts of a map captured in an area to be pasted elsewhere.
+//!
+//! They use the same format as the [map descriptions](MapDescription), with positions relative
+//! to the center of the captured area, and are saved as JSON.
+
+use crate::{
+    BuildingDescription, BuildingKind, IntersectionDescription, IntersectionID, Map,
+    MapDescription, RoadDescription, RoadID, RoadSegmentKind, MAP_DESCRIPTION_VERSION,
+};
+use common::FastMap;
+use geom::{Vec2, AABB};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Blueprint {
+    pub layout: MapDescription,
+}
+
+impl Blueprint {
+    /// Captures the roads having both intersections in the area, and the special buildings
+    /// connected to them. Houses are left out since they grow by themselves on the lots.
+    pub fn capture(map: &Map, area: AABB) -> Self {
+        let center = area.center();
+
+        let mut inter_idx: FastMap<IntersectionID, usize> = FastMap::default();
+        let mut road_idx: FastMap<RoadID, usize> = FastMap::default();
+        let mut layout = MapDescription {
+            version: MAP_DESCRIPTION_VERSION,
+            intersections: vec![],
+            roads: vec![],
+            buildings: vec![],
+            waters: vec![],
+        };
+
+        for road in map.roads().values() {
+            let src = unwrap_cont!(map.intersections().get(road.src));
+            let dst = unwrap_cont!(map.intersections().get(road.dst));
+            if !area.contains(src.pos) || !area.contains(dst.pos) {
+                continue;
+            }
+
+            let mut idx = |id: IntersectionID| {
+                let inter = &map.intersections()[id];
+                let intersections = &mut layout.intersections;
+                *inter_idx.entry(id).or_insert_with(|| {
+                    intersections.push(IntersectionDescription {
+                        pos: inter.pos - center,
+                        turn_policy: inter.turn_policy,
+                        light_policy: inter.light_policy,
+                    });
+                    intersections.len() - 1
+                })
+            };
+
+            let (src, dst) = (idx(src.id), idx(dst.id));
+            road_idx.insert(road.id, layout.roads.len());
+            layout.roads.push(RoadDescription {
+                src,
+                dst,
+                pattern: road.pattern(map.lanes()),
+                segment: road.segment,
+            });
+        }
+
+        for b in map.buildings().values() {
+            if matches!(b.kind, BuildingKind::House) || !area.contains(b.obb.center()) {
+                continue;
+            }
+            let road = unwrap_cont!(map.door_road(b.door_pos));
+            let &road = unwrap_cont!(road_idx.get(&road.id));
+            let mut obb = b.obb;
+            for c in &mut obb.corners {
+                *c -= center;
+            }
+            layout.buildings.push(BuildingDescription {
+                road,
+                kind: b.kind,
+                gen: b.gen,
+                obb,
+                level: b.level,
+            });
+        }
+
+        info!(
+            "captured blueprint with {} roads and {} buildings",
+            layout.roads.len(),
+            layout.buildings.len()
+        );
+
+        Self { layout }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.layout.roads.is_empty()
+    }
+
+    /// The layout placed at `pos`, rotated by `cossin`
+    pub fn placed(&self, pos: Vec2, cossin: Vec2) -> MapDescription {
+        let tr = |p: Vec2| pos + p.rotated_by(cossin);
+
+        let mut layout = self.layout.clone();
+        for inter in &mut layout.intersections {
+            inter.pos = tr(inter.pos);
+        }
+        for road in &mut layout.roads {
+            if let RoadSegmentKind::Curved((from_derivative, to_derivative)) = road.segment {
+                road.segment = RoadSegmentKind::Curved((
+                    from_derivative.rotated_by(cossin),
+                    to_derivative.rotated_by(cossin),
+                ));
+            }
+        }
+        for b in &mut layout.buildings {
+            for c in &mut b.obb.corners {
+                *c = tr(*c);
+            }
+        }
+        layout
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        let json = serde_json::to_string_pretty(self)?;
+        std::fs::write(path, json)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        Ok(serde_json::from_slice(&std::fs::read(path)?)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Blueprint, BuildingGen, BuildingKind, LanePatternBuilder, Map, RoadSegmentKind};
+    use geom::{vec2, Vec2, AABB, OBB};
+
+    #[test]
+    fn capture_and_place() {
+        let mut m = Map::empty();
+        let pat = LanePatternBuilder::new().build();
+        let (a, b, c) = (Vec2::ZERO, vec2(150.0, 0.0), vec2(150.0, 150.0));
+        let ab = m.make_connection(m.project(a, 1.0), m.project(b, 1.0), None, &pat);
+        m.make_connection(m.project(b, 1.0), m.project(c, 1.0), None, &pat);
+        // outside of the area
+        m.make_connection(
+            m.project(c, 1.0),
+            m.project(vec2(600.0, 150.0), 1.0),
+            None,
+            &pat,
+        );
+
+        let obb = OBB::new(vec2(75.0, 20.0), vec2(1.0, 0.0), 20.0, 20.0);
+        m.build_special_building(ab.unwrap().1, &obb, BuildingKind::Park, BuildingGen::Park)
+            .unwrap();
+
+        let bp = Blueprint::capture(&m, AABB::new(vec2(-10.0, -10.0), vec2(160.0, 160.0)));
+        assert_eq!(bp.layout.intersections.len(), 3);
+        assert_eq!(bp.layout.roads.len(), 2);
+        assert_eq!(bp.layout.buildings.len(), 1);
+        assert!(bp
+            .layout
+            .roads
+            .iter()
+            .all(|r| matches!(r.segment, RoadSegmentKind::Straight)));
+
+        // a quarter turn around (1000, 0)
+        let placed = bp.placed(vec2(1000.0, 0.0), vec2(0.0, 1.0));
+        let first = bp.layout.intersections[bp.layout.roads[0].src].pos;
+        let moved = placed.intersections[placed.roads[0].src].pos;
+        assert!(moved.is_close(vec2(1000.0 - first.y, first.x), 0.01));
+
+        let mut m2 = Map::empty();
+        m2.load_description(&placed).unwrap();
+        assert_eq!(m2.roads().len(), 2);
+        assert_eq!(m2.buildings().len(), 1);
+    }
+}
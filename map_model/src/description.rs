@@ -0,0 +1,342 @@
+//! Human-readable JSON description of a map, independent of the slotmap IDs.
+//!
+//! Intersections, roads, buildings and waters are listed in order and refer to each other by their
+//! index in these lists. Lanes, lots and parking spots are not described since they are
+//! generated from the roads when the map is rebuilt.
+//!
+//! ```json
+//! {
+//!   "version": 1,
+//!   "intersections": [
+//!     { "pos": { "x": 0.0, "y": 0.0 } },
+//!     { "pos": { "x": 100.0, "y": 0.0 }, "light_policy": "StopSigns" }
+//!   ],
+//!   "roads": [
+//!     {
+//!       "src": 0,
+//!       "dst": 1,
+//!       "pattern": {
+//!         "lanes_forward": [["Driving", 12.0], ["Walking", 12.0]],
+//!         "lanes_backward": [["Driving", 12.0], ["Walking", 12.0]]
+//!       }
+//!     }
+//!   ],
+//!   "buildings": []
+//! }
+//! ```
+
+use crate::{
+    BuildingGen, BuildingKind, IntersectionID, LanePattern, LightPolicy, Map, RoadID,
+    RoadSegmentKind, TurnPolicy, WaterKind,
+};
+use common::{unwrap_cont, FastMap};
+use geom::{Vec2, OBB};
+use serde::{Deserialize, Serialize};
+use std::io::{Error, ErrorKind};
+use std::path::Path;
+
+/// Version of the format, increased when old descriptions can no longer be read
+pub const MAP_DESCRIPTION_VERSION: u32 = 1;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MapDescription {
+    pub version: u32,
+    pub intersections: Vec<IntersectionDescription>,
+    pub roads: Vec<RoadDescription>,
+    #[serde(default)]
+    pub buildings: Vec<BuildingDescription>,
+    #[serde(default)]
+    pub waters: Vec<WaterDescription>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct IntersectionDescription {
+    pub pos: Vec2,
+    #[serde(default)]
+    pub turn_policy: TurnPolicy,
+    #[serde(default)]
+    pub light_policy: LightPolicy,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RoadDescription {
+    /// Index of the source intersection
+    pub src: usize,
+    /// Index of the destination intersection
+    pub dst: usize,
+    pub pattern: LanePattern,
+    #[serde(default)]
+    pub segment: RoadSegmentKind,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BuildingDescription {
+    /// Index of the road the building is connected to
+    pub road: usize,
+    pub kind: BuildingKind,
+    #[serde(default)]
+    pub gen: BuildingGen,
+    pub obb: OBB,
+    #[serde(default)]
+    pub level: u32,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct WaterDescription {
+    pub kind: WaterKind,
+    pub points: Vec<Vec2>,
+}
+
+fn invalid(msg: String) -> Error {
+    Error::new(ErrorKind::InvalidData, msg)
+}
+
+impl Map {
+    pub fn describe(&self) -> MapDescription {
+        let inter_idx: FastMap<IntersectionID, usize> = self
+            .intersections
+            .keys()
+            .enumerate()
+            .map(|(i, id)| (id, i))
+            .collect();
+        let road_idx: FastMap<RoadID, usize> = self
+            .roads
+            .keys()
+            .enumerate()
+            .map(|(i, id)| (id, i))
+            .collect();
+
+        MapDescription {
+            version: MAP_DESCRIPTION_VERSION,
+            intersections: self
+                .intersections
+                .values()
+                .map(|inter| IntersectionDescription {
+                    pos: inter.pos,
+                    turn_policy: inter.turn_policy,
+                    light_policy: inter.light_policy,
+                })
+                .collect(),
+            roads: self
+                .roads
+                .values()
+                .filter_map(|road| {
+                    Some(RoadDescription {
+                        src: *inter_idx.get(&road.src)?,
+                        dst: *inter_idx.get(&road.dst)?,
+                        pattern: road.pattern(&self.lanes),
+                        segment: road.segment,
+                    })
+                })
+                .collect(),
+            buildings: self
+                .buildings
+                .values()
+                .filter_map(|b| {
+                    Some(BuildingDescription {
+                        road: *road_idx.get(&self.door_road(b.door_pos)?.id)?,
+                        kind: b.kind,
+                        gen: b.gen,
+                        obb: b.obb,
+                        level: b.level,
+                    })
+                })
+                .collect(),
+            waters: self
+                .waters
+                .values()
+                .map(|w| WaterDescription {
+                    kind: w.kind,
+                    points: w.points.as_slice().to_vec(),
+                })
+                .collect(),
+        }
+    }
+
+    /// Adds the described waters, intersections, roads and buildings to the map.
+    /// The description is checked first, the map is left untouched if it is invalid.
+    pub fn load_description(&mut self, desc: &MapDescription) -> std::io::Result<()> {
+        desc.validate()?;
+
+        // the water goes first so the roads crossing it are built as bridges
+        for (i, w) in desc.waters.iter().enumerate() {
+            if self.add_water(w.kind, w.points.clone()).is_none() {
+                log::error!("water {} covers a building, skipping it", i);
+            }
+        }
+
+        let inters: Vec<IntersectionID> = desc
+            .intersections
+            .iter()
+            .map(|inter| self.add_intersection(inter.pos))
+            .collect();
+
+        let mut roads = Vec::with_capacity(desc.roads.len());
+        for road in &desc.roads {
+            #[allow(clippy::indexing_slicing)]
+            let (src, dst) = (inters[road.src], inters[road.dst]);
+            roads.push(self.connect(src, dst, &road.pattern, road.segment));
+        }
+
+        for (inter, id) in desc.intersections.iter().zip(&inters) {
+            if !self.intersections.contains_key(*id) {
+                continue;
+            }
+            self.update_intersection(*id, |i| {
+                i.turn_policy = inter.turn_policy;
+                i.light_policy = inter.light_policy;
+            });
+        }
+
+        for (i, b) in desc.buildings.iter().enumerate() {
+            #[allow(clippy::indexing_slicing)]
+            let road = unwrap_cont!(roads[b.road]);
+            let id = match self.build_special_building(road, &b.obb, b.kind, b.gen) {
+                Some(id) => id,
+                None => {
+                    log::error!("building {} overlaps another one, skipping it", i);
+                    continue;
+                }
+            };
+            self.restore_house_level(id, b.level);
+        }
+
+        Ok(())
+    }
+
+    pub fn save_json(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        let json = serde_json::to_string_pretty(&self.describe())?;
+        std::fs::write(path, json)
+    }
+}
+
+impl MapDescription {
+    /// Reads and checks a map description saved with `Map::save_json`
+    pub fn load_json(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        let desc: MapDescription = serde_json::from_slice(&std::fs::read(path)?)?;
+        desc.validate()?;
+        Ok(desc)
+    }
+
+    /// Checks the version and that every index refers to an existing element
+    pub fn validate(&self) -> std::io::Result<()> {
+        if self.version > MAP_DESCRIPTION_VERSION {
+            return Err(invalid(format!(
+                "map description version {} is newer than the supported version {}",
+                self.version, MAP_DESCRIPTION_VERSION
+            )));
+        }
+        for (i, w) in self.waters.iter().enumerate() {
+            let enough = match w.kind {
+                WaterKind::River { .. } => w.points.len() >= 2,
+                WaterKind::Lake => w.points.len() >= 3,
+            };
+            if !enough {
+                return Err(invalid(format!("water {} has too few points", i)));
+            }
+        }
+        let n_inters = self.intersections.len();
+        for (i, road) in self.roads.iter().enumerate() {
+            if road.src >= n_inters || road.dst >= n_inters {
+                return Err(invalid(format!("road {} has an unknown intersection", i)));
+            }
+            if road.src == road.dst {
+                return Err(invalid(format!("road {} is a loop", i)));
+            }
+        }
+        for (i, b) in self.buildings.iter().enumerate() {
+            if b.road >= self.roads.len() {
+                return Err(invalid(format!("building {} has an unknown road", i)));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{BuildingGen, BuildingKind, LightPolicy, Map, MapDescription};
+    use geom::{vec2, OBB};
+
+    #[test]
+    fn description_round_trip() {
+        let desc: MapDescription = serde_json::from_str(
+            r#"{
+              "version": 1,
+              "intersections": [
+                { "pos": { "x": 0.0, "y": 0.0 } },
+                { "pos": { "x": 150.0, "y": 0.0 }, "light_policy": "StopSigns" },
+                { "pos": { "x": 150.0, "y": 150.0 } }
+              ],
+              "roads": [
+                { "src": 0, "dst": 1, "pattern": {
+                    "lanes_forward": [["Driving", 12.0], ["Walking", 12.0]],
+                    "lanes_backward": [["Driving", 12.0], ["Walking", 12.0]] } },
+                { "src": 1, "dst": 2, "pattern": {
+                    "lanes_forward": [["Driving", 20.0], ["Parking", 20.0], ["Walking", 20.0]],
+                    "lanes_backward": [["Walking", 20.0]] } }
+              ]
+            }"#,
+        )
+        .unwrap();
+
+        let mut m = Map::empty();
+        m.load_description(&desc).unwrap();
+        assert_eq!(m.intersections().len(), 3);
+        assert_eq!(m.roads().len(), 2);
+        assert_eq!(m.lanes().len(), 8);
+
+        let obb = OBB::new(vec2(75.0, 20.0), vec2(1.0, 0.0), 20.0, 20.0);
+        let road = m.roads().keys().next().unwrap();
+        m.build_special_building(road, &obb, BuildingKind::Park, BuildingGen::Park)
+            .unwrap();
+
+        let desc = m.describe();
+        let mut m2 = Map::empty();
+        m2.load_description(&desc).unwrap();
+
+        assert_eq!(m2.roads().len(), 2);
+        assert_eq!(m2.lanes().len(), 8);
+        assert_eq!(m2.buildings().len(), 1);
+        assert!(m2
+            .intersections()
+            .values()
+            .any(|i| i.light_policy == LightPolicy::StopSigns));
+        let desc2 = m2.describe();
+        assert_eq!(
+            serde_json::to_string(&desc).unwrap(),
+            serde_json::to_string(&desc2).unwrap()
+        );
+    }
+
+    #[test]
+    fn invalid_description_changes_nothing() {
+        let mut desc: MapDescription = serde_json::from_str(
+            r#"{
+              "version": 1,
+              "intersections": [
+                { "pos": { "x": 0.0, "y": 0.0 } },
+                { "pos": { "x": 150.0, "y": 0.0 } }
+              ],
+              "roads": [
+                { "src": 0, "dst": 1, "pattern": {
+                    "lanes_forward": [["Driving", 12.0]],
+                    "lanes_backward": [["Driving", 12.0]] } },
+                { "src": 1, "dst": 2, "pattern": {
+                    "lanes_forward": [["Driving", 12.0]],
+                    "lanes_backward": [["Driving", 12.0]] } }
+              ]
+            }"#,
+        )
+        .unwrap();
+
+        let mut m = Map::empty();
+        assert!(m.load_description(&desc).is_err());
+        assert_eq!(m.intersections().len(), 0);
+        assert_eq!(m.roads().len(), 0);
+
+        desc.roads.pop();
+        m.load_description(&desc).unwrap();
+        assert_eq!(m.roads().len(), 1);
+    }
+}
@@ -0,0 +1,178 @@
+//! GeoJSON export of the map.
+//! Coordinates are in meters in the map frame, not in WGS84 as RFC 7946 expects, so the
+//! collection carries the non-standard "crs" member of the 2008 GeoJSON specification
+//! naming that frame. Every feature has a "layer" property telling which kind of object
+//! it is and an "id" property with its ID.
+
+use crate::{Map, TraverseDirection};
+use geom::{Vec2, OBB};
+use serde_json::{json, Map as Props, Value};
+use slotmap::Key;
+
+/// Numeric form of an ID, stable for a given map
+pub fn id(key: impl Key) -> u64 {
+    key.data().as_ffi()
+}
+
+pub fn point(p: Vec2) -> Value {
+    json!({ "type": "Point", "coordinates": [p.x, p.y] })
+}
+
+pub fn line_string<'a>(points: impl IntoIterator<Item = &'a Vec2>) -> Value {
+    let coords: Vec<_> = points.into_iter().map(|p| json!([p.x, p.y])).collect();
+    json!({ "type": "LineString", "coordinates": coords })
+}
+
+pub fn polygon(obb: &OBB) -> Value {
+    let ring: Vec<_> = obb
+        .corners
+        .iter()
+        .chain(obb.corners.first())
+        .map(|p| json!([p.x, p.y]))
+        .collect();
+    json!({ "type": "Polygon", "coordinates": [ring] })
+}
+
+/// A feature of the given layer, `props` must be an object
+pub fn feature(layer: &str, geometry: Value, props: Value) -> Value {
+    let mut properties = match props {
+        Value::Object(o) => o,
+        _ => Props::new(),
+    };
+    properties.insert("layer".to_string(), json!(layer));
+    json!({ "type": "Feature", "geometry": geometry, "properties": properties })
+}
+
+/// Name of the coordinate system of the export: meters, x to the east and y to the north
+pub const CRS_NAME: &str = "egregoria:map-meters";
+
+pub fn feature_collection(features: Vec<Value>) -> Value {
+    json!({
+        "type": "FeatureCollection",
+        "crs": { "type": "name", "properties": { "name": CRS_NAME } },
+        "features": features,
+    })
+}
+
+/// Intersections, roads, lanes, lots, buildings and parking spots of the map
+pub fn map_features(map: &Map) -> Vec<Value> {
+    let mut features = vec![];
+
+    for inter in map.intersections().values() {
+        features.push(feature(
+            "intersection",
+            point(inter.pos),
+            json!({
+                "id": id(inter.id),
+                "roads": inter.roads.iter().map(|&r| id(r)).collect::<Vec<_>>(),
+                "turn_policy": inter.turn_policy,
+                "light_policy": inter.light_policy,
+            }),
+        ));
+    }
+
+    for road in map.roads().values() {
+        features.push(feature(
+            "road",
+            line_string(road.points.iter()),
+            json!({
+                "id": id(road.id),
+                "src": id(road.src),
+                "dst": id(road.dst),
+                "width": road.width,
+                "length": road.length(),
+            }),
+        ));
+    }
+
+    for (lane_id, lane) in map.lanes() {
+        let direction = match map.roads().get(lane.parent).map(|r| lane.dir_from(r.src)) {
+            Some(TraverseDirection::Backward) => "backward",
+            _ => "forward",
+        };
+        features.push(feature(
+            "lane",
+            line_string(lane.points.iter()),
+            json!({
+                "id": id(lane_id),
+                "road": id(lane.parent),
+                "src": id(lane.src),
+                "dst": id(lane.dst),
+                "kind": format!("{:?}", lane.kind),
+                "direction": direction,
+                "speed_limit": lane.speed_limit,
+            }),
+        ));
+    }
+
+    for lot in map.lots().values() {
+        features.push(feature(
+            "lot",
+            polygon(&lot.shape),
+            json!({
+                "id": id(lot.id),
+                "road": id(lot.parent),
+                "kind": format!("{:?}", lot.kind),
+            }),
+        ));
+    }
+
+    for building in map.buildings().values() {
+        features.push(feature(
+            "building",
+            polygon(&building.obb),
+            json!({
+                "id": id(building.id),
+                "kind": format!("{:?}", building.kind),
+                "level": building.level,
+                "door": [building.door_pos.x, building.door_pos.y],
+            }),
+        ));
+    }
+
+    for (spot_id, spot) in map.parking.iter() {
+        features.push(feature(
+            "parking_spot",
+            point(spot.trans.position()),
+            json!({
+                "id": id(spot_id),
+                "lane": id(spot.parent),
+            }),
+        ));
+    }
+
+    features
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{LanePatternBuilder, Map, MapProject};
+    use geom::vec2;
+
+    #[test]
+    fn export_road() {
+        let mut m = Map::empty();
+        let ground = |x| MapProject::ground(vec2(x, 0.0));
+        let pattern = LanePatternBuilder::new().build();
+        let n_lanes = pattern.lanes_forward.len() + pattern.lanes_backward.len();
+        m.make_connection(ground(0.0), ground(100.0), None, &pattern);
+
+        let features = super::map_features(&m);
+        let count = |layer: &str| {
+            features
+                .iter()
+                .filter(|f| f["properties"]["layer"] == layer)
+                .count()
+        };
+        assert_eq!(count("intersection"), 2);
+        assert_eq!(count("road"), 1);
+        assert_eq!(count("lane"), n_lanes);
+        assert!(features
+            .iter()
+            .filter(|f| f["properties"]["layer"] == "lane")
+            .any(|f| f["properties"]["direction"] == "backward"));
+
+        let collection = super::feature_collection(features);
+        assert_eq!(collection["crs"]["properties"]["name"], super::CRS_NAME);
+    }
+}
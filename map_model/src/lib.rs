@@ -18,6 +18,8 @@ mod objects {
     mod parking;
     mod road;
     mod turn;
+    mod turn_conflict;
+    mod water;
 
     pub use building::*;
     pub use intersection::*;
@@ -26,38 +28,55 @@ mod objects {
     pub use parking::*;
     pub use road::*;
     pub use turn::*;
+    pub use turn_conflict::*;
+    pub use water::*;
 }
 
 pub use objects::*;
 
 pub mod procgen {
     mod building;
+    mod citygen;
     pub mod heightmap;
+    mod osm;
     mod presets;
     mod trees;
 
     pub use building::*;
+    pub use citygen::*;
+    pub use osm::*;
     pub use presets::*;
     pub use trees::*;
 }
 
+mod analysis;
+mod blueprint;
+mod description;
+pub mod geojson;
 mod light_policy;
 mod map;
 mod pathfinding;
 mod serializing;
 mod spatial_map;
+mod terrain;
 mod traffic_control;
 mod traversable;
 mod turn_policy;
+mod validation;
 
 // Use self or else it would be ambiguous with "pathfinding" crate
 pub use self::pathfinding::*;
+pub use analysis::*;
+pub use blueprint::*;
+pub use description::*;
 pub use light_policy::*;
 pub use map::*;
 pub use spatial_map::*;
+pub use terrain::*;
 pub use traffic_control::*;
 pub use traversable::*;
 pub use turn_policy::*;
+pub use validation::*;
 
 pub use ::pathfinding as pathfinding_crate;
 
@@ -1,12 +1,14 @@
-use crate::procgen::Trees;
+use crate::procgen::{Trees, LEVEL_GROWTH};
 use crate::serializing::SerializedMap;
 use crate::{
-    Building, BuildingGen, BuildingID, BuildingKind, Intersection, IntersectionID, Lane, LaneID,
-    LaneKind, LanePattern, Lot, LotID, LotKind, ParkingSpotID, ParkingSpots, ProjectFilter,
-    ProjectKind, Road, RoadID, RoadSegmentKind, SpatialMap,
+    edits_bounds, Building, BuildingGen, BuildingID, BuildingKind, HeightEdit, Heightmap,
+    Intersection, IntersectionID, Lane, LaneID, LaneKind, LanePattern, Lot, LotID, LotKind,
+    ParkingSpotID, ParkingSpots, ProjectFilter, ProjectKind, Road, RoadID, RoadSegmentKind,
+    SpatialMap, TerraformKind, Water, WaterID, WaterKind, BUILDABLE_LEVEL, MAX_ROAD_SLOPE,
 };
-use geom::{pseudo_angle, Circle, Intersect, Shape, Vec2};
-use geom::{Spline, OBB};
+use common::{unwrap_or, unwrap_ret};
+use geom::{pseudo_angle, BoldLine, Circle, Intersect, PolyLine, Shape, Vec2};
+use geom::{Spline, Transform, OBB};
 use ordered_float::OrderedFloat;
 use serde::{Deserialize, Serialize};
 use slotmap::DenseSlotMap;
@@ -17,6 +19,7 @@ pub type Lanes = DenseSlotMap<LaneID, Lane>;
 pub type Intersections = DenseSlotMap<IntersectionID, Intersection>;
 pub type Buildings = DenseSlotMap<BuildingID, Building>;
 pub type Lots = DenseSlotMap<LotID, Lot>;
+pub type Waters = DenseSlotMap<WaterID, Water>;
 
 #[derive(Copy, Clone, Debug, Serialize, Deserialize)]
 pub struct MapProject {
@@ -24,6 +27,15 @@ pub struct MapProject {
     pub kind: ProjectKind,
 }
 
+impl MapProject {
+    pub fn ground(pos: Vec2) -> Self {
+        Self {
+            pos,
+            kind: ProjectKind::Ground,
+        }
+    }
+}
+
 // can't derive Serialize because it would clone
 #[derive(Deserialize)]
 #[serde(from = "SerializedMap")]
@@ -33,9 +45,11 @@ pub struct Map {
     pub(crate) intersections: Intersections,
     pub(crate) buildings: Buildings,
     pub(crate) lots: Lots,
+    pub(crate) waters: Waters,
     pub(crate) spatial_map: SpatialMap,
     pub trees: Trees,
     pub parking: ParkingSpots,
+    pub heightmap: Heightmap,
     pub dirt_id: Wrapping<u32>,
 }
 
@@ -56,7 +70,9 @@ impl Map {
             parking: ParkingSpots::default(),
             buildings: Buildings::default(),
             lots: Lots::default(),
+            waters: Waters::default(),
             trees: Trees::default(),
+            heightmap: Heightmap::default(),
             dirt_id: Wrapping(1),
             spatial_map: SpatialMap::default(),
         }
@@ -85,7 +101,7 @@ impl Map {
         self.check_invariants()
     }
 
-    fn remove_intersection_inner(&mut self, src: IntersectionID) {
+    pub(crate) fn remove_intersection_inner(&mut self, src: IntersectionID) {
         let inter = unwrap_ret!(self.intersections.remove(src));
 
         for road in inter.roads {
@@ -102,6 +118,7 @@ impl Map {
 
         let b = self.buildings.remove(b)?;
         self.spatial_map.remove(b.id);
+        self.parking.remove_lot_spots(b.id);
 
         self.dirt_id += Wrapping(1);
 
@@ -111,12 +128,130 @@ impl Map {
         Some(b)
     }
 
+    /// Edits the terrain, removing the trees and lots that end up under water
+    pub fn terraform(&mut self, center: Vec2, radius: f32, kind: TerraformKind, amount: f32) {
+        info!("terraform {:?} at {:?} ({})", kind, center, radius);
+        self.dirt_id += Wrapping(1);
+        let edits = self.heightmap.brush(center, radius, kind, amount);
+        self.heightmap.set_heights(&edits);
+        self.remove_flooded(&edits);
+    }
+
+    /// Sets the heights of the terrain vertices like `terraform`, unless a road or a building
+    /// would end up under water or on a slope steeper than `MAX_ROAD_SLOPE`.
+    /// Returns the edits restoring the previous heights, or why the terrain cannot be changed.
+    pub fn set_heights(&mut self, edits: &[HeightEdit]) -> Result<Vec<HeightEdit>, String> {
+        info!("set {} terrain heights", edits.len());
+        let bbox = unwrap_or!(edits_bounds(edits), return Ok(vec![]));
+        let objects: Vec<ProjectKind> = self
+            .spatial_map
+            .query(bbox, ProjectFilter::ROAD | ProjectFilter::BUILDING)
+            .collect();
+        let problems: Vec<_> = objects.iter().map(|&k| self.terrain_problem(k)).collect();
+
+        let restore = self.heightmap.set_heights(edits);
+
+        // only refuse the edits creating new problems, so that bad terrain can be fixed
+        for (&k, before) in objects.iter().zip(problems) {
+            if let (None, Some(problem)) = (before, self.terrain_problem(k)) {
+                self.heightmap.set_heights(&restore);
+                let what = match k {
+                    ProjectKind::Road(_) => "road",
+                    _ => "building",
+                };
+                return Err(format!("A {} would end up {}", what, problem));
+            }
+        }
+
+        self.dirt_id += Wrapping(1);
+        self.remove_flooded(edits);
+        Ok(restore)
+    }
+
+    /// Removes the trees and lots under water after the edits
+    fn remove_flooded(&mut self, edits: &[HeightEdit]) {
+        let bbox = unwrap_ret!(edits_bounds(edits));
+
+        let heightmap = &self.heightmap;
+        self.trees
+            .remove_near_filter(bbox, |p| heightmap.height(p) < BUILDABLE_LEVEL);
+
+        let flooded: Vec<_> = self
+            .spatial_map
+            .query(bbox, ProjectFilter::LOT)
+            .filter_map(|k| match k {
+                ProjectKind::Lot(id) => self.lots.get(id),
+                _ => None,
+            })
+            .filter(|lot| heightmap.height(lot.shape.center()) < BUILDABLE_LEVEL)
+            .map(|lot| lot.id)
+            .collect();
+        for id in flooded {
+            self.lots.remove(id);
+            self.spatial_map.remove(id);
+        }
+    }
+
+    /// Whether the road or the building is under water or too steep for the current terrain
+    fn terrain_problem(&self, kind: ProjectKind) -> Option<&'static str> {
+        let points: Vec<Vec2> = match kind {
+            ProjectKind::Road(id) => {
+                let road = self.roads.get(id)?;
+                // bridges stand above the terrain
+                if road.bridge {
+                    return None;
+                }
+                road.points.as_slice().to_vec()
+            }
+            ProjectKind::Building(id) => {
+                let b = self.buildings.get(id)?;
+                b.obb
+                    .corners
+                    .iter()
+                    .chain(b.obb.corners.first())
+                    .copied()
+                    .collect()
+            }
+            _ => return None,
+        };
+        if self.heightmap.min_height(&points) < BUILDABLE_LEVEL {
+            return Some("under water");
+        }
+        if self.heightmap.max_slope(&points) > MAX_ROAD_SLOPE {
+            return Some("on a too steep slope");
+        }
+        None
+    }
+
+    /// Builds a road between the two points, unless it crosses water
     pub fn make_connection(
         &mut self,
         from: MapProject,
         to: MapProject,
         interpoint: Option<Vec2>,
         pattern: &LanePattern,
+    ) -> Option<(IntersectionID, RoadID)> {
+        self.make_connection_inner(from, to, interpoint, pattern, false)
+    }
+
+    /// Builds a road between the two points, which may cross water
+    pub fn make_bridge(
+        &mut self,
+        from: MapProject,
+        to: MapProject,
+        interpoint: Option<Vec2>,
+        pattern: &LanePattern,
+    ) -> Option<(IntersectionID, RoadID)> {
+        self.make_connection_inner(from, to, interpoint, pattern, true)
+    }
+
+    fn make_connection_inner(
+        &mut self,
+        from: MapProject,
+        to: MapProject,
+        interpoint: Option<Vec2>,
+        pattern: &LanePattern,
+        bridge: bool,
     ) -> Option<(IntersectionID, RoadID)> {
         if !from.kind.check_valid(self) || !to.kind.check_valid(self) {
             return None;
@@ -127,11 +262,21 @@ impl Map {
             None => RoadSegmentKind::Straight,
         };
 
+        if !bridge
+            && self.crosses_water(
+                &connection_segment.points(from.pos, to.pos),
+                pattern.width(),
+            )
+        {
+            return None;
+        }
+
         let mut mk_inter = |proj: MapProject| {
             Some(match proj.kind {
                 ProjectKind::Ground => self.add_intersection(proj.pos),
                 ProjectKind::Inter(id) => id,
                 ProjectKind::Road(id) => self.split_road(id, proj.pos)?,
+                ProjectKind::Water(_) => return None,
                 ProjectKind::Building(_) | ProjectKind::Lot(_) => unreachable!(),
             })
         };
@@ -147,6 +292,86 @@ impl Map {
         Some((to, r))
     }
 
+    /// Adds a river or a lake, the roads crossing it become bridges.
+    /// Returns None if it has too few points or if it would cover a building.
+    pub fn add_water(&mut self, kind: WaterKind, points: Vec<Vec2>) -> Option<WaterID> {
+        info!("add water {:?} with {} points", kind, points.len());
+        let enough = match kind {
+            WaterKind::River { .. } => points.len() >= 2,
+            WaterKind::Lake => points.len() >= 3,
+        };
+        if !enough {
+            return None;
+        }
+        let water = Water {
+            id: WaterID::default(),
+            kind,
+            points: PolyLine::new(points),
+        };
+        let shape = water.shape();
+        if self
+            .spatial_map
+            .query(&shape, ProjectFilter::BUILDING)
+            .next()
+            .is_some()
+        {
+            info!("the water would cover a building");
+            return None;
+        }
+        self.dirt_id += Wrapping(1);
+
+        let id = self.waters.insert_with_key(|id| Water { id, ..water });
+
+        let covered: Vec<_> = self
+            .spatial_map
+            .query(&shape, ProjectFilter::LOT | ProjectFilter::ROAD)
+            .collect();
+        for kind in covered {
+            match kind {
+                ProjectKind::Lot(lot) => {
+                    self.lots.remove(lot);
+                    self.spatial_map.remove(lot);
+                }
+                ProjectKind::Road(road) => {
+                    if let Some(r) = self.roads.get_mut(road) {
+                        r.bridge = true;
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        self.trees
+            .remove_near_filter(shape.bbox(), |p| shape.intersects(&p));
+        self.spatial_map.insert(id, shape);
+
+        Some(id)
+    }
+
+    pub fn remove_water(&mut self, id: WaterID) -> Option<Water> {
+        info!("remove water {:?}", id);
+        let water = self.waters.remove(id)?;
+        self.spatial_map.remove(id);
+        self.dirt_id += Wrapping(1);
+
+        let roads: Vec<_> = self
+            .spatial_map
+            .query(&water.shape(), ProjectFilter::ROAD)
+            .collect();
+        for kind in roads {
+            if let ProjectKind::Road(road) = kind {
+                let bridge = match self.roads.get(road) {
+                    Some(r) => self.crosses_water(r.points.as_slice(), r.width),
+                    None => continue,
+                };
+                if let Some(r) = self.roads.get_mut(road) {
+                    r.bridge = bridge;
+                }
+            }
+        }
+        Some(water)
+    }
+
     pub fn build_special_building(
         &mut self,
         road: RoadID,
@@ -178,17 +403,22 @@ impl Map {
 
         self.trees.remove_near_filter(obb.bbox(), |_| true);
 
-        let v = Some(Building::make(
+        let id = Building::make(
             &mut self.buildings,
             &mut self.spatial_map,
             self.roads.get(road)?,
             *obb,
             kind,
             gen,
-        ));
+        );
+
+        if let BuildingKind::ParkingLot = kind {
+            self.gen_lot_spots(id, road);
+        }
+
         #[cfg(debug_assertions)]
         self.check_invariants();
-        v
+        Some(id)
     }
 
     pub fn build_house(&mut self, id: LotID) -> Option<BuildingID> {
@@ -216,6 +446,99 @@ impl Map {
         v
     }
 
+    /// Rebuilds the house as a larger building with one more floor. Its footprint grows
+    /// away from the road over the lots and trees around it.
+    /// Returns its new level, or None if it is not a house or there is no room to grow.
+    pub fn densify_house(&mut self, id: BuildingID) -> Option<u32> {
+        let b = self.buildings.get(id)?;
+        if !matches!(b.kind, BuildingKind::House) {
+            return None;
+        }
+
+        let level = b.level + 1;
+        let growth = (1.0 + LEVEL_GROWTH * level as f32) / (1.0 + LEVEL_GROWTH * b.level as f32);
+        // the first two corners are the side facing the road
+        let [along, depth] = b.obb.axis();
+        let front = (b.obb.corners[0] + b.obb.corners[1]) * 0.5;
+        let obb = OBB::new(
+            front + depth * growth * 0.5,
+            depth.try_normalize()?,
+            depth.magnitude() * growth,
+            along.magnitude() * growth,
+        );
+
+        let blocking = ProjectFilter::BUILDING
+            | ProjectFilter::ROAD
+            | ProjectFilter::INTER
+            | ProjectFilter::WATER;
+        if self
+            .spatial_map
+            .query(obb, blocking)
+            .any(|k| k != ProjectKind::Building(id))
+        {
+            return None;
+        }
+        info!("densify house {:?}", id);
+
+        let road = self.door_road(b.door_pos)?;
+        let (mesh, door_pos) = Building::gen_mesh(road, obb, BuildingGen::House, level);
+
+        let to_clean: Vec<_> = self.spatial_map.query(obb, ProjectFilter::LOT).collect();
+        for kind in to_clean {
+            if let ProjectKind::Lot(lot) = kind {
+                self.spatial_map.remove(lot);
+                self.lots.remove(lot);
+            }
+        }
+        self.trees.remove_near_filter(obb.bbox(), |_| true);
+
+        let b = self.buildings.get_mut(id)?;
+        b.mesh = mesh;
+        b.door_pos = door_pos;
+        b.obb = obb;
+        b.level = level;
+        self.spatial_map.update(id, obb);
+        self.dirt_id += Wrapping(1);
+
+        #[cfg(debug_assertions)]
+        self.check_invariants();
+        Some(level)
+    }
+
+    /// Gives its floors back to a house that was rebuilt with the footprint it had at
+    /// this level, like when loading a map or undoing its removal
+    pub fn restore_house_level(&mut self, id: BuildingID, level: u32) -> Option<()> {
+        let b = self.buildings.get(id)?;
+        if !matches!(b.kind, BuildingKind::House) {
+            return None;
+        }
+        if b.level == level {
+            return Some(());
+        }
+
+        let road = self.door_road(b.door_pos)?;
+        let (mesh, door_pos) = Building::gen_mesh(road, b.obb, BuildingGen::House, level);
+
+        let b = self.buildings.get_mut(id)?;
+        b.mesh = mesh;
+        b.door_pos = door_pos;
+        b.level = level;
+        self.dirt_id += Wrapping(1);
+        Some(())
+    }
+
+    /// Road a building with its door at `door_pos` is connected to
+    pub fn door_road(&self, door_pos: Vec2) -> Option<&Road> {
+        let roads = &self.roads;
+        self.spatial_map
+            .query_around(door_pos, 50.0, ProjectFilter::ROAD)
+            .filter_map(|k| match k {
+                ProjectKind::Road(r) => roads.get(r),
+                _ => None,
+            })
+            .min_by_key(|r| OrderedFloat(r.points.project_dist2(door_pos)))
+    }
+
     pub fn remove_road(&mut self, road_id: RoadID) -> Option<Road> {
         info!("remove_road {:?}", road_id);
 
@@ -247,9 +570,81 @@ impl Map {
 
         self.invalidate(road.src);
         self.invalidate(road.dst);
+        self.relink_parking_lots();
         Some(road)
     }
 
+    fn gen_lot_spots(&mut self, id: BuildingID, road: RoadID) {
+        let b = unwrap_ret!(self.buildings.get(id));
+        let road = unwrap_ret!(self.roads.get(road));
+        let entrance = unwrap_retlog!(
+            Self::closest_driving_lane(&self.lanes, road, b.door_pos),
+            "parking lot {:?} is not next to a driving lane",
+            id
+        );
+
+        let axis = b.obb.axis()[0].normalize();
+        let size = b.obb.axis()[0].magnitude();
+        let center = b.obb.center();
+
+        self.parking.generate_lot_spots(
+            id,
+            entrance,
+            crate::procgen::parking_lot_spots(size)
+                .into_iter()
+                .map(|(pos, dir)| {
+                    Transform::new_cos_sin(pos.rotated_by(axis) + center, dir.rotated_by(axis))
+                }),
+        );
+    }
+
+    fn closest_driving_lane(lanes: &Lanes, road: &Road, p: Vec2) -> Option<LaneID> {
+        road.lanes_iter()
+            .filter(|(_, kind)| matches!(kind, LaneKind::Driving))
+            .flat_map(|(id, _)| lanes.get(id))
+            .min_by_key(|lane| OrderedFloat(lane.points.project_dist2(p)))
+            .map(|lane| lane.id)
+    }
+
+    /// Parking lots are connected to a lane which might have been removed when editing roads
+    fn relink_parking_lots(&mut self) {
+        let to_relink: Vec<BuildingID> = self
+            .parking
+            .lots()
+            .map(|(id, _)| id)
+            .filter(|&id| {
+                self.parking
+                    .lot_entrance(id)
+                    .map(|l| !self.lanes.contains_key(l))
+                    .unwrap_or(false)
+            })
+            .collect();
+
+        for id in to_relink {
+            let door_pos = unwrap_cont!(self.buildings.get(id)).door_pos;
+            let road = self
+                .spatial_map
+                .query_around(door_pos, 20.0, ProjectFilter::ROAD)
+                .filter_map(|kind| match kind {
+                    ProjectKind::Road(r) => self.roads.get(r),
+                    _ => None,
+                })
+                .min_by_key(|r| OrderedFloat(r.points.project_dist2(door_pos)));
+
+            let entrance = unwrap_or!(
+                road.and_then(|r| Self::closest_driving_lane(&self.lanes, r, door_pos)),
+                {
+                    log::warn!(
+                        "could not reconnect parking lot {:?} to the road network",
+                        id
+                    );
+                    continue;
+                }
+            );
+            self.parking.set_lot_entrance(id, entrance);
+        }
+    }
+
     pub fn set_lot_kind(&mut self, lot: LotID, kind: LotKind) {
         match self.lots.get_mut(lot) {
             Some(lot) => {
@@ -264,6 +659,11 @@ impl Map {
         info!("clear");
         let before = std::mem::take(self);
         self.trees = before.trees;
+        self.heightmap = before.heightmap;
+        for water in before.waters.values() {
+            self.spatial_map.insert(water.id, water.shape());
+        }
+        self.waters = before.waters;
         self.dirt_id = before.dirt_id + Wrapping(1);
 
         #[cfg(debug_assertions)]
@@ -465,6 +865,7 @@ impl Map {
 
         Lot::remove_intersecting_lots(self, id);
         Lot::generate_along_road(self, id);
+        self.relink_parking_lots();
 
         #[allow(clippy::indexing_slicing)]
         let r = &self.roads[id];
@@ -484,6 +885,7 @@ impl Map {
         let mk_proj = move |kind| MapProject { pos, kind };
 
         let mut qroad = None;
+        let mut qwater = None;
         for pkind in self
             .spatial_map
             .query_around(pos, tolerance, ProjectFilter::ALL)
@@ -514,6 +916,7 @@ impl Map {
                 ProjectKind::Building(id) => {
                     return mk_proj(ProjectKind::Building(id));
                 }
+                ProjectKind::Water(id) => qwater = Some(id),
                 ProjectKind::Ground => {}
             }
         }
@@ -525,6 +928,10 @@ impl Map {
             };
         }
 
+        if let Some(id) = qwater {
+            return mk_proj(ProjectKind::Water(id));
+        }
+
         mk_proj(ProjectKind::Ground)
     }
 
@@ -547,17 +954,102 @@ impl Map {
     pub fn lots(&self) -> &Lots {
         &self.lots
     }
+    pub fn waters(&self) -> &Waters {
+        &self.waters
+    }
     pub fn spatial_map(&self) -> &SpatialMap {
         &self.spatial_map
     }
 
+    /// Whether a road of this width along the points would cross water
+    pub fn crosses_water(&self, points: &[Vec2], width: f32) -> bool {
+        let line = BoldLine::new(PolyLine::new(points.to_vec()), width * 0.5);
+        self.spatial_map
+            .query(line, ProjectFilter::WATER)
+            .next()
+            .is_some()
+    }
+
+    /// Whether the building would overlap another building or water
     pub fn building_overlaps(&self, obb: OBB) -> bool {
         self.spatial_map
-            .query(obb, ProjectFilter::BUILDING)
+            .query(obb, ProjectFilter::BUILDING | ProjectFilter::WATER)
             .next()
             .is_some()
     }
 
+    /// Finds room for a square building of the given size, facing the road of one of the lots
+    /// of this kind. The building can only cover lots of the same kind.
+    pub fn find_zoned_spot(&self, kind: LotKind, size: f32) -> Option<(RoadID, OBB)> {
+        self.lots
+            .values()
+            .filter(|lot| lot.kind == kind)
+            .find_map(|lot| {
+                let [_, along] = lot.shape.axis();
+                let dir = along.try_normalize()?;
+                let front = lot.shape.center() - along * 0.5;
+                let obb = OBB::new(front + dir * size * 0.5, dir, size, size);
+
+                let blocking = ProjectFilter::INTER | ProjectFilter::ROAD | ProjectFilter::BUILDING;
+                if self.spatial_map.query(obb, blocking).next().is_some() {
+                    return None;
+                }
+
+                let mut covered = 0.0;
+                for p in self.spatial_map.query(obb, ProjectFilter::LOT) {
+                    let other = match p {
+                        ProjectKind::Lot(id) => self.lots.get(id)?,
+                        _ => continue,
+                    };
+                    if other.kind != kind {
+                        return None;
+                    }
+                    let [a, b] = other.shape.axis();
+                    covered += a.magnitude() * b.magnitude();
+                }
+
+                // Gaps between lots are fine, but not a building on empty ground
+                if covered < size * size * 0.5 {
+                    return None;
+                }
+                Some((lot.parent, obb))
+            })
+    }
+
+    /// Intersection at `pos`, up to floating point errors
+    pub fn intersection_at(&self, pos: Vec2) -> Option<IntersectionID> {
+        self.spatial_map
+            .query_around(pos, 0.1, ProjectFilter::INTER)
+            .filter_map(|k| match k {
+                ProjectKind::Inter(id) => self.intersections.get(id),
+                _ => None,
+            })
+            .find(|inter| inter.pos.is_close(pos, 0.1))
+            .map(|inter| inter.id)
+    }
+
+    /// Building covering `pos`
+    pub fn building_at(&self, pos: Vec2) -> Option<BuildingID> {
+        self.spatial_map
+            .query_around(pos, 0.1, ProjectFilter::BUILDING)
+            .find_map(|k| match k {
+                ProjectKind::Building(id) => Some(id),
+                _ => None,
+            })
+    }
+
+    /// Lot covering `pos`
+    pub fn lot_at(&self, pos: Vec2) -> Option<LotID> {
+        self.spatial_map
+            .query_around(pos, 0.1, ProjectFilter::LOT)
+            .filter_map(|k| match k {
+                ProjectKind::Lot(id) => self.lots.get(id),
+                _ => None,
+            })
+            .find(|lot| lot.shape.contains(pos))
+            .map(|lot| lot.id)
+    }
+
     pub fn find_road(&self, src: IntersectionID, dst: IntersectionID) -> Option<RoadID> {
         for &r in &self.intersections.get(src)?.roads {
             let road = unwrap_cont!(self.roads.get(r));
@@ -623,6 +1115,14 @@ impl Map {
                 assert!(self.lanes.contains_key(turn.id.src));
                 assert!(self.lanes.contains_key(turn.id.dst));
                 assert!(turn.points.n_points() >= 2);
+
+                for conflict in inter.conflicts(turn.id) {
+                    assert!(inter.find_turn(conflict.other).is_some());
+                    assert_eq!(
+                        inter.conflict_between(conflict.other, turn.id),
+                        conflict.kind
+                    );
+                }
             }
 
             assert!(inter.pos.is_finite());
@@ -646,6 +1146,10 @@ impl Map {
             }
         }
 
+        for water in self.waters.values() {
+            assert!(self.spatial_map.contains(water.id));
+        }
+
         for road in self.roads.values() {
             log::debug!("{:?}", road.id);
             let src = self.intersections.get(road.src).unwrap();
@@ -707,6 +1211,60 @@ impl Map {
             assert!(obj.check_valid(self));
         }
 
+        for (id, _) in self.parking.lots() {
+            assert!(self.buildings.contains_key(id), "{:?}", id);
+        }
+
         assert!(self.parking.reuse_spot.is_empty());
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::procgen::LEVEL_GROWTH;
+    use crate::{LanePatternBuilder, Map, ProjectFilter, ProjectKind};
+    use geom::{vec2, Circle};
+
+    #[test]
+    fn densify_house_grows_footprint() {
+        let mut m = Map::empty();
+        let (from, to) = (
+            m.project(vec2(0.0, 0.0), 1.0),
+            m.project(vec2(300.0, 0.0), 1.0),
+        );
+        m.make_connection(from, to, None, &LanePatternBuilder::new().build());
+
+        let lot = m
+            .lots()
+            .values()
+            .min_by_key(|lot| lot.shape.center().distance2(vec2(150.0, 30.0)) as i32)
+            .unwrap()
+            .id;
+        let id = m.build_house(lot).unwrap();
+        let old = m.buildings()[id].obb;
+
+        assert_eq!(m.densify_house(id), Some(1));
+        let obb = m.buildings()[id].obb;
+        let [a, b] = old.axis();
+        let [na, nb] = obb.axis();
+        assert!((na.magnitude() / a.magnitude() - (1.0 + LEVEL_GROWTH)).abs() < 1e-3);
+        assert!((nb.magnitude() / b.magnitude() - (1.0 + LEVEL_GROWTH)).abs() < 1e-3);
+        // the house grows away from the road
+        let front = (old.corners[0] + old.corners[1]) * 0.5;
+        assert!(front.distance((obb.corners[0] + obb.corners[1]) * 0.5) < 1e-3);
+
+        let back = Circle::new(obb.corners[2] * 0.99 + obb.corners[0] * 0.01, 0.1);
+        assert!(!old.contains(back.center));
+        let found: Vec<_> = m
+            .spatial_map()
+            .query(back, ProjectFilter::BUILDING | ProjectFilter::LOT)
+            .collect();
+        assert_eq!(found, vec![ProjectKind::Building(id)]);
+
+        let mut m2 = Map::empty();
+        m2.load_description(&m.describe()).unwrap();
+        let b2 = m2.buildings().values().next().unwrap();
+        assert_eq!(b2.level, 1);
+        assert!(b2.obb.center().distance(obb.center()) < 1e-3);
+    }
+}
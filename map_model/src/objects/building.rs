@@ -16,17 +16,50 @@ debug_inspect_impl!(BuildingID);
 pub enum BuildingKind {
     House,
     Company(u32),
+    ParkingLot,
+    Park,
+    Service(ServiceKind),
 }
 
+/// Public services provided by the city
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum ServiceKind {
+    School,
+    Hospital,
+    Police,
+    FireStation,
+}
+
+impl ServiceKind {
+    pub fn values() -> &'static [Self] {
+        &[
+            ServiceKind::School,
+            ServiceKind::Hospital,
+            ServiceKind::Police,
+            ServiceKind::FireStation,
+        ]
+    }
+}
+
+debug_inspect_impl!(ServiceKind);
+
 #[derive(Debug, Copy, Clone, Serialize, Deserialize)]
 pub enum BuildingGen {
     House,
     Farm,
+    ParkingLot,
+    Park,
     CenteredDoor {
         vertical_factor: f32, // 1.0 means that the door is at the bottom, just on the street
     },
 }
 
+impl Default for BuildingGen {
+    fn default() -> Self {
+        BuildingGen::House
+    }
+}
+
 #[derive(Clone, Serialize, Deserialize)]
 pub struct Building {
     pub id: BuildingID,
@@ -34,6 +67,12 @@ pub struct Building {
     pub kind: BuildingKind,
     pub mesh: ColoredMesh,
     pub obb: OBB,
+    /// Number of times the building was densified, see `Map::densify_house`
+    #[serde(default)]
+    pub level: u32,
+    /// How the mesh was generated, older saves only had houses written with it
+    #[serde(default)]
+    pub gen: BuildingGen,
 }
 
 impl Building {
@@ -45,6 +84,24 @@ impl Building {
         kind: BuildingKind,
         gen: BuildingGen,
     ) -> BuildingID {
+        let (mesh, door_pos) = Self::gen_mesh(road, obb, gen, 0);
+
+        buildings.insert_with_key(move |id| {
+            spatial_map.insert(id, obb);
+            Self {
+                id,
+                mesh,
+                kind,
+                door_pos,
+                obb,
+                level: 0,
+                gen,
+            }
+        })
+    }
+
+    /// Generates the mesh of the building in world space along with its door position
+    pub fn gen_mesh(road: &Road, obb: OBB, gen: BuildingGen, level: u32) -> (ColoredMesh, Vec2) {
         let at = obb.center();
         let axis = (obb.corners[1] - obb.corners[0]).normalize();
         let size = obb.corners[0].distance(obb.corners[1]);
@@ -52,8 +109,10 @@ impl Building {
         let r = common::rand::rand2(obb.center().x, obb.center().y).to_bits();
 
         let (mut mesh, mut door_pos) = match gen {
-            BuildingGen::House => crate::procgen::gen_exterior_house(size, r as u64),
+            BuildingGen::House => crate::procgen::gen_exterior_house_level(size, r as u64, level),
             BuildingGen::Farm => crate::procgen::gen_exterior_farm(size, r as u64),
+            BuildingGen::ParkingLot => crate::procgen::gen_parking_lot(size),
+            BuildingGen::Park => crate::procgen::gen_park(size, r as u64),
             BuildingGen::CenteredDoor { vertical_factor } => {
                 (Default::default(), Vec2::y(-vertical_factor * 0.5 * size))
             }
@@ -79,15 +138,6 @@ impl Building {
 
         mesh.faces.push((walkway, Color::gray(0.4).into()));
 
-        buildings.insert_with_key(move |id| {
-            spatial_map.insert(id, obb);
-            Self {
-                id,
-                mesh,
-                kind,
-                door_pos,
-                obb,
-            }
-        })
+        (mesh, door_pos)
     }
 }
@@ -1,6 +1,6 @@
 use crate::{
-    Intersections, LaneID, LaneKind, Lanes, LightPolicy, Road, RoadID, Roads, SpatialMap,
-    TraverseDirection, Turn, TurnID, TurnPolicy,
+    ConflictKind, Intersections, LaneID, LaneKind, Lanes, LightPolicy, Road, RoadID, Roads,
+    SpatialMap, TraverseDirection, Turn, TurnConflict, TurnConflicts, TurnID, TurnPolicy,
 };
 use geom::Polygon;
 use geom::Spline;
@@ -27,6 +27,10 @@ pub struct Intersection {
 
     turns: Vec<Turn>,
 
+    // derived from turns, recomputed on load
+    #[serde(skip)]
+    conflicts: TurnConflicts,
+
     // sorted by angle
     pub roads: Vec<RoadID>,
 
@@ -42,6 +46,7 @@ impl Intersection {
             id,
             pos,
             turns: Default::default(),
+            conflicts: Default::default(),
             roads: Default::default(),
             turn_policy: Default::default(),
             light_policy: Default::default(),
@@ -91,6 +96,12 @@ impl Intersection {
         for turn in self.turns.iter_mut() {
             turn.make_points(lanes);
         }
+
+        self.update_conflicts();
+    }
+
+    pub fn update_conflicts(&mut self) {
+        self.conflicts = TurnConflicts::compute(&self.turns);
     }
 
     pub fn update_traffic_control(&self, lanes: &mut Lanes, roads: &Roads) {
@@ -265,4 +276,20 @@ impl Intersection {
     pub fn turns(&self) -> &Vec<Turn> {
         &self.turns
     }
+
+    /// All the turns conflicting with the given turn, with the kind of conflict and where it happens
+    pub fn conflicts(&self, turn: TurnID) -> &[TurnConflict] {
+        match self.turns.iter().position(|x| x.id == turn) {
+            Some(idx) => self.conflicts.get(idx),
+            None => &[],
+        }
+    }
+
+    pub fn conflict_between(&self, a: TurnID, b: TurnID) -> ConflictKind {
+        self.conflicts(a)
+            .iter()
+            .find(|x| x.other == b)
+            .map(|x| x.kind)
+            .unwrap_or(ConflictKind::NonConflicting)
+    }
 }
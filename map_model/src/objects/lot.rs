@@ -1,5 +1,4 @@
-use crate::procgen::heightmap::height;
-use crate::{Map, ProjectFilter, ProjectKind, RoadID};
+use crate::{Map, ProjectFilter, ProjectKind, RoadID, BUILDABLE_LEVEL};
 use geom::Polygon;
 use geom::Vec2;
 use geom::OBB;
@@ -11,10 +10,12 @@ new_key_type! {
     pub struct LotID;
 }
 
-#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum LotKind {
     Unassigned,
     Residential,
+    Commercial,
+    Industrial,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -35,7 +36,16 @@ impl Lot {
     ) -> Option<LotID> {
         let shape = OBB::new(at + axis * size * 0.5, axis, size, size);
 
-        if height(at).0 < 0.12 {
+        if map.heightmap.height(at) < BUILDABLE_LEVEL {
+            return None;
+        }
+
+        if map
+            .spatial_map
+            .query(shape, ProjectFilter::WATER)
+            .next()
+            .is_some()
+        {
             return None;
         }
 
@@ -1,4 +1,4 @@
-use crate::{Lane, LaneID, LaneKind, CROSSWALK_WIDTH};
+use crate::{BuildingID, Lane, LaneID, LaneKind, CROSSWALK_WIDTH};
 use flat_spatial::ShapeGrid;
 use geom::{Transform, Vec2};
 use ordered_float::OrderedFloat;
@@ -14,6 +14,7 @@ pub const PARKING_SPOT_LENGTH: f32 = 6.0;
 
 #[derive(Debug, Copy, Clone, Serialize, Deserialize)]
 pub struct ParkingSpot {
+    /// For spots in a parking lot, this is the driving lane the lot entrance is connected to
     pub parent: LaneID,
     pub trans: Transform,
 }
@@ -22,6 +23,10 @@ pub struct ParkingSpot {
 pub struct ParkingSpots {
     spots: SlotMap<ParkingSpotID, ParkingSpot>,
     lane_spots: SecondaryMap<LaneID, Vec<ParkingSpotID>>,
+    lot_spots: SecondaryMap<BuildingID, Vec<ParkingSpotID>>,
+    /// Spots of the removed parking lots, kept until the cars parked there are moved
+    #[serde(default)]
+    removed_lot_spots: Vec<ParkingSpotID>,
     pub(crate) reuse_spot: ShapeGrid<ParkingSpotID, Vec2>,
 }
 
@@ -30,6 +35,8 @@ impl Default for ParkingSpots {
         Self {
             spots: Default::default(),
             lane_spots: Default::default(),
+            lot_spots: Default::default(),
+            removed_lot_spots: Default::default(),
             reuse_spot: ShapeGrid::new(10),
         }
     }
@@ -44,6 +51,15 @@ impl ParkingSpots {
         self.spots.contains_key(spot)
     }
 
+    /// Spots along the lanes and in the parking lots, without the ones waiting to be reused
+    pub fn iter(&self) -> impl Iterator<Item = (ParkingSpotID, &ParkingSpot)> + '_ {
+        self.lane_spots
+            .values()
+            .chain(self.lot_spots.values())
+            .flatten()
+            .filter_map(move |&id| Some((id, self.spots.get(id)?)))
+    }
+
     pub fn remove_spots(&mut self, lane: LaneID) {
         if let Some(spots) = self.lane_spots.remove(lane) {
             for spot in spots {
@@ -119,9 +135,86 @@ impl ParkingSpots {
         self.lane_spots.insert(lane.id, spots);
     }
 
+    /// Spots are given in world space, the entrance lane is the parent of all the spots
+    pub fn generate_lot_spots(
+        &mut self,
+        building: BuildingID,
+        entrance: LaneID,
+        spots: impl Iterator<Item = Transform>,
+    ) {
+        self.remove_lot_spots(building);
+
+        let ids = spots
+            .map(|trans| {
+                self.spots.insert(ParkingSpot {
+                    parent: entrance,
+                    trans,
+                })
+            })
+            .collect();
+
+        self.lot_spots.insert(building, ids);
+    }
+
+    /// The spots cannot be reserved anymore but still exist until [`Self::clean_removed_lot_spots`]
+    /// is called, so that the cars parked there can be moved elsewhere
+    pub fn remove_lot_spots(&mut self, building: BuildingID) {
+        if let Some(spots) = self.lot_spots.remove(building) {
+            self.removed_lot_spots.extend(spots);
+        }
+    }
+
+    pub fn removed_lot_spots(&self) -> &[ParkingSpotID] {
+        &self.removed_lot_spots
+    }
+
+    pub fn clean_removed_lot_spots(&mut self) {
+        for spot in self.removed_lot_spots.drain(..) {
+            self.spots.remove(spot);
+        }
+    }
+
+    /// Reconnects the lot to the road network after its entrance lane got removed
+    pub fn set_lot_entrance(&mut self, building: BuildingID, entrance: LaneID) {
+        let ids = unwrap_ret!(self.lot_spots.get(building));
+        for &id in ids {
+            if let Some(spot) = self.spots.get_mut(id) {
+                spot.parent = entrance;
+            }
+        }
+    }
+
+    pub fn lot_entrance(&self, building: BuildingID) -> Option<LaneID> {
+        let first = *self.lot_spots.get(building)?.first()?;
+        self.spots.get(first).map(|x| x.parent)
+    }
+
+    pub fn lot_spots(&self, building: BuildingID) -> Option<&[ParkingSpotID]> {
+        self.lot_spots.get(building).map(Vec::as_slice)
+    }
+
+    pub fn lots(&self) -> impl Iterator<Item = (BuildingID, &[ParkingSpotID])> + '_ {
+        self.lot_spots.iter().map(|(id, v)| (id, v.as_slice()))
+    }
+
+    pub(crate) fn retain_spots(&mut self, f: impl Fn(&ParkingSpot) -> bool) {
+        let spots = &mut self.spots;
+        spots.retain(|_, spot| f(spot));
+        for ids in self
+            .lane_spots
+            .values_mut()
+            .chain(self.lot_spots.values_mut())
+        {
+            ids.retain(|&id| spots.contains_key(id));
+        }
+        self.removed_lot_spots.retain(|&id| spots.contains_key(id));
+    }
+
     pub fn clear(&mut self) {
         self.spots.clear();
         self.lane_spots.clear();
+        self.lot_spots.clear();
+        self.removed_lot_spots.clear();
         for _ in self.reuse_spot.clear() {}
     }
 
@@ -161,3 +254,60 @@ impl ParkingSpots {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{BuildingGen, BuildingKind, LaneKind, LanePatternBuilder, Map, MapProject};
+    use geom::{vec2, OBB};
+
+    #[test]
+    fn lot_spots() {
+        let mut m = Map::empty();
+        let pat = LanePatternBuilder::new().parking(false).build();
+        let (_, road) = m
+            .make_connection(
+                MapProject::ground(vec2(0.0, 0.0)),
+                MapProject::ground(vec2(200.0, 0.0)),
+                None,
+                &pat,
+            )
+            .unwrap();
+
+        let obb = OBB::new(vec2(100.0, 40.0), vec2(1.0, 0.0), 50.0, 50.0);
+        let b = m
+            .build_special_building(
+                road,
+                &obb,
+                BuildingKind::ParkingLot,
+                BuildingGen::ParkingLot,
+            )
+            .unwrap();
+        let spots = m.parking.lot_spots(b).unwrap().to_vec();
+        assert!(!spots.is_empty());
+        let entrance = m.parking.lot_entrance(b).unwrap();
+        assert_eq!(m.lanes()[entrance].kind, LaneKind::Driving);
+
+        // the lot is reconnected to the other direction of the road
+        let other = m.roads()[road]
+            .lanes_iter()
+            .find(|&(id, kind)| kind == LaneKind::Driving && id != entrance)
+            .unwrap()
+            .0;
+        m.parking.set_lot_entrance(b, other);
+        assert_eq!(m.parking.lot_entrance(b), Some(other));
+        assert!(spots
+            .iter()
+            .all(|&s| m.parking.get(s).unwrap().parent == other));
+
+        // the spots are kept until the cars parked there are moved
+        m.remove_building(b);
+        assert!(m.parking.lot_spots(b).is_none());
+        assert_eq!(m.parking.removed_lot_spots(), &*spots);
+        assert!(spots.iter().all(|&s| m.parking.contains(s)));
+        assert!(m.parking.iter().all(|(s, _)| !spots.contains(&s)));
+
+        m.parking.clean_removed_lot_spots();
+        assert!(m.parking.removed_lot_spots().is_empty());
+        assert!(spots.iter().all(|&s| !m.parking.contains(s)));
+    }
+}
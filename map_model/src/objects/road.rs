@@ -1,6 +1,6 @@
 use crate::{
     Intersection, IntersectionID, Lane, LaneDirection, LaneID, LaneKind, LanePattern, Lanes,
-    ParkingSpots, Roads, SpatialMap,
+    ParkingSpots, ProjectFilter, Roads, SpatialMap,
 };
 use geom::BoldLine;
 use geom::PolyLine;
@@ -19,6 +19,12 @@ pub enum RoadSegmentKind {
     Curved((Vec2, Vec2)), // The two derivatives for the spline
 }
 
+impl Default for RoadSegmentKind {
+    fn default() -> Self {
+        RoadSegmentKind::Straight
+    }
+}
+
 impl RoadSegmentKind {
     pub fn from_elbow(from: Vec2, to: Vec2, elbow: Vec2) -> RoadSegmentKind {
         RoadSegmentKind::Curved((
@@ -26,6 +32,25 @@ impl RoadSegmentKind {
             (to - elbow) * std::f32::consts::FRAC_1_SQRT_2,
         ))
     }
+
+    /// Points of a road of this kind going from `from` to `to`
+    pub fn points(self, from: Vec2, to: Vec2) -> Vec<Vec2> {
+        match self {
+            RoadSegmentKind::Straight => {
+                vec![from, to]
+            }
+            RoadSegmentKind::Curved((from_derivative, to_derivative)) => {
+                let s = Spline {
+                    from,
+                    to,
+                    from_derivative,
+                    to_derivative,
+                };
+
+                s.smart_points(1.0, 0.0, 1.0).collect()
+            }
+        }
+    }
 }
 
 #[derive(Clone, Serialize, Deserialize)]
@@ -39,6 +64,9 @@ pub struct Road {
     // always from src to dst
     pub points: PolyLine,
     pub width: f32,
+    /// Whether the road crosses water
+    #[serde(default)]
+    pub bridge: bool,
 
     src_interface: f32,
     dst_interface: f32,
@@ -65,6 +93,13 @@ impl Road {
         spatial: &mut SpatialMap,
     ) -> RoadID {
         let points = Self::generate_points(src, dst, segment);
+        let bridge = spatial
+            .query(
+                BoldLine::new(points.clone(), lane_pattern.width() * 0.5),
+                ProjectFilter::WATER,
+            )
+            .next()
+            .is_some();
 
         let id = roads.insert_with_key(|id| Self {
             id,
@@ -74,6 +109,7 @@ impl Road {
             dst_interface: 9.0,
             segment,
             width: lane_pattern.width(),
+            bridge,
             lanes_forward: vec![],
             lanes_backward: vec![],
             points,
@@ -108,6 +144,11 @@ impl Road {
     }
 
     /// Returns lanes in left to right order from the source
+    pub(crate) fn retain_lanes(&mut self, f: impl Fn(LaneID) -> bool) {
+        self.lanes_forward.retain(|&(id, _)| f(id));
+        self.lanes_backward.retain(|&(id, _)| f(id));
+    }
+
     pub fn lanes_iter(&self) -> impl DoubleEndedIterator<Item = (LaneID, LaneKind)> + '_ {
         self.lanes_forward
             .iter()
@@ -225,24 +266,7 @@ impl Road {
         dst: &Intersection,
         segment: RoadSegmentKind,
     ) -> PolyLine {
-        let from = src.pos;
-        let to = dst.pos;
-
-        PolyLine::new(match segment {
-            RoadSegmentKind::Straight => {
-                vec![from, to]
-            }
-            RoadSegmentKind::Curved((from_derivative, to_derivative)) => {
-                let s = Spline {
-                    from: src.pos,
-                    to: dst.pos,
-                    from_derivative,
-                    to_derivative,
-                };
-
-                s.smart_points(1.0, 0.0, 1.0).collect()
-            }
-        })
+        PolyLine::new(segment.points(src.pos, dst.pos))
     }
 
     pub fn interface_point(&self, id: IntersectionID) -> Vec2 {
@@ -0,0 +1,136 @@
+use crate::{Turn, TurnID, TurnKind};
+use geom::Vec2;
+use serde::{Deserialize, Serialize};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize, Hash)]
+pub enum ConflictKind {
+    /// The two turns cross each other somewhere inside the intersection
+    Crossing,
+    /// The two turns come from different lanes and end on the same lane
+    Merging,
+    /// The two turns start from the same lane and end on different lanes
+    Diverging,
+    NonConflicting,
+}
+
+impl ConflictKind {
+    pub fn is_conflicting(self) -> bool {
+        !matches!(self, ConflictKind::NonConflicting)
+    }
+}
+
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct TurnConflict {
+    pub other: TurnID,
+    pub kind: ConflictKind,
+    /// Where the two turns meet: the crossing point, the merge point or the divergence point
+    pub pos: Vec2,
+}
+
+/// Conflicts between the turns of an intersection, indexed the same way as the turns.
+/// Pairs that do not conflict are not stored.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct TurnConflicts {
+    conflicts: Vec<Vec<TurnConflict>>,
+}
+
+impl TurnConflicts {
+    #[allow(clippy::indexing_slicing)] // i and j < turns.len()
+    pub fn compute(turns: &[Turn]) -> Self {
+        let mut conflicts = vec![vec![]; turns.len()];
+
+        for (i, a) in turns.iter().enumerate() {
+            for (j, b) in turns.iter().enumerate().skip(i + 1) {
+                let (kind, pos) = unwrap_cont!(classify(a, b));
+
+                conflicts[i].push(TurnConflict {
+                    other: b.id,
+                    kind,
+                    pos,
+                });
+                conflicts[j].push(TurnConflict {
+                    other: a.id,
+                    kind,
+                    pos,
+                });
+            }
+        }
+
+        Self { conflicts }
+    }
+
+    pub fn get(&self, turn_idx: usize) -> &[TurnConflict] {
+        self.conflicts
+            .get(turn_idx)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.conflicts.is_empty()
+    }
+}
+
+/// Returns None if the turns do not conflict
+fn classify(a: &Turn, b: &Turn) -> Option<(ConflictKind, Vec2)> {
+    let a_walk = !matches!(a.kind, TurnKind::Driving);
+    let b_walk = !matches!(b.kind, TurnKind::Driving);
+
+    // Pedestrians do not get in each other's way
+    if a_walk && b_walk {
+        return None;
+    }
+
+    if a.points.is_empty() || b.points.is_empty() {
+        return None;
+    }
+
+    if !a_walk && !b_walk {
+        if a.id.src == b.id.src {
+            return Some((ConflictKind::Diverging, a.points.first()));
+        }
+        if a.id.dst == b.id.dst {
+            return Some((ConflictKind::Merging, a.points.last()));
+        }
+    }
+
+    for sa in a.points.segments() {
+        for sb in b.points.segments() {
+            if let Some(p) = sa.intersection_point(&sb) {
+                return Some((ConflictKind::Crossing, p));
+            }
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::procgen::load_testfield;
+    use crate::{ConflictKind, Map, TurnKind};
+    use geom::Vec2;
+
+    #[test]
+    fn four_way_has_all_conflict_kinds() {
+        let mut m = Map::empty();
+        load_testfield(&mut m, Vec2::ZERO, 3, 100.0);
+
+        let inter = m
+            .intersections()
+            .values()
+            .find(|i| i.roads.len() == 4)
+            .unwrap();
+
+        let kinds: Vec<ConflictKind> = inter
+            .turns()
+            .iter()
+            .filter(|t| matches!(t.kind, TurnKind::Driving))
+            .flat_map(|t| inter.conflicts(t.id).iter().map(|c| c.kind))
+            .collect();
+
+        assert!(kinds.contains(&ConflictKind::Crossing));
+        assert!(kinds.contains(&ConflictKind::Merging));
+        assert!(kinds.contains(&ConflictKind::Diverging));
+    }
+}
@@ -0,0 +1,100 @@
+use geom::{BoldLine, PolyLine, Polygon, ShapeEnum};
+use serde::{Deserialize, Serialize};
+use slotmap::new_key_type;
+
+new_key_type! {
+    pub struct WaterID;
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum WaterKind {
+    /// Flows along its points, from the source to the mouth
+    River { width: f32 },
+    /// Its points are the outline of the lake
+    Lake,
+}
+
+/// Nothing can be built on water, except roads built as bridges
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Water {
+    pub id: WaterID,
+    pub kind: WaterKind,
+    pub points: PolyLine,
+}
+
+impl Water {
+    pub fn shape(&self) -> ShapeEnum {
+        match self.kind {
+            WaterKind::River { width } => {
+                ShapeEnum::BoldLine(BoldLine::new(self.points.clone(), width * 0.5))
+            }
+            WaterKind::Lake => ShapeEnum::Polygon(Polygon(self.points.as_slice().to_vec())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{BuildingGen, BuildingKind, LanePatternBuilder, Map, MapProject, WaterKind};
+    use geom::{vec2, Vec2, OBB};
+
+    #[test]
+    fn roads_over_water() {
+        let mut m = Map::empty();
+        let lake = vec![
+            vec2(-50.0, -50.0),
+            vec2(50.0, -50.0),
+            vec2(50.0, 50.0),
+            vec2(-50.0, 50.0),
+        ];
+        m.add_water(WaterKind::Lake, lake).unwrap();
+
+        let pat = LanePatternBuilder::new().build();
+        let (from, to) = (
+            MapProject::ground(vec2(-150.0, 0.0)),
+            MapProject::ground(vec2(150.0, 0.0)),
+        );
+
+        assert!(m.make_connection(from, to, None, &pat).is_none());
+        let (_, id) = m.make_bridge(from, to, None, &pat).unwrap();
+        assert!(m.roads()[id].bridge);
+
+        assert!(!m.lots().is_empty());
+        for lot in m.lots().values() {
+            assert!(!lot.shape.center().is_close(Vec2::ZERO, 50.0));
+        }
+        m.check_invariants();
+    }
+
+    #[test]
+    fn water_does_not_cover_buildings() {
+        let mut m = Map::empty();
+        let pat = LanePatternBuilder::new().build();
+        let (_, road) = m
+            .make_connection(
+                MapProject::ground(vec2(0.0, 0.0)),
+                MapProject::ground(vec2(200.0, 0.0)),
+                None,
+                &pat,
+            )
+            .unwrap();
+        let obb = OBB::new(vec2(100.0, 30.0), vec2(1.0, 0.0), 20.0, 20.0);
+        m.build_special_building(road, &obb, BuildingKind::Park, BuildingGen::Park)
+            .unwrap();
+
+        let lake = |y| {
+            vec![
+                vec2(80.0, y),
+                vec2(120.0, y),
+                vec2(120.0, y + 40.0),
+                vec2(80.0, y + 40.0),
+            ]
+        };
+        assert!(m.add_water(WaterKind::Lake, lake(20.0)).is_none());
+        assert!(m.waters().is_empty());
+        assert_eq!(m.buildings().len(), 1);
+
+        assert!(m.add_water(WaterKind::Lake, lake(100.0)).is_some());
+        m.check_invariants();
+    }
+}
@@ -10,7 +10,7 @@ pub trait Pathfinder {
     fn local_route(&self, map: &Map, lane: LaneID, start: Vec2, end: Vec2) -> Option<PolyLine>;
 }
 
-#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum PathKind {
     Pedestrian,
     Vehicle,
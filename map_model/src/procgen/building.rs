@@ -30,6 +30,18 @@ impl ColoredMesh {
 }
 
 pub fn gen_exterior_house(size: f32, seed: u64) -> (ColoredMesh, Vec2) {
+    gen_exterior_house_level(size, seed, 0)
+}
+
+/// Height of a floor of a densified house, in meters
+pub const FLOOR_HEIGHT: f32 = 3.5;
+/// Growth of the sides of the footprint of a house for each level, relative to a level 0 house
+pub const LEVEL_GROWTH: f32 = 0.12;
+
+/// Same as `gen_exterior_house` for a house that was densified `level` times:
+/// the roof stands on `level` floors of walls. The footprint grows with the lot of the
+/// house, see `Map::densify_house`.
+pub fn gen_exterior_house_level(size: f32, seed: u64, level: u32) -> (ColoredMesh, Vec2) {
     let mut retry_cnt = 0;
     'retry: loop {
         let mut rng = SmallRng::seed_from_u64((retry_cnt << 32) + seed);
@@ -66,8 +78,9 @@ pub fn gen_exterior_house(size: f32, seed: u64) -> (ColoredMesh, Vec2) {
             p.simplify();
         }
 
+        let scale = size / 40.0;
         for x in p.iter_mut() {
-            *x *= size / 40.0;
+            *x *= scale;
         }
 
         let c = p.bbox().center();
@@ -141,6 +154,21 @@ pub fn gen_exterior_house(size: f32, seed: u64) -> (ColoredMesh, Vec2) {
             roofs.faces.push((face, roof_col));
         }
 
+        if level > 0 {
+            let h = FLOOR_HEIGHT * level as f32;
+            for (face, _) in &mut roofs.faces {
+                for v in face {
+                    v.z += h;
+                }
+            }
+
+            let wall_col = LinearColor::from(common::config().wall_col);
+            for s in p.segments() {
+                let wall = vec![s.src.z(0.0), s.dst.z(0.0), s.dst.z(h), s.src.z(h)];
+                roofs.faces.push((wall, wall_col));
+            }
+        }
+
         return (roofs, lowest_segment.middle());
     }
 }
@@ -163,6 +191,120 @@ pub fn gen_exterior_farm(size: f32, seed: u64) -> (ColoredMesh, Vec2) {
     (mesh, door_pos)
 }
 
+/// Local frame: door is at the bottom (towards the road), spots are laid out in rows of
+/// two separated by aisles.
+pub fn parking_lot_spots(size: f32) -> Vec<(Vec2, Vec2)> {
+    const SPOT_W: f32 = 3.0;
+    const SPOT_L: f32 = 6.0;
+    const AISLE: f32 = 6.0;
+    const MARGIN: f32 = 1.0;
+
+    let half = size * 0.5;
+    let mut spots = vec![];
+
+    let mut base = -half + AISLE;
+    while base + SPOT_L <= half - MARGIN {
+        for (row_center, dir) in &[
+            (base + SPOT_L * 0.5, Vec2::UNIT_Y),
+            (base + SPOT_L * 1.5, -Vec2::UNIT_Y),
+        ] {
+            if row_center + SPOT_L * 0.5 > half - MARGIN {
+                break;
+            }
+            let mut x = -half + MARGIN + SPOT_W * 0.5;
+            while x + SPOT_W * 0.5 <= half - MARGIN {
+                spots.push((vec2(x, *row_center), *dir));
+                x += SPOT_W;
+            }
+        }
+        base += SPOT_L * 2.0 + AISLE;
+    }
+
+    spots
+}
+
+pub fn gen_parking_lot(size: f32) -> (ColoredMesh, Vec2) {
+    const Z: f32 = 0.05;
+    let half = size * 0.5;
+    let mut mesh = ColoredMesh::default();
+
+    let asphalt = LinearColor::gray(0.25);
+    let marking = LinearColor::gray(0.7);
+
+    mesh.faces.push((
+        vec![
+            vec2(-half, -half).z(Z),
+            vec2(half, -half).z(Z),
+            vec2(half, half).z(Z),
+            vec2(-half, half).z(Z),
+        ],
+        asphalt,
+    ));
+
+    for (pos, dir) in parking_lot_spots(size) {
+        // separator line on the left of each spot
+        let a = pos - vec2(1.5, 0.0) - dir * 3.0;
+        let b = pos - vec2(1.5, 0.0) + dir * 3.0;
+        mesh.faces.push((
+            vec![
+                (a - vec2(0.1, 0.0)).z(Z + 0.01),
+                (a + vec2(0.1, 0.0)).z(Z + 0.01),
+                (b + vec2(0.1, 0.0)).z(Z + 0.01),
+                (b - vec2(0.1, 0.0)).z(Z + 0.01),
+            ],
+            marking,
+        ));
+    }
+
+    (mesh, Vec2::y(-half))
+}
+
+pub fn gen_park(size: f32, seed: u64) -> (ColoredMesh, Vec2) {
+    const Z: f32 = 0.05;
+    let half = size * 0.5;
+    let mut mesh = ColoredMesh::default();
+    let mut rng = SmallRng::seed_from_u64(seed);
+
+    let grass = LinearColor::new(0.25, 0.5, 0.2, 1.0);
+    let path = LinearColor::new(0.7, 0.65, 0.5, 1.0);
+    let bush = LinearColor::new(0.12, 0.35, 0.1, 1.0);
+
+    let rect = |ll: Vec2, ur: Vec2, z: f32| {
+        vec![
+            ll.z(z),
+            vec2(ur.x, ll.y).z(z),
+            ur.z(z),
+            vec2(ll.x, ur.y).z(z),
+        ]
+    };
+
+    mesh.faces
+        .push((rect(Vec2::splat(-half), Vec2::splat(half), Z), grass));
+
+    // path from the door to the center, then across the park
+    mesh.faces
+        .push((rect(vec2(-1.0, -half), vec2(1.0, 0.0), Z + 0.01), path));
+    mesh.faces.push((
+        rect(vec2(-half * 0.8, -1.0), vec2(half * 0.8, 1.0), Z + 0.01),
+        path,
+    ));
+
+    for _ in 0..(size * size / 200.0) as usize {
+        let c = vec2(
+            rng.gen_range(-half + 3.0..half - 3.0),
+            rng.gen_range(-half + 3.0..half - 3.0),
+        );
+        if c.x.abs() < 4.0 || c.y.abs() < 4.0 {
+            continue;
+        }
+        let r = rng.gen_range(1.0..2.5);
+        mesh.faces
+            .push((rect(c - Vec2::splat(r), c + Vec2::splat(r), Z + 0.02), bush));
+    }
+
+    (mesh, Vec2::y(-half))
+}
+
 // How to gen a house
 // Idea: Make everything out of rectangles
 // 1. Make exterior
@@ -0,0 +1,299 @@
+#![allow(clippy::indexing_slicing)]
+
+use super::presets::print_stats;
+use crate::{
+    IntersectionID, LanePattern, LanePatternBuilder, LotKind, Map, ProjectFilter, RoadID,
+    RoadSegmentKind, TerraformKind, WaterKind, BUILDABLE_LEVEL, SEA_LEVEL,
+};
+use common::FastMap;
+use geom::{vec2, Vec2};
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CityStyle {
+    /// Straight streets on a regular grid
+    Grid,
+    /// Winding streets, with some of the local streets missing
+    Organic,
+}
+
+impl Default for CityStyle {
+    fn default() -> Self {
+        CityStyle::Grid
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CityGenParams {
+    pub seed: u64,
+    /// Radius of the city, in meters
+    pub size: f32,
+    /// Side of a block, in meters
+    pub block_size: f32,
+    /// Number of blocks between two arterials
+    pub arterial_every: u32,
+    /// Number of blocks between two collectors
+    pub collector_every: u32,
+    pub style: CityStyle,
+    /// Whether a river crosses the city
+    pub river: bool,
+    /// Number of roads crossing the river
+    pub river_crossings: u32,
+}
+
+impl Default for CityGenParams {
+    fn default() -> Self {
+        Self {
+            seed: 0,
+            size: 1000.0,
+            block_size: 120.0,
+            arterial_every: 6,
+            collector_every: 3,
+            style: CityStyle::Grid,
+            river: true,
+            river_crossings: 3,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum RoadClass {
+    Arterial,
+    Collector,
+    Local,
+}
+
+impl RoadClass {
+    fn of_line(i: i32, params: &CityGenParams) -> Self {
+        if i.rem_euclid(params.arterial_every.max(1) as i32) == 0 {
+            RoadClass::Arterial
+        } else if i.rem_euclid(params.collector_every.max(1) as i32) == 0 {
+            RoadClass::Collector
+        } else {
+            RoadClass::Local
+        }
+    }
+
+    fn pattern(self) -> LanePattern {
+        match self {
+            RoadClass::Arterial => LanePatternBuilder::new()
+                .n_lanes(2)
+                .speed_limit(20.0)
+                .parking(false)
+                .build(),
+            RoadClass::Collector => LanePatternBuilder::new().speed_limit(15.0).build(),
+            RoadClass::Local => LanePatternBuilder::new().speed_limit(10.0).build(),
+        }
+    }
+}
+
+/// Half width of the river, in meters
+const RIVER_HALF_WIDTH: f32 = 40.0;
+
+/// Generates a city of roads and zoned lots around `center`.
+/// The same parameters always give the same city.
+pub fn generate_city(map: &mut Map, center: Vec2, params: &CityGenParams) {
+    let time = std::time::Instant::now();
+    let mut rng = SmallRng::seed_from_u64(params.seed);
+    let tau = std::f32::consts::TAU;
+
+    let block = params.block_size.max(40.0);
+    let n = (params.size / block).ceil() as i32;
+    let axis = Vec2::from_angle(rng.gen_range(0.0..tau));
+
+    if params.river {
+        let dir = Vec2::from_angle(rng.gen_range(0.0..tau));
+        let origin = center + dir.perpendicular() * rng.gen_range(-0.3..0.3) * params.size;
+        let length = params.size * 1.5;
+        let mut path = vec![];
+        let mut d = -length;
+        while d <= length {
+            let p = origin + dir * d;
+            map.terraform(
+                p,
+                RIVER_HALF_WIDTH * 1.5,
+                TerraformKind::Flatten(SEA_LEVEL - 0.03),
+                1.0,
+            );
+            path.push(p);
+            d += RIVER_HALF_WIDTH;
+        }
+        map.add_water(
+            WaterKind::River {
+                width: RIVER_HALF_WIDTH * 2.0,
+            },
+            path,
+        );
+    }
+
+    // smooth displacement of the organic style, small enough to keep the blocks convex
+    let warp_freq = tau / (6.0 * block);
+    let phases = (rng.gen_range(0.0..tau), rng.gen_range(0.0..tau));
+
+    let mut positions: FastMap<(i32, i32), Vec2> = FastMap::default();
+    for y in -n..=n {
+        for x in -n..=n {
+            let grid = vec2(x as f32, y as f32) * block;
+            if grid.magnitude() > params.size {
+                continue;
+            }
+            let mut pos = grid;
+            if let CityStyle::Organic = params.style {
+                pos += vec2(
+                    (grid.y * warp_freq + phases.0).sin(),
+                    (grid.x * warp_freq + phases.1).sin(),
+                ) * block
+                    * 0.5;
+                pos += vec2(rng.gen_range(-0.1..0.1), rng.gen_range(-0.1..0.1)) * block;
+            }
+            let pos = center + pos.rotated_by(axis);
+            let wet = map
+                .spatial_map()
+                .query_around(pos, 10.0, ProjectFilter::WATER)
+                .next()
+                .is_some();
+            if wet || map.heightmap.height(pos) < BUILDABLE_LEVEL {
+                continue;
+            }
+            positions.insert((x, y), pos);
+        }
+    }
+
+    // Walk along every row and column, linking the consecutive nodes.
+    // Links over a gap or over water are river crossings.
+    let mut lines = vec![];
+    for i in -n..=n {
+        let class = RoadClass::of_line(i, params);
+        lines.push((class, (-n..=n).map(|j| (j, i)).collect::<Vec<_>>()));
+        lines.push((class, (-n..=n).map(|j| (i, j)).collect::<Vec<_>>()));
+    }
+
+    let in_city = |(x, y): (i32, i32)| vec2(x as f32, y as f32).magnitude() * block <= params.size;
+
+    let map_ref = &*map;
+    let crosses_water = |a: Vec2, b: Vec2| {
+        let n = (a.distance(b) / 10.0).ceil() as usize;
+        map_ref.crosses_water(&[a, b], 1.0)
+            || (1..n)
+                .any(|i| map_ref.heightmap.height(a.lerp(b, i as f32 / n as f32)) < BUILDABLE_LEVEL)
+    };
+
+    let mut edges = vec![];
+    let mut crossings = vec![];
+    for (class, line) in &lines {
+        let mut prev: Option<(i32, i32)> = None;
+        let mut wet = false;
+        for &node in line {
+            if positions.contains_key(&node) {
+                if let Some(p) = prev {
+                    if wet || crosses_water(positions[&p], positions[&node]) {
+                        crossings.push((*class, p, node));
+                    } else {
+                        edges.push((*class, p, node));
+                    }
+                }
+                prev = Some(node);
+                wet = false;
+            } else if in_city(node) {
+                wet = true;
+            } else {
+                prev = None;
+                wet = false;
+            }
+        }
+    }
+
+    // the main roads nearest to the center get the bridges, the roads over the river become
+    // bridges when they are built
+    crossings.sort_by_key(|&(class, a, b)| {
+        let mid = positions[&a].lerp(positions[&b], 0.5);
+        (class, mid.distance(center) as i32)
+    });
+    edges.extend(crossings.into_iter().take(params.river_crossings as usize));
+
+    let mut inters: FastMap<(i32, i32), IntersectionID> = FastMap::default();
+    let mut road_class: FastMap<RoadID, RoadClass> = FastMap::default();
+    for (class, a, b) in edges {
+        if class == RoadClass::Local
+            && params.style == CityStyle::Organic
+            && rng.gen_range(0.0..1.0) < 0.2
+        {
+            continue;
+        }
+        let mut node = |k: (i32, i32)| {
+            *inters
+                .entry(k)
+                .or_insert_with(|| map.add_intersection(positions[&k]))
+        };
+        let (src, dst) = (node(a), node(b));
+        if let Some(id) = map.connect(src, dst, &class.pattern(), RoadSegmentKind::Straight) {
+            road_class.insert(id, class);
+        }
+    }
+
+    // commerce downtown and along the arterials, industry in one sector of the outskirts
+    let industry_dir = Vec2::from_angle(rng.gen_range(0.0..tau));
+    let zoning: Vec<_> = map
+        .lots
+        .values()
+        .filter_map(|lot| {
+            let class = *road_class.get(&lot.parent)?;
+            let off = lot.shape.center() - center;
+            let d = off.magnitude() / params.size;
+            let kind = if d < 0.15 || (d < 0.5 && class == RoadClass::Arterial) {
+                LotKind::Commercial
+            } else if d > 0.6 && off.normalize().dot(industry_dir) > 0.8 {
+                LotKind::Industrial
+            } else {
+                LotKind::Residential
+            };
+            Some((lot.id, kind))
+        })
+        .collect();
+    for (id, kind) in zoning {
+        map.set_lot_kind(id, kind);
+    }
+
+    info!(
+        "generating city took {}ms",
+        time.elapsed().as_secs_f32() * 1000.0
+    );
+
+    map.check_invariants();
+    print_stats(map);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn citygen_valid() {
+        for &style in &[CityStyle::Grid, CityStyle::Organic] {
+            let mut m = Map::empty();
+            let params = CityGenParams {
+                seed: 42,
+                style,
+                ..Default::default()
+            };
+            generate_city(&mut m, Vec2::ZERO, &params);
+            m.check_invariants();
+
+            assert!(m.roads().len() > 100);
+            assert!(m.roads().values().any(|r| r.bridge));
+            for kind in &[
+                LotKind::Residential,
+                LotKind::Commercial,
+                LotKind::Industrial,
+            ] {
+                assert!(m.lots().values().any(|l| l.kind == *kind));
+            }
+
+            let mut m2 = Map::empty();
+            generate_city(&mut m2, Vec2::ZERO, &params);
+            assert_eq!(m.roads().len(), m2.roads().len());
+        }
+    }
+}
@@ -0,0 +1,541 @@
+//! Import of OpenStreetMap extracts, in the XML (.osm) or PBF (.osm.pbf) format
+
+mod pbf;
+mod xml;
+
+use crate::procgen::print_stats;
+use crate::{IntersectionID, LanePatternBuilder, Map, RoadSegmentKind, WaterKind};
+use common::FastMap;
+use flat_spatial::SparseGrid;
+use geom::{vec2, Vec2};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{Display, Formatter};
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+/// Intersections closer than this are merged into one, in meters
+const MERGE_DIST: f32 = 40.0;
+/// A node in the middle of a way is kept to follow its shape when the way turns by more
+/// than about 30° there, or when the last kept node is further than this
+const MAX_SEGMENT_LENGTH: f32 = 150.0;
+const MIN_TURN_COS: f32 = 0.85;
+const EARTH_RADIUS: f64 = 6_371_000.0;
+
+/// Nodes, and ways with a highway or a water tag read from an extract
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct OsmData {
+    /// Latitude and longitude of the nodes, in degrees
+    pub nodes: FastMap<i64, (f64, f64)>,
+    pub ways: Vec<OsmWay>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct OsmWay {
+    pub id: i64,
+    pub nodes: Vec<i64>,
+    pub tags: BTreeMap<String, String>,
+}
+
+/// Why a way with a highway tag was not turned into roads
+#[derive(Debug, Clone)]
+pub enum SkipReason {
+    /// The highway is not for vehicles, like footways or cycleways
+    NotDrivable(String),
+    /// Some of its nodes are not in the extract
+    MissingNodes,
+    /// All of its nodes were merged into a single intersection
+    TooShort,
+    /// The map could not build its roads
+    ConnectFailed,
+}
+
+impl Display for SkipReason {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SkipReason::NotDrivable(highway) => write!(f, "highway={} is not drivable", highway),
+            SkipReason::MissingNodes => f.write_str("some nodes are missing from the extract"),
+            SkipReason::TooShort => f.write_str("too short"),
+            SkipReason::ConnectFailed => f.write_str("the roads could not be built"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SkippedWay {
+    pub id: i64,
+    pub reason: SkipReason,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct OsmReport {
+    pub n_intersections: usize,
+    pub n_roads: usize,
+    /// Rivers and lakes
+    pub n_waters: usize,
+    /// Ways with a highway tag that were not imported
+    pub skipped: Vec<SkippedWay>,
+}
+
+/// Reads an OpenStreetMap extract to be imported with `import_osm`.
+/// Files ending in `.pbf` are read as PBF, the others as XML.
+pub fn read_osm(path: impl AsRef<Path>) -> std::io::Result<OsmData> {
+    let path = path.as_ref();
+    let file = BufReader::new(File::open(path)?);
+    if path.extension().map(|e| e == "pbf").unwrap_or(false) {
+        pbf::read(file)
+    } else {
+        xml::read(file)
+    }
+}
+
+/// Imports the roads, rivers and lakes of an OpenStreetMap extract, centered on the origin
+pub fn import_osm(map: &mut Map, data: &OsmData) -> OsmReport {
+    let time = std::time::Instant::now();
+    let report = import(map, data);
+
+    info!(
+        "importing the extract took {}ms: {} intersections, {} roads, {} waters, {} ways skipped",
+        time.elapsed().as_secs_f32() * 1000.0,
+        report.n_intersections,
+        report.n_roads,
+        report.n_waters,
+        report.skipped.len()
+    );
+    for skipped in &report.skipped {
+        debug!("skipped way {}: {}", skipped.id, skipped.reason);
+    }
+
+    map.check_invariants();
+    print_stats(map);
+    report
+}
+
+/// A piece of road between two intersections, built once even if several ways go through it
+struct Edge {
+    way: i64,
+    /// Whether it can be driven from the smallest intersection to the largest, and back
+    forward: bool,
+    backward: bool,
+    pattern: LanePatternBuilder,
+}
+
+fn import(map: &mut Map, data: &OsmData) -> OsmReport {
+    let mut report = OsmReport::default();
+
+    let mut roads = vec![];
+    let mut waters = vec![];
+    for way in &data.ways {
+        if let Some(kind) = water_kind(&way.tags) {
+            if way.nodes.iter().all(|n| data.nodes.contains_key(n)) {
+                waters.push((way, kind));
+            }
+            continue;
+        }
+        let reason = match road_tags(&way.tags) {
+            Ok(_) if way.nodes.len() < 2 => SkipReason::TooShort,
+            Ok(_) if way.nodes.iter().any(|n| !data.nodes.contains_key(n)) => {
+                SkipReason::MissingNodes
+            }
+            Ok(tags) => {
+                roads.push((way, tags));
+                continue;
+            }
+            Err(reason) => reason,
+        };
+        report.skipped.push(SkippedWay { id: way.id, reason });
+    }
+
+    let project = projection(
+        roads
+            .iter()
+            .flat_map(|(way, _)| &way.nodes)
+            .chain(waters.iter().flat_map(|(way, _)| &way.nodes)),
+        data,
+    );
+
+    // the water goes first so the roads crossing it are built as bridges
+    for &(way, kind) in &waters {
+        let mut points: Vec<Vec2> = way
+            .nodes
+            .iter()
+            .filter_map(|n| data.nodes.get(n))
+            .map(|&coords| project(coords))
+            .collect();
+        if let WaterKind::Lake = kind {
+            // the outline of an area is closed
+            if way.nodes.len() < 4 || way.nodes.first() != way.nodes.last() {
+                continue;
+            }
+            points.pop();
+        }
+        if map.add_water(kind, points).is_some() {
+            report.n_waters += 1;
+        }
+    }
+
+    let mut pos: FastMap<i64, Vec2> = FastMap::default();
+    // Nodes shared by several ways are junctions. The ends count twice so they are always kept.
+    let mut uses: FastMap<i64, u32> = FastMap::default();
+    for (way, _) in &roads {
+        for node in &way.nodes {
+            if let Some(&coords) = data.nodes.get(node) {
+                pos.insert(*node, project(coords));
+            }
+            *uses.entry(*node).or_default() += 1;
+        }
+        for end in way.nodes.first().into_iter().chain(way.nodes.last()) {
+            *uses.entry(*end).or_default() += 1;
+        }
+    }
+
+    let shapes: Vec<Vec<i64>> = roads
+        .iter()
+        .map(|(way, _)| shape_nodes(&way.nodes, &uses, &pos))
+        .collect();
+
+    let mut grid = SparseGrid::new(50);
+    let mut inters: FastMap<i64, IntersectionID> = FastMap::default();
+    let mut created = vec![];
+    for &node in shapes.iter().flatten() {
+        if inters.contains_key(&node) {
+            continue;
+        }
+        let p = unwrap_cont!(pos.get(&node).copied());
+        let close = grid.query_around(p, MERGE_DIST).next().map(|(h, _)| h);
+        let id = match close {
+            Some(h) => {
+                let (_, &close_id) = unwrap_cont!(grid.get(h));
+                let inter = unwrap_cont!(map.intersections.get_mut(close_id));
+                let newpos = (inter.pos + p) * 0.5;
+                inter.pos = newpos;
+                grid.set_position(h, newpos);
+                grid.maintain();
+                close_id
+            }
+            None => {
+                let id = map.add_intersection(p);
+                grid.insert(p, id);
+                created.push(id);
+                id
+            }
+        };
+        inters.insert(node, id);
+    }
+
+    let mut edges: BTreeMap<(IntersectionID, IntersectionID), Edge> = BTreeMap::new();
+    for ((way, (pattern, reversed)), shape) in roads.iter().zip(&shapes) {
+        let ids: Vec<IntersectionID> = shape
+            .iter()
+            .filter_map(|n| inters.get(n).copied())
+            .collect();
+        let mut n_edges = 0;
+        for w in ids.windows(2) {
+            let (a, b) = match *w {
+                [a, b] if a != b => (a, b),
+                _ => continue,
+            };
+            let (src, dst) = if *reversed { (b, a) } else { (a, b) };
+            let forward = src < dst;
+            let edge = edges.entry((src.min(dst), src.max(dst))).or_insert(Edge {
+                way: way.id,
+                forward: false,
+                backward: false,
+                pattern: *pattern,
+            });
+            edge.forward |= forward || !pattern.one_way;
+            edge.backward |= !forward || !pattern.one_way;
+            edge.pattern.n_lanes = edge.pattern.n_lanes.max(pattern.n_lanes);
+            n_edges += 1;
+        }
+        if n_edges == 0 {
+            report.skipped.push(SkippedWay {
+                id: way.id,
+                reason: SkipReason::TooShort,
+            });
+        }
+    }
+
+    let mut failed = BTreeSet::new();
+    for ((a, b), mut edge) in edges {
+        let (src, dst) = if edge.forward { (a, b) } else { (b, a) };
+        edge.pattern.one_way = !(edge.forward && edge.backward);
+        if map
+            .connect(src, dst, &edge.pattern.build(), RoadSegmentKind::Straight)
+            .is_some()
+        {
+            report.n_roads += 1;
+        } else {
+            failed.insert(edge.way);
+        }
+    }
+    report
+        .skipped
+        .extend(failed.into_iter().map(|id| SkippedWay {
+            id,
+            reason: SkipReason::ConnectFailed,
+        }));
+
+    for id in created {
+        let unused = unwrap_cont!(map.intersections.get(id)).roads.is_empty();
+        if unused {
+            map.remove_intersection(id);
+        } else {
+            report.n_intersections += 1;
+        }
+    }
+
+    report
+}
+
+/// Projects the coordinates on a plane tangent to the earth at the center of the nodes,
+/// in meters with y pointing north
+fn projection<'a>(
+    nodes: impl Iterator<Item = &'a i64>,
+    data: &OsmData,
+) -> impl Fn((f64, f64)) -> Vec2 {
+    let (mut min, mut max) = ((f64::MAX, f64::MAX), (f64::MIN, f64::MIN));
+    for &(lat, lon) in nodes.filter_map(|n| data.nodes.get(n)) {
+        min = (min.0.min(lat), min.1.min(lon));
+        max = (max.0.max(lat), max.1.max(lon));
+    }
+    let (lat0, lon0) = ((min.0 + max.0) * 0.5, (min.1 + max.1) * 0.5);
+    let cos = lat0.to_radians().cos();
+
+    move |(lat, lon)| {
+        vec2(
+            ((lon - lon0).to_radians() * cos * EARTH_RADIUS) as f32,
+            ((lat - lat0).to_radians() * EARTH_RADIUS) as f32,
+        )
+    }
+}
+
+/// Nodes of the way that become intersections: the junctions, and enough of the others
+/// to follow the shape of the way
+fn shape_nodes(nodes: &[i64], uses: &FastMap<i64, u32>, pos: &FastMap<i64, Vec2>) -> Vec<i64> {
+    let pos = |n: &i64| pos.get(n).copied().unwrap_or(Vec2::ZERO);
+
+    let mut kept: Vec<i64> = vec![];
+    for (i, node) in nodes.iter().enumerate() {
+        let keep = match (kept.last(), nodes.get(i + 1)) {
+            (Some(last), Some(next)) => {
+                let (last, p, next) = (pos(last), pos(node), pos(next));
+                let d = last.distance(p);
+                let turns = match ((p - last).try_normalize(), (next - p).try_normalize()) {
+                    (Some(a), Some(b)) => a.dot(b) < MIN_TURN_COS,
+                    _ => false,
+                };
+                uses.get(node).copied().unwrap_or(0) > 1
+                    || d >= MAX_SEGMENT_LENGTH
+                    || (turns && d >= MERGE_DIST)
+            }
+            _ => true,
+        };
+        if keep {
+            kept.push(*node);
+        }
+    }
+    kept
+}
+
+/// Whether the reader keeps a way with these tags
+pub(crate) fn is_imported(tags: &BTreeMap<String, String>) -> bool {
+    tags.contains_key("highway") || water_kind(tags).is_some()
+}
+
+/// Kind of the water mapped by a way, None if it isn't water
+fn water_kind(tags: &BTreeMap<String, String>) -> Option<WaterKind> {
+    let tag = |k: &str| tags.get(k).map(String::as_str);
+
+    let width = match tag("waterway") {
+        Some("river") => 30.0,
+        Some("canal") => 20.0,
+        Some("stream") => 8.0,
+        _ => {
+            return match tag("natural") {
+                Some("water") => Some(WaterKind::Lake),
+                _ => None,
+            }
+        }
+    };
+    let width = tag("width")
+        .and_then(|w| w.trim_end_matches('m').trim().parse::<f32>().ok())
+        .unwrap_or(width);
+    Some(WaterKind::River { width })
+}
+
+/// Lanes of the roads made from a way, and whether it is one way against the order of its nodes
+fn road_tags(tags: &BTreeMap<String, String>) -> Result<(LanePatternBuilder, bool), SkipReason> {
+    let tag = |k: &str| tags.get(k).map(String::as_str);
+
+    let highway = tag("highway").unwrap_or_default();
+    let kind = highway.trim_end_matches("_link");
+    let (n_lanes, speed, sidewalks, parking) = match kind {
+        "motorway" => (2, 30.0, false, false),
+        "trunk" => (2, 25.0, false, false),
+        "primary" => (2, 14.0, true, false),
+        "secondary" => (1, 14.0, true, true),
+        "tertiary" => (1, 12.0, true, true),
+        "unclassified" | "residential" | "road" => (1, 9.0, true, true),
+        "living_street" => (1, 5.0, true, true),
+        "service" => (1, 6.0, false, false),
+        _ => return Err(SkipReason::NotDrivable(highway.to_string())),
+    };
+    let n_lanes = if kind == highway { n_lanes } else { 1 };
+
+    let (one_way, reversed) = match tag("oneway") {
+        Some("yes") | Some("true") | Some("1") => (true, false),
+        Some("-1") | Some("reverse") => (true, true),
+        Some("no") | Some("false") | Some("0") => (false, false),
+        _ => (
+            kind == "motorway" || matches!(tag("junction"), Some("roundabout") | Some("circular")),
+            false,
+        ),
+    };
+
+    // lanes counts both directions
+    let n_lanes = tag("lanes")
+        .and_then(|l| l.parse::<u32>().ok())
+        .map(|l| if one_way { l } else { l.div_ceil(2) })
+        .unwrap_or(n_lanes)
+        .max(1);
+
+    let speed = tag("maxspeed").and_then(parse_maxspeed).unwrap_or(speed);
+
+    // Sidewalks mapped as separate ways are not imported, so they are put back on the road
+    let sidewalks = match tag("sidewalk") {
+        Some("both") | Some("left") | Some("right") | Some("yes") | Some("separate") => true,
+        Some("no") | Some("none") => false,
+        _ => sidewalks,
+    };
+
+    let parking = parking_tag(tags).unwrap_or(parking);
+
+    Ok((
+        *LanePatternBuilder::new()
+            .n_lanes(n_lanes)
+            .one_way(one_way)
+            .speed_limit(speed)
+            .sidewalks(sidewalks)
+            .parking(parking),
+        reversed,
+    ))
+}
+
+/// Speed limit in m/s from a maxspeed tag in km/h or mph
+fn parse_maxspeed(v: &str) -> Option<f32> {
+    let speed = match v {
+        "none" => return Some(40.0),
+        "walk" => return Some(4.0),
+        _ if v.ends_with("mph") => v.trim_end_matches("mph").trim().parse::<f32>().ok()? * 0.447,
+        _ => v.trim().parse::<f32>().ok()? / 3.6,
+    };
+    Some(speed.clamp(4.0, 40.0))
+}
+
+/// Whether there is parking on any side of the road, None if the way doesn't say
+fn parking_tag(tags: &BTreeMap<String, String>) -> Option<bool> {
+    let mut parking = None;
+    for prefix in &["parking:lane:", "parking:"] {
+        for side in &["both", "left", "right"] {
+            match tags.get(&format!("{}{}", prefix, side)).map(String::as_str) {
+                None => {}
+                Some("no") | Some("no_parking") | Some("no_stopping") | Some("separate")
+                | Some("fire_lane") => parking = Some(false),
+                Some(_) => return Some(true),
+            }
+        }
+    }
+    parking
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn osm_xml_import() {
+        let extract = r#"<?xml version="1.0" encoding="UTF-8"?>
+<osm version="0.6">
+  <node id="1" lat="48.8500" lon="2.3500"/>
+  <node id="2" lat="48.8510" lon="2.3500"/>
+  <node id="3" lat="48.8520" lon="2.3500"/>
+  <node id="4" lat="48.8510" lon="2.3520"/>
+  <way id="10">
+    <nd ref="1"/><nd ref="2"/><nd ref="3"/>
+    <tag k="highway" v="residential"/>
+    <tag k="maxspeed" v="30"/>
+  </way>
+  <way id="11">
+    <nd ref="2"/><nd ref="4"/>
+    <tag k="highway" v="primary"/>
+    <tag k="oneway" v="yes"/>
+    <tag k="lanes" v="2"/>
+  </way>
+  <way id="12">
+    <nd ref="1"/><nd ref="4"/>
+    <tag k="highway" v="footway"/>
+  </way>
+  <way id="13">
+    <nd ref="3"/><nd ref="99"/>
+    <tag k="highway" v="service"/>
+  </way>
+  <way id="14">
+    <nd ref="1"/><nd ref="3"/>
+    <tag k="building" v="yes"/>
+  </way>
+</osm>"#;
+
+        let data = xml::read(extract.as_bytes()).unwrap();
+        assert_eq!(data.ways.len(), 4);
+
+        let mut m = Map::empty();
+        let report = import(&mut m, &data);
+        m.check_invariants();
+
+        assert_eq!(report.n_intersections, 4);
+        assert_eq!(report.n_roads, 3);
+        let skipped: Vec<i64> = report.skipped.iter().map(|s| s.id).collect();
+        assert_eq!(skipped, vec![12, 13]);
+    }
+
+    #[test]
+    fn osm_water_import() {
+        let extract = r#"<?xml version="1.0" encoding="UTF-8"?>
+<osm version="0.6">
+  <node id="1" lat="48.8500" lon="2.3500"/>
+  <node id="2" lat="48.8520" lon="2.3500"/>
+  <node id="3" lat="48.8510" lon="2.3480"/>
+  <node id="4" lat="48.8510" lon="2.3520"/>
+  <node id="5" lat="48.8530" lon="2.3540"/>
+  <node id="6" lat="48.8530" lon="2.3560"/>
+  <node id="7" lat="48.8540" lon="2.3550"/>
+  <way id="10">
+    <nd ref="1"/><nd ref="2"/>
+    <tag k="highway" v="residential"/>
+  </way>
+  <way id="11">
+    <nd ref="3"/><nd ref="4"/>
+    <tag k="waterway" v="river"/>
+  </way>
+  <way id="12">
+    <nd ref="5"/><nd ref="6"/><nd ref="7"/><nd ref="5"/>
+    <tag k="natural" v="water"/>
+  </way>
+</osm>"#;
+
+        let data = xml::read(extract.as_bytes()).unwrap();
+        assert_eq!(data.ways.len(), 3);
+
+        let mut m = Map::empty();
+        let report = import(&mut m, &data);
+        m.check_invariants();
+
+        assert_eq!(report.n_waters, 2);
+        assert_eq!(report.n_roads, 1);
+        assert!(report.skipped.is_empty());
+        assert!(m.roads().values().all(|r| r.bridge));
+        assert!(m.waters().values().any(|w| w.kind == WaterKind::Lake));
+    }
+}
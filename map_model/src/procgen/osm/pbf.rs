@@ -0,0 +1,384 @@
+use super::{is_imported, OsmData, OsmWay};
+use std::borrow::Cow;
+use std::convert::TryInto;
+use std::io::{Error, ErrorKind, Read};
+
+/// Maximum sizes of the blob headers and the blobs allowed by the format
+const MAX_HEADER_SIZE: usize = 64 * 1024;
+const MAX_BLOB_SIZE: usize = 32 * 1024 * 1024;
+
+fn invalid(msg: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, format!("invalid pbf: {}", msg))
+}
+
+/// Reads the nodes, the highways and the water of an OSM PBF file
+pub(crate) fn read(mut r: impl Read) -> std::io::Result<OsmData> {
+    let mut data = OsmData::default();
+
+    loop {
+        let mut len = [0; 4];
+        match r.read_exact(&mut len) {
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => break,
+            x => x?,
+        }
+        let len = u32::from_be_bytes(len) as usize;
+        if len > MAX_HEADER_SIZE {
+            return Err(invalid("blob header too large"));
+        }
+        let header = read_bytes(&mut r, len)?;
+
+        let mut kind = Cow::Borrowed("");
+        let mut size = 0;
+        for (field, value) in Message(&header) {
+            match (field, value) {
+                (1, Value::Bytes(b)) => kind = String::from_utf8_lossy(b),
+                (3, Value::Int(s)) => size = s as usize,
+                _ => {}
+            }
+        }
+        if size > MAX_BLOB_SIZE {
+            return Err(invalid("blob too large"));
+        }
+        let blob = read_bytes(&mut r, size)?;
+
+        // The OSMHeader blob only describes the file
+        if kind == "OSMData" {
+            read_block(&blob_data(&blob)?, &mut data);
+        }
+    }
+
+    Ok(data)
+}
+
+fn read_bytes(r: &mut impl Read, len: usize) -> std::io::Result<Vec<u8>> {
+    let mut v = vec![0; len];
+    r.read_exact(&mut v)?;
+    Ok(v)
+}
+
+/// Uncompressed content of a blob
+fn blob_data(blob: &[u8]) -> std::io::Result<Vec<u8>> {
+    for (field, value) in Message(blob) {
+        match (field, value) {
+            (1, Value::Bytes(raw)) => return Ok(raw.to_vec()),
+            (3, Value::Bytes(zlib)) => {
+                return miniz_oxide::inflate::decompress_to_vec_zlib(zlib)
+                    .map_err(|_| invalid("corrupted zlib data"))
+            }
+            (4, _) | (6, _) | (7, _) => return Err(invalid("only zlib compression is supported")),
+            _ => {}
+        }
+    }
+    Err(invalid("empty blob"))
+}
+
+fn read_block(block: &[u8], data: &mut OsmData) {
+    let mut strings: Vec<Cow<str>> = vec![];
+    let mut groups = vec![];
+    let mut granularity = 100;
+    let mut lat_offset = 0;
+    let mut lon_offset = 0;
+    for (field, value) in Message(block) {
+        match (field, value) {
+            (1, Value::Bytes(table)) => {
+                strings = Message(table)
+                    .filter_map(|x| match x {
+                        (1, Value::Bytes(s)) => Some(String::from_utf8_lossy(s)),
+                        _ => None,
+                    })
+                    .collect()
+            }
+            (2, Value::Bytes(group)) => groups.push(group),
+            (17, Value::Int(v)) => granularity = v as i64,
+            (19, Value::Int(v)) => lat_offset = v as i64,
+            (20, Value::Int(v)) => lon_offset = v as i64,
+            _ => {}
+        }
+    }
+    let coords = |lat: i64, lon: i64| {
+        (
+            1e-9 * (lat_offset + granularity * lat) as f64,
+            1e-9 * (lon_offset + granularity * lon) as f64,
+        )
+    };
+    let string = |i: u64| strings.get(i as usize).map(|s| s.to_string());
+
+    for group in groups {
+        for (field, value) in Message(group) {
+            let msg = match value {
+                Value::Bytes(msg) => msg,
+                Value::Int(_) => continue,
+            };
+            match field {
+                // Node
+                1 => {
+                    let (mut id, mut lat, mut lon) = (0, 0, 0);
+                    for (field, value) in Message(msg) {
+                        match (field, value) {
+                            (1, Value::Int(v)) => id = zigzag(v),
+                            (8, Value::Int(v)) => lat = zigzag(v),
+                            (9, Value::Int(v)) => lon = zigzag(v),
+                            _ => {}
+                        }
+                    }
+                    data.nodes.insert(id, coords(lat, lon));
+                }
+                // DenseNodes, delta coded
+                2 => {
+                    let (mut ids, mut lats, mut lons) = (&[][..], &[][..], &[][..]);
+                    for (field, value) in Message(msg) {
+                        match (field, value) {
+                            (1, Value::Bytes(b)) => ids = b,
+                            (8, Value::Bytes(b)) => lats = b,
+                            (9, Value::Bytes(b)) => lons = b,
+                            _ => {}
+                        }
+                    }
+                    let (mut id, mut lat, mut lon) = (0, 0, 0);
+                    for ((di, dlat), dlon) in packed(ids).zip(packed(lats)).zip(packed(lons)) {
+                        id += zigzag(di);
+                        lat += zigzag(dlat);
+                        lon += zigzag(dlon);
+                        data.nodes.insert(id, coords(lat, lon));
+                    }
+                }
+                // Way
+                3 => {
+                    let mut way = OsmWay {
+                        id: 0,
+                        nodes: vec![],
+                        tags: Default::default(),
+                    };
+                    let (mut keys, mut vals) = (&[][..], &[][..]);
+                    for (field, value) in Message(msg) {
+                        match (field, value) {
+                            (1, Value::Int(v)) => way.id = v as i64,
+                            (2, Value::Bytes(b)) => keys = b,
+                            (3, Value::Bytes(b)) => vals = b,
+                            (8, Value::Bytes(refs)) => {
+                                let mut node = 0;
+                                way.nodes = packed(refs)
+                                    .map(|d| {
+                                        node += zigzag(d);
+                                        node
+                                    })
+                                    .collect();
+                            }
+                            _ => {}
+                        }
+                    }
+                    way.tags = packed(keys)
+                        .zip(packed(vals))
+                        .filter_map(|(k, v)| Some((string(k)?, string(v)?)))
+                        .collect();
+                    if is_imported(&way.tags) {
+                        data.ways.push(way);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+enum Value<'a> {
+    Int(u64),
+    Bytes(&'a [u8]),
+}
+
+/// Iterator over the fields of a protobuf message, stopping at the first malformed one
+struct Message<'a>(&'a [u8]);
+
+impl<'a> Iterator for Message<'a> {
+    type Item = (u64, Value<'a>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let key = varint(&mut self.0)?;
+        let value = match key & 7 {
+            0 => Value::Int(varint(&mut self.0)?),
+            1 => Value::Int(u64::from_le_bytes(take(&mut self.0, 8)?.try_into().ok()?)),
+            2 => {
+                let len = varint(&mut self.0)? as usize;
+                Value::Bytes(take(&mut self.0, len)?)
+            }
+            5 => Value::Int(u32::from_le_bytes(take(&mut self.0, 4)?.try_into().ok()?) as u64),
+            _ => return None,
+        };
+        Some((key >> 3, value))
+    }
+}
+
+fn take<'a>(buf: &mut &'a [u8], n: usize) -> Option<&'a [u8]> {
+    let v = buf.get(..n)?;
+    *buf = buf.get(n..)?;
+    Some(v)
+}
+
+fn varint(buf: &mut &[u8]) -> Option<u64> {
+    let mut v = 0;
+    for shift in (0..64).step_by(7) {
+        let (&b, rest) = buf.split_first()?;
+        *buf = rest;
+        v |= ((b & 0x7f) as u64) << shift;
+        if b & 0x80 == 0 {
+            return Some(v);
+        }
+    }
+    None
+}
+
+/// Values of a packed repeated field
+fn packed(mut buf: &[u8]) -> impl Iterator<Item = u64> + '_ {
+    std::iter::from_fn(move || varint(&mut buf))
+}
+
+/// Decodes a sint64
+fn zigzag(v: u64) -> i64 {
+    (v >> 1) as i64 ^ -((v & 1) as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn put_varint(buf: &mut Vec<u8>, mut v: u64) {
+        while v >= 0x80 {
+            buf.push(v as u8 | 0x80);
+            v >>= 7;
+        }
+        buf.push(v as u8);
+    }
+
+    fn int(buf: &mut Vec<u8>, field: u64, v: u64) {
+        put_varint(buf, field << 3);
+        put_varint(buf, v);
+    }
+
+    fn bytes(buf: &mut Vec<u8>, field: u64, b: &[u8]) {
+        put_varint(buf, field << 3 | 2);
+        put_varint(buf, b.len() as u64);
+        buf.extend_from_slice(b);
+    }
+
+    fn sint(v: i64) -> u64 {
+        ((v << 1) ^ (v >> 63)) as u64
+    }
+
+    /// Packed sint64 values, delta coded
+    fn deltas(vs: &[i64]) -> Vec<u8> {
+        let mut buf = vec![];
+        let mut last = 0;
+        for &v in vs {
+            put_varint(&mut buf, sint(v - last));
+            last = v;
+        }
+        buf
+    }
+
+    fn packed_ints(vs: &[u64]) -> Vec<u8> {
+        let mut buf = vec![];
+        for &v in vs {
+            put_varint(&mut buf, v);
+        }
+        buf
+    }
+
+    fn blob(out: &mut Vec<u8>, kind: &str, data: &[u8]) {
+        let mut blob = vec![];
+        int(&mut blob, 2, data.len() as u64);
+        bytes(
+            &mut blob,
+            3,
+            &miniz_oxide::deflate::compress_to_vec_zlib(data, 6),
+        );
+        let mut header = vec![];
+        bytes(&mut header, 1, kind.as_bytes());
+        int(&mut header, 3, blob.len() as u64);
+
+        out.extend_from_slice(&(header.len() as u32).to_be_bytes());
+        out.extend_from_slice(&header);
+        out.extend_from_slice(&blob);
+    }
+
+    #[test]
+    fn pbf_round_trip() {
+        let mut table = vec![];
+        for s in &["", "highway", "residential", "building", "yes"] {
+            bytes(&mut table, 1, s.as_bytes());
+        }
+
+        // granularity of 100 nanodegrees
+        let lats = [488_500_000, 488_510_000, 488_520_000];
+        let lons = [23_500_000, 23_500_000, 23_520_000];
+        let mut dense = vec![];
+        bytes(&mut dense, 1, &deltas(&[1, 2, 3]));
+        bytes(&mut dense, 8, &deltas(&lats));
+        bytes(&mut dense, 9, &deltas(&lons));
+        let mut dense_group = vec![];
+        bytes(&mut dense_group, 2, &dense);
+
+        let mut node = vec![];
+        int(&mut node, 1, sint(4));
+        int(&mut node, 8, sint(-10_000));
+        int(&mut node, 9, sint(23_510_000));
+
+        let mut road = vec![];
+        int(&mut road, 1, 10);
+        bytes(&mut road, 2, &packed_ints(&[1]));
+        bytes(&mut road, 3, &packed_ints(&[2]));
+        bytes(&mut road, 8, &deltas(&[1, 2, 3, 4]));
+
+        let mut house = vec![];
+        int(&mut house, 1, 11);
+        bytes(&mut house, 2, &packed_ints(&[3]));
+        bytes(&mut house, 3, &packed_ints(&[4]));
+        bytes(&mut house, 8, &deltas(&[1, 2, 3, 1]));
+
+        let mut group = vec![];
+        bytes(&mut group, 1, &node);
+        bytes(&mut group, 3, &road);
+        bytes(&mut group, 3, &house);
+
+        let mut block = vec![];
+        bytes(&mut block, 1, &table);
+        bytes(&mut block, 2, &dense_group);
+        bytes(&mut block, 2, &group);
+
+        let mut file = vec![];
+        blob(&mut file, "OSMHeader", &[]);
+        blob(&mut file, "OSMData", &block);
+
+        let data = read(&file[..]).unwrap();
+
+        let expected = [
+            (1, 48.85, 2.35),
+            (2, 48.851, 2.35),
+            (3, 48.852, 2.352),
+            (4, -0.001, 2.351),
+        ];
+        assert_eq!(data.nodes.len(), expected.len());
+        for &(id, lat, lon) in &expected {
+            let (rlat, rlon) = data.nodes[&id];
+            assert!((rlat - lat).abs() < 1e-9, "lat of {}: {}", id, rlat);
+            assert!((rlon - lon).abs() < 1e-9, "lon of {}: {}", id, rlon);
+        }
+
+        assert_eq!(
+            data.ways,
+            vec![OsmWay {
+                id: 10,
+                nodes: vec![1, 2, 3, 4],
+                tags: vec![("highway".to_string(), "residential".to_string())]
+                    .into_iter()
+                    .collect(),
+            }]
+        );
+    }
+
+    #[test]
+    fn pbf_truncated_is_an_error() {
+        let mut file = vec![];
+        blob(&mut file, "OSMData", &[]);
+        assert!(read(&file[..file.len() - 1]).is_err());
+    }
+}
@@ -0,0 +1,128 @@
+use super::{is_imported, OsmData, OsmWay};
+use std::io::{Error, ErrorKind, Read};
+use xml::reader::{EventReader, XmlEvent};
+
+/// Reads the nodes, the highways and the water of an OSM XML document
+pub(crate) fn read(r: impl Read) -> std::io::Result<OsmData> {
+    let mut data = OsmData::default();
+    let mut way: Option<OsmWay> = None;
+
+    for event in EventReader::new(r) {
+        match event.map_err(|e| Error::new(ErrorKind::InvalidData, e))? {
+            XmlEvent::StartElement {
+                name, attributes, ..
+            } => {
+                let attr = |k: &str| {
+                    attributes
+                        .iter()
+                        .find(|a| a.name.local_name == k)
+                        .map(|a| a.value.as_str())
+                };
+                let parse = |k: &str| attr(k).and_then(|v| v.parse::<f64>().ok());
+                let id = attr("id").and_then(|v| v.parse::<i64>().ok());
+
+                match name.local_name.as_str() {
+                    "node" => {
+                        if let (Some(id), Some(lat), Some(lon)) = (id, parse("lat"), parse("lon")) {
+                            data.nodes.insert(id, (lat, lon));
+                        }
+                    }
+                    "way" => {
+                        way = id.map(|id| OsmWay {
+                            id,
+                            nodes: vec![],
+                            tags: Default::default(),
+                        })
+                    }
+                    "nd" => {
+                        let node = attr("ref").and_then(|v| v.parse::<i64>().ok());
+                        if let (Some(way), Some(node)) = (&mut way, node) {
+                            way.nodes.push(node);
+                        }
+                    }
+                    "tag" => {
+                        if let (Some(way), Some(k), Some(v)) = (&mut way, attr("k"), attr("v")) {
+                            way.tags.insert(k.to_string(), v.to_string());
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            XmlEvent::EndElement { name } if name.local_name == "way" => {
+                if let Some(way) = way.take() {
+                    if is_imported(&way.tags) {
+                        data.ways.push(way);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fmt::Write;
+
+    fn write(data: &OsmData) -> String {
+        let mut s =
+            String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<osm version=\"0.6\">\n");
+        let mut nodes: Vec<_> = data.nodes.iter().collect();
+        nodes.sort_by_key(|(id, _)| **id);
+        for (id, (lat, lon)) in nodes {
+            writeln!(s, "  <node id=\"{}\" lat=\"{}\" lon=\"{}\"/>", id, lat, lon).unwrap();
+        }
+        for way in &data.ways {
+            writeln!(s, "  <way id=\"{}\">", way.id).unwrap();
+            for node in &way.nodes {
+                writeln!(s, "    <nd ref=\"{}\"/>", node).unwrap();
+            }
+            for (k, v) in &way.tags {
+                writeln!(s, "    <tag k=\"{}\" v=\"{}\"/>", k, v).unwrap();
+            }
+            s.push_str("  </way>\n");
+        }
+        s.push_str("</osm>\n");
+        s
+    }
+
+    fn way(id: i64, nodes: &[i64], tags: &[(&str, &str)]) -> OsmWay {
+        OsmWay {
+            id,
+            nodes: nodes.to_vec(),
+            tags: tags
+                .iter()
+                .map(|&(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn xml_round_trip() {
+        let mut data = OsmData::default();
+        data.nodes.insert(1, (48.85, 2.35));
+        data.nodes.insert(2, (48.851, 2.35));
+        data.nodes.insert(-3, (-33.5, -70.25));
+        data.ways = vec![
+            way(10, &[1, 2], &[("highway", "residential"), ("lanes", "2")]),
+            way(11, &[2, -3, 1, 2], &[("natural", "water")]),
+        ];
+
+        assert_eq!(read(write(&data).as_bytes()).unwrap(), data);
+
+        // ways that are neither roads nor water are not kept
+        let mut with_house = data.clone();
+        with_house
+            .ways
+            .push(way(12, &[1, 2, -3, 1], &[("building", "yes")]));
+        assert_eq!(read(write(&with_house).as_bytes()).unwrap(), data);
+    }
+
+    #[test]
+    fn xml_malformed_is_an_error() {
+        assert!(read("<osm><node id=\"1\"></osm>".as_bytes()).is_err());
+    }
+}
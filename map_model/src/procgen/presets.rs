@@ -220,7 +220,7 @@ pub fn add_grid(m: &mut Map, pos: Vec2, size: u32, spacing: f32) {
     }
 }
 
-fn print_stats(map: &Map) {
+pub(crate) fn print_stats(map: &Map) {
     info!("{} intersections", map.intersections.len());
     info!("{} roads", map.roads.len());
     info!("{} lanes", map.lanes.len());
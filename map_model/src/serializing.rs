@@ -1,5 +1,7 @@
 use crate::procgen::Trees;
-use crate::{Buildings, Intersections, Lanes, Lots, Map, ParkingSpots, Roads, SpatialMap};
+use crate::{
+    Buildings, Heightmap, Intersections, Lanes, Lots, Map, ParkingSpots, Roads, SpatialMap, Waters,
+};
 use serde::{Deserialize, Serialize, Serializer};
 use std::num::Wrapping;
 
@@ -11,7 +13,11 @@ pub(crate) struct SerializedMap {
     pub lanes: Lanes,
     pub parking: ParkingSpots,
     pub lots: Lots,
+    #[serde(default)]
+    pub waters: Waters,
     pub trees: Trees,
+    #[serde(default)]
+    pub heightmap: Heightmap,
     pub dirt_id: u32,
 }
 
@@ -28,7 +34,9 @@ impl From<&Map> for SerializedMap {
             lanes: m.lanes.clone(),
             parking: m.parking.clone(),
             lots: m.lots.clone(),
+            waters: m.waters.clone(),
             trees: m.trees.clone(),
+            heightmap: m.heightmap.clone(),
             dirt_id: m.dirt_id.0,
         }
     }
@@ -38,6 +46,7 @@ impl From<SerializedMap> for Map {
     fn from(mut sel: SerializedMap) -> Self {
         for inter in sel.intersections.values_mut() {
             inter.update_polygon(&sel.roads);
+            inter.update_conflicts();
         }
 
         let spatial_map = mk_spatial_map(&sel);
@@ -48,8 +57,10 @@ impl From<SerializedMap> for Map {
             buildings: sel.buildings,
             spatial_map,
             lots: sel.lots,
+            waters: sel.waters,
             parking: sel.parking,
             trees: sel.trees,
+            heightmap: sel.heightmap,
             dirt_id: Wrapping(sel.dirt_id),
         }
     }
@@ -69,6 +80,9 @@ fn mk_spatial_map(m: &SerializedMap) -> SpatialMap {
     for l in m.lots.values() {
         sm.insert(l.id, l.shape);
     }
+    for w in m.waters.values() {
+        sm.insert(w.id, w.shape());
+    }
     sm
 }
 
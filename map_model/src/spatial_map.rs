@@ -1,4 +1,4 @@
-use crate::{BuildingID, IntersectionID, LotID, Map, RoadID};
+use crate::{BuildingID, IntersectionID, LotID, Map, RoadID, WaterID};
 use flat_spatial::shapegrid::ShapeGridHandle;
 use flat_spatial::ShapeGrid;
 use geom::{Circle, Intersect, Shape, ShapeEnum, Vec2, AABB};
@@ -12,6 +12,7 @@ pub enum ProjectKind {
     Road(RoadID),
     Building(BuildingID),
     Lot(LotID),
+    Water(WaterID),
     Ground,
 }
 
@@ -29,6 +30,7 @@ impl_from_pk!(IntersectionID, ProjectKind::Inter);
 impl_from_pk!(RoadID, ProjectKind::Road);
 impl_from_pk!(BuildingID, ProjectKind::Building);
 impl_from_pk!(LotID, ProjectKind::Lot);
+impl_from_pk!(WaterID, ProjectKind::Water);
 
 impl ProjectKind {
     pub fn to_lot(self) -> Option<LotID> {
@@ -45,6 +47,7 @@ impl ProjectKind {
             ProjectKind::Road(id) => map.roads.contains_key(id),
             ProjectKind::Building(id) => map.buildings.contains_key(id),
             ProjectKind::Lot(id) => map.lots.contains_key(id),
+            ProjectKind::Water(id) => map.waters.contains_key(id),
             ProjectKind::Ground => true,
         }
     }
@@ -150,6 +153,7 @@ impl ProjectFilter {
     pub const ROAD: Self = Self(2);
     pub const BUILDING: Self = Self(4);
     pub const LOT: Self = Self(8);
+    pub const WATER: Self = Self(16);
     pub const ALL: Self = Self(!0);
 
     pub fn test(self, p: &ProjectKind) -> bool {
@@ -158,6 +162,7 @@ impl ProjectFilter {
             ProjectKind::Road(_) => (self.0 & Self::ROAD.0) != 0,
             ProjectKind::Building(_) => (self.0 & Self::BUILDING.0) != 0,
             ProjectKind::Lot(_) => (self.0 & Self::LOT.0) != 0,
+            ProjectKind::Water(_) => (self.0 & Self::WATER.0) != 0,
             ProjectKind::Ground => true,
         }
     }
@@ -0,0 +1,340 @@
+use crate::procgen::heightmap;
+use geom::{vec2, Vec2, AABB};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+pub const HEIGHT_CHUNK_SIZE: f32 = 1000.0;
+/// Number of cells along a side of a chunk
+pub const HEIGHT_CHUNK_RES: i32 = 40;
+pub const HEIGHT_CELL_SIZE: f32 = HEIGHT_CHUNK_SIZE / HEIGHT_CHUNK_RES as f32;
+/// Meters of elevation per unit of height
+pub const ELEVATION_SCALE: f32 = 1000.0;
+pub const SEA_LEVEL: f32 = 0.1;
+/// Nothing can be built below it
+pub const BUILDABLE_LEVEL: f32 = 0.12;
+/// Maximum slope of a road, in meters of elevation per meter
+pub const MAX_ROAD_SLOPE: f32 = 0.12;
+/// Largest radius of a terraforming brush, in meters
+pub const MAX_TERRAFORM_RADIUS: f32 = 500.0;
+
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub enum TerraformKind {
+    Raise,
+    Lower,
+    /// Brings the terrain to the given height
+    Flatten(f32),
+    Smooth,
+}
+
+/// New height of a vertex of the grid
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HeightEdit {
+    pub x: i32,
+    pub y: i32,
+    pub height: f32,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct HeightChunk {
+    /// Height of the vertices, row by row
+    heights: Vec<f32>,
+    /// Increased on every edit, so the renderer knows when to rebuild the chunk
+    version: u32,
+}
+
+impl HeightChunk {
+    fn new(chunk: (i32, i32)) -> Self {
+        let mut heights = Vec::with_capacity((HEIGHT_CHUNK_RES * HEIGHT_CHUNK_RES) as usize);
+        for y in 0..HEIGHT_CHUNK_RES {
+            for x in 0..HEIGHT_CHUNK_RES {
+                let p = vertex_pos(
+                    chunk.0 * HEIGHT_CHUNK_RES + x,
+                    chunk.1 * HEIGHT_CHUNK_RES + y,
+                );
+                heights.push(heightmap::height(p).0);
+            }
+        }
+        Self {
+            heights,
+            version: 0,
+        }
+    }
+}
+
+/// Height of the terrain between 0 and 1, on a grid of `HEIGHT_CELL_SIZE` meters.
+/// Only the edited chunks are stored, the others come from the noise function.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct Heightmap {
+    chunks: BTreeMap<(i32, i32), HeightChunk>,
+}
+
+fn vertex_pos(x: i32, y: i32) -> Vec2 {
+    vec2(x as f32, y as f32) * HEIGHT_CELL_SIZE
+}
+
+/// Chunk of the vertex and its index in the chunk
+fn locate(x: i32, y: i32) -> ((i32, i32), usize) {
+    let chunk = (
+        x.div_euclid(HEIGHT_CHUNK_RES),
+        y.div_euclid(HEIGHT_CHUNK_RES),
+    );
+    let idx = y.rem_euclid(HEIGHT_CHUNK_RES) * HEIGHT_CHUNK_RES + x.rem_euclid(HEIGHT_CHUNK_RES);
+    (chunk, idx as usize)
+}
+
+/// Area whose height changes with the edits, None if there are none
+pub fn edits_bounds(edits: &[HeightEdit]) -> Option<AABB> {
+    let (first, rest) = edits.split_first()?;
+    let (mut ll, mut ur) = ((first.x, first.y), (first.x, first.y));
+    for e in rest {
+        ll = (ll.0.min(e.x), ll.1.min(e.y));
+        ur = (ur.0.max(e.x), ur.1.max(e.y));
+    }
+    // the heights are interpolated in the cells around the vertices
+    Some(AABB::new(
+        vertex_pos(ll.0 - 1, ll.1 - 1),
+        vertex_pos(ur.0 + 1, ur.1 + 1),
+    ))
+}
+
+impl Heightmap {
+    fn vertex(&self, x: i32, y: i32) -> f32 {
+        let (chunk, idx) = locate(x, y);
+        match self.chunks.get(&chunk).and_then(|c| c.heights.get(idx)) {
+            Some(&h) => h,
+            None => heightmap::height(vertex_pos(x, y)).0,
+        }
+    }
+
+    /// Height and gradient, interpolated between the vertices
+    pub fn height_grad(&self, p: Vec2) -> (f32, Vec2) {
+        let cell = p / HEIGHT_CELL_SIZE;
+        let (x, y) = (cell.x.floor() as i32, cell.y.floor() as i32);
+        let (fx, fy) = (cell.x - x as f32, cell.y - y as f32);
+
+        let h00 = self.vertex(x, y);
+        let h10 = self.vertex(x + 1, y);
+        let h01 = self.vertex(x, y + 1);
+        let h11 = self.vertex(x + 1, y + 1);
+
+        let bottom = h00 + (h10 - h00) * fx;
+        let top = h01 + (h11 - h01) * fx;
+        let grad =
+            vec2((h10 - h00) + (h11 - h10 - h01 + h00) * fy, top - bottom) / HEIGHT_CELL_SIZE;
+
+        (bottom + (top - bottom) * fy, grad)
+    }
+
+    pub fn height(&self, p: Vec2) -> f32 {
+        self.height_grad(p).0
+    }
+
+    /// Steepest slope along the polyline, in meters of elevation per meter
+    pub fn max_slope(&self, points: &[Vec2]) -> f32 {
+        let step = HEIGHT_CELL_SIZE * 0.5;
+        let mut max: f32 = 0.0;
+        for w in points.windows(2) {
+            let (a, b) = match *w {
+                [a, b] => (a, b),
+                _ => continue,
+            };
+            let n = (a.distance(b) / step).ceil().max(1.0) as usize;
+            let d = a.distance(b) / n as f32;
+            if d <= 0.0 {
+                continue;
+            }
+            let mut last = self.height(a);
+            for i in 1..=n {
+                let h = self.height(a + (b - a) * (i as f32 / n as f32));
+                max = max.max((h - last).abs() * ELEVATION_SCALE / d);
+                last = h;
+            }
+        }
+        max
+    }
+
+    /// Lowest height along the polyline
+    pub fn min_height(&self, points: &[Vec2]) -> f32 {
+        let step = HEIGHT_CELL_SIZE * 0.5;
+        let mut min = f32::INFINITY;
+        for w in points.windows(2) {
+            let (a, b) = match *w {
+                [a, b] => (a, b),
+                _ => continue,
+            };
+            let n = (a.distance(b) / step).ceil().max(1.0) as usize;
+            for i in 0..=n {
+                min = min.min(self.height(a + (b - a) * (i as f32 / n as f32)));
+            }
+        }
+        min
+    }
+
+    /// Vertices changed by a brush changing the height by at most `amount` at its center,
+    /// fading out to its radius. The radius is clamped to `MAX_TERRAFORM_RADIUS`,
+    /// a non-positive or non-finite amount changes nothing.
+    pub fn brush(
+        &self,
+        center: Vec2,
+        radius: f32,
+        kind: TerraformKind,
+        amount: f32,
+    ) -> Vec<HeightEdit> {
+        if radius.is_nan() || radius <= 0.0 || !amount.is_finite() || amount <= 0.0 {
+            return vec![];
+        }
+        if let TerraformKind::Flatten(target) = kind {
+            if !target.is_finite() {
+                return vec![];
+            }
+        }
+        let radius = radius.min(MAX_TERRAFORM_RADIUS);
+        let ll = ((center - Vec2::splat(radius)) / HEIGHT_CELL_SIZE).floor();
+        let ur = (center + Vec2::splat(radius)) / HEIGHT_CELL_SIZE;
+        let (x0, y0) = (ll.x as i32, ll.y as i32);
+        let (x1, y1) = (ur.x.ceil() as i32, ur.y.ceil() as i32);
+
+        let mut edits = vec![];
+        for y in y0..=y1 {
+            for x in x0..=x1 {
+                let dist = vertex_pos(x, y).distance(center);
+                if dist >= radius {
+                    continue;
+                }
+                let t = 1.0 - dist / radius;
+                let falloff = t * t * (3.0 - 2.0 * t);
+
+                let step = amount * falloff;
+                let h = self.vertex(x, y);
+                let new = match kind {
+                    TerraformKind::Raise => h + step,
+                    TerraformKind::Lower => h - step,
+                    TerraformKind::Flatten(target) => h + (target - h).clamp(-step, step),
+                    TerraformKind::Smooth => {
+                        let avg = (self.vertex(x - 1, y)
+                            + self.vertex(x + 1, y)
+                            + self.vertex(x, y - 1)
+                            + self.vertex(x, y + 1))
+                            * 0.25;
+                        h + (avg - h).clamp(-step, step)
+                    }
+                };
+                let height = new.clamp(0.0, 1.0);
+                if height != h {
+                    edits.push(HeightEdit { x, y, height });
+                }
+            }
+        }
+        edits
+    }
+
+    /// Sets the height of the vertices, returns the edits restoring their previous heights
+    pub fn set_heights(&mut self, edits: &[HeightEdit]) -> Vec<HeightEdit> {
+        let mut restore = Vec::with_capacity(edits.len());
+        let mut touched = vec![];
+        for edit in edits {
+            let (chunk, idx) = locate(edit.x, edit.y);
+            let c = self
+                .chunks
+                .entry(chunk)
+                .or_insert_with(|| HeightChunk::new(chunk));
+            if let Some(v) = c.heights.get_mut(idx) {
+                restore.push(HeightEdit {
+                    height: *v,
+                    ..*edit
+                });
+                *v = edit.height.clamp(0.0, 1.0);
+            }
+            if !touched.contains(&chunk) {
+                c.version += 1;
+                touched.push(chunk);
+            }
+        }
+        // the first value of a vertex edited twice must be restored last
+        restore.reverse();
+        restore
+    }
+
+    /// Applies a brush, see `brush`. Returns the area that was modified.
+    pub fn terraform(
+        &mut self,
+        center: Vec2,
+        radius: f32,
+        kind: TerraformKind,
+        amount: f32,
+    ) -> Option<AABB> {
+        let edits = self.brush(center, radius, kind, amount);
+        self.set_heights(&edits);
+        edits_bounds(&edits)
+    }
+
+    /// Volume of earth moved by the edits, in m³
+    pub fn volume(&self, edits: &[HeightEdit]) -> f32 {
+        edits
+            .iter()
+            .map(|e| (e.height - self.vertex(e.x, e.y)).abs())
+            .sum::<f32>()
+            * ELEVATION_SCALE
+            * HEIGHT_CELL_SIZE
+            * HEIGHT_CELL_SIZE
+    }
+
+    /// Edited chunks and their version
+    pub fn edited_chunks(&self) -> impl Iterator<Item = ((i32, i32), u32)> + '_ {
+        self.chunks.iter().map(|(&k, c)| (k, c.version))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Heightmap, TerraformKind, HEIGHT_CELL_SIZE, MAX_TERRAFORM_RADIUS};
+    use geom::vec2;
+
+    #[test]
+    fn terraform() {
+        let mut h = Heightmap::default();
+        let p = vec2(-1234.0, 567.0);
+        let far = p + vec2(200.0, 0.0);
+        let before = h.height(p);
+        let before_far = h.height(far);
+        assert!((before - crate::procgen::heightmap::height(p).0).abs() < 0.01);
+
+        h.terraform(p, 100.0, TerraformKind::Raise, 0.05);
+        assert!(h.height(p) > before + 0.04);
+        assert!((h.height(far) - before_far).abs() < 1e-6);
+
+        for _ in 0..5 {
+            h.terraform(p, 200.0, TerraformKind::Flatten(0.3), 1.0);
+        }
+        assert!((h.height(p) - 0.3).abs() < 0.01);
+        assert!(h.max_slope(&[p, p + vec2(HEIGHT_CELL_SIZE, 0.0)]) < 0.01);
+    }
+
+    #[test]
+    fn brush_is_clamped_and_undone() {
+        let mut h = Heightmap::default();
+        let p = vec2(300.0, -700.0);
+        let before = h.height(p);
+
+        let huge = h.brush(p, 1e9, TerraformKind::Raise, 0.01);
+        let max = h.brush(p, MAX_TERRAFORM_RADIUS, TerraformKind::Raise, 0.01);
+        assert_eq!(huge.len(), max.len());
+        assert!(h.brush(p, f32::NAN, TerraformKind::Raise, 0.01).is_empty());
+        for &amount in &[-0.01, 0.0, f32::NAN, f32::INFINITY] {
+            assert!(h.brush(p, 100.0, TerraformKind::Raise, amount).is_empty());
+            assert!(h.brush(p, 100.0, TerraformKind::Smooth, amount).is_empty());
+            assert!(h
+                .brush(p, 100.0, TerraformKind::Flatten(0.5), amount)
+                .is_empty());
+        }
+        assert!(h
+            .brush(p, 100.0, TerraformKind::Flatten(f32::NAN), 0.01)
+            .is_empty());
+
+        let restore = h.set_heights(&max);
+        assert!(h.height(p) > before);
+        assert!(h.volume(&restore) > 0.0);
+        h.set_heights(&restore);
+        assert!((h.height(p) - before).abs() < 1e-6);
+    }
+}
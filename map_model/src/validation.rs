@@ -0,0 +1,293 @@
+//! Validation and repair of maps, for saves that were corrupted or made by older versions.
+//!
+//! Unlike [`Map::check_invariants`] which panics on the first broken invariant, the validator
+//! lists every problem it finds so that they can be shown to the player and repaired.
+
+use crate::{
+    BuildingID, IntersectionID, LaneID, LaneKind, Map, ParkingSpotID, PathKind, ProjectFilter,
+    ProjectKind, TurnID,
+};
+use pathfinding::undirected::connected_components::connected_components;
+use std::collections::BTreeSet;
+use std::fmt::{Display, Formatter};
+use std::num::Wrapping;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MapProblem {
+    /// The road or one of the intersections of the lane doesn't exist
+    DanglingLane(LaneID),
+    /// The intersection has no roads
+    LonelyIntersection(IntersectionID),
+    /// The lane of the parking spot doesn't exist
+    OrphanParkingSpot(ParkingSpotID),
+    OverlappingBuildings(BuildingID, BuildingID),
+    /// Lanes that cannot be reached from the largest part of the network for this kind of path
+    UnreachableLanes(PathKind, Vec<LaneID>),
+    /// The turn goes from or to a lane that doesn't exist
+    MissingTurnLane(TurnID),
+}
+
+impl MapProblem {
+    /// Whether [`Map::repair`] fixes the problem
+    pub fn repairable(&self) -> bool {
+        !matches!(
+            self,
+            MapProblem::OverlappingBuildings(..) | MapProblem::UnreachableLanes(..)
+        )
+    }
+}
+
+impl Display for MapProblem {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MapProblem::DanglingLane(id) => write!(f, "lane {:?} has no road", id),
+            MapProblem::LonelyIntersection(id) => {
+                write!(f, "intersection {:?} has no roads", id)
+            }
+            MapProblem::OrphanParkingSpot(id) => {
+                write!(f, "parking spot {:?} has no lane", id)
+            }
+            MapProblem::OverlappingBuildings(a, b) => {
+                write!(f, "buildings {:?} and {:?} overlap", a, b)
+            }
+            MapProblem::UnreachableLanes(kind, lanes) => write!(
+                f,
+                "{} lanes are unreachable for {:?} paths, starting with {:?}",
+                lanes.len(),
+                kind,
+                lanes.first()
+            ),
+            MapProblem::MissingTurnLane(id) => write!(f, "turn {:?} has a missing lane", id),
+        }
+    }
+}
+
+impl Map {
+    /// Lists the problems of the map, an empty list means the map is valid
+    pub fn validate(&self) -> Vec<MapProblem> {
+        let mut problems = vec![];
+
+        for lane in self.lanes.values() {
+            if !self.roads.contains_key(lane.parent)
+                || !self.intersections.contains_key(lane.src)
+                || !self.intersections.contains_key(lane.dst)
+            {
+                problems.push(MapProblem::DanglingLane(lane.id));
+            }
+        }
+
+        for inter in self.intersections.values() {
+            if inter.roads.iter().all(|&r| !self.roads.contains_key(r)) {
+                problems.push(MapProblem::LonelyIntersection(inter.id));
+            }
+            for turn in inter.turns() {
+                if !self.lanes.contains_key(turn.id.src) || !self.lanes.contains_key(turn.id.dst) {
+                    problems.push(MapProblem::MissingTurnLane(turn.id));
+                }
+            }
+        }
+
+        for (id, spot) in self.parking.all_spots() {
+            if !self.lanes.contains_key(spot.parent) {
+                problems.push(MapProblem::OrphanParkingSpot(id));
+            }
+        }
+
+        let mut overlaps = BTreeSet::new();
+        for b in self.buildings.values() {
+            // buildings sharing a side are fine
+            for kind in self
+                .spatial_map
+                .query(b.obb.expand(-1.0), ProjectFilter::BUILDING)
+            {
+                if let ProjectKind::Building(other) = kind {
+                    if other != b.id {
+                        overlaps.insert((b.id.min(other), b.id.max(other)));
+                    }
+                }
+            }
+        }
+        problems.extend(
+            overlaps
+                .into_iter()
+                .map(|(a, b)| MapProblem::OverlappingBuildings(a, b)),
+        );
+
+        for &kind in &[PathKind::Vehicle, PathKind::Pedestrian] {
+            let mut components = self.lane_components(kind);
+            components.sort_by_key(|c| std::cmp::Reverse(c.len()));
+            for comp in components.into_iter().skip(1) {
+                problems.push(MapProblem::UnreachableLanes(kind, comp));
+            }
+        }
+
+        problems
+    }
+
+    /// Fixes the problems that can be fixed automatically, and returns the ones left
+    pub fn repair(&mut self) -> Vec<MapProblem> {
+        let problems = self.validate();
+        info!("repairing {} map problems", problems.len());
+
+        let mut turns_to_update = BTreeSet::new();
+        for problem in &problems {
+            match *problem {
+                MapProblem::DanglingLane(id) => {
+                    let lane = unwrap_cont!(self.lanes.remove(id));
+                    self.parking.remove_spots(id);
+                    if let Some(road) = self.roads.get_mut(lane.parent) {
+                        road.retain_lanes(|l| l != id);
+                    }
+                    turns_to_update.insert(lane.src);
+                    turns_to_update.insert(lane.dst);
+                }
+                MapProblem::MissingTurnLane(id) => {
+                    turns_to_update.insert(id.parent);
+                }
+                _ => {}
+            }
+        }
+
+        // roads may still list lanes that don't exist anymore, and intersections roads
+        let lanes = &self.lanes;
+        for road in self.roads.values_mut() {
+            road.retain_lanes(|l| lanes.contains_key(l));
+        }
+        let roads = &self.roads;
+        for inter in self.intersections.values_mut() {
+            if inter.roads.iter().any(|&r| !roads.contains_key(r)) {
+                inter.roads.retain(|&r| roads.contains_key(r));
+                turns_to_update.insert(inter.id);
+            }
+        }
+
+        for id in turns_to_update {
+            let inter = unwrap_cont!(self.intersections.get_mut(id));
+            if inter.roads.is_empty() {
+                continue;
+            }
+            inter.update_traffic_control(&mut self.lanes, &self.roads);
+            inter.update_turns(&self.lanes, &self.roads);
+        }
+
+        // parking lots are reconnected to the nearest road, the spots along the lanes are removed
+        let lots: Vec<_> = self
+            .parking
+            .lots()
+            .map(|(b, _)| (b, self.parking.lot_entrance(b)))
+            .collect();
+        for (b, entrance) in lots {
+            if matches!(entrance, Some(l) if self.lanes.contains_key(l)) {
+                continue;
+            }
+            let door = self.buildings.get(b).map(|b| b.door_pos);
+            match door.and_then(|pos| self.nearest_lane(pos, LaneKind::Driving)) {
+                Some(lane) => self.parking.set_lot_entrance(b, lane),
+                None => self.parking.remove_lot_spots(b),
+            }
+        }
+        let lanes = &self.lanes;
+        self.parking
+            .retain_spots(|spot| lanes.contains_key(spot.parent));
+
+        let lonely: Vec<_> = self
+            .intersections
+            .values()
+            .filter(|i| i.roads.is_empty())
+            .map(|i| i.id)
+            .collect();
+        for id in lonely {
+            self.remove_intersection_inner(id);
+        }
+
+        self.dirt_id += Wrapping(1);
+
+        let left = self.validate();
+        info!(
+            "repaired {} map problems, {} left",
+            problems.len().saturating_sub(left.len()),
+            left.len()
+        );
+        left
+    }
+
+    /// Lanes of this kind of path that are connected to each other through turns,
+    /// whatever their direction
+    pub(crate) fn lane_components(&self, kind: PathKind) -> Vec<Vec<LaneID>> {
+        let lane_kind = match kind {
+            PathKind::Vehicle => LaneKind::Driving,
+            PathKind::Pedestrian => LaneKind::Walking,
+        };
+        let starts: Vec<LaneID> = self
+            .lanes
+            .values()
+            .filter(|l| l.kind == lane_kind)
+            .map(|l| l.id)
+            .collect();
+
+        let neighbours = |&id: &LaneID| {
+            let lane = self.lanes.get(id);
+            lane.into_iter()
+                .flat_map(|l| vec![l.src, l.dst])
+                .filter_map(|i| self.intersections.get(i))
+                .flat_map(move |inter| inter.turns_from(id))
+                .map(move |(turn, _)| if turn.src == id { turn.dst } else { turn.src })
+                .filter(|l| self.lanes.contains_key(*l))
+                .collect::<Vec<_>>()
+        };
+
+        connected_components(&starts, neighbours)
+            .into_iter()
+            .map(|comp| {
+                let mut comp: Vec<_> = comp.into_iter().collect();
+                comp.sort();
+                comp
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{LanePatternBuilder, MapProject, RoadID};
+    use geom::{vec2, Vec2};
+    use slotmap::Key;
+
+    #[test]
+    fn validate_and_repair() {
+        let mut m = Map::empty();
+        let ground = MapProject::ground;
+        let pat = LanePatternBuilder::new().parking(false).build();
+        let (_, r) = m
+            .make_connection(ground(Vec2::ZERO), ground(vec2(100.0, 0.0)), None, &pat)
+            .unwrap();
+        m.make_connection(
+            ground(vec2(0.0, 300.0)),
+            ground(vec2(100.0, 300.0)),
+            None,
+            &pat,
+        );
+        assert!(m.validate().iter().all(|p| !p.repairable()));
+
+        // corrupt the map
+        let lane = |kind| m.roads[r].lanes_iter().find(|(_, k)| *k == kind).unwrap().0;
+        let (driving, walking) = (lane(LaneKind::Driving), lane(LaneKind::Walking));
+        m.lanes[driving].parent = RoadID::null();
+        m.lanes.remove(walking);
+        m.add_intersection(vec2(500.0, 500.0));
+
+        let problems = m.validate();
+        assert!(problems.contains(&MapProblem::DanglingLane(driving)));
+        assert!(problems
+            .iter()
+            .any(|p| matches!(p, MapProblem::MissingTurnLane(_))));
+        assert!(problems
+            .iter()
+            .any(|p| matches!(p, MapProblem::LonelyIntersection(_))));
+
+        let left = m.repair();
+        assert!(left.iter().all(|p| !p.repairable()));
+        m.check_invariants();
+    }
+}
@@ -16,7 +16,7 @@ use crate::context::Context;
 use crate::gui::windows::debug::DebugObjs;
 use crate::gui::windows::network::NetworkConnectionInfo;
 use crate::gui::windows::settings::Settings;
-use crate::gui::{FollowEntity, Gui, UiTextures};
+use crate::gui::{FollowEntity, Gui, MapHistory, UiTextures};
 use crate::input::{KeyCode, KeyboardInfo, MouseInfo};
 use crate::network::NetworkState;
 use crate::rendering::imgui_wrapper::ImguiWrapper;
@@ -24,7 +24,7 @@ use crate::rendering::{CameraHandler3D, InstancedRender, RoadRenderer, TerrainRe
 use crate::uiworld::{ReceivedCommands, UiWorld};
 use common::saveload::Encoder;
 use common::timestep::Timestep;
-use egregoria::engine_interaction::WorldCommands;
+use egregoria::engine_interaction::{LastUndo, WorldCommand, WorldCommands};
 use egregoria::utils::scheduler::SeqSchedule;
 use networking::{Frame, PollResult, ServerPollResult};
 use std::convert::{TryFrom, TryInto};
@@ -74,6 +74,8 @@ impl State {
             Self::manage_settings(ctx, &s);
         }
 
+        let terrain = TerrainRender::new(&mut ctx.gfx, &goria.map());
+
         Self {
             uiw: uiworld,
             game_schedule,
@@ -81,7 +83,7 @@ impl State {
             imgui_render,
             instanced_renderer: InstancedRender::new(&mut ctx.gfx),
             road_renderer: RoadRenderer::new(&mut ctx.gfx, &goria),
-            terrain: TerrainRender::new(&mut ctx.gfx),
+            terrain,
             gui,
             all_audio: GameAudio::new(&mut ctx.audio),
             light: LightRender::new(&mut ctx.gfx),
@@ -111,8 +113,14 @@ impl State {
                 let mut commands_once = Some(commands.clone());
                 step.prepare_frame(settings.time_warp);
                 while step.tick() || (has_commands && commands_once.is_some()) {
-                    let t = goria.tick(sched, &commands_once.take().unwrap_or_default());
+                    let applied = commands_once.take();
+                    let t = goria.tick(sched, applied.as_ref().unwrap_or(&Default::default()));
                     timings.world_update.add_value(t.as_secs_f32());
+                    if let Some(applied) = applied {
+                        self.uiw
+                            .write::<MapHistory>()
+                            .record(applied.iter().zip(&goria.read::<LastUndo>().0));
+                    }
                 }
 
                 if commands_once.is_none() {
@@ -152,6 +160,7 @@ impl State {
                 PollResult::GameWorld(commands, prepared_goria) => {
                     if let Ok(x) = prepared_goria.try_into() {
                         self.goria = x;
+                        self.uiw.write::<MapHistory>().clear();
                     } else {
                         log::error!("couldn't decode serialized goria sent by server");
                         *net_state = NetworkState::Singleplayer(Timestep::default());
@@ -176,6 +185,21 @@ impl State {
                     .map(|x| x.inp.clone())
                     .collect();
                 let t = self.goria.tick(&mut self.game_schedule, &commands);
+                let mine = frame_commands
+                    .inputs
+                    .iter()
+                    .flat_map(|x| x.inp.iter().map(move |_| x.sent_by_me));
+                self.uiw.write::<MapHistory>().record(
+                    commands
+                        .iter()
+                        .zip(&self.goria.read::<LastUndo>().0)
+                        .zip(mine)
+                        // a reset sent by anyone replaces the world
+                        .filter(|((command, _), mine)| {
+                            *mine || matches!(command, WorldCommand::ResetSave)
+                        })
+                        .map(|(x, _)| x),
+                );
                 self.uiw
                     .write::<Timings>()
                     .world_update
@@ -239,7 +263,7 @@ impl State {
     pub fn render(&mut self, ctx: &mut FrameContext) {
         let start = Instant::now();
 
-        self.terrain.render(&self.uiw, ctx);
+        self.terrain.render(&self.goria.map(), ctx);
 
         self.immtess.meshbuilder.clear();
         self.camera.cull_tess(&mut self.immtess);
@@ -0,0 +1,155 @@
+use super::Tool;
+use crate::input::{KeyCode, KeyboardInfo, MouseButton, MouseInfo};
+use crate::rendering::immediate::{ImmediateDraw, ImmediateSound};
+use crate::uiworld::UiWorld;
+use common::{AudioKind, Z_TOOL};
+use egregoria::Egregoria;
+use geom::{Vec2, AABB, OBB};
+use imgui::ImString;
+use map_model::Blueprint;
+use std::path::PathBuf;
+
+/// Directory where the blueprints are saved
+pub const BLUEPRINTS_DIR: &str = "blueprints";
+
+register_resource_noserialize!(BlueprintResource);
+pub struct BlueprintResource {
+    /// The captured blueprint, the tool pastes it when there is one and captures one otherwise
+    pub blueprint: Option<Blueprint>,
+    /// Corner where the selection started
+    pub selection_start: Option<Vec2>,
+    /// Rotation of the pasted blueprint, in degrees
+    pub rotation: f32,
+    pub name: ImString,
+}
+
+impl BlueprintResource {
+    pub fn path(&self) -> PathBuf {
+        PathBuf::from(BLUEPRINTS_DIR).join(format!("{}.json", self.name.to_str()))
+    }
+
+    pub fn save(&self) {
+        let bp = unwrap_ret!(&self.blueprint);
+        let _ = std::fs::create_dir(BLUEPRINTS_DIR);
+        let path = self.path();
+        match bp.save(&path) {
+            Ok(_) => log::info!("saved blueprint to {}", path.display()),
+            Err(e) => log::error!("could not save blueprint {}: {}", path.display(), e),
+        }
+    }
+
+    pub fn load(&mut self) {
+        let path = self.path();
+        match Blueprint::load(&path) {
+            Ok(bp) => self.blueprint = Some(bp),
+            Err(e) => log::error!("could not load blueprint {}: {}", path.display(), e),
+        }
+    }
+
+    /// Names of the saved blueprints
+    pub fn saved() -> Vec<String> {
+        let dir = unwrap_or!(std::fs::read_dir(BLUEPRINTS_DIR).ok(), return vec![]);
+        let mut names: Vec<String> = dir
+            .filter_map(|e| e.ok())
+            .filter_map(|e| {
+                let name = e.file_name().into_string().ok()?;
+                Some(name.strip_suffix(".json")?.to_string())
+            })
+            .collect();
+        names.sort();
+        names
+    }
+}
+
+pub fn blueprint(goria: &Egregoria, uiworld: &mut UiWorld) {
+    let state = &mut *uiworld.write::<BlueprintResource>();
+    let map = goria.map();
+
+    let tool = *uiworld.read::<Tool>();
+    if !matches!(tool, Tool::Blueprint) {
+        state.selection_start = None;
+        return;
+    }
+
+    let mouseinfo = uiworld.read::<MouseInfo>();
+    let kbinfo = uiworld.read::<KeyboardInfo>();
+    let mut draw = uiworld.write::<ImmediateDraw>();
+    let mut sound = uiworld.write::<ImmediateSound>();
+    let commands = &mut *uiworld.commands();
+
+    let mpos = mouseinfo.unprojected;
+    let mut col = common::config().gui_primary;
+
+    if mouseinfo.just_pressed.contains(&MouseButton::Right) {
+        state.blueprint = None;
+        state.selection_start = None;
+    }
+
+    let bp = match state.blueprint {
+        Some(ref bp) => bp,
+        None => {
+            // select the area to capture
+            let start = match state.selection_start {
+                Some(start) => start,
+                None => {
+                    if mouseinfo.just_pressed.contains(&MouseButton::Left) {
+                        state.selection_start = Some(mpos);
+                    }
+                    return;
+                }
+            };
+            let area = AABB::new(start.min(mpos), start.max(mpos));
+
+            col.a = 0.2;
+            draw.obb(OBB::from(&area)).color(col).z(Z_TOOL);
+
+            if !mouseinfo.pressed.contains(&MouseButton::Left) {
+                state.selection_start = None;
+                let bp = Blueprint::capture(&map, area);
+                if !bp.is_empty() {
+                    state.blueprint = Some(bp);
+                }
+            }
+            return;
+        }
+    };
+
+    if kbinfo.just_pressed.contains(&KeyCode::R) {
+        state.rotation = (state.rotation + 90.0) % 360.0;
+    }
+
+    let layout = bp.placed(mpos, Vec2::from_angle(state.rotation.to_radians()));
+
+    col.a = 0.5;
+    for road in &layout.roads {
+        let (src, dst) = match (
+            layout.intersections.get(road.src),
+            layout.intersections.get(road.dst),
+        ) {
+            (Some(src), Some(dst)) => (src.pos, dst.pos),
+            _ => continue,
+        };
+        draw.polyline(road.segment.points(src, dst), road.pattern.width())
+            .color(col)
+            .z(Z_TOOL);
+    }
+    for b in &layout.buildings {
+        draw.obb(b.obb).color(col).z(Z_TOOL);
+    }
+
+    if mouseinfo.just_pressed.contains(&MouseButton::Left) {
+        sound.play("road_lay", AudioKind::Ui);
+        commands.map_paste_blueprint(layout);
+    }
+}
+
+impl Default for BlueprintResource {
+    fn default() -> Self {
+        Self {
+            blueprint: None,
+            selection_start: None,
+            rotation: 0.0,
+            name: ImString::with_capacity(64),
+        }
+    }
+}
@@ -61,7 +61,7 @@ pub fn bulldozer(goria: &Egregoria, uiworld: &mut UiWorld) {
             ProjectKind::Building(id) => {
                 commands.map_remove_building(id);
             }
-            ProjectKind::Ground | ProjectKind::Lot(_) => {}
+            ProjectKind::Ground | ProjectKind::Lot(_) | ProjectKind::Water(_) => {}
         }
 
         for id in potentially_empty {
@@ -0,0 +1,89 @@
+use crate::input::{KeyCode, KeyboardInfo};
+use crate::uiworld::UiWorld;
+use egregoria::engine_interaction::{WorldCommand, WorldCommands};
+
+/// Maximum number of actions that can be undone, or redone
+const MAX_HISTORY: usize = 100;
+
+register_resource_noserialize!(MapHistory);
+/// Undo and redo stacks of the map edits of this player.
+/// Each entry holds the commands undoing (or redoing) one action.
+#[derive(Default)]
+pub struct MapHistory {
+    undo: Vec<Vec<WorldCommand>>,
+    redo: Vec<Vec<WorldCommand>>,
+}
+
+impl MapHistory {
+    /// Records the commands sent by this player during a frame and the commands undoing them.
+    /// The commands sent together, like the ones of a blueprint paste, are undone together.
+    /// Resetting the save forgets everything done before.
+    pub fn record<'a>(
+        &mut self,
+        applied: impl Iterator<Item = (&'a WorldCommand, &'a Vec<WorldCommand>)>,
+    ) {
+        let mut action = vec![];
+        for (command, undo) in applied {
+            match (command, undo.as_slice()) {
+                (WorldCommand::ResetSave, _) => {
+                    action.clear();
+                    self.clear();
+                }
+                // refused, it can be tried again
+                (WorldCommand::Undo(undo), []) => self.undo.push(undo.clone()),
+                (WorldCommand::Redo(redo), []) => self.redo.push(redo.clone()),
+                (_, []) => {}
+                (WorldCommand::Undo(_), [WorldCommand::Redo(redo)]) => self.redo.push(redo.clone()),
+                (WorldCommand::Redo(_), [WorldCommand::Undo(undo)]) => self.undo.push(undo.clone()),
+                _ => action.push(undo.clone()),
+            }
+        }
+        if !action.is_empty() {
+            // the last command applied must be the first one undone
+            self.undo.push(action.into_iter().rev().flatten().collect());
+            self.redo.clear();
+        }
+        for stack in &mut [&mut self.undo, &mut self.redo] {
+            if stack.len() > MAX_HISTORY {
+                let excess = stack.len() - MAX_HISTORY;
+                stack.drain(..excess);
+            }
+        }
+    }
+
+    /// Forgets every action, the world they were done in was replaced
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+
+    pub fn undo(&mut self, commands: &mut WorldCommands) {
+        if let Some(undo) = self.undo.pop() {
+            commands.undo(undo);
+        }
+    }
+
+    pub fn redo(&mut self, commands: &mut WorldCommands) {
+        if let Some(redo) = self.redo.pop() {
+            commands.redo(redo);
+        }
+    }
+}
+
+pub fn history(uiworld: &mut UiWorld) {
+    let kb = uiworld.read::<KeyboardInfo>();
+    let ctrl = kb.pressed.contains(&KeyCode::LControl) || kb.pressed.contains(&KeyCode::RControl);
+    if !ctrl {
+        return;
+    }
+    let undo = kb.just_pressed.contains(&KeyCode::Z);
+    let redo = kb.just_pressed.contains(&KeyCode::Y);
+    drop(kb);
+
+    let mut history = uiworld.write::<MapHistory>();
+    if undo {
+        history.undo(&mut *uiworld.commands());
+    } else if redo {
+        history.redo(&mut *uiworld.commands());
+    }
+}
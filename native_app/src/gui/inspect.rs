@@ -1,13 +1,19 @@
 use crate::gui::follow::FollowEntity;
 use crate::uiworld::UiWorld;
-use egregoria::economy::{Market, Workers};
+use egregoria::economy::{Ledger, Market, Money, Occupation, Workers};
 use egregoria::map_dynamic::{Itinerary, Router};
 use egregoria::pedestrians::{Location, Pedestrian};
 use egregoria::physics::{Collider, Kinematics};
 use egregoria::rendering::assets::AssetRender;
-use egregoria::souls::desire::{BuyFood, Home, Work};
+use egregoria::souls::desire::{
+    BuyFood, BuyGoods, Desire, Healthcare, Home, Leisure, OwnCar, School, Work,
+};
 use egregoria::souls::goods_company::GoodsCompany;
+use egregoria::souls::household::HouseholdMember;
 use egregoria::souls::human::HumanDecision;
+use egregoria::souls::needs::Needs;
+use egregoria::souls::schedule::Agenda;
+use egregoria::souls::services::{PublicService, ServiceVehicle};
 use egregoria::vehicles::{Vehicle, VehicleID, VehicleState};
 use egregoria::{Egregoria, SoulID};
 use geom::Transform;
@@ -31,7 +37,11 @@ impl InspectRenderer {
         if let Some(x) = c {
             <T as InspectRenderDefault<T>>::render(
                 &[x],
-                std::any::type_name::<T>().split("::").last().unwrap_or(""),
+                std::any::type_name::<T>()
+                    .split("::")
+                    .last()
+                    .unwrap_or("")
+                    .trim_end_matches('>'),
                 ui,
                 &InspectArgsDefault::default(),
             )
@@ -65,11 +75,23 @@ impl InspectRenderer {
         self.inspect_component::<Itinerary>(goria, ui);
         self.inspect_component::<Router>(goria, ui);
         self.inspect_component::<HumanDecision>(goria, ui);
+        self.inspect_component::<Needs>(goria, ui);
+        self.inspect_component::<HouseholdMember>(goria, ui);
+        self.inspect_component::<Agenda>(goria, ui);
+        self.inspect_component::<Occupation>(goria, ui);
         self.inspect_component::<Workers>(goria, ui);
-        self.inspect_component::<Work>(goria, ui);
-        self.inspect_component::<Home>(goria, ui);
-        self.inspect_component::<BuyFood>(goria, ui);
+        self.inspect_component::<Desire<Work>>(goria, ui);
+        self.inspect_component::<Desire<Home>>(goria, ui);
+        self.inspect_component::<Desire<BuyFood>>(goria, ui);
+        self.inspect_component::<Desire<OwnCar>>(goria, ui);
+        self.inspect_component::<Desire<Leisure>>(goria, ui);
+        self.inspect_component::<Desire<BuyGoods>>(goria, ui);
+        self.inspect_component::<Desire<Healthcare>>(goria, ui);
+        self.inspect_component::<Desire<School>>(goria, ui);
         self.inspect_component::<GoodsCompany>(goria, ui);
+        self.inspect_component::<Ledger>(goria, ui);
+        self.inspect_component::<PublicService>(goria, ui);
+        self.inspect_component::<ServiceVehicle>(goria, ui);
 
         if let Some(v) = goria.comp::<Vehicle>(self.entity) {
             if matches!(v.state, VehicleState::Driving | VehicleState::Panicking(_)) {
@@ -97,6 +119,11 @@ impl InspectRenderer {
         }
 
         let market = goria.read::<Market>();
+        let money = market.money(SoulID(self.entity));
+        if money != Money::ZERO {
+            ui.text(im_str!("Money: {}", money));
+        }
+
         let mut capitals = vec![];
         for (kind, market) in market.inner() {
             let cap = unwrap_or!(market.capital(SoulID(self.entity)), continue);
@@ -11,32 +11,42 @@ use geom::{Camera, AABB};
 use roadbuild::RoadBuildResource;
 use wgpu_engine::GfxContext;
 
+mod blueprint;
 mod bulldozer;
 mod follow;
+mod history;
 mod inspect;
 mod inspected_aura;
 mod lotbrush;
+mod overlay;
 mod roadbuild;
 mod roadeditor;
 mod selectable;
 mod specialbuilding;
+mod terraform;
 mod topgui;
 
 pub mod windows;
 
 pub use follow::FollowEntity;
+pub use history::MapHistory;
 pub use inspect::*;
+pub use overlay::Overlay;
 pub use topgui::*;
 
 pub fn run_ui_systems(goria: &Egregoria, uiworld: &mut UiWorld) {
+    blueprint::blueprint(goria, uiworld);
     bulldozer::bulldozer(goria, uiworld);
     inspected_aura::inspected_aura(goria, uiworld);
     lotbrush::lotbrush(goria, uiworld);
+    overlay::overlay(goria, uiworld);
     roadbuild::roadbuild(goria, uiworld);
     roadeditor::roadeditor(goria, uiworld);
     selectable::selectable(goria, uiworld);
     specialbuilding::specialbuilding(goria, uiworld);
+    terraform::terraform(goria, uiworld);
     hand_reset(uiworld);
+    history::history(uiworld);
 
     let eye = uiworld.read::<Camera>().pos;
     let cam = AABB::new(eye, eye).expand(2000.0);
@@ -78,6 +88,8 @@ pub enum Tool {
     Bulldozer,
     LotBrush,
     SpecialBuilding,
+    Terraform,
+    Blueprint,
 }
 
 #[derive(Copy, Clone, Hash, Eq, PartialEq)]
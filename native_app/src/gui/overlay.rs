@@ -0,0 +1,104 @@
+use crate::rendering::immediate::ImmediateDraw;
+use crate::uiworld::UiWorld;
+use common::Z_TOOL_BG;
+use egregoria::map_dynamic::{cell_center, Cell, Environment, CELL_SIZE};
+use egregoria::Egregoria;
+use geom::{vec2, Camera, Color, AABB, OBB};
+use map_model::{Connectivity, NetworkAnalysis};
+
+register_resource_noserialize!(Overlay);
+/// Field of the map drawn on top of everything
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum Overlay {
+    None,
+    Pollution,
+    Noise,
+    /// Parts of the road network that agents cannot route to or from
+    Connectivity,
+}
+
+register_resource_noserialize!(NetworkAnalysisCache);
+/// Analysis of the network for the dirt id of the map it was made for
+#[derive(Default)]
+pub struct NetworkAnalysisCache(Option<(u32, NetworkAnalysis)>);
+
+impl Default for Overlay {
+    fn default() -> Self {
+        Overlay::None
+    }
+}
+
+pub fn overlay(goria: &Egregoria, uiworld: &mut UiWorld) {
+    let env = goria.read::<Environment>();
+    let (field, value, col): (_, fn(&Environment, Cell) -> f32, _) =
+        match *uiworld.read::<Overlay>() {
+            Overlay::None => return,
+            Overlay::Connectivity => return connectivity(goria, uiworld),
+            Overlay::Pollution => (
+                &env.pollution,
+                Environment::pollution_at,
+                Color::new(0.5, 0.3, 0.1, 1.0),
+            ),
+            Overlay::Noise => (
+                &env.noise,
+                Environment::noise_at,
+                Color::new(0.8, 0.1, 0.6, 1.0),
+            ),
+        };
+
+    let cam = *uiworld.read::<Camera>();
+    let visible = AABB::new(cam.pos, cam.pos).expand(cam.dist * 2.0);
+
+    let mut draw = uiworld.write::<ImmediateDraw>();
+
+    for (cell, _) in field.cells() {
+        let center = cell_center(cell);
+        if !visible.contains(center) {
+            continue;
+        }
+        draw.obb(OBB::new(center, vec2(1.0, 0.0), CELL_SIZE, CELL_SIZE))
+            .color(col.a(0.6 * value(&env, cell)))
+            .z(Z_TOOL_BG);
+    }
+}
+
+/// Draws the lanes outside of the main components in their component colour,
+/// the dead ends in red and the unreachable buildings in purple
+fn connectivity(goria: &Egregoria, uiworld: &mut UiWorld) {
+    let map = goria.map();
+    let mut cache = uiworld.write::<NetworkAnalysisCache>();
+    if !matches!(cache.0, Some((dirt_id, _)) if dirt_id == map.dirt_id.0) {
+        cache.0 = Some((map.dirt_id.0, map.analyze_network()));
+    }
+    let analysis = unwrap_ret!(cache.0.as_ref().map(|(_, a)| a));
+
+    let mut draw = uiworld.write::<ImmediateDraw>();
+    let lanes = map.lanes();
+
+    let mut draw_connectivity = |c: &Connectivity| {
+        for (i, comp) in c.components.iter().enumerate().skip(1) {
+            let r = common::rand::randu(i as u32);
+            let col = Color::hsv(r * 360.0, 0.8, 0.6, 0.8);
+            for lane in comp.iter().filter_map(|&id| lanes.get(id)) {
+                draw.polyline(lane.points.as_slice(), lane.kind.width())
+                    .color(col)
+                    .z(Z_TOOL_BG);
+            }
+        }
+        for lane in c.dead_ends.iter().filter_map(|&id| lanes.get(id)) {
+            draw.polyline(lane.points.as_slice(), lane.kind.width())
+                .color(Color::RED)
+                .z(Z_TOOL_BG);
+        }
+    };
+    draw_connectivity(&analysis.vehicle);
+    draw_connectivity(&analysis.pedestrian);
+
+    for b in analysis
+        .unreachable_buildings
+        .iter()
+        .filter_map(|&id| map.buildings().get(id))
+    {
+        draw.obb(b.obb).color(Color::PURPLE.a(0.6)).z(Z_TOOL_BG);
+    }
+}
@@ -7,9 +7,9 @@ use egregoria::engine_interaction::{WorldCommand, WorldCommands};
 use egregoria::Egregoria;
 use geom::{vec2, Vec2, AABB};
 use geom::{Camera, Spline};
-use map_model::{LanePatternBuilder, Map, MapProject, ProjectKind};
+use map_model::{LanePatternBuilder, Map, MapProject, ProjectKind, MAX_ROAD_SLOPE};
 use BuildState::{Hover, Interpolation, Start};
-use ProjectKind::{Building, Ground, Inter, Road};
+use ProjectKind::{Building, Ground, Inter, Road, Water};
 
 const MAX_TURN_ANGLE: f32 = 30.0 * std::f32::consts::PI / 180.0;
 
@@ -32,6 +32,8 @@ pub struct RoadBuildResource {
     pub build_state: BuildState,
     pub pattern_builder: LanePatternBuilder,
     pub snap_to_grid: bool,
+    /// Build bridges, which may cross water
+    pub bridge: bool,
 }
 
 pub fn roadbuild(goria: &Egregoria, uiworld: &mut UiWorld) {
@@ -87,7 +89,9 @@ pub fn roadbuild(goria: &Egregoria, uiworld: &mut UiWorld) {
             *uiworld.read::<Tool>(),
             Tool::RoadbuildCurved | Tool::RoadbuildStraight
         ) {
-            if let WorldCommand::MapMakeConnection(_, to, _, _) = command {
+            if let WorldCommand::MapMakeConnection(_, to, _, _)
+            | WorldCommand::MapMakeBridge(_, to, _, _) = command
+            {
                 let proj = map.project(to.pos, 0.0);
                 if matches!(proj.kind, ProjectKind::Inter(_)) {
                     state.build_state = BuildState::Start(proj);
@@ -129,12 +133,20 @@ pub fn roadbuild(goria: &Egregoria, uiworld: &mut UiWorld) {
         }
     }
 
+    // roads must go around water, bridges are flat and may cross it
+    let terrain_ok = |points: &[Vec2]| {
+        state.bridge
+            || (!map.crosses_water(points, patwidth)
+                && map.heightmap.max_slope(points) <= MAX_ROAD_SLOPE)
+    };
+
     let is_valid = match (state.build_state, cur_proj.kind) {
-        (Hover, Building(_)) => false,
+        (Hover, Building(_)) | (Hover, Water(_)) => false,
         (Start(selected_proj), _) => {
             compatible(map, cur_proj.kind, selected_proj.kind)
                 && check_angle(map, selected_proj, cur_proj.pos)
                 && check_angle(map, cur_proj, selected_proj.pos)
+                && terrain_ok(&[selected_proj.pos, cur_proj.pos])
         }
         (Interpolation(interpoint, selected_proj), _) => {
             let sp = Spline {
@@ -148,6 +160,7 @@ pub fn roadbuild(goria: &Egregoria, uiworld: &mut UiWorld) {
                 && check_angle(map, selected_proj, interpoint)
                 && check_angle(map, cur_proj, interpoint)
                 && !sp.is_steep(state.pattern_builder.width())
+                && terrain_ok(&sp.smart_points(1.0, 0.0, 1.0).collect::<Vec<_>>())
         }
         _ => true,
     };
@@ -174,24 +187,14 @@ pub fn roadbuild(goria: &Egregoria, uiworld: &mut UiWorld) {
             (Start(selected_proj), _, _) => {
                 // Straight connection to something
                 immsound.play("road_lay", AudioKind::Ui);
-                commands.map_make_connection(
-                    selected_proj,
-                    cur_proj,
-                    None,
-                    state.pattern_builder.build(),
-                );
+                state.make_connection(commands, selected_proj, cur_proj, None);
 
                 state.build_state = Hover;
             }
             (Interpolation(interpoint, selected_proj), _, _) => {
                 // Interpolated connection to something
                 immsound.play("road_lay", AudioKind::Ui);
-                commands.map_make_connection(
-                    selected_proj,
-                    cur_proj,
-                    Some(interpoint),
-                    state.pattern_builder.build(),
-                );
+                state.make_connection(commands, selected_proj, cur_proj, Some(interpoint));
 
                 state.build_state = Hover;
             }
@@ -246,6 +249,21 @@ fn compatible(map: &Map, x: ProjectKind, y: ProjectKind) -> bool {
 }
 
 impl RoadBuildResource {
+    fn make_connection(
+        &self,
+        commands: &mut WorldCommands,
+        from: MapProject,
+        to: MapProject,
+        interpoint: Option<Vec2>,
+    ) {
+        let pat = self.pattern_builder.build();
+        if self.bridge {
+            commands.map_make_bridge(from, to, interpoint, pat);
+        } else {
+            commands.map_make_connection(from, to, interpoint, pat);
+        }
+    }
+
     pub fn update_drawing(
         &self,
         immdraw: &mut ImmediateDraw,
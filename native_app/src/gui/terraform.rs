@@ -0,0 +1,71 @@
+use super::Tool;
+use crate::input::{MouseButton, MouseInfo};
+use crate::rendering::immediate::ImmediateDraw;
+use crate::uiworld::UiWorld;
+use common::Z_TOOL;
+use egregoria::Egregoria;
+use map_model::TerraformKind;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+/// Time between two strokes of the brush while the mouse is held
+const STROKE_INTERVAL: Duration = Duration::from_millis(50);
+
+register_resource!(TerraformResource, "terraform");
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct TerraformResource {
+    pub kind: TerraformKind,
+    pub radius: f32,
+    /// Height change at the center of the brush for each stroke
+    pub strength: f32,
+    #[serde(skip)]
+    pub last_stroke: Option<Instant>,
+}
+
+pub fn terraform(goria: &Egregoria, uiworld: &mut UiWorld) {
+    let tool = *uiworld.read::<Tool>();
+    if !matches!(tool, Tool::Terraform) {
+        return;
+    }
+
+    let mut res = uiworld.write::<TerraformResource>();
+    let mouseinfo = uiworld.read::<MouseInfo>();
+    let mut draw = uiworld.write::<ImmediateDraw>();
+    let commands = &mut *uiworld.commands();
+
+    let mpos = mouseinfo.unprojected;
+    let mut col = common::config().gui_primary;
+    col.a = 0.2;
+    draw.circle(mpos, res.radius).color(col).z(Z_TOOL);
+
+    if mouseinfo.just_pressed.contains(&MouseButton::Left) {
+        // flatten to the height where the stroke starts
+        if let TerraformKind::Flatten(_) = res.kind {
+            res.kind = TerraformKind::Flatten(goria.map().heightmap.height(mpos));
+        }
+        res.last_stroke = None;
+    }
+
+    if !mouseinfo.pressed.contains(&MouseButton::Left) {
+        return;
+    }
+
+    if matches!(res.last_stroke, Some(t) if t.elapsed() < STROKE_INTERVAL) {
+        return;
+    }
+    res.last_stroke = Some(Instant::now());
+
+    commands.map_terraform(mpos, res.radius, res.kind, res.strength);
+}
+
+impl Default for TerraformResource {
+    fn default() -> Self {
+        Self {
+            kind: TerraformKind::Raise,
+            radius: 100.0,
+            strength: 0.0005,
+            last_stroke: None,
+        }
+    }
+}
@@ -1,24 +1,37 @@
+use crate::gui::blueprint::BlueprintResource;
 use crate::gui::bulldozer::BulldozerState;
 use crate::gui::lotbrush::LotBrushResource;
 use crate::gui::roadeditor::RoadEditorResource;
 use crate::gui::specialbuilding::SpecialBuildingResource;
+use crate::gui::terraform::TerraformResource;
 use crate::gui::windows::settings::Settings;
 use crate::gui::windows::ImguiWindows;
-use crate::gui::{InspectedEntity, RoadBuildResource, Tool, UiTex, UiTextures};
+use crate::gui::{InspectedEntity, Overlay, RoadBuildResource, Tool, UiTex, UiTextures};
 use crate::input::{KeyCode, KeyboardInfo};
 use crate::uiworld::UiWorld;
 use common::saveload::Encoder;
+use egregoria::economy::Treasury;
 use egregoria::souls::goods_company::GoodsCompanyRegistry;
+use egregoria::souls::services::service_description;
 use egregoria::utils::time::GameTime;
 use egregoria::Egregoria;
 use imgui::{im_str, StyleColor, StyleVar, Ui, Window};
 use imgui_inspect::{
     InspectArgsDefault, InspectArgsStruct, InspectRenderDefault, InspectRenderStruct,
 };
-use map_model::{LanePatternBuilder, LightPolicy, LotKind, TurnPolicy};
+use map_model::procgen::parking_lot_spots;
+use map_model::{
+    BuildingGen, BuildingKind, LanePatternBuilder, LightPolicy, LotKind, ServiceKind,
+    TerraformKind, TurnPolicy, MAX_TERRAFORM_RADIUS,
+};
 use serde::{Deserialize, Serialize};
 use std::time::{Duration, Instant};
 
+const PARKING_LOT_SIZE: f32 = 50.0;
+const PARK_SIZE: f32 = 60.0;
+/// Refused commands are shown in the menu bar for 5 seconds
+const REFUSAL_DISPLAY_TICKS: u32 = 100;
+
 #[derive(Serialize, Deserialize)]
 #[serde(default)]
 pub struct Gui {
@@ -139,6 +152,28 @@ impl Gui {
                     }
                     tok.pop(ui);
                 }
+
+                let tok =
+                    ui.push_style_var(StyleVar::Alpha(if matches!(cur_tool, Tool::Terraform) {
+                        1.0
+                    } else {
+                        0.6
+                    }));
+                if ui.button(im_str!("Terrain"), [toolbox_w, 30.0]) {
+                    *cur_tool = Tool::Terraform;
+                }
+                tok.pop(ui);
+
+                let tok =
+                    ui.push_style_var(StyleVar::Alpha(if matches!(cur_tool, Tool::Blueprint) {
+                        1.0
+                    } else {
+                        0.6
+                    }));
+                if ui.button(im_str!("Blueprint"), [toolbox_w, 30.0]) {
+                    *cur_tool = Tool::Blueprint;
+                }
+                tok.pop(ui);
             });
 
         let spacing_left = ui.push_style_var(StyleVar::WindowPadding([4.0, 4.0]));
@@ -204,6 +239,7 @@ impl Gui {
                 .build(ui, || {
                     let mut roadbuild = uiworld.write::<RoadBuildResource>();
                     ui.checkbox(im_str!("snap to grid"), &mut roadbuild.snap_to_grid);
+                    ui.checkbox(im_str!("bridge"), &mut roadbuild.bridge);
                     let pat = &mut roadbuild.pattern_builder;
 
                     if ui.button(im_str!("Street"), [rbw, 30.0]) {
@@ -282,7 +318,12 @@ impl Gui {
                 });
         }
 
-        let brushes = [(im_str!("Residential"), LotKind::Residential)];
+        let brushes = [
+            (im_str!("Residential"), LotKind::Residential),
+            (im_str!("Commercial"), LotKind::Commercial),
+            (im_str!("Industrial"), LotKind::Industrial),
+            (im_str!("Unassigned"), LotKind::Unassigned),
+        ];
 
         if matches!(*uiworld.read::<Tool>(), Tool::LotBrush) {
             let lbw = 130.0;
@@ -326,6 +367,112 @@ impl Gui {
                 });
         }
 
+        let terraform_brushes = [
+            (im_str!("Raise"), TerraformKind::Raise),
+            (im_str!("Lower"), TerraformKind::Lower),
+            (im_str!("Flatten"), TerraformKind::Flatten(0.0)),
+            (im_str!("Smooth"), TerraformKind::Smooth),
+        ];
+
+        if matches!(*uiworld.read::<Tool>(), Tool::Terraform) {
+            let tbw = 130.0;
+            Window::new(im_str!("Terrain"))
+                .size(
+                    [tbw, 80.0 + terraform_brushes.len() as f32 * 35.0],
+                    imgui::Condition::Always,
+                )
+                .position(
+                    [w - toolbox_w - tbw, h * 0.5 - 30.0],
+                    imgui::Condition::Always,
+                )
+                .scroll_bar(false)
+                .title_bar(true)
+                .movable(false)
+                .collapsible(false)
+                .resizable(false)
+                .build(ui, || {
+                    let mut res = uiworld.write::<TerraformResource>();
+
+                    for (name, brush) in &terraform_brushes {
+                        let tok = ui.push_style_var(StyleVar::Alpha(
+                            if std::mem::discriminant(brush) == std::mem::discriminant(&res.kind) {
+                                1.0
+                            } else {
+                                0.5
+                            },
+                        ));
+                        if ui.button(name, [tbw, 35.0]) {
+                            res.kind = *brush;
+                        }
+                        tok.pop(ui);
+                    }
+
+                    imgui::Drag::new(im_str!("size"))
+                        .range(25.0..=MAX_TERRAFORM_RADIUS)
+                        .display_format(im_str!("%.0f"))
+                        .build(ui, &mut res.radius);
+                    imgui::Drag::new(im_str!("strength"))
+                        .range(0.0001..=0.005)
+                        .speed(0.0001)
+                        .display_format(im_str!("%.4f"))
+                        .build(ui, &mut res.strength);
+                });
+        }
+
+        if matches!(*uiworld.read::<Tool>(), Tool::Blueprint) {
+            let bpw = 200.0;
+            Window::new(im_str!("Blueprint"))
+                .size_constraints([bpw, 0.0], [bpw, 1000.0])
+                .position(
+                    [w - toolbox_w - bpw, h * 0.5 - 30.0],
+                    imgui::Condition::Always,
+                )
+                .scroll_bar(false)
+                .title_bar(true)
+                .movable(false)
+                .collapsible(false)
+                .resizable(false)
+                .build(ui, || {
+                    let mut res = uiworld.write::<BlueprintResource>();
+
+                    match res.blueprint {
+                        Some(ref bp) => {
+                            ui.text(im_str!(
+                                "{} roads, {} buildings",
+                                bp.layout.roads.len(),
+                                bp.layout.buildings.len()
+                            ));
+                            ui.text("Click to paste, R to rotate");
+                            ui.text("Right click to clear");
+                        }
+                        None => ui.text("Drag to select an area"),
+                    }
+
+                    imgui::Slider::new(im_str!("rotation"))
+                        .range(0.0..=360.0)
+                        .display_format(im_str!("%.0f"))
+                        .build(ui, &mut res.rotation);
+
+                    ui.separator();
+                    ui.input_text(im_str!("name"), &mut res.name).build();
+                    if ui.small_button(im_str!("save")) {
+                        res.save();
+                    }
+                    ui.same_line(0.0);
+                    if ui.small_button(im_str!("load")) {
+                        res.load();
+                    }
+
+                    for name in BlueprintResource::saved() {
+                        if ui.small_button(&im_str!("{}", name)) {
+                            res.name.clear();
+                            res.name.push_str(&name);
+                            res.load();
+                        }
+                    }
+                });
+        }
+
         if matches!(*uiworld.read::<Tool>(), Tool::Bulldozer) {
             let lbw = 80.0;
             Window::new(im_str!("Bulldozer"))
@@ -380,6 +527,7 @@ impl Gui {
 
                     let cur_kind = cur_build.opt.as_ref().unwrap().0;
 
+                    const SCROLLBAR_W: f32 = 10.0;
                     let mut picked_descr = None;
                     for descr in gbuildings {
                         let tok = ui.push_style_var(StyleVar::Alpha(if descr.bkind == cur_kind {
@@ -388,7 +536,6 @@ impl Gui {
                         } else {
                             0.5
                         }));
-                        const SCROLLBAR_W: f32 = 10.0;
                         if ui.button(
                             &im_str!("{}", descr.name),
                             [building_select_w - SCROLLBAR_W, 35.0],
@@ -403,8 +550,91 @@ impl Gui {
                         tok.pop(ui);
                     }
 
+                    let tok = ui.push_style_var(StyleVar::Alpha(
+                        if matches!(cur_kind, BuildingKind::ParkingLot) {
+                            1.0
+                        } else {
+                            0.5
+                        },
+                    ));
+                    if ui.button(
+                        im_str!("Parking lot"),
+                        [building_select_w - SCROLLBAR_W, 35.0],
+                    ) {
+                        cur_build.opt = Some((
+                            BuildingKind::ParkingLot,
+                            BuildingGen::ParkingLot,
+                            PARKING_LOT_SIZE,
+                            "assets/parking_lot.png".to_string(),
+                        ));
+                    }
+                    tok.pop(ui);
+
+                    let tok = ui.push_style_var(StyleVar::Alpha(
+                        if matches!(cur_kind, BuildingKind::Park) {
+                            1.0
+                        } else {
+                            0.5
+                        },
+                    ));
+                    if ui.button(im_str!("Park"), [building_select_w - SCROLLBAR_W, 35.0]) {
+                        cur_build.opt = Some((
+                            BuildingKind::Park,
+                            BuildingGen::Park,
+                            PARK_SIZE,
+                            "assets/park.png".to_string(),
+                        ));
+                    }
+                    tok.pop(ui);
+
+                    for &kind in ServiceKind::values() {
+                        let descr = service_description(kind);
+                        let tok = ui.push_style_var(StyleVar::Alpha(
+                            if cur_kind == BuildingKind::Service(kind) {
+                                1.0
+                            } else {
+                                0.5
+                            },
+                        ));
+                        if ui.button(
+                            &im_str!("{}", descr.name),
+                            [building_select_w - SCROLLBAR_W, 35.0],
+                        ) {
+                            cur_build.opt = Some((
+                                BuildingKind::Service(kind),
+                                BuildingGen::House,
+                                descr.size,
+                                descr.asset_location.to_string(),
+                            ));
+                        }
+                        tok.pop(ui);
+                    }
+
                     let bdescrpt_w = 180.0;
 
+                    if matches!(cur_kind, BuildingKind::ParkingLot) {
+                        Window::new(im_str!("Building description"))
+                            .size_constraints([bdescrpt_w, 10.0], [bdescrpt_w, 10000.0])
+                            .always_auto_resize(true)
+                            .position(
+                                [
+                                    w - toolbox_w - building_select_w - bdescrpt_w,
+                                    h * 0.5 - 30.0,
+                                ],
+                                imgui::Condition::Always,
+                            )
+                            .title_bar(true)
+                            .movable(false)
+                            .collapsible(false)
+                            .resizable(false)
+                            .build(ui, || {
+                                ui.text(im_str!(
+                                    "capacity: {} cars",
+                                    parking_lot_spots(PARKING_LOT_SIZE).len()
+                                ));
+                            });
+                    }
+
                     if let Some(descr) = picked_descr {
                         let tok = ui.push_style_vars(&[
                             StyleVar::WindowPadding([5.0, 5.0]),
@@ -504,7 +734,7 @@ impl Gui {
             .collapsible(false)
             .resizable(false)
             .build(ui, || {
-                ui.text(im_str!(" Day {}", time.day));
+                ui.text(im_str!(" {} Day {}", time.weekday().short_name(), time.day));
 
                 ui.same_line(115.0);
 
@@ -568,14 +798,39 @@ impl Gui {
                 uiworld.save_to_disk();
             }
 
+            ui.menu(im_str!("Overlay"), true, || {
+                let mut overlay = uiworld.write::<Overlay>();
+                ui.radio_button(im_str!("None"), &mut *overlay, Overlay::None);
+                ui.radio_button(im_str!("Pollution"), &mut *overlay, Overlay::Pollution);
+                ui.radio_button(im_str!("Noise"), &mut *overlay, Overlay::Noise);
+                ui.radio_button(
+                    im_str!("Connectivity"),
+                    &mut *overlay,
+                    Overlay::Connectivity,
+                );
+            });
+
             ui.menu(im_str!("Help"), true, || {
                 ui.text(im_str!("Pan: Right click or Arrow keys"));
                 ui.text(im_str!("Select: Left click"));
                 ui.text(im_str!("Deselect: Escape"));
                 ui.text(im_str!("Delete (use with caution): Delete"));
+                ui.text(im_str!("Undo/Redo map edits: Ctrl+Z / Ctrl+Y"));
                 ui.text(im_str!("Use the tools on the right\nto build and modify roads"));
                 ui.text(im_str!("Use the \"Map\" window to build houses\nor load prebuilt maps such as Paris\n(takes a few seconds to load)"));
             });
+
+            let treasury = goria.read::<Treasury>();
+            if treasury.sandbox {
+                ui.text(im_str!("Sandbox"));
+            } else {
+                ui.text(im_str!("{}", treasury.money));
+            }
+            if let Some(refusal) = treasury.refusals.last() {
+                if goria.get_tick() < refusal.tick + REFUSAL_DISPLAY_TICKS {
+                    ui.text_colored([1.0, 0.3, 0.3, 1.0], &refusal.reason);
+                }
+            }
         });
         t.pop(ui);
     }
@@ -0,0 +1,66 @@
+use crate::uiworld::UiWorld;
+use egregoria::economy::{CityAccounts, Treasury};
+use egregoria::Egregoria;
+use imgui::{im_str, Ui};
+
+pub fn budget(window: imgui::Window, ui: &Ui, uiworld: &mut UiWorld, goria: &Egregoria) {
+    let treasury = goria.read::<Treasury>();
+
+    window.build(ui, || {
+        ui.text(format!("Treasury: {}", treasury.money));
+
+        let mut sandbox = treasury.sandbox;
+        if ui.checkbox(im_str!("sandbox (everything is free)"), &mut sandbox) {
+            uiworld.commands().set_sandbox(sandbox);
+        }
+        ui.separator();
+
+        let mut taxes = treasury.taxes;
+        let mut income = taxes.income * 100.0;
+        let mut company = taxes.company * 100.0;
+        let mut changed = imgui::Slider::new(im_str!("income tax"))
+            .range(0.0..=100.0)
+            .display_format(im_str!("%.0f%%"))
+            .build(ui, &mut income);
+        changed |= imgui::Slider::new(im_str!("company tax"))
+            .range(0.0..=100.0)
+            .display_format(im_str!("%.0f%%"))
+            .build(ui, &mut company);
+        if changed {
+            taxes.income = income / 100.0;
+            taxes.company = company / 100.0;
+            uiworld.commands().set_taxes(taxes);
+        }
+        ui.separator();
+
+        ui.columns(3, im_str!("Accounts"), false);
+        ui.next_column();
+        ui.text("Today");
+        ui.next_column();
+        ui.text("Yesterday");
+        ui.next_column();
+
+        let row = |name: &str, f: fn(&CityAccounts) -> String| {
+            ui.text(name);
+            ui.next_column();
+            ui.text(f(&treasury.today));
+            ui.next_column();
+            ui.text(f(&treasury.yesterday));
+            ui.next_column();
+        };
+        row("Taxes", |a| format!("{}", a.taxes));
+        row("Construction", |a| format!("{}", -a.construction));
+        row("Road upkeep", |a| format!("{}", -a.upkeep));
+        row("Services", |a| format!("{}", -a.services));
+        row("Balance", |a| format!("{}", a.balance()));
+        ui.columns(1, im_str!("Accounts"), false);
+
+        if !treasury.refusals.is_empty() {
+            ui.separator();
+            ui.text("Refused:");
+            for refusal in treasury.refusals.iter().rev() {
+                ui.text_colored([1.0, 0.3, 0.3, 1.0], &refusal.reason);
+            }
+        }
+    });
+}
@@ -12,13 +12,15 @@ use egregoria::Egregoria;
 use geom::{vec2, Camera, Color, Intersect, LinearColor, Segment, Spline, Vec2, AABB, OBB};
 use imgui::im_str;
 use imgui::Ui;
-use map_model::{IntersectionID, Map, RoadSegmentKind};
+use map_model::{IntersectionID, Map, MapProblem, RoadSegmentKind};
 use wgpu_engine::Tesselator;
 
 register_resource_noserialize!(DebugState);
 #[derive(Default)]
 pub struct DebugState {
     connectivity: (u32, Vec<Vec<IntersectionID>>),
+    /// Problems found the last time the map was validated
+    problems: Option<Vec<MapProblem>>,
 }
 
 register_resource_noserialize!(DebugObjs);
@@ -86,6 +88,25 @@ pub fn debug(window: imgui::Window, ui: &Ui, uiworld: &mut UiWorld, goria: &Egre
             ));
         }
 
+        ui.separator();
+        let mut state = uiworld.write::<DebugState>();
+        if ui.small_button(im_str!("validate map")) {
+            state.problems = Some(goria.map().validate());
+        }
+        if let Some(ref problems) = state.problems {
+            let repairable = problems.iter().filter(|p| p.repairable()).count();
+            ui.text(im_str!("{} problems", problems.len()));
+            for problem in problems.iter().take(20) {
+                ui.text(im_str!("{}", problem));
+            }
+            if repairable > 0 && ui.small_button(&im_str!("repair {} problems", repairable)) {
+                uiworld.commands().map_repair();
+                state.problems = None;
+            }
+        }
+        drop(state);
+        ui.separator();
+
         let timings = uiworld.read::<Timings>();
         let mouse = uiworld.read::<MouseInfo>().unprojected;
         let cam = uiworld.read::<Camera>().pos;
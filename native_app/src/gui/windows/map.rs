@@ -1,11 +1,18 @@
 use crate::network::NetworkState;
 use crate::uiworld::UiWorld;
+use egregoria::economy::Bankruptcy;
+use egregoria::engine_interaction::LastOsmReport;
+use egregoria::map_dynamic::ParkingManagement;
 use egregoria::pedestrians::Pedestrian;
+use egregoria::souls::migration::Migration;
+use egregoria::souls::services::ServiceBudget;
 use egregoria::vehicles::Vehicle;
 use egregoria::Egregoria;
-use geom::Camera;
-use imgui::{im_str, Ui};
+use geom::{Camera, Vec2};
+use imgui::{im_str, ImString, Ui};
 use legion::IntoQuery;
+use map_model::procgen::{CityGenParams, CityStyle};
+use map_model::MapDescription;
 
 register_resource_noserialize!(TestFieldProperties);
 
@@ -15,11 +22,102 @@ struct TestFieldProperties {
     spacing: f32,
 }
 
+register_resource_noserialize!(CityGenProperties);
+
+#[derive(Default)]
+struct CityGenProperties(CityGenParams);
+
+register_resource_noserialize!(OsmFile);
+
+/// Path of the OpenStreetMap extract to import, .osm or .osm.pbf
+struct OsmFile {
+    path: ImString,
+    /// Why the extract could not be read
+    error: Option<String>,
+}
+
+/// Number of skipped ways listed after an OpenStreetMap import
+const MAX_SKIPPED_SHOWN: usize = 10;
+
+register_resource_noserialize!(JsonFile);
+
+/// Path of the map description to save or load, see `map_model::MapDescription`
+struct JsonFile {
+    path: ImString,
+    /// Why the description could not be saved or read
+    error: Option<String>,
+}
+
 pub fn map(window: imgui::Window, ui: &Ui, uiworld: &mut UiWorld, goria: &Egregoria) {
     window.build(ui, || {
         if ui.small_button(im_str!("load Paris map")) {
             uiworld.commands().map_load_paris();
         }
+
+        let mut osm = uiworld.write::<OsmFile>();
+        ui.input_text(im_str!("osm file"), &mut osm.path).build();
+        let mut extract = None;
+        if ui.small_button(im_str!("load OSM file")) {
+            let path = osm.path.to_str().to_string();
+            match map_model::procgen::read_osm(&path) {
+                Ok(data) => {
+                    osm.error = None;
+                    extract = Some(data);
+                }
+                Err(e) => osm.error = Some(format!("could not read {}: {}", path, e)),
+            }
+        }
+        if let Some(ref e) = osm.error {
+            ui.text(e);
+        }
+        drop(osm);
+        if let Some(data) = extract {
+            uiworld.commands().map_load_osm(data);
+        }
+        if let Some(ref report) = goria.read::<LastOsmReport>().0 {
+            ui.text(im_str!(
+                "imported {} intersections, {} roads and {} waters",
+                report.n_intersections,
+                report.n_roads,
+                report.n_waters
+            ));
+            if !report.skipped.is_empty() {
+                ui.text(im_str!("{} ways skipped:", report.skipped.len()));
+                for skipped in report.skipped.iter().take(MAX_SKIPPED_SHOWN) {
+                    ui.text(im_str!("  way {}: {}", skipped.id, skipped.reason));
+                }
+            }
+        }
+
+        let mut json = uiworld.write::<JsonFile>();
+        ui.input_text(im_str!("json file"), &mut json.path).build();
+        if ui.small_button(im_str!("save map as JSON")) {
+            let path = json.path.to_str().to_string();
+            json.error = goria
+                .map()
+                .save_json(&path)
+                .err()
+                .map(|e| format!("could not save {}: {}", path, e));
+        }
+        ui.same_line(0.0);
+        let mut desc = None;
+        if ui.small_button(im_str!("load JSON map")) {
+            let path = json.path.to_str().to_string();
+            match MapDescription::load_json(&path) {
+                Ok(d) => {
+                    json.error = None;
+                    desc = Some(d);
+                }
+                Err(e) => json.error = Some(format!("could not read {}: {}", path, e)),
+            }
+        }
+        if let Some(ref e) = json.error {
+            ui.text(e);
+        }
+        drop(json);
+        if let Some(desc) = desc {
+            uiworld.commands().map_load_json(desc);
+        }
         ui.separator();
         let mut state = uiworld.write::<TestFieldProperties>();
 
@@ -40,6 +138,83 @@ pub fn map(window: imgui::Window, ui: &Ui, uiworld: &mut UiWorld, goria: &Egrego
             );
         }
 
+        drop(state);
+        ui.separator();
+        let mut city = uiworld.write::<CityGenProperties>();
+        let params = &mut city.0;
+
+        let mut seed = params.seed as i32;
+        if ui.input_int(im_str!("seed"), &mut seed).build() {
+            params.seed = seed as u64;
+        }
+        imgui::Drag::new(im_str!("city size"))
+            .range(300.0..=5000.0)
+            .display_format(im_str!("%.0f"))
+            .build(ui, &mut params.size);
+        imgui::Drag::new(im_str!("block size"))
+            .range(60.0..=300.0)
+            .display_format(im_str!("%.0f"))
+            .build(ui, &mut params.block_size);
+        imgui::Drag::new(im_str!("blocks between arterials"))
+            .range(2..=20)
+            .build(ui, &mut params.arterial_every);
+        imgui::Drag::new(im_str!("blocks between collectors"))
+            .range(1..=10)
+            .build(ui, &mut params.collector_every);
+        if ui.radio_button_bool(im_str!("grid"), params.style == CityStyle::Grid) {
+            params.style = CityStyle::Grid;
+        }
+        ui.same_line(0.0);
+        if ui.radio_button_bool(im_str!("organic"), params.style == CityStyle::Organic) {
+            params.style = CityStyle::Organic;
+        }
+        ui.checkbox(im_str!("river"), &mut params.river);
+        imgui::Drag::new(im_str!("river crossings"))
+            .range(0..=10)
+            .build(ui, &mut params.river_crossings);
+
+        if matches!(
+            *uiworld.read::<NetworkState>(),
+            NetworkState::Singleplayer { .. }
+        ) && ui.small_button(im_str!("new game on a generated city"))
+        {
+            let mut commands = uiworld.commands();
+            commands.reset_save();
+            commands.map_generate_city(Vec2::ZERO, params.clone());
+        }
+        drop(city);
+        ui.separator();
+
+        let mut search = goria.read::<ParkingManagement>().search_on_arrival;
+        if ui.checkbox(im_str!("search parking on arrival"), &mut search) {
+            uiworld.commands().set_parking_search(search);
+        }
+
+        let mut births = goria.read::<Migration>().births_and_deaths;
+        if ui.checkbox(im_str!("births and deaths"), &mut births) {
+            uiworld.commands().set_births_and_deaths(births);
+        }
+
+        let mut delay = goria.read::<Bankruptcy>().unprofitable_days;
+        if imgui::Drag::new(im_str!("days before bankruptcy"))
+            .range(1..=100)
+            .build(ui, &mut delay)
+        {
+            uiworld.commands().set_bankruptcy_delay(delay);
+        }
+
+        let budget = goria.read::<ServiceBudget>();
+        let mut funding = budget.funding * 100.0;
+        if imgui::Slider::new(im_str!("services funding"))
+            .range(0.0..=100.0)
+            .display_format(im_str!("%.0f%%"))
+            .build(ui, &mut funding)
+        {
+            uiworld.commands().set_service_funding(funding / 100.0);
+        }
+        ui.text(im_str!("services cost {} per day", budget.spent));
+        drop(budget);
+
         if matches!(
             *uiworld.read::<NetworkState>(),
             NetworkState::Singleplayer { .. }
@@ -59,6 +234,24 @@ pub fn map(window: imgui::Window, ui: &Ui, uiworld: &mut UiWorld, goria: &Egrego
     })
 }
 
+impl Default for OsmFile {
+    fn default() -> Self {
+        Self {
+            path: ImString::with_capacity(256),
+            error: None,
+        }
+    }
+}
+
+impl Default for JsonFile {
+    fn default() -> Self {
+        Self {
+            path: ImString::with_capacity(256),
+            error: None,
+        }
+    }
+}
+
 impl Default for TestFieldProperties {
     fn default() -> Self {
         Self {
@@ -4,6 +4,7 @@ use serde::{Deserialize, Serialize};
 use crate::uiworld::UiWorld;
 use egregoria::Egregoria;
 
+mod budget;
 mod config;
 pub mod debug;
 mod economy;
@@ -49,6 +50,7 @@ impl Default for ImguiWindows {
         s.insert(imgui::im_str!("Debug"), debug::debug, false);
         s.insert(imgui::im_str!("Settings"), settings::settings, false);
         s.insert(imgui::im_str!("Network"), network::network, false);
+        s.insert(imgui::im_str!("Budget"), budget::budget, false);
         s
     }
 }
@@ -1,10 +1,11 @@
 use common::{
-    FastMap, Z_ARROW, Z_BSPRITE, Z_CROSSWALK, Z_INTER_BG, Z_LANE, Z_LANE_BG, Z_LOT, Z_SIDEWALK,
+    FastMap, Z_ARROW, Z_BRIDGE, Z_BSPRITE, Z_CROSSWALK, Z_INTER_BG, Z_LANE, Z_LANE_BG, Z_LOT,
+    Z_SIDEWALK, Z_WATER,
 };
 use egregoria::souls::goods_company::GoodsCompanyRegistry;
 use egregoria::Egregoria;
 use geom::{vec2, LinearColor, Polygon, Vec2, Vec3};
-use map_model::{BuildingKind, LaneKind, LotKind, Map, TurnKind, CROSSWALK_WIDTH};
+use map_model::{BuildingKind, LaneKind, LotKind, Map, TurnKind, WaterKind, CROSSWALK_WIDTH};
 use std::ops::Mul;
 use std::rc::Rc;
 use wgpu_engine::earcut::earcut;
@@ -353,11 +354,32 @@ impl MapBuilders {
             }
         }
 
+        // Water
+        tess.set_color(common::config().sea_col);
+        for water in map.waters().values() {
+            match water.kind {
+                WaterKind::River { width } => {
+                    tess.draw_polyline(water.points.as_slice(), Z_WATER, width);
+                }
+                WaterKind::Lake => {
+                    tess.draw_filled_polygon(water.points.as_slice(), Z_WATER);
+                }
+            }
+        }
+
+        // Bridge decks
+        tess.set_color(LinearColor::gray(0.4));
+        for road in map.roads().values().filter(|r| r.bridge) {
+            tess.draw_polyline(road.points.as_slice(), Z_BRIDGE, road.width + 3.0);
+        }
+
         // Lots
         for lot in lots.values() {
             let col = match lot.kind {
                 LotKind::Unassigned => common::config().lot_unassigned_col,
                 LotKind::Residential => common::config().lot_residential_col,
+                LotKind::Commercial => common::config().lot_commercial_col,
+                LotKind::Industrial => common::config().lot_industrial_col,
             };
             tess.set_color(col);
             tess.draw_filled_polygon(&lot.shape.corners, Z_LOT);
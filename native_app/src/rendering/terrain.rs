@@ -1,6 +1,8 @@
-use crate::uiworld::UiWorld;
 use common::FastMap;
 use geom::{vec2, vec3, LinearColor};
+use map_model::{
+    Map, BUILDABLE_LEVEL, ELEVATION_SCALE, HEIGHT_CHUNK_RES, HEIGHT_CHUNK_SIZE, SEA_LEVEL,
+};
 use std::mem::MaybeUninit;
 use std::sync::Arc;
 use wgpu_engine::pbuffer::PBuffer;
@@ -8,8 +10,8 @@ use wgpu_engine::wgpu::BufferUsage;
 use wgpu_engine::{FrameContext, GfxContext, Mesh, Texture};
 use wgpu_engine::{IndexType, MeshVertex};
 
-const CHUNK_SIZE: f32 = 1000.0;
-const RESOLUTION: usize = 40;
+const CHUNK_SIZE: f32 = HEIGHT_CHUNK_SIZE;
+const RESOLUTION: usize = HEIGHT_CHUNK_RES as usize;
 const LOD: usize = 1;
 
 struct TerrainChunk {
@@ -18,17 +20,20 @@ struct TerrainChunk {
 
 pub struct TerrainRender {
     chunks: FastMap<(i32, i32), TerrainChunk>,
+    /// Version of the edited heightmap chunks when they were last generated
+    versions: FastMap<(i32, i32), u32>,
     indices: [(PBuffer, usize); LOD],
     albedo: Arc<Texture>,
     bg: Arc<wgpu_engine::wgpu::BindGroup>,
 }
 
 impl TerrainRender {
-    pub fn new(gfx: &mut GfxContext) -> Self {
+    pub fn new(gfx: &mut GfxContext, map: &Map) -> Self {
         let indices = Self::generate_indices(gfx);
         let pal = gfx.palette();
         let mut me = TerrainRender {
             chunks: Default::default(),
+            versions: Default::default(),
             indices,
             bg: Arc::new(pal.bindgroup(&gfx.device, &Texture::bindgroup_layout(&gfx.device))),
             albedo: pal,
@@ -36,13 +41,39 @@ impl TerrainRender {
 
         for y in -10..10 {
             for x in -10..10 {
-                me.generate(gfx, x, y);
+                me.generate(gfx, map, x, y);
             }
         }
+        me.update(gfx, map);
         me
     }
 
-    fn generate(&mut self, gfx: &mut GfxContext, x: i32, y: i32) {
+    /// Regenerates the chunks touching the heightmap chunks edited since last time
+    fn update(&mut self, gfx: &mut GfxContext, map: &Map) {
+        let mut dirty = vec![];
+        for (chunk, version) in map.heightmap.edited_chunks() {
+            if self.versions.insert(chunk, version) != Some(version) {
+                dirty.push(chunk);
+            }
+        }
+        let edited: Vec<_> = map.heightmap.edited_chunks().map(|(c, _)| c).collect();
+        self.versions.retain(|c, _| {
+            let keep = edited.contains(c);
+            if !keep {
+                dirty.push(*c);
+            }
+            keep
+        });
+
+        // a chunk also holds the first row and column of vertices of its neighbours
+        for (x, y) in dirty {
+            for &(dx, dy) in &[(0, 0), (-1, 0), (0, -1), (-1, -1)] {
+                self.generate(gfx, map, x + dx, y + dy);
+            }
+        }
+    }
+
+    fn generate(&mut self, gfx: &mut GfxContext, map: &Map, x: i32, y: i32) {
         let mut v = vec![];
         for lod in 0..LOD {
             let resolution = RESOLUTION / (1 << lod);
@@ -58,18 +89,17 @@ impl TerrainRender {
                     let pos = vec2(x, y);
                     let pos = pos * CHUNK_SIZE + offset;
 
-                    let (height, mut grad) = map_model::procgen::heightmap::height(pos);
+                    let (height, mut grad) = map.heightmap.height_grad(pos);
 
-                    let col: LinearColor = if height < 0.1 {
+                    let col: LinearColor = if height < SEA_LEVEL {
                         common::config().sea_col.into()
-                    } else if height < 0.12 {
+                    } else if height < BUILDABLE_LEVEL {
                         common::config().sand_col.into()
                     } else {
                         0.37 * LinearColor::from(common::config().grass_col)
                     };
 
-                    grad *= 2.0 * height * 3000.0;
-                    //height = height * height * 3000.0;
+                    grad *= ELEVATION_SCALE;
 
                     mesh.push(MeshVertex {
                         position: [pos.x, pos.y, 0.0],
@@ -133,7 +163,9 @@ impl TerrainRender {
         collect_arr4(v)
     }
 
-    pub fn render(&mut self, _uiw: &UiWorld, fctx: &mut FrameContext) {
+    pub fn render(&mut self, map: &Map, fctx: &mut FrameContext) {
+        self.update(fctx.gfx, map);
+
         for chunk in self.chunks.values() {
             fctx.objs.push(Box::new(chunk.lods[0].clone()))
         }